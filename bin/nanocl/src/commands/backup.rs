@@ -45,6 +45,17 @@ pub async fn exec_backup(
       .iter()
       .map(|vm| vm.spec.clone().into())
       .collect::<Vec<VmSpecPartial>>();
+    pg.set_message("(processing: resources)");
+    let resources = cli_conf
+      .client
+      .list_resource(Some(&GenericFilterNsp {
+        namespace: Some(namespace.name.clone()),
+        ..Default::default()
+      }))
+      .await?
+      .iter()
+      .map(|resource| resource.clone().into())
+      .collect::<Vec<ResourcePartial>>();
     pg.set_message(format!("(writing statefile: {}.yml)", namespace.name));
     let state_file = Statefile {
       api_version: cli_conf.client.version.clone(),
@@ -53,7 +64,7 @@ pub async fn exec_backup(
       group: None,
       namespace: Some(namespace.name.clone()),
       secrets: None,
-      resources: None,
+      resources: Some(resources),
       cargoes: Some(cargoes),
       virtual_machines: Some(vms),
       jobs: None,
@@ -129,36 +140,5 @@ pub async fn exec_backup(
   })?;
   std::fs::write(&file_path, data)?;
   pg.finish_with_message("(backup: secrets.yml)");
-  // Backup resources
-  let file_path = format!("{}/resources.yml", dir_path);
-  let pg_style = utils::progress::create_spinner_style("resources", "green");
-  let pg = utils::progress::create_progress("(processing)", &pg_style);
-  let resources = cli_conf
-    .client
-    .list_resource(None)
-    .await?
-    .iter()
-    .map(|resource| resource.clone().into())
-    .collect::<Vec<ResourcePartial>>();
-  if std::path::Path::new(&file_path).exists() && !opts.skip_confirm {
-    utils::dialog::confirm("File already exist override ?")?;
-  }
-  let state_file = Statefile {
-    api_version: cli_conf.client.version.clone(),
-    sub_states: None,
-    args: None,
-    group: None,
-    namespace: None,
-    secrets: None,
-    resources: Some(resources),
-    cargoes: None,
-    virtual_machines: None,
-    jobs: None,
-  };
-  let data = serde_yaml::to_string(&state_file).map_err(|err| {
-    IoError::interrupted("Backup state", err.to_string().as_str())
-  })?;
-  std::fs::write(&file_path, data)?;
-  pg.finish_with_message("(backup: resources.yml)");
   Ok(())
 }
@@ -0,0 +1,151 @@
+use nanocl_error::io::{FromIo, IoResult};
+use nanocld_client::stubs::auth::Identity;
+
+use crate::{
+  config::CliConfig,
+  models::{
+    ApiTokenRow, GenericDefaultOpts, IdentityArg, IdentityCommand,
+    IdentityCreateOpts, IdentityRow, IdentityTokenArg, IdentityTokenCommand,
+    IdentityTokenCreateOpts, IdentityTokenListOpts, IdentityTokenRemoveOpts,
+    IdentityUpdateOpts,
+  },
+  utils,
+};
+
+use super::{
+  GenericCommand, GenericCommandInspect, GenericCommandLs, GenericCommandRm,
+};
+
+impl GenericCommand for IdentityArg {
+  fn object_name() -> &'static str {
+    "identities"
+  }
+}
+
+impl GenericCommandLs for IdentityArg {
+  type Item = IdentityRow;
+  type Args = IdentityArg;
+  type ApiItem = Identity;
+
+  fn get_key(item: &Self::Item) -> String {
+    item.name.clone()
+  }
+}
+
+impl GenericCommandRm<GenericDefaultOpts, String> for IdentityArg {}
+
+impl GenericCommandInspect for IdentityArg {
+  type ApiItem = Identity;
+}
+
+async fn exec_identity_create(
+  cli_conf: &CliConfig,
+  opts: &IdentityCreateOpts,
+) -> IoResult<()> {
+  cli_conf
+    .client
+    .create_identity(&opts.clone().into())
+    .await?;
+  Ok(())
+}
+
+async fn exec_identity_update(
+  cli_conf: &CliConfig,
+  opts: &IdentityUpdateOpts,
+) -> IoResult<()> {
+  cli_conf
+    .client
+    .patch_identity(&opts.name, &opts.clone().into())
+    .await?;
+  Ok(())
+}
+
+async fn exec_identity_token_create(
+  cli_conf: &CliConfig,
+  opts: &IdentityTokenCreateOpts,
+) -> IoResult<()> {
+  let token = cli_conf
+    .client
+    .create_identity_token(&opts.name, &opts.clone().into())
+    .await?;
+  println!("{}", token.token.unwrap_or_default());
+  Ok(())
+}
+
+async fn exec_identity_token_ls(
+  cli_conf: &CliConfig,
+  opts: &IdentityTokenListOpts,
+) -> IoResult<()> {
+  let tokens = cli_conf.client.list_identity_token(&opts.name).await?;
+  if opts.quiet {
+    for token in tokens {
+      println!("{}", token.key);
+    }
+    return Ok(());
+  }
+  let rows = tokens
+    .into_iter()
+    .map(ApiTokenRow::from)
+    .collect::<Vec<_>>();
+  utils::print::print_table(rows);
+  Ok(())
+}
+
+async fn exec_identity_token_rm(
+  cli_conf: &CliConfig,
+  opts: &IdentityTokenRemoveOpts,
+) -> IoResult<()> {
+  if !opts.skip_confirm {
+    utils::dialog::confirm(&format!(
+      "Revoke tokens {} of identity {} ?",
+      opts.keys.join(","),
+      opts.name
+    ))
+    .map_err(|err| err.map_err_context(|| "Delete"))?;
+  }
+  for key in &opts.keys {
+    cli_conf
+      .client
+      .delete_identity_token(&opts.name, key)
+      .await?;
+  }
+  Ok(())
+}
+
+async fn exec_identity_token(
+  cli_conf: &CliConfig,
+  args: &IdentityTokenArg,
+) -> IoResult<()> {
+  match &args.command {
+    IdentityTokenCommand::Create(opts) => {
+      exec_identity_token_create(cli_conf, opts).await
+    }
+    IdentityTokenCommand::List(opts) => {
+      exec_identity_token_ls(cli_conf, opts).await
+    }
+    IdentityTokenCommand::Remove(opts) => {
+      exec_identity_token_rm(cli_conf, opts).await
+    }
+  }
+}
+
+/// Function that execute when running `nanocl identity`
+pub async fn exec_identity(
+  cli_conf: &CliConfig,
+  args: &IdentityArg,
+) -> IoResult<()> {
+  match &args.command {
+    IdentityCommand::List(opts) => {
+      IdentityArg::exec_ls(&cli_conf.client, args, opts).await
+    }
+    IdentityCommand::Remove(opts) => {
+      IdentityArg::exec_rm(&cli_conf.client, opts, None).await
+    }
+    IdentityCommand::Inspect(opts) => {
+      IdentityArg::exec_inspect(cli_conf, opts, None).await
+    }
+    IdentityCommand::Create(opts) => exec_identity_create(cli_conf, opts).await,
+    IdentityCommand::Update(opts) => exec_identity_update(cli_conf, opts).await,
+    IdentityCommand::Token(args) => exec_identity_token(cli_conf, args).await,
+  }
+}
@@ -3,7 +3,11 @@ use nanocld_client::stubs::metric::Metric;
 
 use crate::{
   config::CliConfig,
-  models::{MetricArg, MetricCommand, MetricRow},
+  models::{
+    MetricAggregateOpts, MetricAggregateRow, MetricArg, MetricCommand,
+    MetricRow,
+  },
+  utils,
 };
 
 use super::{GenericCommand, GenericCommandInspect, GenericCommandLs};
@@ -28,6 +32,23 @@ impl GenericCommandInspect for MetricArg {
   type ApiItem = Metric;
 }
 
+/// Function that execute when running `nanocl metric aggregate`
+async fn exec_metric_aggregate(
+  cli_conf: &CliConfig,
+  opts: &MetricAggregateOpts,
+) -> IoResult<()> {
+  let aggregates = cli_conf.client.aggregate_metric(&opts.into()).await?;
+  if let Some(display) = &opts.display {
+    return utils::print::display_format(display, aggregates);
+  }
+  let rows = aggregates
+    .iter()
+    .flat_map(MetricAggregateRow::from_aggregate)
+    .collect::<Vec<_>>();
+  utils::print::print_table(rows);
+  Ok(())
+}
+
 /// Function that execute when running `nanocl metric`
 pub async fn exec_metric(
   cli_conf: &CliConfig,
@@ -40,5 +61,8 @@ pub async fn exec_metric(
     MetricCommand::Inspect(opts) => {
       MetricArg::exec_inspect(cli_conf, opts, None).await
     }
+    MetricCommand::Aggregate(opts) => {
+      exec_metric_aggregate(cli_conf, opts).await
+    }
   }
 }
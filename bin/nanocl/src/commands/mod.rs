@@ -3,6 +3,7 @@ mod cargo;
 mod context;
 mod event;
 mod generic;
+mod identity;
 mod info;
 #[cfg(not(target_os = "windows"))]
 mod install;
@@ -12,6 +13,7 @@ mod namespace;
 mod node;
 mod process;
 mod resource;
+mod role;
 mod secret;
 mod state;
 #[cfg(not(target_os = "windows"))]
@@ -26,6 +28,7 @@ pub use backup::exec_backup;
 pub use cargo::exec_cargo;
 pub use context::exec_context;
 pub use event::exec_event;
+pub use identity::exec_identity;
 pub use info::exec_info;
 #[cfg(not(target_os = "windows"))]
 pub use install::exec_install;
@@ -35,6 +38,7 @@ pub use namespace::exec_namespace;
 pub use node::exec_node;
 pub use process::{exec_process, inspect_process, logs_process};
 pub use resource::exec_resource;
+pub use role::exec_role;
 pub use secret::exec_secret;
 pub use state::exec_state;
 #[cfg(not(target_os = "windows"))]
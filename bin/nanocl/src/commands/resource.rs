@@ -1,11 +1,14 @@
 use nanocl_error::io::IoResult;
-use nanocld_client::stubs::resource::Resource;
+use nanocld_client::stubs::{
+  generic::{GenericFilter, GenericListQueryNsp, GenericNspQuery},
+  resource::{Resource, ResourceMigrate},
+};
 
 use crate::{
   config::CliConfig,
   models::{
-    GenericDefaultOpts, ResourceArg, ResourceCommand, ResourceHistoryOpts,
-    ResourceRevertOpts, ResourceRow,
+    GenericDefaultOpts, GenericRemoveOpts, ResourceArg, ResourceCommand,
+    ResourceHistoryOpts, ResourceMigrateOpts, ResourceRevertOpts, ResourceRow,
   },
   utils,
 };
@@ -28,9 +31,25 @@ impl GenericCommandLs for ResourceArg {
   fn get_key(item: &Self::Item) -> String {
     item.name.clone()
   }
+
+  fn transform_filter(
+    args: &Self::Args,
+    filter: &GenericFilter,
+  ) -> impl serde::Serialize {
+    GenericListQueryNsp::try_from(filter.clone())
+      .unwrap()
+      .with_namespace(args.namespace.as_deref())
+  }
 }
 
-impl GenericCommandRm<GenericDefaultOpts, String> for ResourceArg {}
+impl GenericCommandRm<GenericDefaultOpts, GenericNspQuery> for ResourceArg {
+  fn get_query(
+    _opts: &GenericRemoveOpts<GenericDefaultOpts>,
+    namespace: Option<String>,
+  ) -> Option<GenericNspQuery> {
+    Some(GenericNspQuery::new(namespace.as_deref()))
+  }
+}
 
 impl GenericCommandInspect for ResourceArg {
   type ApiItem = Resource;
@@ -39,10 +58,13 @@ impl GenericCommandInspect for ResourceArg {
 /// Function that execute when running `nanocl resource history`
 async fn exec_resource_history(
   cli_conf: &CliConfig,
+  args: &ResourceArg,
   opts: &ResourceHistoryOpts,
 ) -> IoResult<()> {
   let client = &cli_conf.client;
-  let history = client.list_history_resource(&opts.name).await?;
+  let history = client
+    .list_history_resource(&opts.name, args.namespace.as_deref())
+    .await?;
   utils::print::print_yml(history)?;
   Ok(())
 }
@@ -50,14 +72,38 @@ async fn exec_resource_history(
 /// Function that execute when running `nanocl resource revert`
 async fn exec_resource_revert(
   cli_conf: &CliConfig,
+  args: &ResourceArg,
   opts: &ResourceRevertOpts,
 ) -> IoResult<()> {
   let client = &cli_conf.client;
-  let resource = client.revert_resource(&opts.name, &opts.key).await?;
+  let resource = client
+    .revert_resource(&opts.name, &opts.key, args.namespace.as_deref())
+    .await?;
   utils::print::print_yml(resource)?;
   Ok(())
 }
 
+/// Function that execute when running `nanocl resource migrate`
+async fn exec_resource_migrate(
+  cli_conf: &CliConfig,
+  args: &ResourceArg,
+  opts: &ResourceMigrateOpts,
+) -> IoResult<()> {
+  let client = &cli_conf.client;
+  let payload = ResourceMigrate {
+    version: opts.version.clone(),
+  };
+  let mut resources = Vec::new();
+  for name in &opts.names {
+    let resource = client
+      .migrate_resource(name, &payload, args.namespace.as_deref())
+      .await?;
+    resources.push(resource);
+  }
+  utils::print::print_yml(resources)?;
+  Ok(())
+}
+
 /// Function that execute when running `nanocl resource`
 pub async fn exec_resource(
   cli_conf: &CliConfig,
@@ -68,14 +114,19 @@ pub async fn exec_resource(
       ResourceArg::exec_ls(&cli_conf.client, args, opts).await
     }
     ResourceCommand::Remove(opts) => {
-      ResourceArg::exec_rm(&cli_conf.client, opts, None).await
+      ResourceArg::exec_rm(&cli_conf.client, opts, args.namespace.clone()).await
     }
     ResourceCommand::Inspect(opts) => {
-      ResourceArg::exec_inspect(cli_conf, opts, None).await
+      ResourceArg::exec_inspect(cli_conf, opts, args.namespace.clone()).await
     }
     ResourceCommand::History(opts) => {
-      exec_resource_history(cli_conf, opts).await
+      exec_resource_history(cli_conf, args, opts).await
+    }
+    ResourceCommand::Revert(opts) => {
+      exec_resource_revert(cli_conf, args, opts).await
+    }
+    ResourceCommand::Migrate(opts) => {
+      exec_resource_migrate(cli_conf, args, opts).await
     }
-    ResourceCommand::Revert(opts) => exec_resource_revert(cli_conf, opts).await,
   }
 }
@@ -43,6 +43,15 @@ async fn exec_secret_create(
   Ok(())
 }
 
+async fn exec_secret_rotate_key(cli_conf: &CliConfig) -> IoResult<()> {
+  let rotation = cli_conf.client.rotate_secret_key().await?;
+  println!(
+    "Secrets encrypted with key {}: {}",
+    rotation.key_id, rotation.count
+  );
+  Ok(())
+}
+
 /// Function that execute when running `nanocl secret`
 pub async fn exec_secret(
   cli_conf: &CliConfig,
@@ -59,5 +68,6 @@ pub async fn exec_secret(
       SecretArg::exec_inspect(cli_conf, opts, None).await
     }
     SecretCommand::Create(opts) => exec_secret_create(cli_conf, opts).await,
+    SecretCommand::RotateKey => exec_secret_rotate_key(cli_conf).await,
   }
 }
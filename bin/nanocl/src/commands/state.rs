@@ -8,6 +8,7 @@ use std::{
 
 use async_recursion::async_recursion;
 use clap::{Arg, ArgAction, Command};
+use colored::Colorize;
 use futures::{
   join,
   stream::{FuturesOrdered, FuturesUnordered},
@@ -47,8 +48,9 @@ use crate::{
   models::{
     CargoArg, Context, DisplayFormat, GenericDefaultOpts,
     GenericRemoveForceOpts, GenericRemoveOpts, JobArg, ResourceArg, SecretArg,
-    StateApplyOpts, StateArg, StateCommand, StateLogsOpts, StateRef,
-    StateRemoveOpts, StateRoot, VmArg,
+    StateApplyOpts, StateArg, StateCommand, StateLogsOpts, StatePlanAction,
+    StatePlanChange, StatePlanEntry, StatePlanOpts, StateRef, StateRemoveOpts,
+    StateRoot, VmArg,
   },
   utils,
 };
@@ -253,6 +255,7 @@ fn gen_client(
         url: cli_conf.host.clone(),
         ssl: cli_conf.client.ssl.clone(),
         version: Some(api_version.clone()),
+        token: cli_conf.client.token.clone(),
       })?
     }
     _ => {
@@ -437,18 +440,20 @@ async fn read_state_file(
   read_from_file(&path, format)
 }
 
+/// Render a Statefile, the namespace is created if missing unless `dry_run` is set
 async fn render_template(
   state_ref: &StateRef<Statefile>,
   args: &serde_json::Value,
   client: &NanocldClient,
   cli_conf: &CliConfig,
+  dry_run: bool,
 ) -> IoResult<StateRef<Statefile>> {
   let mut namespace = match &state_ref.data.namespace {
     Some(namespace) => namespace.clone(),
     None => "global".to_owned(),
   };
   namespace = inject_namespace(&namespace, args)?;
-  if client.inspect_namespace(&namespace).await.is_err() {
+  if !dry_run && client.inspect_namespace(&namespace).await.is_err() {
     client.create_namespace(&namespace).await?;
   }
   let mut state_ref =
@@ -466,9 +471,11 @@ async fn parse_state_file_recurr(
   cli_conf: &CliConfig,
   state_file: &StateRef<Statefile>,
   args: &Value,
+  dry_run: bool,
 ) -> IoResult<Vec<StateRef<Statefile>>> {
   let client = gen_client(cli_conf, state_file)?;
-  let state_file = render_template(state_file, args, &client, cli_conf).await?;
+  let state_file =
+    render_template(state_file, args, &client, cli_conf, dry_run).await?;
   let sub_states = state_file.data.sub_states.clone().unwrap_or_default();
   let parsed_sub_states = sub_states
     .iter()
@@ -513,6 +520,7 @@ async fn parse_state_file_recurr(
             cli_conf,
             &state_file,
             &Value::Object(compiled_values),
+            dry_run,
           )
           .await;
         }
@@ -549,6 +557,7 @@ async fn parse_state_file_recurr(
           cli_conf,
           &state_file,
           &Value::Object(compiled_values),
+          dry_run,
         )
         .await
       }
@@ -771,16 +780,21 @@ async fn state_apply(
       let pg = utils::progress::create_progress("(submitting)", &pg_style);
       let metadata = insert_nanocl_group(&resource.metadata, &nanocl_group);
       resource.metadata = Some(metadata);
-      match client.inspect_resource(&resource.name).await {
+      match client
+        .inspect_resource(&resource.name, Some(&namespace))
+        .await
+      {
         Err(_) => {
-          client.create_resource(&resource).await?;
+          client.create_resource(&resource, Some(&namespace)).await?;
           pg.set_message("(created)");
         }
         Ok(inspect) => {
           let cmp: ResourcePartial = inspect.into();
           if (cmp != resource) || opts.reload {
             let update: ResourceUpdate = resource.clone().into();
-            client.put_resource(&resource.name, &update).await?;
+            client
+              .put_resource(&resource.name, &update, Some(&namespace))
+              .await?;
             pg.set_message("(updated)");
           } else {
             pg.finish_with_message("(unchanged)");
@@ -801,10 +815,11 @@ fn print_states(states: &[StateRef<Statefile>]) {
   println!("{raw}");
 }
 
-async fn remove_orphans(
+/// List the elements of the group of a Statefile that are no longer in it
+async fn get_orphans(
   cli_conf: &CliConfig,
   state: &StateRef<Statefile>,
-) -> IoResult<()> {
+) -> IoResult<StateRef<Statefile>> {
   let filter = GenericFilter::new().r#where(
     "metadata",
     GenericClause::Contains(serde_json::json!({
@@ -840,7 +855,12 @@ async fn remove_orphans(
     .collect();
   let old_resources: Vec<ResourcePartial> = cli_conf
     .client
-    .list_resource(Some(&filter))
+    .list_resource(Some(&GenericFilterNsp {
+      filter: Some(filter.clone()),
+      namespace: Some(
+        state.data.namespace.clone().unwrap_or("global".to_owned()),
+      ),
+    }))
     .await?
     .iter()
     .map(|resource| resource.clone().into())
@@ -869,7 +889,7 @@ async fn remove_orphans(
       .filter(|r| !resources.iter().any(|nr| nr.name == r.name))
       .collect::<Vec<_>>()
   });
-  let old_state = StateRef {
+  Ok(StateRef {
     raw: "".to_owned(),
     format: state.format.clone(),
     data: Statefile {
@@ -881,11 +901,255 @@ async fn remove_orphans(
     },
     root: state.root.clone(),
     location: state.location.clone(),
-  };
+  })
+}
+
+async fn remove_orphans(
+  cli_conf: &CliConfig,
+  state: &StateRef<Statefile>,
+) -> IoResult<()> {
+  let old_state = get_orphans(cli_conf, state).await?;
   state_remove(cli_conf, &old_state).await?;
   Ok(())
 }
 
+/// Create a plan entry without changes
+fn plan_entry(
+  kind: &str,
+  name: &str,
+  namespace: Option<&str>,
+  action: StatePlanAction,
+) -> StatePlanEntry {
+  StatePlanEntry {
+    kind: kind.to_owned(),
+    name: name.to_owned(),
+    namespace: namespace.map(str::to_owned),
+    action,
+    restart: false,
+    changes: Vec::new(),
+  }
+}
+
+/// Fields that differ between a current element and the wanted one
+fn plan_changes<T>(before: &T, after: &T) -> IoResult<Vec<StatePlanChange>>
+where
+  T: serde::Serialize,
+{
+  let before = serde_json::to_value(before)?;
+  let after = serde_json::to_value(after)?;
+  Ok(utils::state::diff_value(&before, &after))
+}
+
+/// Compute what `state_apply` would do for a Statefile without changing anything
+async fn state_plan(
+  cli_conf: &CliConfig,
+  opts: &StatePlanOpts,
+  state_file: &StateRef<Statefile>,
+) -> IoResult<Vec<StatePlanEntry>> {
+  let client = &cli_conf.client;
+  let namespace = state_file.data.namespace.clone().unwrap_or("global".into());
+  let nanocl_group = get_nanocl_group(state_file);
+  let mut entries = Vec::new();
+  let namespace_exists = client.inspect_namespace(&namespace).await.is_ok();
+  if !namespace_exists {
+    entries.push(plan_entry(
+      "namespace",
+      &namespace,
+      None,
+      StatePlanAction::Create,
+    ));
+  }
+  if opts.remove_orphans && namespace_exists {
+    let orphans = get_orphans(cli_conf, state_file).await?.data;
+    let delete = StatePlanAction::Delete;
+    for secret in orphans.secrets.unwrap_or_default() {
+      entries.push(plan_entry("secret", &secret.name, None, delete));
+    }
+    for cargo in orphans.cargoes.unwrap_or_default() {
+      entries.push(plan_entry("cargo", &cargo.name, Some(&namespace), delete));
+    }
+    for vm in orphans.virtual_machines.unwrap_or_default() {
+      entries.push(plan_entry("vm", &vm.name, Some(&namespace), delete));
+    }
+    for resource in orphans.resources.unwrap_or_default() {
+      entries.push(plan_entry(
+        "resource",
+        &resource.name,
+        Some(&namespace),
+        delete,
+      ));
+    }
+  }
+  for mut secret in state_file.data.secrets.clone().unwrap_or_default() {
+    secret.metadata =
+      Some(insert_nanocl_group(&secret.metadata, &nanocl_group));
+    let mut entry =
+      plan_entry("secret", &secret.name, None, StatePlanAction::Create);
+    if let Ok(inspect) = client.inspect_secret(&secret.name).await {
+      let cmp: SecretPartial = inspect.into();
+      entry.action = if cmp != secret {
+        StatePlanAction::Update
+      } else {
+        StatePlanAction::Unchanged
+      };
+      entry.changes = plan_changes(&cmp, &secret)?;
+    }
+    entries.push(entry);
+  }
+  for mut job in state_file.data.jobs.clone().unwrap_or_default() {
+    job.metadata = Some(insert_nanocl_group(&job.metadata, &nanocl_group));
+    let mut entry = plan_entry("job", &job.name, None, StatePlanAction::Create);
+    // Existing jobs are always deleted and created again by an apply
+    if let Ok(inspect) = client.inspect_job(&job.name).await {
+      let cmp: JobPartial = inspect.spec.into();
+      entry.action = StatePlanAction::Replace;
+      entry.restart = true;
+      entry.changes = plan_changes(&cmp, &job)?;
+    }
+    entries.push(entry);
+  }
+  for mut cargo in state_file.data.cargoes.clone().unwrap_or_default() {
+    cargo.metadata = Some(insert_nanocl_group(&cargo.metadata, &nanocl_group));
+    let mut entry = plan_entry(
+      "cargo",
+      &cargo.name,
+      Some(&namespace),
+      StatePlanAction::Create,
+    );
+    if let Ok(inspect) =
+      client.inspect_cargo(&cargo.name, Some(&namespace)).await
+    {
+      let cmp: CargoSpecPartial = inspect.spec.into();
+      if (cmp != cargo) || opts.reload {
+        entry.action = StatePlanAction::Update;
+        entry.restart = true;
+      } else {
+        entry.action = StatePlanAction::Unchanged;
+      }
+      entry.changes = plan_changes(&cmp, &cargo)?;
+    }
+    entries.push(entry);
+  }
+  for mut vm in state_file.data.virtual_machines.clone().unwrap_or_default() {
+    vm.metadata = Some(insert_nanocl_group(&vm.metadata, &nanocl_group));
+    let mut entry =
+      plan_entry("vm", &vm.name, Some(&namespace), StatePlanAction::Create);
+    if let Ok(inspect) = client.inspect_vm(&vm.name, Some(&namespace)).await {
+      let cmp: VmSpecPartial = inspect.spec.into();
+      if (cmp != vm) || opts.reload {
+        entry.action = StatePlanAction::Update;
+        entry.restart = true;
+      } else {
+        entry.action = StatePlanAction::Unchanged;
+      }
+      entry.changes = plan_changes(&cmp, &vm)?;
+    }
+    entries.push(entry);
+  }
+  for mut resource in state_file.data.resources.clone().unwrap_or_default() {
+    resource.metadata =
+      Some(insert_nanocl_group(&resource.metadata, &nanocl_group));
+    let mut entry = plan_entry(
+      "resource",
+      &resource.name,
+      Some(&namespace),
+      StatePlanAction::Create,
+    );
+    if let Ok(inspect) = client
+      .inspect_resource(&resource.name, Some(&namespace))
+      .await
+    {
+      let cmp: ResourcePartial = inspect.into();
+      entry.action = if (cmp != resource) || opts.reload {
+        StatePlanAction::Update
+      } else {
+        StatePlanAction::Unchanged
+      };
+      entry.changes = plan_changes(&cmp, &resource)?;
+    }
+    entries.push(entry);
+  }
+  Ok(entries)
+}
+
+/// Print the planned actions with their field changes and a summary
+fn print_plan(entries: &[StatePlanEntry]) {
+  for entry in entries {
+    let (sign, color) = match entry.action {
+      StatePlanAction::Create => ("+", "green"),
+      StatePlanAction::Update => ("~", "yellow"),
+      StatePlanAction::Replace => ("-/+", "yellow"),
+      StatePlanAction::Delete => ("-", "red"),
+      StatePlanAction::Unchanged => ("=", "white"),
+    };
+    let key = match &entry.namespace {
+      Some(namespace) => format!("{}/{}.{namespace}", entry.kind, entry.name),
+      None => format!("{}/{}", entry.kind, entry.name),
+    };
+    let restart = if entry.restart { ", restart" } else { "" };
+    println!("{} {key} ({}{restart})", sign.color(color), entry.action);
+    if entry.action == StatePlanAction::Unchanged {
+      continue;
+    }
+    // Secret values are never printed
+    let sensitive = |change: &StatePlanChange| {
+      entry.kind == "secret" && change.path.starts_with("Data")
+    };
+    for change in &entry.changes {
+      let print_value = |value: &Option<Value>| match value {
+        None => "(none)".to_owned(),
+        Some(_) if sensitive(change) => "(sensitive)".to_owned(),
+        Some(value) => value.to_string(),
+      };
+      let (sign, color) = match (&change.before, &change.after) {
+        (None, _) => ("+", "green"),
+        (_, None) => ("-", "red"),
+        _ => ("~", "yellow"),
+      };
+      println!(
+        "    {} {}: {} -> {}",
+        sign.color(color),
+        change.path,
+        print_value(&change.before),
+        print_value(&change.after),
+      );
+    }
+  }
+  let count = |action: StatePlanAction| {
+    entries
+      .iter()
+      .filter(|entry| entry.action == action)
+      .count()
+  };
+  println!(
+    "Plan: {} to create, {} to update, {} to replace, {} to delete, {} to restart, {} unchanged",
+    count(StatePlanAction::Create),
+    count(StatePlanAction::Update),
+    count(StatePlanAction::Replace),
+    count(StatePlanAction::Delete),
+    entries.iter().filter(|entry| entry.restart).count(),
+    count(StatePlanAction::Unchanged),
+  );
+}
+
+/// Function called when running `nanocl state plan`
+async fn exec_state_plan(
+  cli_conf: &CliConfig,
+  opts: &StatePlanOpts,
+) -> IoResult<()> {
+  let format = cli_conf.user_config.display_format.clone();
+  let state_file = read_state_file(&opts.state_location, &format).await?;
+  let args = parse_build_args(&state_file.data, &opts.args)?;
+  let states =
+    parse_state_file_recurr(cli_conf, &state_file, &args, true).await?;
+  let mut entries = Vec::new();
+  for state in &states {
+    entries.extend(state_plan(cli_conf, opts, state).await?);
+  }
+  print_plan(&entries);
+  Ok(())
+}
+
 /// Function called when running `nanocl state apply`
 async fn exec_state_apply(
   cli_conf: &CliConfig,
@@ -894,7 +1158,8 @@ async fn exec_state_apply(
   let format = cli_conf.user_config.display_format.clone();
   let state_file = read_state_file(&opts.state_location, &format).await?;
   let args = parse_build_args(&state_file.data, &opts.args)?;
-  let states = parse_state_file_recurr(cli_conf, &state_file, &args).await?;
+  let states =
+    parse_state_file_recurr(cli_conf, &state_file, &args, false).await?;
   if !opts.skip_confirm {
     print_states(&states);
     utils::dialog::confirm("Are you sure to apply this state ?")
@@ -966,7 +1231,8 @@ async fn exec_state_logs(
   let format = cli_conf.user_config.display_format.clone();
   let state_file = read_state_file(&opts.state_location, &format).await?;
   let args = parse_build_args(&state_file.data, &opts.args)?;
-  let states = parse_state_file_recurr(cli_conf, &state_file, &args).await?;
+  let states =
+    parse_state_file_recurr(cli_conf, &state_file, &args, false).await?;
   states
     .iter()
     .map(|state| state_logs(cli_conf, opts, state))
@@ -1012,7 +1278,9 @@ async fn state_remove(
       .iter()
       .map(|resource| resource.name.clone())
       .collect();
-    let _ = ResourceArg::exec_rm(client, &gen_rm_opts, None).await;
+    let _ =
+      ResourceArg::exec_rm(client, &gen_rm_opts, Some(namespace.to_owned()))
+        .await;
   }
   if let Some(secrets) = &state_file.data.secrets {
     gen_rm_opts.keys =
@@ -1031,7 +1299,7 @@ async fn exec_state_remove(
   let state_file = read_state_file(&opts.state_location, &format).await?;
   let args = parse_build_args(&state_file.data, &opts.args)?;
   let state_files =
-    parse_state_file_recurr(cli_conf, &state_file, &args).await?;
+    parse_state_file_recurr(cli_conf, &state_file, &args, false).await?;
   if !opts.skip_confirm {
     print_states(&state_files);
     utils::dialog::confirm("Are you sure to remove this state ?")
@@ -1047,6 +1315,7 @@ async fn exec_state_remove(
 pub async fn exec_state(cli_conf: &CliConfig, args: &StateArg) -> IoResult<()> {
   match &args.command {
     StateCommand::Apply(opts) => exec_state_apply(cli_conf, opts).await,
+    StateCommand::Plan(opts) => exec_state_plan(cli_conf, opts).await,
     StateCommand::Remove(opts) => exec_state_remove(cli_conf, opts).await,
     StateCommand::Logs(opts) => exec_state_logs(cli_conf, opts).await,
   }
@@ -68,9 +68,13 @@ fn create_cli_config(cli_args: &Cli) -> IoResult<CliConfig> {
   if let Ok(h) = std::env::var("HOST") {
     host = h;
   }
+  let token = std::env::var("NANOCL_TOKEN")
+    .ok()
+    .or(endpoint.token.clone());
   let client = NanocldClient::connect_to(&ConnectOpts {
     url: host.clone(),
     ssl,
+    token,
     ..Default::default()
   })?;
   Ok(CliConfig {
@@ -90,6 +94,8 @@ async fn execute_arg(cli_args: &Cli) -> IoResult<()> {
     Command::Resource(args) => commands::exec_resource(&cli_conf, args).await,
     Command::Cargo(args) => commands::exec_cargo(&cli_conf, args).await,
     Command::Secret(args) => commands::exec_secret(&cli_conf, args).await,
+    Command::Role(args) => commands::exec_role(&cli_conf, args).await,
+    Command::Identity(args) => commands::exec_identity(&cli_conf, args).await,
     Command::Event(args) => commands::exec_event(&cli_conf, args).await,
     Command::State(args) => commands::exec_state(&cli_conf, args).await,
     Command::Version => commands::exec_version(&cli_conf).await,
@@ -333,7 +339,7 @@ mod tests {
     assert_cli_ok!("resource", "history", "deploy-example.com");
     let client = get_test_client();
     let history = client
-      .list_history_resource("deploy-example.com")
+      .list_history_resource("deploy-example.com", None)
       .await
       .unwrap()
       .first()
@@ -613,6 +619,7 @@ mod tests {
 
   #[ntex::test]
   async fn state() {
+    assert_cli_ok!("state", "plan", "-s", "../../examples/deploy_example.yml",);
     assert_cli_ok!(
       "state",
       "apply",
@@ -635,6 +642,14 @@ mod tests {
       "../../examples/deploy_example.yml",
     );
     assert_cli_ok!("state", "logs", "-s", "../../examples/deploy_example.yml");
+    assert_cli_ok!(
+      "state",
+      "plan",
+      "-r",
+      "--remove-orphans",
+      "-s",
+      "../../examples/deploy_example.yml",
+    );
     assert_cli_ok!("state", "rm", "-ys", "../../examples/deploy_example.yml");
     assert_cli_ok!("state", "apply", "-ys", "../../examples/cargo_example.yml");
     assert_cli_ok!("state", "apply", "-ys", "../../examples/cargo_example.yml");
@@ -733,6 +748,29 @@ mod tests {
     assert_cli_ok!("metric", "ls", "--offset", "1");
     assert_cli_ok!("metric", "ls", "--limit", "2", "--offset", "1");
     assert_cli_ok!("metric", "ls", "-q", "--limit", "2", "--offset", "1");
+    assert_cli_ok!("metric", "aggregate", "nanocl.io/metrs");
+    assert_cli_ok!(
+      "metric",
+      "aggregate",
+      "ncproxy.io/http",
+      "--interval",
+      "300",
+      "--group-by",
+      "host,status",
+      "--fields",
+      "request_time",
+    );
+    assert_cli_ok!(
+      "metric",
+      "aggregate",
+      "nanocl.io/metrs",
+      "-g",
+      "node_name",
+      "-f",
+      "Memory.used",
+      "--display",
+      "json",
+    );
   }
 
   #[ntex::test]
@@ -781,6 +819,24 @@ mod tests {
     assert_cli_ok!("secret", "rm", "-y", "test-cli");
   }
 
+  #[ntex::test]
+  async fn auth() {
+    assert_cli_ok!("role", "ls");
+    assert_cli_err!("role", "create", "test-cli", "--kind", "cargoes");
+    assert_cli_ok!(
+      "role", "create", "test-cli", "--kind", "cargoes", "--verb", "read"
+    );
+    assert_cli_ok!("role", "inspect", "test-cli");
+    assert_cli_ok!("identity", "ls");
+    assert_cli_ok!("identity", "create", "test-cli", "--role", "test-cli");
+    assert_cli_ok!("identity", "update", "test-cli", "--subject", "test-cli");
+    assert_cli_ok!("identity", "token", "create", "test-cli");
+    assert_cli_ok!("identity", "token", "ls", "test-cli");
+    assert_cli_ok!("identity", "inspect", "test-cli");
+    assert_cli_ok!("identity", "rm", "-y", "test-cli");
+    assert_cli_ok!("role", "rm", "-y", "test-cli");
+  }
+
   #[ntex::test]
   async fn virtual_machine() {
     assert_cli_ok!(
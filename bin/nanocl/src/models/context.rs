@@ -38,6 +38,9 @@ pub struct ContextEndpoint {
   pub host: String,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub ssl: Option<SslConfig>,
+  /// Api token used to authenticate to the daemon
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub token: Option<String>,
 }
 
 /// A context metadata definition
@@ -72,6 +75,7 @@ impl std::default::Default for Context {
             host: std::env::var("NANOCL_HOST")
               .unwrap_or("unix:///run/nanocl/nanocl.sock".into()),
             ssl: None,
+            token: None,
           },
         );
         map
@@ -0,0 +1,210 @@
+use chrono::TimeZone;
+use clap::{Parser, Subcommand};
+use tabled::Tabled;
+
+use nanocld_client::stubs::auth::{
+  ApiToken, ApiTokenPartial, Identity, IdentityPartial, IdentityUpdate,
+};
+
+use super::{GenericInspectOpts, GenericListOpts, GenericRemoveOpts};
+
+/// `nanocl identity` available commands
+#[derive(Clone, Subcommand)]
+pub enum IdentityCommand {
+  /// Remove existing identity
+  #[clap(alias("rm"))]
+  Remove(GenericRemoveOpts),
+  /// List existing identity
+  #[clap(alias("ls"))]
+  List(GenericListOpts),
+  /// Inspect an identity
+  Inspect(GenericInspectOpts),
+  /// Create a new identity
+  Create(IdentityCreateOpts),
+  /// Update the roles or the certificate subject of an identity
+  Update(IdentityUpdateOpts),
+  /// Manage the api tokens of an identity
+  Token(IdentityTokenArg),
+}
+
+/// `nanocl identity` available arguments
+#[derive(Clone, Parser)]
+pub struct IdentityArg {
+  /// Identity command
+  #[clap(subcommand)]
+  pub command: IdentityCommand,
+}
+
+/// `nanocl identity create` available options
+#[derive(Clone, Parser)]
+pub struct IdentityCreateOpts {
+  /// Name of the identity
+  pub name: String,
+  /// Roles bound to the identity
+  #[clap(long = "role", short = 'r')]
+  pub roles: Vec<String>,
+  /// Common name of the client certificate authenticating as this identity
+  #[clap(long)]
+  pub subject: Option<String>,
+}
+
+impl From<IdentityCreateOpts> for IdentityPartial {
+  fn from(opts: IdentityCreateOpts) -> Self {
+    Self {
+      name: opts.name,
+      roles: opts.roles,
+      subject: opts.subject,
+    }
+  }
+}
+
+/// `nanocl identity update` available options
+#[derive(Clone, Parser)]
+pub struct IdentityUpdateOpts {
+  /// Name of the identity
+  pub name: String,
+  /// Replace the roles bound to the identity
+  #[clap(long = "role", short = 'r')]
+  pub roles: Option<Vec<String>>,
+  /// Common name of the client certificate authenticating as this identity
+  #[clap(long)]
+  pub subject: Option<String>,
+}
+
+impl From<IdentityUpdateOpts> for IdentityUpdate {
+  fn from(opts: IdentityUpdateOpts) -> Self {
+    Self {
+      roles: opts.roles,
+      subject: opts.subject,
+    }
+  }
+}
+
+/// `nanocl identity token` available commands
+#[derive(Clone, Subcommand)]
+pub enum IdentityTokenCommand {
+  /// Create a new api token, it's only displayed once
+  Create(IdentityTokenCreateOpts),
+  /// List the api tokens of an identity
+  #[clap(alias("ls"))]
+  List(IdentityTokenListOpts),
+  /// Revoke api tokens of an identity
+  #[clap(alias("rm"))]
+  Remove(IdentityTokenRemoveOpts),
+}
+
+/// `nanocl identity token` available arguments
+#[derive(Clone, Parser)]
+pub struct IdentityTokenArg {
+  /// Identity token command
+  #[clap(subcommand)]
+  pub command: IdentityTokenCommand,
+}
+
+/// `nanocl identity token create` available options
+#[derive(Clone, Parser)]
+pub struct IdentityTokenCreateOpts {
+  /// Name of the identity
+  pub name: String,
+  /// Number of seconds before the token expire, never expire if not set
+  #[clap(long)]
+  pub expires_in: Option<u64>,
+}
+
+impl From<IdentityTokenCreateOpts> for ApiTokenPartial {
+  fn from(opts: IdentityTokenCreateOpts) -> Self {
+    Self {
+      expires_in: opts.expires_in,
+    }
+  }
+}
+
+/// `nanocl identity token ls` available options
+#[derive(Clone, Parser)]
+pub struct IdentityTokenListOpts {
+  /// Name of the identity
+  pub name: String,
+  /// Only show keys
+  #[clap(long, short)]
+  pub quiet: bool,
+}
+
+/// `nanocl identity token rm` available options
+#[derive(Clone, Parser)]
+pub struct IdentityTokenRemoveOpts {
+  /// Name of the identity
+  pub name: String,
+  /// Keys of the tokens to revoke
+  #[clap(required = true)]
+  pub keys: Vec<String>,
+  #[clap(short = 'y', long)]
+  pub skip_confirm: bool,
+}
+
+/// Format a date in the current timezone
+fn format_date(date: &chrono::NaiveDateTime) -> String {
+  let binding = chrono::Local::now();
+  let tz = binding.offset();
+  tz.timestamp_opt(date.and_utc().timestamp(), 0)
+    .unwrap()
+    .format("%Y-%m-%d %H:%M:%S")
+    .to_string()
+}
+
+/// A row of the identity table
+#[derive(Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct IdentityRow {
+  /// The name of the identity
+  pub name: String,
+  /// The roles bound to the identity
+  pub roles: String,
+  /// The certificate subject of the identity
+  pub subject: String,
+  /// When the identity have been created
+  #[tabled(rename = "CREATED AT")]
+  pub created_at: String,
+  /// When the identity have been updated
+  #[tabled(rename = "UPDATED AT")]
+  pub updated_at: String,
+}
+
+impl From<Identity> for IdentityRow {
+  fn from(identity: Identity) -> Self {
+    Self {
+      name: identity.name,
+      roles: identity.roles.join(","),
+      subject: identity.subject.unwrap_or_default(),
+      created_at: format_date(&identity.created_at),
+      updated_at: format_date(&identity.updated_at),
+    }
+  }
+}
+
+/// A row of the api token table
+#[derive(Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct ApiTokenRow {
+  /// The key of the token
+  pub key: String,
+  /// When the token have been created
+  #[tabled(rename = "CREATED AT")]
+  pub created_at: String,
+  /// When the token expire
+  #[tabled(rename = "EXPIRES AT")]
+  pub expires_at: String,
+}
+
+impl From<ApiToken> for ApiTokenRow {
+  fn from(token: ApiToken) -> Self {
+    Self {
+      key: token.key.to_string(),
+      created_at: format_date(&token.created_at),
+      expires_at: token
+        .expires_at
+        .as_ref()
+        .map(format_date)
+        .unwrap_or("never".to_owned()),
+    }
+  }
+}
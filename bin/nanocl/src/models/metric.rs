@@ -2,9 +2,11 @@ use chrono::TimeZone;
 use clap::{Parser, Subcommand};
 use tabled::Tabled;
 
-use nanocld_client::stubs::metric::Metric;
+use nanocld_client::stubs::metric::{
+  Metric, MetricAggregate, MetricAggregateQuery,
+};
 
-use super::{GenericInspectOpts, GenericListOpts};
+use super::{DisplayFormat, GenericInspectOpts, GenericListOpts};
 
 #[derive(Clone, Parser)]
 pub struct MetricArg {
@@ -20,6 +22,46 @@ pub enum MetricCommand {
   List(GenericListOpts),
   /// Inspect a metric
   Inspect(GenericInspectOpts),
+  /// Aggregate the metrics of a kind by time bucket
+  Aggregate(MetricAggregateOpts),
+}
+
+/// `nanocl metric aggregate` available options
+#[derive(Clone, Parser)]
+pub struct MetricAggregateOpts {
+  /// Width of the time buckets in seconds
+  #[clap(long, short)]
+  pub interval: Option<u64>,
+  /// Start of the time range in UTC, like 2024-12-24T00:00:00
+  #[clap(long)]
+  pub since: Option<chrono::NaiveDateTime>,
+  /// End of the time range in UTC, now by default
+  #[clap(long)]
+  pub until: Option<chrono::NaiveDateTime>,
+  /// Comma separated fields to group by, like host,status or node_name
+  #[clap(long, short)]
+  pub group_by: Option<String>,
+  /// Comma separated numeric fields to aggregate, like request_time
+  #[clap(long, short)]
+  pub fields: Option<String>,
+  /// Display format instead of a table
+  #[clap(long)]
+  pub display: Option<DisplayFormat>,
+  /// Kind of the metrics, like ncproxy.io/http
+  pub kind: String,
+}
+
+impl From<&MetricAggregateOpts> for MetricAggregateQuery {
+  fn from(opts: &MetricAggregateOpts) -> Self {
+    Self {
+      kind: opts.kind.clone(),
+      interval: opts.interval,
+      since: opts.since,
+      until: opts.until,
+      group_by: opts.group_by.clone(),
+      fields: opts.fields.clone(),
+    }
+  }
 }
 
 #[derive(Clone, Tabled)]
@@ -33,21 +75,92 @@ pub struct MetricRow {
   pub note: String,
 }
 
+/// Format a date in UTC to the current timezone
+fn format_local(date: &chrono::NaiveDateTime) -> String {
+  let binding = chrono::Local::now();
+  let tz = binding.offset();
+  tz.timestamp_opt(date.and_utc().timestamp(), 0)
+    .unwrap()
+    .format("%Y-%m-%d %H:%M:%S")
+    .to_string()
+}
+
 impl From<Metric> for MetricRow {
   fn from(metric: Metric) -> Self {
-    let binding = chrono::Local::now();
-    let tz = binding.offset();
-    // Convert the created_at and updated_at to the current timezone
-    let created_at = tz
-      .timestamp_opt(metric.created_at.and_utc().timestamp(), 0)
-      .unwrap()
-      .format("%Y-%m-%d %H:%M:%S");
+    // Convert the created_at to the current timezone
+    let created_at = format_local(&metric.created_at);
     Self {
       key: metric.key.to_string(),
-      created_at: created_at.to_string(),
+      created_at,
       kind: metric.kind,
       node: metric.node_name,
       note: metric.note.unwrap_or("<none>".to_owned()),
     }
   }
 }
+
+/// A row of the aggregated values of a field in a time bucket
+#[derive(Clone, Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct MetricAggregateRow {
+  pub bucket: String,
+  pub group: String,
+  pub count: i64,
+  pub field: String,
+  pub avg: String,
+  pub p50: String,
+  pub p95: String,
+  pub p99: String,
+  pub max: String,
+}
+
+impl MetricAggregateRow {
+  /// Rows of an aggregate, one by aggregated field
+  pub fn from_aggregate(aggregate: &MetricAggregate) -> Vec<Self> {
+    let bucket = format_local(&aggregate.bucket);
+    let group = aggregate
+      .group
+      .iter()
+      .map(|(name, value)| {
+        format!("{name}={}", value.as_deref().unwrap_or("<none>"))
+      })
+      .collect::<Vec<_>>()
+      .join(" ");
+    let group = if group.is_empty() {
+      "<none>".to_owned()
+    } else {
+      group
+    };
+    let format = |value: Option<f64>| match value {
+      Some(value) => format!("{value:.3}"),
+      None => "<none>".to_owned(),
+    };
+    let row = Self {
+      bucket,
+      group,
+      count: aggregate.count,
+      field: "<none>".to_owned(),
+      avg: "<none>".to_owned(),
+      p50: "<none>".to_owned(),
+      p95: "<none>".to_owned(),
+      p99: "<none>".to_owned(),
+      max: "<none>".to_owned(),
+    };
+    if aggregate.fields.is_empty() {
+      return vec![row];
+    }
+    aggregate
+      .fields
+      .iter()
+      .map(|field| Self {
+        field: field.field.clone(),
+        avg: format(field.avg),
+        p50: format(field.p50),
+        p95: format(field.p95),
+        p99: format(field.p99),
+        max: format(field.max),
+        ..row.clone()
+      })
+      .collect()
+  }
+}
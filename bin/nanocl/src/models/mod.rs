@@ -7,6 +7,7 @@ mod cargo;
 mod context;
 mod event;
 mod generic;
+mod identity;
 mod install;
 mod job;
 mod metric;
@@ -14,6 +15,7 @@ mod namespace;
 mod node;
 mod process;
 mod resource;
+mod role;
 mod secret;
 mod state;
 mod uninstall;
@@ -26,6 +28,7 @@ pub use cargo::*;
 pub use context::*;
 pub use event::*;
 pub use generic::*;
+pub use identity::*;
 pub use install::*;
 pub use job::*;
 pub use metric::*;
@@ -33,6 +36,7 @@ pub use namespace::*;
 pub use node::*;
 pub use process::*;
 pub use resource::*;
+pub use role::*;
 pub use secret::*;
 pub use state::*;
 pub use uninstall::*;
@@ -95,6 +99,10 @@ pub enum Command {
   Namespace(NamespaceArg),
   /// Manage secrets
   Secret(SecretArg),
+  /// Manage roles of the api
+  Role(RoleArg),
+  /// Manage identities of the api and their tokens
+  Identity(IdentityArg),
   /// Manage jobs
   Job(JobArg),
   /// Manage cargoes
@@ -90,17 +90,16 @@ impl From<Process> for ProcessRow {
     let config = container.config.unwrap_or_default();
     let network = container.network_settings.unwrap_or_default();
     let networks = network.networks.unwrap_or_default();
-    let mut ip_addr = if let Some(network) = networks.get("nanoclbr0") {
+    let network_mode = container
+      .host_config
+      .unwrap_or_default()
+      .network_mode
+      .unwrap_or("<none>".to_owned());
+    // Workloads are attached to the network of their namespace
+    let mut ip_addr = if let Some(network) = networks.get(&network_mode) {
       network.ip_address.clone().unwrap_or("<none>".to_owned())
     } else {
-      format!(
-        "<{}>",
-        container
-          .host_config
-          .unwrap_or_default()
-          .network_mode
-          .unwrap_or("<none>".to_owned())
-      )
+      format!("<{network_mode}>")
     };
     if ip_addr.is_empty() {
       "<none>".clone_into(&mut ip_addr);
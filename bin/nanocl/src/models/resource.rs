@@ -21,11 +21,17 @@ pub enum ResourceCommand {
   History(ResourceHistoryOpts),
   /// Revert a resource to a specific history
   Revert(ResourceRevertOpts),
+  /// Migrate resources to another version of their kind
+  Migrate(ResourceMigrateOpts),
 }
 
 /// `nanocl resource` available arguments
 #[derive(Clone, Parser)]
 pub struct ResourceArg {
+  /// namespace to target by default global is used,
+  /// every namespace is listed when not set
+  #[clap(long, short)]
+  pub namespace: Option<String>,
   #[clap(subcommand)]
   pub command: ResourceCommand,
 }
@@ -36,8 +42,12 @@ pub struct ResourceArg {
 pub struct ResourceRow {
   /// Name of the resource
   pub name: String,
+  /// Namespace of the resource
+  pub namespace: String,
   /// Kind of resource
   pub kind: String,
+  /// Status of the resource with the controller of its kind
+  pub status: String,
   /// When the resource was created
   #[tabled(rename = "CREATED AT")]
   pub created_at: String,
@@ -61,8 +71,10 @@ impl From<Resource> for ResourceRow {
       .unwrap()
       .format("%Y-%m-%d %H:%M:%S");
     Self {
-      name: resource.spec.resource_key,
+      name: resource.name,
+      namespace: resource.namespace_name,
       kind: format!("{}/{}", resource.kind, resource.spec.version),
+      status: resource.status.phase.to_string(),
       created_at: format!("{created_at}"),
       updated_at: format!("{updated_at}"),
     }
@@ -84,3 +96,14 @@ pub struct ResourceRevertOpts {
   /// The key of the history to revert to
   pub key: String,
 }
+
+/// `nanocl resource migrate` available options
+#[derive(Clone, Parser)]
+pub struct ResourceMigrateOpts {
+  /// Version to migrate to, the current version of the kind by default
+  #[clap(long)]
+  pub version: Option<String>,
+  /// Names of the resources to migrate
+  #[clap(required = true)]
+  pub names: Vec<String>,
+}
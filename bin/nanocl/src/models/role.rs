@@ -0,0 +1,116 @@
+use chrono::TimeZone;
+use clap::{Parser, Subcommand};
+use tabled::Tabled;
+
+use nanocld_client::stubs::auth::{Role, RolePartial, RoleRule, RoleVerb};
+
+use super::{GenericInspectOpts, GenericListOpts, GenericRemoveOpts};
+
+/// `nanocl role` available commands
+#[derive(Clone, Subcommand)]
+pub enum RoleCommand {
+  /// Remove existing role
+  #[clap(alias("rm"))]
+  Remove(GenericRemoveOpts),
+  /// List existing role
+  #[clap(alias("ls"))]
+  List(GenericListOpts),
+  /// Inspect a role
+  Inspect(GenericInspectOpts),
+  /// Create a new role
+  Create(RoleCreateOpts),
+}
+
+/// `nanocl role` available arguments
+#[derive(Clone, Parser)]
+pub struct RoleArg {
+  /// Role command
+  #[clap(subcommand)]
+  pub command: RoleCommand,
+}
+
+/// Parse a verb given in the command line
+fn parse_role_verb(value: &str) -> Result<RoleVerb, String> {
+  match value.to_lowercase().as_str() {
+    "read" => Ok(RoleVerb::Read),
+    "write" => Ok(RoleVerb::Write),
+    "exec" => Ok(RoleVerb::Exec),
+    "all" => Ok(RoleVerb::All),
+    _ => Err(format!(
+      "invalid verb {value} expected read, write, exec or all"
+    )),
+  }
+}
+
+/// `nanocl role create` available options
+#[derive(Clone, Parser)]
+pub struct RoleCreateOpts {
+  /// Name of the role
+  pub name: String,
+  /// Kinds of objects allowed (eg: cargoes, secrets) or `*` for every kind
+  #[clap(long = "kind", short = 'k', required = true)]
+  pub kinds: Vec<String>,
+  /// Namespaces where the role apply, every namespace by default
+  #[clap(long = "namespace", short = 'n')]
+  pub namespaces: Vec<String>,
+  /// Verbs allowed: read, write, exec or all
+  #[clap(long = "verb", short = 'v', required = true, value_parser = parse_role_verb)]
+  pub verbs: Vec<RoleVerb>,
+}
+
+impl From<RoleCreateOpts> for RolePartial {
+  fn from(opts: RoleCreateOpts) -> Self {
+    let namespaces = if opts.namespaces.is_empty() {
+      vec!["*".to_owned()]
+    } else {
+      opts.namespaces
+    };
+    Self {
+      name: opts.name,
+      rules: vec![RoleRule {
+        kinds: opts.kinds,
+        namespaces,
+        verbs: opts.verbs,
+      }],
+    }
+  }
+}
+
+/// A row of the role table
+#[derive(Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct RoleRow {
+  /// The name of the role
+  pub name: String,
+  /// The number of rules of the role
+  pub rules: usize,
+  /// When the role have been created
+  #[tabled(rename = "CREATED AT")]
+  pub created_at: String,
+  /// When the role have been updated
+  #[tabled(rename = "UPDATED AT")]
+  pub updated_at: String,
+}
+
+impl From<Role> for RoleRow {
+  fn from(role: Role) -> Self {
+    // Get the current timezone
+    let binding = chrono::Local::now();
+    let tz = binding.offset();
+    // Convert the created_at and updated_at to the current timezone
+    let created_at = tz
+      .timestamp_opt(role.created_at.and_utc().timestamp(), 0)
+      .unwrap()
+      .format("%Y-%m-%d %H:%M:%S");
+    let updated_at = tz
+      .timestamp_opt(role.updated_at.and_utc().timestamp(), 0)
+      .unwrap()
+      .format("%Y-%m-%d %H:%M:%S");
+    Self {
+      name: role.name,
+      rules: role.rules.len(),
+      created_at: format!("{created_at}"),
+      updated_at: format!("{updated_at}"),
+    }
+  }
+}
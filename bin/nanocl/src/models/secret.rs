@@ -21,6 +21,8 @@ pub enum SecretCommand {
   Inspect(GenericInspectOpts),
   /// Create a new secret
   Create(SecretCreateOpts),
+  /// Generate a new encryption key and encrypt every secret with it
+  RotateKey,
 }
 
 /// `nanocl secret` available arguments
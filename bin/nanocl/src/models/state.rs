@@ -70,11 +70,30 @@ pub struct StateRemoveOpts {
   pub args: Vec<String>,
 }
 
+/// `nanocl state plan` available options
+#[derive(Parser, Clone)]
+pub struct StatePlanOpts {
+  /// Path or Url to the Statefile
+  #[clap(long, short = 's')]
+  pub state_location: Option<String>,
+  /// Plan an apply even if state didn't changed
+  #[clap(long, short = 'r')]
+  pub reload: bool,
+  /// Additional arguments to pass to the file
+  #[clap(last = true, raw = true)]
+  pub args: Vec<String>,
+  /// Include orphaned elements that would be removed
+  #[clap(long)]
+  pub remove_orphans: bool,
+}
+
 /// `nanocl state` available commands
 #[derive(Subcommand)]
 pub enum StateCommand {
   /// Create or Update elements from a Statefile
   Apply(StateApplyOpts),
+  /// Show what an apply of a Statefile would change
+  Plan(StatePlanOpts),
   /// Logs elements from a Statefile
   Logs(StateLogsOpts),
   /// Remove elements from a Statefile
@@ -126,3 +145,54 @@ where
   /// Path to the Statefile
   pub location: String,
 }
+
+/// Action `nanocl state apply` would perform on an element
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatePlanAction {
+  Create,
+  Update,
+  /// The element is deleted and created again
+  Replace,
+  Delete,
+  Unchanged,
+}
+
+impl Display for StatePlanAction {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    match self {
+      StatePlanAction::Create => write!(f, "create"),
+      StatePlanAction::Update => write!(f, "update"),
+      StatePlanAction::Replace => write!(f, "replace"),
+      StatePlanAction::Delete => write!(f, "delete"),
+      StatePlanAction::Unchanged => write!(f, "unchanged"),
+    }
+  }
+}
+
+/// A field that differ between the current element and the Statefile
+#[derive(Clone, Debug, PartialEq)]
+pub struct StatePlanChange {
+  /// Path of the field like `Container.Env[0]`
+  pub path: String,
+  /// Current value, none if the field is added
+  pub before: Option<serde_json::Value>,
+  /// Wanted value, none if the field is removed
+  pub after: Option<serde_json::Value>,
+}
+
+/// Planned action on an element of a Statefile
+#[derive(Clone, Debug)]
+pub struct StatePlanEntry {
+  /// Kind of the element like `cargo` or `secret`
+  pub kind: String,
+  /// Name of the element
+  pub name: String,
+  /// Namespace of the element if it's namespaced
+  pub namespace: Option<String>,
+  /// Action that would be performed
+  pub action: StatePlanAction,
+  /// If running instances would be restarted
+  pub restart: bool,
+  /// Fields that would change
+  pub changes: Vec<StatePlanChange>,
+}
@@ -1,7 +1,7 @@
 use liquid::ObjectView;
 use regex::Regex;
 
-use crate::models::{DisplayFormat, StateRef, StateRoot};
+use crate::models::{DisplayFormat, StatePlanChange, StateRef, StateRoot};
 use nanocl_error::io::{FromIo, IoError, IoResult};
 
 use super::liquid::StateSource;
@@ -157,3 +157,116 @@ pub fn compile(
   })?;
   Ok(output)
 }
+
+/// Compare two serialized elements and return the fields that differ.
+/// A null value is considered as a missing field.
+pub fn diff_value(
+  before: &serde_json::Value,
+  after: &serde_json::Value,
+) -> Vec<StatePlanChange> {
+  let mut changes = Vec::new();
+  diff_value_at("", before, after, &mut changes);
+  changes
+}
+
+fn diff_value_at(
+  path: &str,
+  before: &serde_json::Value,
+  after: &serde_json::Value,
+  changes: &mut Vec<StatePlanChange>,
+) {
+  use serde_json::{Map, Value};
+  match (before, after) {
+    (Value::Object(_), Value::Object(_) | Value::Null)
+    | (Value::Null, Value::Object(_)) => {
+      let empty = Map::new();
+      let before = before.as_object().unwrap_or(&empty);
+      let after = after.as_object().unwrap_or(&empty);
+      let mut keys = before.keys().chain(after.keys()).collect::<Vec<_>>();
+      keys.sort();
+      keys.dedup();
+      for key in keys {
+        let path = if path.is_empty() {
+          key.to_owned()
+        } else {
+          format!("{path}.{key}")
+        };
+        diff_value_at(
+          &path,
+          before.get(key).unwrap_or(&Value::Null),
+          after.get(key).unwrap_or(&Value::Null),
+          changes,
+        );
+      }
+    }
+    (Value::Array(before), Value::Array(after)) => {
+      for index in 0..before.len().max(after.len()) {
+        diff_value_at(
+          &format!("{path}[{index}]"),
+          before.get(index).unwrap_or(&Value::Null),
+          after.get(index).unwrap_or(&Value::Null),
+          changes,
+        );
+      }
+    }
+    (before, after) if before != after => {
+      let to_option = |value: &Value| match value {
+        Value::Null => None,
+        value => Some(value.clone()),
+      };
+      changes.push(StatePlanChange {
+        path: path.to_owned(),
+        before: to_option(before),
+        after: to_option(after),
+      });
+    }
+    _ => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn diff_value_fields() {
+    let before = json!({
+      "Name": "web",
+      "Container": {
+        "Image": "nginx:1.25",
+        "Env": ["A=1", "B=2"],
+      },
+      "Metadata": null,
+      "Replication": { "Mode": "Auto" },
+    });
+    let after = json!({
+      "Name": "web",
+      "Container": {
+        "Image": "nginx:1.26",
+        "Env": ["A=1", "B=2", "C=3"],
+      },
+      "Metadata": { "io.nanocl.group": "web" },
+    });
+    let changes = diff_value(&before, &after);
+    let paths = changes
+      .iter()
+      .map(|change| change.path.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      paths,
+      vec![
+        "Container.Env[2]",
+        "Container.Image",
+        "Metadata.io.nanocl.group",
+        "Replication.Mode",
+      ]
+    );
+    assert_eq!(changes[0].before, None);
+    assert_eq!(changes[0].after, Some(json!("C=3")));
+    assert_eq!(changes[1].before, Some(json!("nginx:1.25")));
+    assert_eq!(changes[3].after, None);
+    assert!(diff_value(&before, &before).is_empty());
+  }
+}
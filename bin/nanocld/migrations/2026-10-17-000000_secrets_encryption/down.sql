@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS "secrets_key_id_idx";
+ALTER TABLE "secrets" DROP COLUMN IF EXISTS "key_id";
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE "secrets" ADD COLUMN IF NOT EXISTS "key_id" VARCHAR;
+
+CREATE INDEX IF NOT EXISTS "secrets_key_id_idx" ON "secrets" ("key_id");
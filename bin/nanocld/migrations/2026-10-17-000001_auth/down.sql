@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "api_tokens";
+DROP TABLE IF EXISTS "identities";
+DROP TABLE IF EXISTS "roles";
@@ -0,0 +1,35 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS "roles" (
+  "key" VARCHAR NOT NULL UNIQUE PRIMARY KEY,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "updated_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "rules" JSONB NOT NULL
+);
+
+CREATE INDEX "roles_key_idx" ON "roles" ("key");
+CREATE INDEX "roles_created_at_idx" ON "roles" ("created_at");
+
+CREATE TABLE IF NOT EXISTS "identities" (
+  "key" VARCHAR NOT NULL UNIQUE PRIMARY KEY,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "updated_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "subject" VARCHAR UNIQUE,
+  "roles" JSONB NOT NULL
+);
+
+CREATE INDEX "identities_key_idx" ON "identities" ("key");
+CREATE INDEX "identities_created_at_idx" ON "identities" ("created_at");
+CREATE INDEX "identities_subject_idx" ON "identities" ("subject");
+CREATE INDEX "identities_roles_idx" ON "identities" USING GIN ("roles");
+
+CREATE TABLE IF NOT EXISTS "api_tokens" (
+  "key" UUID NOT NULL UNIQUE PRIMARY KEY,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "identity_key" VARCHAR NOT NULL REFERENCES identities("key") ON DELETE CASCADE,
+  "token_hash" VARCHAR NOT NULL UNIQUE,
+  "expires_at" TIMESTAMPTZ
+);
+
+CREATE INDEX "api_tokens_key_idx" ON "api_tokens" ("key");
+CREATE INDEX "api_tokens_identity_key_idx" ON "api_tokens" ("identity_key");
+CREATE INDEX "api_tokens_token_hash_idx" ON "api_tokens" ("token_hash");
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE "jobs" ADD COLUMN IF NOT EXISTS "next_run_at" TIMESTAMPTZ;
+ALTER TABLE "jobs" ADD COLUMN IF NOT EXISTS "last_run_at" TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS "jobs_next_run_at_idx" ON "jobs" ("next_run_at");
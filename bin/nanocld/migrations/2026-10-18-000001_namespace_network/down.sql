@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "namespaces" DROP COLUMN IF EXISTS "network";
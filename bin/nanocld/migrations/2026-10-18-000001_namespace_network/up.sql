@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE "namespaces" ADD COLUMN IF NOT EXISTS "network" JSONB;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "namespaces" DROP COLUMN IF EXISTS "limit_range";
+ALTER TABLE "namespaces" DROP COLUMN IF EXISTS "quota";
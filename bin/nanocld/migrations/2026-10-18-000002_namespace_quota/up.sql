@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE "namespaces" ADD COLUMN IF NOT EXISTS "quota" JSONB;
+ALTER TABLE "namespaces" ADD COLUMN IF NOT EXISTS "limit_range" JSONB;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS "metrics_kind_created_at_idx";
+DROP TABLE IF EXISTS "metric_rollups";
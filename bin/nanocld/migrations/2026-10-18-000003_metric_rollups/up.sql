@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS "metric_rollups" (
+  "key" UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "expires_at" TIMESTAMPTZ NOT NULL,
+  "node_name" VARCHAR NOT NULL,
+  "kind" VARCHAR NOT NULL,
+  "resolution" BIGINT NOT NULL,
+  "bucket" TIMESTAMPTZ NOT NULL,
+  "labels" JSONB NOT NULL,
+  "count" BIGINT NOT NULL,
+  "fields" JSONB NOT NULL
+) WITH (ttl_expiration_expression = 'expires_at');
+
+CREATE INDEX "metric_rollups_kind_resolution_bucket_idx" ON "metric_rollups" ("kind", "resolution", "bucket");
+CREATE INDEX "metric_rollups_expires_at_idx" ON "metric_rollups" ("expires_at");
+CREATE INDEX "metrics_kind_created_at_idx" ON "metrics" ("kind", "created_at");
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS "resources_namespace_name_idx";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "namespace_name";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "name";
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "name" VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "namespace_name" VARCHAR NOT NULL DEFAULT 'global';
+
+CREATE INDEX IF NOT EXISTS "resources_namespace_name_idx" ON "resources" ("namespace_name");
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+UPDATE "specs" SET "kind_key" = "resources"."name"
+FROM "resources"
+WHERE "specs"."kind_name" = 'Resource'
+  AND "specs"."kind_key" = "resources"."key"
+  AND "resources"."namespace_name" = 'global';
+
+UPDATE "resources" SET "key" = "name", "name" = ''
+WHERE "namespace_name" = 'global';
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- The resources created before the namespaces move into `global`,
+-- their key and the key of their history become `<name>.global`
+INSERT INTO "namespaces" ("name") VALUES ('global') ON CONFLICT DO NOTHING;
+
+UPDATE "specs" SET "kind_key" = "kind_key" || '.global'
+WHERE "kind_name" = 'Resource'
+  AND "kind_key" IN (SELECT "key" FROM "resources" WHERE "name" = '');
+
+UPDATE "resources" SET "name" = "key", "key" = "key" || '.global'
+WHERE "name" = '';
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "resources" DROP CONSTRAINT IF EXISTS "resources_namespace_name_fkey";
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE "resources" ADD CONSTRAINT "resources_namespace_name_fkey"
+  FOREIGN KEY ("namespace_name") REFERENCES namespaces("name");
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS "resources_status_idx";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "status_updated_at";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "next_attempt_at";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "attempts";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "observed_spec_key";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "status_message";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "status";
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Existing resources start pending so the reconciler applies them once
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "status" VARCHAR NOT NULL DEFAULT 'pending';
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "status_message" VARCHAR;
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "observed_spec_key" UUID;
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "attempts" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "next_attempt_at" TIMESTAMPTZ;
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "status_updated_at" TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX IF NOT EXISTS "resources_status_idx" ON "resources" ("status");
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS "namespace_quota_locks" (
+  "namespace_name" VARCHAR NOT NULL PRIMARY KEY,
+  "token" UUID NOT NULL,
+  "expires_at" TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS "node_leases" (
+  "name" VARCHAR NOT NULL PRIMARY KEY,
+  "node_name" VARCHAR NOT NULL,
+  "expires_at" TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "jobs" ADD COLUMN IF NOT EXISTS "attempts" JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE "jobs" ADD COLUMN IF NOT EXISTS "steps" JSONB NOT NULL DEFAULT '[]';
+DROP TABLE IF EXISTS "job_runs";
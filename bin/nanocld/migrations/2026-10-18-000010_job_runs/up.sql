@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS "job_runs" (
+  "key" VARCHAR NOT NULL PRIMARY KEY,
+  "job_key" VARCHAR NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "attempts" JSONB NOT NULL DEFAULT '[]',
+  "steps" JSONB NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX IF NOT EXISTS "job_runs_job_key_idx" ON "job_runs" ("job_key");
+
+ALTER TABLE "jobs" DROP COLUMN IF EXISTS "attempts";
+ALTER TABLE "jobs" DROP COLUMN IF EXISTS "steps";
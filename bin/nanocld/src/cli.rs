@@ -42,6 +42,17 @@ pub struct Cli {
   /// Optional ssl options
   #[clap(flatten)]
   pub ssl: Option<SslConfig>,
+  /// Provider of the key used to encrypt secrets at rest
+  /// file:///path/to/keyring or env://VARIABLE
+  /// [default: file://{state_dir}/secret.key]
+  /// A cluster of nodes must share the key, the default is refused
+  /// once more than one node is registered
+  #[clap(long)]
+  pub secret_key: Option<String>,
+  /// Require tcp clients to authenticate with an api token
+  /// or a client certificate mapped to an identity
+  #[clap(long)]
+  pub enable_auth: bool,
 }
 
 impl Default for Cli {
@@ -58,6 +69,8 @@ impl Default for Cli {
       advertise_addr: None,
       gid: 0,
       ssl: None,
+      secret_key: None,
+      enable_auth: false,
     }
   }
 }
@@ -1,9 +1,9 @@
-use nanocl_stubs::config::{DaemonConfig, DaemonConfigFile};
+use nanocl_stubs::config::{DaemonConfig, DaemonConfigFile, MetricRetention};
 
 use nanocl_error::io::{FromIo, IoResult};
 use nanocl_utils::unix;
 
-use crate::cli::Cli;
+use crate::{cli::Cli, utils};
 
 /// Merge cli and config file together to generate the daemon config
 fn gen_daemon_conf(
@@ -58,6 +58,16 @@ fn gen_daemon_conf(
   } else {
     config.store_addr.clone()
   };
+  let secret_key = if let Some(ref secret_key) = args.secret_key {
+    Some(secret_key.to_owned())
+  } else {
+    config.secret_key.clone()
+  };
+  let metric_retention = config
+    .metric_retention
+    .clone()
+    .unwrap_or_else(MetricRetention::defaults);
+  utils::metric::validate_retention(&metric_retention)?;
   Ok(DaemonConfig {
     hosts,
     gateway,
@@ -70,6 +80,9 @@ fn gen_daemon_conf(
     nodes: args.nodes.clone(),
     conf_dir: args.conf_dir.clone(),
     ssl: args.ssl.clone(),
+    secret_key,
+    enable_auth: args.enable_auth || config.enable_auth.unwrap_or_default(),
+    metric_retention,
   })
 }
 
@@ -124,6 +137,9 @@ mod tests {
       store_addr: None,
       gateway: None,
       hostname: None,
+      secret_key: None,
+      enable_auth: None,
+      metric_retention: None,
     };
     let merged = gen_daemon_conf(&args, &config).unwrap();
     assert_eq!(merged.hosts, args.hosts.unwrap());
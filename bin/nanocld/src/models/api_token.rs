@@ -0,0 +1,49 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use nanocl_stubs::auth::ApiToken;
+
+use crate::schema::api_tokens;
+
+/// This structure represent an api token in the database.
+/// Only a hash of the token is stored, the token itself is returned once
+/// when it's created.
+#[derive(
+  Clone, Debug, Serialize, Deserialize, Queryable, Identifiable, Insertable,
+)]
+#[serde(rename_all = "PascalCase")]
+#[diesel(primary_key(key))]
+#[diesel(table_name = api_tokens)]
+pub struct ApiTokenDb {
+  /// The key of the token
+  pub key: uuid::Uuid,
+  /// The creation date
+  pub created_at: chrono::NaiveDateTime,
+  /// The name of the identity authenticated by the token
+  pub identity_key: String,
+  /// Sha256 hash of the token
+  pub token_hash: String,
+  /// When the token expire
+  pub expires_at: Option<chrono::NaiveDateTime>,
+}
+
+impl ApiTokenDb {
+  /// Check if the token can still be used
+  pub fn is_expired(&self) -> bool {
+    self
+      .expires_at
+      .is_some_and(|expires_at| expires_at <= chrono::Utc::now().naive_utc())
+  }
+}
+
+impl From<ApiTokenDb> for ApiToken {
+  fn from(db: ApiTokenDb) -> Self {
+    ApiToken {
+      key: db.key,
+      identity: db.identity_key,
+      created_at: db.created_at,
+      expires_at: db.expires_at,
+      token: None,
+    }
+  }
+}
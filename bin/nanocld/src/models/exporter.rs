@@ -0,0 +1,98 @@
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+  },
+};
+
+use nanocl_stubs::metric::HttpMetric;
+
+/// Upper bounds in seconds of the buckets of the proxy request duration
+pub const HTTP_DURATION_BUCKETS: [f64; 11] = [
+  0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Labels of the requests counted by the proxy
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HttpRequestLabels {
+  pub host: String,
+  pub method: String,
+  pub status: i64,
+}
+
+/// Cumulative histogram of the duration of the requests of a host
+#[derive(Debug, Clone, Default)]
+pub struct HttpDuration {
+  /// Number of requests for each bucket of `HTTP_DURATION_BUCKETS`
+  pub buckets: [u64; HTTP_DURATION_BUCKETS.len()],
+  /// Sum of the duration of the requests in seconds
+  pub sum: f64,
+  /// Number of requests
+  pub count: u64,
+}
+
+impl HttpDuration {
+  pub fn observe(&mut self, seconds: f64) {
+    for (bucket, bound) in
+      self.buckets.iter_mut().zip(HTTP_DURATION_BUCKETS.iter())
+    {
+      if seconds <= *bound {
+        *bucket += 1;
+      }
+    }
+    self.sum += seconds;
+    self.count += 1;
+  }
+}
+
+/// Counters of the proxied requests
+#[derive(Debug, Clone, Default)]
+pub struct HttpCounters {
+  /// Number of requests by host, method and status
+  pub requests: HashMap<HttpRequestLabels, u64>,
+  /// Duration of the requests by host
+  pub durations: HashMap<String, HttpDuration>,
+  /// Bytes sent to the clients by host
+  pub bytes_sent: HashMap<String, u64>,
+}
+
+/// Metrics kept in memory since the start of the daemon for the exporter
+#[derive(Debug, Default)]
+pub struct ExporterMetrics {
+  /// Counters of the requests pushed by ncproxy
+  pub http: Mutex<HttpCounters>,
+  /// Number of events handled by the event loop
+  pub events: AtomicU64,
+  /// Delay in microseconds between the creation of the last event and its handling
+  pub event_lag: AtomicU64,
+}
+
+impl ExporterMetrics {
+  /// Count a request from a `ncproxy.io/http` metric
+  pub fn observe_http(&self, metric: &HttpMetric) {
+    let Ok(mut http) = self.http.lock() else {
+      return;
+    };
+    let labels = HttpRequestLabels {
+      host: metric.host.clone(),
+      method: metric.request_method.clone(),
+      status: metric.status,
+    };
+    *http.requests.entry(labels).or_default() += 1;
+    http
+      .durations
+      .entry(metric.host.clone())
+      .or_default()
+      .observe(metric.request_time);
+    *http.bytes_sent.entry(metric.host.clone()).or_default() +=
+      metric.bytes_sent.max(0) as u64;
+  }
+
+  /// Record the handling of an event by the event loop
+  pub fn observe_event(&self, lag: chrono::Duration) {
+    self.events.fetch_add(1, Ordering::Relaxed);
+    let lag = lag.num_microseconds().unwrap_or_default().max(0) as u64;
+    self.event_lag.store(lag, Ordering::Relaxed);
+  }
+}
@@ -0,0 +1,85 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use nanocl_error::io::IoError;
+use nanocl_stubs::auth::{Identity, IdentityPartial, IdentityUpdate};
+
+use crate::schema::identities;
+
+/// This structure represent an identity in the database.
+/// An identity authenticate with api tokens or a client certificate
+/// and is authorized by the roles bound to it.
+#[derive(
+  Clone, Debug, Serialize, Deserialize, Queryable, Identifiable, Insertable,
+)]
+#[serde(rename_all = "PascalCase")]
+#[diesel(primary_key(key))]
+#[diesel(table_name = identities)]
+pub struct IdentityDb {
+  /// The name of the identity
+  pub key: String,
+  /// The creation date
+  pub created_at: chrono::NaiveDateTime,
+  /// The last update date
+  pub updated_at: chrono::NaiveDateTime,
+  /// Common name of the client certificate of the identity
+  pub subject: Option<String>,
+  /// Names of the roles bound to the identity
+  pub roles: serde_json::Value,
+}
+
+impl TryFrom<&IdentityPartial> for IdentityDb {
+  type Error = IoError;
+
+  fn try_from(identity: &IdentityPartial) -> Result<Self, Self::Error> {
+    Ok(Self {
+      key: identity.name.clone(),
+      created_at: chrono::Utc::now().naive_utc(),
+      updated_at: chrono::Utc::now().naive_utc(),
+      subject: identity.subject.clone(),
+      roles: serde_json::to_value(&identity.roles)?,
+    })
+  }
+}
+
+impl TryFrom<IdentityDb> for Identity {
+  type Error = IoError;
+
+  fn try_from(db: IdentityDb) -> Result<Self, Self::Error> {
+    Ok(Identity {
+      name: db.key,
+      created_at: db.created_at,
+      updated_at: db.updated_at,
+      subject: db.subject,
+      roles: serde_json::from_value(db.roles)?,
+    })
+  }
+}
+
+/// This structure is used to update an identity in the database.
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = identities)]
+pub struct IdentityUpdateDb {
+  /// The last update date
+  pub updated_at: Option<chrono::NaiveDateTime>,
+  /// Common name of the client certificate of the identity
+  pub subject: Option<String>,
+  /// Names of the roles bound to the identity
+  pub roles: Option<serde_json::Value>,
+}
+
+impl TryFrom<&IdentityUpdate> for IdentityUpdateDb {
+  type Error = IoError;
+
+  fn try_from(update: &IdentityUpdate) -> Result<Self, Self::Error> {
+    Ok(Self {
+      updated_at: Some(chrono::Utc::now().naive_utc()),
+      subject: update.subject.clone(),
+      roles: update
+        .roles
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()?,
+    })
+  }
+}
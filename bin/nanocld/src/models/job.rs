@@ -1,6 +1,6 @@
 use diesel::prelude::*;
 
-use crate::schema::jobs;
+use crate::schema::{job_runs, jobs};
 
 /// This structure represent a job to run.
 /// It will create and run a list of containers.
@@ -20,12 +20,44 @@ pub struct JobDb {
   pub data: serde_json::Value,
   /// The metadata
   pub metadata: Option<serde_json::Value>,
+  /// When the job will be started next by its schedule
+  pub next_run_at: Option<chrono::NaiveDateTime>,
+  /// When the job was last started by its schedule
+  pub last_run_at: Option<chrono::NaiveDateTime>,
 }
 
 /// This structure represent the update of a job.
 /// It will update the job with the new data.
-#[derive(Clone, AsChangeset)]
+#[derive(Clone, Default, AsChangeset)]
 #[diesel(table_name = jobs)]
 pub struct JobUpdateDb {
   pub updated_at: Option<chrono::NaiveDateTime>,
+  pub next_run_at: Option<Option<chrono::NaiveDateTime>>,
+  pub last_run_at: Option<Option<chrono::NaiveDateTime>>,
+}
+
+/// This structure represent a run of a job.
+/// Each run keeps its own progress so overlapping runs don't overwrite it.
+#[derive(Clone, Queryable, Identifiable, Insertable)]
+#[diesel(primary_key(key))]
+#[diesel(table_name = job_runs)]
+pub struct JobRunDb {
+  /// The id of the run
+  pub key: String,
+  /// The key of the job
+  pub job_key: String,
+  /// The created at date
+  pub created_at: chrono::NaiveDateTime,
+  /// The attempts of the containers during the run
+  pub attempts: serde_json::Value,
+  /// The state of the steps during the run
+  pub steps: serde_json::Value,
+}
+
+/// This structure represent the update of a job run.
+#[derive(Clone, Default, AsChangeset)]
+#[diesel(table_name = job_runs)]
+pub struct JobRunUpdateDb {
+  pub attempts: Option<serde_json::Value>,
+  pub steps: Option<serde_json::Value>,
 }
@@ -6,7 +6,10 @@ use nanocl_error::io::IoResult;
 
 use nanocl_stubs::metric::MetricPartial;
 
-use crate::{schema::metrics, utils};
+use crate::{
+  schema::{metric_rollups, metrics},
+  utils,
+};
 
 /// This structure represent a metric in the database.
 /// A metric is a data point that can be used to monitor the system.
@@ -46,16 +49,23 @@ pub struct MetricNodePartial {
   pub data: serde_json::Value,
   /// Optional note about the metric
   pub note: Option<String>,
+  /// When the metric will expire
+  pub expires_at: chrono::NaiveDateTime,
 }
 
 impl MetricNodePartial {
-  pub fn try_new_node(node_name: &str, item: &MetricPartial) -> IoResult<Self> {
+  pub fn try_new_node(
+    node_name: &str,
+    item: &MetricPartial,
+    expires_at: chrono::NaiveDateTime,
+  ) -> IoResult<Self> {
     utils::key::ensure_kind(&item.kind)?;
     Ok(MetricNodePartial {
       node_name: node_name.to_owned(),
       kind: item.kind.clone(),
       data: item.data.clone(),
       note: item.note.clone(),
+      expires_at,
     })
   }
 }
@@ -65,8 +75,7 @@ impl From<&MetricNodePartial> for MetricDb {
     MetricDb {
       key: Uuid::new_v4(),
       created_at: chrono::Utc::now().naive_utc(),
-      expires_at: chrono::Utc::now().naive_utc()
-        + chrono::Duration::try_days(30).unwrap(),
+      expires_at: p.expires_at,
       node_name: p.node_name.clone(),
       kind: p.kind.clone(),
       data: p.data.clone(),
@@ -81,3 +90,46 @@ impl From<&MetricNodePartial> for MetricDb {
 pub struct MetricNodeDb {
   pub node_name: String,
 }
+
+/// Row of the aggregation of the metrics by time bucket and group
+#[derive(Debug, QueryableByName)]
+pub struct MetricAggregateDb {
+  #[diesel(sql_type = diesel::sql_types::Timestamptz)]
+  pub bucket: chrono::NaiveDateTime,
+  /// Values of the grouped fields in the order of the query
+  #[diesel(sql_type = diesel::sql_types::Array<diesel::sql_types::Nullable<diesel::sql_types::Text>>)]
+  pub labels: Vec<Option<String>>,
+  #[diesel(sql_type = diesel::sql_types::BigInt)]
+  pub count: i64,
+  /// Aggregated values of the fields in the order of the query
+  #[diesel(sql_type = diesel::sql_types::Jsonb)]
+  pub fields: serde_json::Value,
+}
+
+/// Aggregated metrics of a kind in a time bucket for a node and a group,
+/// kept longer than the raw metrics to answer queries over large ranges
+#[derive(Debug, Clone, Insertable, Identifiable, Queryable)]
+#[diesel(primary_key(key))]
+#[diesel(table_name = metric_rollups)]
+pub struct MetricRollupDb {
+  /// The key of the rollup in the database `UUID`
+  pub key: Uuid,
+  /// When the rollup was created
+  pub created_at: chrono::NaiveDateTime,
+  /// When the rollup will expire
+  pub expires_at: chrono::NaiveDateTime,
+  /// The node who saved the metrics
+  pub node_name: String,
+  /// The kind of the metrics
+  pub kind: String,
+  /// Width of the time bucket in seconds
+  pub resolution: i64,
+  /// Start of the time bucket
+  pub bucket: chrono::NaiveDateTime,
+  /// Values of the grouped fields by name
+  pub labels: serde_json::Value,
+  /// Number of metrics
+  pub count: i64,
+  /// Aggregated values of the numeric fields by name
+  pub fields: serde_json::Value,
+}
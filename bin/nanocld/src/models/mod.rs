@@ -35,6 +35,15 @@ pub use resource_kind::*;
 mod secret;
 pub use secret::*;
 
+mod role;
+pub use role::*;
+
+mod identity;
+pub use identity::*;
+
+mod api_token;
+pub use api_token::*;
+
 mod job;
 pub use job::*;
 
@@ -53,6 +62,9 @@ pub use raw_emitter::*;
 mod task_manager;
 pub use task_manager::*;
 
+mod exporter;
+pub use exporter::*;
+
 mod object_process_status;
 pub use object_process_status::*;
 
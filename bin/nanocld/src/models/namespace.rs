@@ -1,9 +1,13 @@
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use nanocl_stubs::namespace::{Namespace, NamespacePartial};
+use nanocl_error::io::IoResult;
+use nanocl_stubs::namespace::{
+  Namespace, NamespaceLimitRange, NamespaceNetwork, NamespacePartial,
+  NamespaceQuota,
+};
 
-use crate::schema::namespaces;
+use crate::schema::{namespace_quota_locks, namespaces};
 
 /// This structure represent the namespace in the database.
 /// A namespace is a group of cargo or virtual machine that share the same network.
@@ -21,6 +25,25 @@ pub struct NamespaceDb {
   pub created_at: chrono::NaiveDateTime,
   /// User defined metadata
   pub metadata: Option<serde_json::Value>,
+  /// Network configuration
+  pub network: Option<serde_json::Value>,
+  /// Maximum objects and resources
+  pub quota: Option<serde_json::Value>,
+  /// Default and maximum resources of the workloads
+  pub limit_range: Option<serde_json::Value>,
+}
+
+/// Lease on the quota of a namespace held while an object is checked
+/// against the quota and created
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = namespace_quota_locks)]
+pub struct NamespaceQuotaLockDb {
+  /// The namespace whose quota is locked
+  pub namespace_name: String,
+  /// Identify the holder of the lease
+  pub token: uuid::Uuid,
+  /// When the lease expires if it isn't released
+  pub expires_at: chrono::NaiveDateTime,
 }
 
 impl NamespaceDb {
@@ -30,6 +53,9 @@ impl NamespaceDb {
       name: name.to_owned(),
       created_at: chrono::Utc::now().naive_utc(),
       metadata: None,
+      network: None,
+      quota: None,
+      limit_range: None,
     }
   }
 }
@@ -40,6 +66,46 @@ impl From<&NamespacePartial> for NamespaceDb {
       name: p.name.clone(),
       created_at: chrono::Utc::now().naive_utc(),
       metadata: p.metadata.clone(),
+      network: p
+        .network
+        .as_ref()
+        .and_then(|network| serde_json::to_value(network).ok()),
+      quota: p
+        .quota
+        .as_ref()
+        .and_then(|quota| serde_json::to_value(quota).ok()),
+      limit_range: p
+        .limit_range
+        .as_ref()
+        .and_then(|limit_range| serde_json::to_value(limit_range).ok()),
+    }
+  }
+}
+
+impl NamespaceDb {
+  /// Network configuration of the namespace
+  pub fn try_to_network(&self) -> IoResult<NamespaceNetwork> {
+    match &self.network {
+      None => Ok(NamespaceNetwork::default()),
+      Some(network) => Ok(serde_json::from_value(network.clone())?),
+    }
+  }
+
+  /// Quota of the namespace if any
+  pub fn try_to_quota(&self) -> IoResult<Option<NamespaceQuota>> {
+    match &self.quota {
+      None => Ok(None),
+      Some(quota) => Ok(Some(serde_json::from_value(quota.clone())?)),
+    }
+  }
+
+  /// Limit range of the namespace if any
+  pub fn try_to_limit_range(&self) -> IoResult<Option<NamespaceLimitRange>> {
+    match &self.limit_range {
+      None => Ok(None),
+      Some(limit_range) => {
+        Ok(Some(serde_json::from_value(limit_range.clone())?))
+      }
     }
   }
 }
@@ -50,6 +116,15 @@ impl From<NamespaceDb> for Namespace {
       name: namespace.name,
       created_at: namespace.created_at,
       metadata: namespace.metadata,
+      network: namespace
+        .network
+        .and_then(|network| serde_json::from_value(network).ok()),
+      quota: namespace
+        .quota
+        .and_then(|quota| serde_json::from_value(quota).ok()),
+      limit_range: namespace
+        .limit_range
+        .and_then(|limit_range| serde_json::from_value(limit_range).ok()),
     }
   }
 }
@@ -1,7 +1,7 @@
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::schema::nodes;
+use crate::schema::{node_leases, nodes};
 
 /// This structure represent a node in the database.
 /// A node is a machine that is connected to nanocl network.
@@ -26,3 +26,27 @@ pub struct NodeDb {
   #[serde(skip_serializing_if = "Option::is_none")]
   pub metadata: Option<serde_json::Value>,
 }
+
+/// Lease of a node on a task that must run on a single node of the cluster
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = node_leases)]
+pub struct NodeLeaseDb {
+  /// Name of the task
+  pub name: String,
+  /// Node holding the lease
+  pub node_name: String,
+  /// When the lease can be taken by another node
+  pub expires_at: chrono::NaiveDateTime,
+}
+
+impl NodeLeaseDb {
+  /// Lease of a node on a task for `ttl` seconds from now
+  pub fn new(name: &str, node_name: &str, ttl: i64) -> Self {
+    Self {
+      name: name.to_owned(),
+      node_name: node_name.to_owned(),
+      expires_at: chrono::Utc::now().naive_utc()
+        + chrono::Duration::seconds(ttl),
+    }
+  }
+}
@@ -12,7 +12,7 @@ use tokio::sync::mpsc::{channel, Receiver, Sender};
 
 use nanocl_error::io::{IoError, IoResult};
 
-use nanocl_stubs::system::{Event, EventCondition};
+use nanocl_stubs::system::{Event, EventActorKind, EventCondition};
 
 /// Stream: Wrap Receiver in our own type, with correct error type
 /// This is needed to return a http stream of bytes
@@ -38,17 +38,45 @@ pub struct RawEventSender(
   pub Sender<Bytes>,
   pub Option<Vec<EventCondition>>,
   pub usize,
+  /// Namespaces the client can see, every event is sent when none
+  pub Option<Vec<String>>,
 );
 
 impl RawEventSender {
   pub fn new(
     condition: Option<Vec<EventCondition>>,
+    namespaces: Option<Vec<String>>,
   ) -> (Self, RawEventReceiver) {
     let (tx, rx) = channel(100);
-    (Self(tx, condition, 0), RawEventReceiver(rx))
+    (Self(tx, condition, 0, namespaces), RawEventReceiver(rx))
+  }
+
+  /// Check if the event belongs to a namespace the client can see
+  fn can_see(&self, e: &Event) -> bool {
+    let Some(namespaces) = &self.3 else {
+      return true;
+    };
+    event_namespace(e)
+      .is_some_and(|namespace| namespaces.iter().any(|n| *n == namespace))
   }
 }
 
+/// Namespace of the object an event is about,
+/// none for cluster wide objects
+fn event_namespace(e: &Event) -> Option<String> {
+  let actor = e.actor.as_ref()?;
+  if actor.kind == EventActorKind::Namespace {
+    return actor.key.clone();
+  }
+  let attributes = actor.attributes.as_ref()?;
+  // Processes carry the labels of their container as attributes
+  attributes
+    .get("Namespace")
+    .or_else(|| attributes.get("io.nanocl.n"))?
+    .as_str()
+    .map(str::to_owned)
+}
+
 /// Trait to convert a type to bytes
 trait TryToBytes {
   type Error;
@@ -128,6 +156,10 @@ impl RawEventEmitter {
     let mut new_clients = Vec::new();
     let msg = e.try_to_bytes()?;
     for client in clients {
+      if !client.can_see(e) {
+        new_clients.push(client);
+        continue;
+      }
       let _ = client.0.try_send(msg.clone());
       let conditions = client.1.clone().unwrap_or_default();
       if conditions.is_empty() {
@@ -150,12 +182,13 @@ impl RawEventEmitter {
     Ok(())
   }
 
-  /// Subscribe to events
+  /// Subscribe to the events of the given namespaces or of all of them
   pub async fn subscribe(
     &self,
     condition: Option<Vec<EventCondition>>,
+    namespaces: Option<Vec<String>>,
   ) -> IoResult<RawEventReceiver> {
-    let (tx, rx) = RawEventSender::new(condition);
+    let (tx, rx) = RawEventSender::new(condition, namespaces);
     let inner = Arc::clone(&self.inner);
     web::block(move || {
       inner.lock()?.clients.push(tx);
@@ -1,7 +1,7 @@
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use nanocl_stubs::resource::ResourceSpec;
+use nanocl_stubs::resource::{ResourcePartial, ResourceSpec};
 
 use super::SpecDb;
 
@@ -19,7 +19,7 @@ use crate::schema::resources;
 #[diesel(primary_key(key))]
 #[diesel(table_name = resources)]
 pub struct ResourceDb {
-  /// The key of the resource
+  /// The key of the resource `<name>.<namespace>`
   pub key: String,
   /// The created at date
   pub created_at: chrono::NaiveDateTime,
@@ -27,6 +27,28 @@ pub struct ResourceDb {
   pub kind: String,
   /// The spec key reference
   pub spec_key: uuid::Uuid,
+  /// The name of the resource
+  pub name: String,
+  /// The namespace of the resource
+  pub namespace_name: String,
+  /// Phase of the reconciliation with the controller of its kind
+  pub status: String,
+  /// Error of the last attempt
+  pub status_message: Option<String>,
+  /// Key of the spec last applied by the controller
+  pub observed_spec_key: Option<uuid::Uuid>,
+  /// Number of failed attempts since the spec was last applied
+  pub attempts: i32,
+  /// When the next attempt is made for a pending resource
+  pub next_attempt_at: Option<chrono::NaiveDateTime>,
+  /// When the status changed
+  pub status_updated_at: chrono::NaiveDateTime,
+}
+
+/// Arguments to create a new resource obj
+pub struct ResourceObjCreateIn {
+  pub namespace: String,
+  pub resource: ResourcePartial,
 }
 
 /// This structure represent the update of a resource in the database.
@@ -39,6 +61,24 @@ pub struct ResourceUpdateDb {
   pub spec_key: Option<uuid::Uuid>,
 }
 
+/// Status of the reconciliation of a resource to save
+#[derive(Clone, Debug, AsChangeset)]
+#[diesel(table_name = resources, treat_none_as_null = true)]
+pub struct ResourceStatusDb {
+  /// Phase of the reconciliation
+  pub status: String,
+  /// Error of the last attempt
+  pub status_message: Option<String>,
+  /// Key of the spec last applied by the controller
+  pub observed_spec_key: Option<uuid::Uuid>,
+  /// Number of failed attempts since the spec was last applied
+  pub attempts: i32,
+  /// When the next attempt is made for a pending resource
+  pub next_attempt_at: Option<chrono::NaiveDateTime>,
+  /// When the status changed
+  pub status_updated_at: chrono::NaiveDateTime,
+}
+
 /// Helper to convert a `SpecDb` to a `ResourceSpec`
 impl From<SpecDb> for ResourceSpec {
   fn from(db: SpecDb) -> Self {
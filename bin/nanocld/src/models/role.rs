@@ -0,0 +1,74 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use nanocl_error::io::IoError;
+use nanocl_stubs::auth::{Role, RolePartial, RoleUpdate};
+
+use crate::schema::roles;
+
+/// This structure represent a role in the database.
+/// A role is a named set of rules granting verbs on kinds of objects
+/// in namespaces, it is bound to identities.
+#[derive(
+  Clone, Debug, Serialize, Deserialize, Queryable, Identifiable, Insertable,
+)]
+#[serde(rename_all = "PascalCase")]
+#[diesel(primary_key(key))]
+#[diesel(table_name = roles)]
+pub struct RoleDb {
+  /// The name of the role
+  pub key: String,
+  /// The creation date
+  pub created_at: chrono::NaiveDateTime,
+  /// The last update date
+  pub updated_at: chrono::NaiveDateTime,
+  /// The rules of the role
+  pub rules: serde_json::Value,
+}
+
+impl TryFrom<&RolePartial> for RoleDb {
+  type Error = IoError;
+
+  fn try_from(role: &RolePartial) -> Result<Self, Self::Error> {
+    Ok(Self {
+      key: role.name.clone(),
+      created_at: chrono::Utc::now().naive_utc(),
+      updated_at: chrono::Utc::now().naive_utc(),
+      rules: serde_json::to_value(&role.rules)?,
+    })
+  }
+}
+
+impl TryFrom<RoleDb> for Role {
+  type Error = IoError;
+
+  fn try_from(db: RoleDb) -> Result<Self, Self::Error> {
+    Ok(Role {
+      name: db.key,
+      created_at: db.created_at,
+      updated_at: db.updated_at,
+      rules: serde_json::from_value(db.rules)?,
+    })
+  }
+}
+
+/// This structure is used to update a role in the database.
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = roles)]
+pub struct RoleUpdateDb {
+  /// The last update date
+  pub updated_at: Option<chrono::NaiveDateTime>,
+  /// The rules of the role
+  pub rules: Option<serde_json::Value>,
+}
+
+impl TryFrom<&RoleUpdate> for RoleUpdateDb {
+  type Error = IoError;
+
+  fn try_from(update: &RoleUpdate) -> Result<Self, Self::Error> {
+    Ok(Self {
+      updated_at: Some(chrono::Utc::now().naive_utc()),
+      rules: Some(serde_json::to_value(&update.rules)?),
+    })
+  }
+}
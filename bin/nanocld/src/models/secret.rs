@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -32,6 +34,9 @@ pub struct SecretDb {
   // The metadata (user defined)
   #[serde(skip_serializing_if = "Option::is_none")]
   pub metadata: Option<serde_json::Value>,
+  /// Id of the key used to encrypt the data, none when stored in plain text
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub key_id: Option<String>,
 }
 
 impl From<&SecretPartial> for SecretDb {
@@ -44,6 +49,7 @@ impl From<&SecretPartial> for SecretDb {
       immutable: secret.immutable,
       data: secret.data.clone(),
       metadata: secret.metadata.clone(),
+      key_id: None,
     }
   }
 }
@@ -72,6 +78,8 @@ pub struct SecretUpdateDb {
   pub data: Option<serde_json::Value>,
   // The metadata (user defined)
   pub metadata: Option<serde_json::Value>,
+  /// Id of the key used to encrypt the data
+  pub key_id: Option<String>,
 }
 
 impl From<&SecretUpdate> for SecretUpdateDb {
@@ -79,6 +87,29 @@ impl From<&SecretUpdate> for SecretUpdateDb {
     Self {
       data: Some(update.data.clone()),
       metadata: update.metadata.clone(),
+      key_id: None,
     }
   }
 }
+
+/// Encrypted form of a secret data stored in the database.
+/// The data is encrypted with its own data key
+/// that is itself encrypted with a key of the keyring.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SecretEnvelope {
+  /// The data key encrypted with the keyring key, base64 encoded
+  pub key: String,
+  /// The data encrypted with the data key, base64 encoded
+  pub data: String,
+}
+
+/// Keys held by the daemon to encrypt the secrets at rest
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SecretKeyring {
+  /// Id of the key used to encrypt new data
+  pub active: String,
+  /// Base64 encoded keys by id
+  pub keys: HashMap<String, String>,
+}
@@ -5,7 +5,9 @@ use ntex::rt;
 
 use nanocl_stubs::{config::DaemonConfig, system::Event};
 
-use super::{Pool, RawEventEmitter, TaskManager};
+use super::{
+  ExporterMetrics, Pool, RawEventEmitter, SecretKeyring, TaskManager,
+};
 
 /// This structure represent the state of the system.
 /// Used to share the state between the different handlers.
@@ -17,8 +19,15 @@ pub struct SystemStateInner {
   pub docker_api: bollard_next::Docker,
   /// The config of the daemon
   pub config: DaemonConfig,
+  /// Keyring used to encrypt secrets at rest
+  pub secret_keyring: std::sync::RwLock<SecretKeyring>,
+  /// Held for writing during a key rotation,
+  /// secrets are encrypted and saved under a read guard
+  pub secret_rotation: tokio::sync::RwLock<()>,
   /// Manager of the tasks
   pub task_manager: TaskManager,
+  /// Metrics of the daemon served to prometheus
+  pub exporter: ExporterMetrics,
   /// Event emitter
   pub(crate) event_emitter: mpsc::UnboundedSender<Event>,
   /// Http event client
@@ -42,8 +42,22 @@ impl ObjCreate for CargoDb {
       ));
     }
     let key = utils::key::gen_key(&obj.namespace, &obj.spec.name);
-    let new_spec =
-      SpecDb::try_from_cargo_partial(&key, &obj.version, &obj.spec)?;
+    utils::container::replication::validate(
+      obj.spec.replication.as_ref(),
+      &key,
+      state,
+    )
+    .await?;
+    utils::container::rollout::RolloutPlan::new(
+      obj.spec.update_strategy.as_ref(),
+    )?;
+    utils::autoscale::validate(
+      obj.spec.autoscale.as_ref(),
+      obj.spec.replication.as_ref(),
+    )?;
+    let (spec, _quota_lock) =
+      utils::quota::cargo(&obj.namespace, &key, &obj.spec, state).await?;
+    let new_spec = SpecDb::try_from_cargo_partial(&key, &obj.version, &spec)?;
     let spec = SpecDb::create_from(new_spec, &state.inner.pool)
       .await?
       .try_to_cargo_spec()?;
@@ -123,6 +137,22 @@ impl ObjPutByPk for CargoDb {
     obj: &Self::ObjPutIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjPutOut> {
+    utils::container::replication::validate(
+      obj.spec.replication.as_ref(),
+      pk,
+      state,
+    )
+    .await?;
+    utils::container::rollout::RolloutPlan::new(
+      obj.spec.update_strategy.as_ref(),
+    )?;
+    utils::autoscale::validate(
+      obj.spec.autoscale.as_ref(),
+      obj.spec.replication.as_ref(),
+    )?;
+    let cargo = CargoDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    let (spec, _quota_lock) =
+      utils::quota::cargo(&cargo.namespace_name, pk, &obj.spec, state).await?;
     let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
     let new_status = ObjPsStatusUpdate {
       wanted: Some(ObjPsStatusKind::Start.to_string()),
@@ -131,7 +161,7 @@ impl ObjPutByPk for CargoDb {
       prev_actual: Some(status.actual),
     };
     ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
-    CargoDb::update_from_spec(pk, &obj.spec, &obj.version, &state.inner.pool)
+    CargoDb::update_from_spec(pk, &spec, &obj.version, &state.inner.pool)
       .await
       .map_err(HttpError::from)
   }
@@ -238,6 +268,16 @@ impl ObjPatchByPk for CargoDb {
         cargo.spec.init_container
       },
       replication: obj.spec.replication.clone(),
+      update_strategy: if obj.spec.update_strategy.is_some() {
+        obj.spec.update_strategy.clone()
+      } else {
+        cargo.spec.update_strategy
+      },
+      autoscale: if obj.spec.autoscale.is_some() {
+        obj.spec.autoscale.clone()
+      } else {
+        cargo.spec.autoscale
+      },
       secrets: if obj.spec.secrets.is_some() {
         obj.spec.secrets.clone()
       } else {
@@ -0,0 +1,88 @@
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::{
+  auth::{Identity, IdentityPartial, IdentityUpdate},
+  system::NativeEventAction,
+};
+
+use crate::{
+  models::{IdentityDb, IdentityUpdateDb, RoleDb, SystemState},
+  repositories::generic::*,
+};
+
+use super::generic::*;
+
+/// Ensure every role bound to an identity exist
+async fn check_roles(roles: &[String], state: &SystemState) -> HttpResult<()> {
+  for role in roles {
+    if RoleDb::read_by_pk(role, &state.inner.pool).await.is_err() {
+      return Err(HttpError::bad_request(format!("Role {role}: not found")));
+    }
+  }
+  Ok(())
+}
+
+impl ObjCreate for IdentityDb {
+  type ObjCreateIn = IdentityPartial;
+  type ObjCreateOut = Identity;
+
+  async fn fn_create_obj(
+    obj: &Self::ObjCreateIn,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjCreateOut> {
+    if IdentityDb::read_by_pk(&obj.name, &state.inner.pool)
+      .await
+      .is_ok()
+    {
+      return Err(HttpError::conflict(format!(
+        "Identity {}: already exist",
+        &obj.name
+      )));
+    }
+    check_roles(&obj.roles, state).await?;
+    let identity = IdentityDb::create_try_from(obj, &state.inner.pool)
+      .await?
+      .try_into()?;
+    Ok(identity)
+  }
+}
+
+impl ObjPatchByPk for IdentityDb {
+  type ObjPatchIn = IdentityUpdate;
+  type ObjPatchOut = Identity;
+
+  fn get_patch_event() -> NativeEventAction {
+    NativeEventAction::Update
+  }
+
+  async fn fn_patch_obj_by_pk(
+    pk: &str,
+    obj: &Self::ObjPatchIn,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjPatchOut> {
+    if let Some(roles) = &obj.roles {
+      check_roles(roles, state).await?;
+    }
+    let update = IdentityUpdateDb::try_from(obj)?;
+    let identity = IdentityDb::update_pk(pk, update, &state.inner.pool)
+      .await?
+      .try_into()?;
+    Ok(identity)
+  }
+}
+
+impl ObjDelByPk for IdentityDb {
+  type ObjDelOut = Identity;
+  type ObjDelOpts = ();
+
+  async fn fn_del_obj_by_pk(
+    pk: &str,
+    _opts: &Self::ObjDelOpts,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjDelOut> {
+    let identity =
+      IdentityDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    // Api tokens of the identity are removed by the foreign key cascade
+    IdentityDb::del_by_pk(pk, &state.inner.pool).await?;
+    Ok(identity)
+  }
+}
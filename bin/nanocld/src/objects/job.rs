@@ -5,7 +5,9 @@ use nanocl_stubs::{
 };
 
 use crate::{
-  models::{JobDb, ObjPsStatusDb, ObjPsStatusUpdate, ProcessDb},
+  models::{
+    JobDb, JobRunDb, NamespaceDb, ObjPsStatusDb, ObjPsStatusUpdate, ProcessDb,
+  },
   repositories::generic::*,
   utils,
 };
@@ -20,6 +22,13 @@ impl ObjCreate for JobDb {
     obj: &Self::ObjCreateIn,
     state: &crate::models::SystemState,
   ) -> HttpResult<Self::ObjCreateOut> {
+    NamespaceDb::read_by_pk(obj.namespace_name(), &state.inner.pool).await?;
+    let (obj, _quota_lock) = &utils::quota::job(obj, state).await?;
+    utils::container::job_step::plan(obj)?;
+    if let Some(schedule) = &obj.schedule {
+      utils::cron::JobSchedule::new(schedule, obj.time_zone.as_deref())?
+        .next_after(&chrono::Utc::now())?;
+    }
     let db_model = JobDb::try_from_partial(obj)?;
     let status = ObjPsStatusPartial {
       key: obj.name.clone(),
@@ -32,8 +41,8 @@ impl ObjCreate for JobDb {
     let job = JobDb::create_from(db_model, &state.inner.pool)
       .await?
       .try_to_spec(&status)?;
-    if let Some(schedule) = &job.schedule {
-      utils::cron::add_cron_rule(&job, schedule, state).await?;
+    if job.schedule.is_some() {
+      utils::cron::schedule_job(&job, &chrono::Utc::now(), state).await?;
     }
     Ok(job)
   }
@@ -72,11 +81,13 @@ impl ObjInspectByPk for JobDb {
     pk: &str,
     state: &crate::models::SystemState,
   ) -> HttpResult<Self::ObjInspectOut> {
-    let job = JobDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    let (job_db, status) = JobDb::read_by_pk(pk, &state.inner.pool).await?;
+    let job = job_db.try_to_spec(&status)?;
     let instances =
       ProcessDb::read_by_kind_key(pk, None, &state.inner.pool).await?;
     let (instance_total, instance_failed, instance_success, instance_running) =
       utils::container::generic::count_status(&instances);
+    let run = JobRunDb::read_last(pk, &state.inner.pool).await?;
     let job_inspect = JobInspect {
       spec: job,
       instance_total,
@@ -84,6 +95,16 @@ impl ObjInspectByPk for JobDb {
       instance_running,
       instance_failed,
       instances,
+      next_run_at: job_db.next_run_at,
+      last_run_at: job_db.last_run_at,
+      attempts: match &run {
+        Some(run) => run.try_to_attempts()?,
+        None => Vec::new(),
+      },
+      steps: match &run {
+        Some(run) => run.try_to_step_states()?,
+        None => Vec::new(),
+      },
     };
     Ok(job_inspect)
   }
@@ -1,7 +1,9 @@
 mod cargo;
+mod identity;
 mod job;
 mod namespace;
 mod resource;
+mod role;
 mod secret;
 mod vm;
 
@@ -2,8 +2,9 @@ use nanocl_error::http::{HttpError, HttpResult};
 use nanocl_stubs::namespace::{Namespace, NamespaceInspect, NamespacePartial};
 
 use crate::{
-  models::{CargoDb, NamespaceDb, SystemState},
+  models::{CargoDb, NamespaceDb, ResourceDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 use super::generic::*;
@@ -25,6 +26,9 @@ impl ObjCreate for NamespaceDb {
         &obj.name
       )));
     }
+    utils::container::network::validate(&obj.name, obj.network.as_ref(), state)
+      .await?;
+    utils::container::network::ensure(&obj.name, state).await?;
     let item = NamespaceDb::create_from(obj, &state.inner.pool)
       .await?
       .into();
@@ -49,9 +53,13 @@ impl ObjInspectByPk for NamespaceDb {
         CargoDb::inspect_obj_by_pk(&cargo.spec.cargo_key, state).await?;
       cargoes.push(cargo);
     }
+    let usage = utils::quota::usage(&namespace.name, state).await?;
     Ok(NamespaceInspect {
+      quota: namespace.try_to_quota()?,
+      limit_range: namespace.try_to_limit_range()?,
       name: namespace.name,
       cargoes,
+      usage,
     })
   }
 }
@@ -67,9 +75,10 @@ impl ObjDelByPk for NamespaceDb {
   ) -> HttpResult<Self::ObjDelOut> {
     let item = NamespaceDb::read_by_pk(pk, &state.inner.pool).await?;
     CargoDb::delete_by_namespace(pk, state).await?;
+    ResourceDb::delete_by_namespace(pk, state).await?;
     NamespaceDb::del_by_pk(pk, &state.inner.pool).await?;
-    if let Err(err) = state.inner.docker_api.remove_network(pk).await {
-      log::error!("Unable to remove network {} got error: {}", pk, err);
+    if let Err(err) = utils::container::network::remove(pk, state).await {
+      log::error!("{err}");
     }
     Ok(item.into())
   }
@@ -5,32 +5,42 @@ use nanocl_stubs::{
 };
 
 use crate::{
-  models::{ResourceDb, SpecDb, SystemState},
+  models::{NamespaceDb, ResourceDb, ResourceObjCreateIn, SpecDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 use super::generic::*;
 
 impl ObjCreate for ResourceDb {
-  type ObjCreateIn = ResourcePartial;
+  type ObjCreateIn = ResourceObjCreateIn;
   type ObjCreateOut = Resource;
 
   async fn fn_create_obj(
     obj: &Self::ObjCreateIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjCreateOut> {
-    if ResourceDb::transform_read_by_pk(&obj.name, &state.inner.pool)
+    NamespaceDb::read_by_pk(&obj.namespace, &state.inner.pool).await?;
+    let key = utils::key::gen_key(&obj.namespace, &obj.resource.name);
+    if ResourceDb::transform_read_by_pk(&key, &state.inner.pool)
       .await
       .is_ok()
     {
       return Err(HttpError::conflict(format!(
-        "Resource {} already exists",
-        &obj.name
+        "Resource {} already exists in namespace {}",
+        &obj.resource.name, &obj.namespace
       )));
     }
-    let obj = ResourceDb::hook_create(obj, &state.inner.pool).await?;
-    let resource =
-      ResourceDb::create_from_spec(&obj, &state.inner.pool).await?;
+    let (resource, error) =
+      ResourceDb::hook_create(&obj.resource, &obj.namespace, &state.inner.pool)
+        .await?;
+    let resource = ResourceDb::create_from_spec(
+      &obj.namespace,
+      &resource,
+      error.as_ref(),
+      &state.inner.pool,
+    )
+    .await?;
     Ok(resource)
   }
 }
@@ -72,10 +82,18 @@ impl ObjPutByPk for ResourceDb {
     obj: &Self::ObjPutIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjPutOut> {
-    ResourceDb::read_by_pk(pk, &state.inner.pool).await?;
-    let resource = ResourceDb::hook_create(obj, &state.inner.pool).await?;
-    let resource =
-      ResourceDb::update_from_spec(&resource, &state.inner.pool).await?;
+    let current =
+      ResourceDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    let (resource, error) =
+      ResourceDb::hook_create(obj, &current.namespace_name, &state.inner.pool)
+        .await?;
+    let resource = ResourceDb::update_from_spec(
+      pk,
+      &resource,
+      error.as_ref(),
+      &state.inner.pool,
+    )
+    .await?;
     Ok(resource)
   }
 }
@@ -0,0 +1,81 @@
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::{
+  auth::{Role, RolePartial, RoleUpdate},
+  generic::{GenericClause, GenericFilter},
+  system::NativeEventAction,
+};
+
+use crate::{
+  models::{IdentityDb, RoleDb, RoleUpdateDb, SystemState},
+  repositories::generic::*,
+};
+
+use super::generic::*;
+
+impl ObjCreate for RoleDb {
+  type ObjCreateIn = RolePartial;
+  type ObjCreateOut = Role;
+
+  async fn fn_create_obj(
+    obj: &Self::ObjCreateIn,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjCreateOut> {
+    if RoleDb::read_by_pk(&obj.name, &state.inner.pool)
+      .await
+      .is_ok()
+    {
+      return Err(HttpError::conflict(format!(
+        "Role {}: already exist",
+        &obj.name
+      )));
+    }
+    let role = RoleDb::create_try_from(obj, &state.inner.pool)
+      .await?
+      .try_into()?;
+    Ok(role)
+  }
+}
+
+impl ObjPutByPk for RoleDb {
+  type ObjPutIn = RoleUpdate;
+  type ObjPutOut = Role;
+
+  fn get_put_event() -> NativeEventAction {
+    NativeEventAction::Update
+  }
+
+  async fn fn_put_obj_by_pk(
+    pk: &str,
+    obj: &Self::ObjPutIn,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjPutOut> {
+    let update = RoleUpdateDb::try_from(obj)?;
+    let role = RoleDb::update_pk(pk, update, &state.inner.pool)
+      .await?
+      .try_into()?;
+    Ok(role)
+  }
+}
+
+impl ObjDelByPk for RoleDb {
+  type ObjDelOut = Role;
+  type ObjDelOpts = ();
+
+  async fn fn_del_obj_by_pk(
+    pk: &str,
+    _opts: &Self::ObjDelOpts,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjDelOut> {
+    let role = RoleDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    let filter = GenericFilter::new()
+      .r#where("roles", GenericClause::Contains(serde_json::json!([pk])));
+    let count = IdentityDb::count_by(&filter, &state.inner.pool).await?;
+    if count > 0 {
+      return Err(HttpError::conflict(format!(
+        "Role {pk}: still bound to {count} identities"
+      )));
+    }
+    RoleDb::del_by_pk(pk, &state.inner.pool).await?;
+    Ok(role)
+  }
+}
@@ -5,8 +5,9 @@ use nanocl_stubs::{
 };
 
 use crate::{
-  models::{SecretDb, SystemState},
+  models::{SecretDb, SecretUpdateDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 use super::generic::*;
@@ -19,9 +20,13 @@ impl ObjCreate for SecretDb {
     obj: &Self::ObjCreateIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjCreateOut> {
-    let secret = SecretDb::create_from(obj, &state.inner.pool).await?;
-    let secret: Secret = secret.try_into()?;
-    Ok(secret)
+    let _rotation = state.inner.secret_rotation.read().await;
+    let mut db_model = SecretDb::from(obj);
+    let (key_id, data) = utils::secret::encrypt(&obj.data, state)?;
+    db_model.key_id = Some(key_id);
+    db_model.data = data;
+    let secret = SecretDb::create_from(db_model, &state.inner.pool).await?;
+    Ok(utils::secret::decrypt(secret, state)?)
   }
 }
 
@@ -34,7 +39,7 @@ impl ObjDelByPk for SecretDb {
     _opts: &Self::ObjDelOpts,
     state: &SystemState,
   ) -> HttpResult<Self::ObjDelOut> {
-    let secret = SecretDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    let secret = SecretDb::read_decrypted_by_pk(pk, state).await?;
     SecretDb::del_by_pk(pk, &state.inner.pool).await?;
     Ok(secret)
   }
@@ -53,9 +58,14 @@ impl ObjPatchByPk for SecretDb {
     obj: &Self::ObjPatchIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjPatchOut> {
-    let secret = SecretDb::update_pk(pk, obj, &state.inner.pool)
-      .await?
-      .try_into()?;
-    Ok(secret)
+    let _rotation = state.inner.secret_rotation.read().await;
+    let (key_id, data) = utils::secret::encrypt(&obj.data, state)?;
+    let update = SecretUpdateDb {
+      data: Some(data),
+      metadata: obj.metadata.clone(),
+      key_id: Some(key_id),
+    };
+    let secret = SecretDb::update_pk(pk, update, &state.inner.pool).await?;
+    Ok(utils::secret::decrypt(secret, state)?)
   }
 }
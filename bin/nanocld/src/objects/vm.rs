@@ -43,6 +43,9 @@ impl ObjCreate for VmDb {
     if name.contains('.') {
       return Err(HttpError::bad_request("VM name cannot contain '.'"));
     }
+    let _quota_lock =
+      utils::quota::vm(namespace, &vm_key, vm.host_config.as_ref(), state)
+        .await?;
     let image =
       VmImageDb::read_by_pk(&vm.disk.image, &state.inner.pool).await?;
     if image.kind.as_str() != "Base" {
@@ -122,6 +125,13 @@ impl ObjPutByPk for VmDb {
     state: &SystemState,
   ) -> HttpResult<Self::ObjPutOut> {
     let vm = VmDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    let _quota_lock = utils::quota::vm(
+      &vm.namespace_name,
+      pk,
+      obj.spec.host_config.as_ref(),
+      state,
+    )
+    .await?;
     let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
     let new_status = ObjPsStatusUpdate {
       wanted: Some(ObjPsStatusKind::Start.to_string()),
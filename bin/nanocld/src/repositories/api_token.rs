@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use nanocl_stubs::generic::GenericFilter;
+
+use nanocl_stubs::auth::ApiToken;
+
+use crate::{
+  gen_sql_multiple, gen_sql_order_by, gen_sql_query,
+  models::{ApiTokenDb, ColumnType},
+  schema::api_tokens,
+};
+
+use super::generic::*;
+
+impl RepositoryBase for ApiTokenDb {
+  fn get_columns<'a>() -> HashMap<&'a str, (ColumnType, &'a str)> {
+    HashMap::from([
+      ("key", (ColumnType::Uuid, "api_tokens.key")),
+      (
+        "created_at",
+        (ColumnType::Timestamptz, "api_tokens.created_at"),
+      ),
+      (
+        "identity_key",
+        (ColumnType::Text, "api_tokens.identity_key"),
+      ),
+      ("token_hash", (ColumnType::Text, "api_tokens.token_hash")),
+      (
+        "expires_at",
+        (ColumnType::Timestamptz, "api_tokens.expires_at"),
+      ),
+    ])
+  }
+}
+
+impl RepositoryCreate for ApiTokenDb {}
+
+impl RepositoryDelByPk for ApiTokenDb {}
+
+impl RepositoryReadBy for ApiTokenDb {
+  type Output = ApiTokenDb;
+
+  fn get_pk() -> &'static str {
+    "key"
+  }
+
+  fn gen_read_query(
+    filter: &GenericFilter,
+    is_multiple: bool,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<
+    'static,
+    diesel::pg::PgConnection,
+    Self::Output,
+  > {
+    let mut query = api_tokens::table.into_boxed();
+    let columns = Self::get_columns();
+    query = gen_sql_query!(query, filter, columns);
+    if let Some(orders) = &filter.order_by {
+      query = gen_sql_order_by!(query, orders, columns);
+    } else {
+      query = query.order(api_tokens::created_at.desc());
+    }
+    if is_multiple {
+      gen_sql_multiple!(query, filter);
+    }
+    query
+  }
+}
+
+impl RepositoryCountBy for ApiTokenDb {
+  fn gen_count_query(
+    filter: &GenericFilter,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<'static, diesel::PgConnection, i64>
+  {
+    let mut query = api_tokens::table.into_boxed();
+    let columns = Self::get_columns();
+    gen_sql_query!(query, filter, columns).count()
+  }
+}
+
+impl RepositoryReadByTransform for ApiTokenDb {
+  type NewOutput = ApiToken;
+
+  fn transform(
+    input: Self::Output,
+  ) -> nanocl_error::io::IoResult<Self::NewOutput> {
+    Ok(input.into())
+  }
+}
@@ -17,8 +17,8 @@ use nanocl_stubs::{
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
   models::{
-    CargoDb, CargoUpdateDb, ColumnType, NamespaceDb, ObjPsStatusDb, Pool,
-    ProcessDb, SpecDb, SystemState,
+    CargoDb, CargoUpdateDb, ColumnType, NamespaceDb, NodeDb, ObjPsStatusDb,
+    Pool, ProcessDb, SpecDb, SystemState,
   },
   objects::generic::*,
   schema::cargoes,
@@ -164,6 +164,34 @@ impl CargoDb {
     Ok(cargo)
   }
 
+  /// Move a cargo to a spec only if it still uses the spec `current`,
+  /// so concurrent callers moving it from the same spec do it once.
+  /// Returns whether the cargo was moved.
+  pub async fn update_spec_key_if(
+    key: &str,
+    current: &uuid::Uuid,
+    spec_key: &uuid::Uuid,
+    pool: &Pool,
+  ) -> IoResult<bool> {
+    let pool = pool.clone();
+    let key = key.to_owned();
+    let current = *current;
+    let spec_key = *spec_key;
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let count = diesel::update(
+        cargoes::table
+          .filter(cargoes::key.eq(key))
+          .filter(cargoes::spec_key.eq(current)),
+      )
+      .set(cargoes::spec_key.eq(spec_key))
+      .execute(&mut conn)
+      .map_err(Self::map_err)?;
+      Ok::<_, IoError>(count == 1)
+    })
+    .await?
+  }
+
   /// Find cargoes by namespace.
   pub async fn read_by_namespace(
     name: &str,
@@ -273,6 +301,7 @@ impl CargoDb {
     CargoDb::del_by_pk(pk, pool).await?;
     SpecDb::del_by_kind_key(pk, pool).await?;
     ObjPsStatusDb::del_by_pk(pk, pool).await?;
+    NodeDb::del_lease(&utils::autoscale::lease_name(pk), pool).await?;
     Ok(())
   }
 }
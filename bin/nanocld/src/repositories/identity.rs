@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use nanocl_stubs::generic::GenericFilter;
+
+use nanocl_stubs::auth::Identity;
+
+use crate::{
+  gen_sql_multiple, gen_sql_order_by, gen_sql_query,
+  models::{ColumnType, IdentityDb, IdentityUpdateDb},
+  schema::identities,
+};
+
+use super::generic::*;
+
+impl RepositoryBase for IdentityDb {
+  fn get_columns<'a>() -> HashMap<&'a str, (ColumnType, &'a str)> {
+    HashMap::from([
+      ("key", (ColumnType::Text, "identities.key")),
+      (
+        "created_at",
+        (ColumnType::Timestamptz, "identities.created_at"),
+      ),
+      (
+        "updated_at",
+        (ColumnType::Timestamptz, "identities.updated_at"),
+      ),
+      ("subject", (ColumnType::Text, "identities.subject")),
+      ("roles", (ColumnType::Json, "identities.roles")),
+    ])
+  }
+}
+
+impl RepositoryCreate for IdentityDb {}
+
+impl RepositoryDelByPk for IdentityDb {}
+
+impl RepositoryUpdate for IdentityDb {
+  type UpdateItem = IdentityUpdateDb;
+}
+
+impl RepositoryReadBy for IdentityDb {
+  type Output = IdentityDb;
+
+  fn get_pk() -> &'static str {
+    "key"
+  }
+
+  fn gen_read_query(
+    filter: &GenericFilter,
+    is_multiple: bool,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<
+    'static,
+    diesel::pg::PgConnection,
+    Self::Output,
+  > {
+    let mut query = identities::table.into_boxed();
+    let columns = Self::get_columns();
+    query = gen_sql_query!(query, filter, columns);
+    if let Some(orders) = &filter.order_by {
+      query = gen_sql_order_by!(query, orders, columns);
+    } else {
+      query = query.order(identities::created_at.desc());
+    }
+    if is_multiple {
+      gen_sql_multiple!(query, filter);
+    }
+    query
+  }
+}
+
+impl RepositoryCountBy for IdentityDb {
+  fn gen_count_query(
+    filter: &GenericFilter,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<'static, diesel::PgConnection, i64>
+  {
+    let mut query = identities::table.into_boxed();
+    let columns = Self::get_columns();
+    gen_sql_query!(query, filter, columns).count()
+  }
+}
+
+impl RepositoryReadByTransform for IdentityDb {
+  type NewOutput = Identity;
+
+  fn transform(
+    input: Self::Output,
+  ) -> nanocl_error::io::IoResult<Self::NewOutput> {
+    input.try_into()
+  }
+}
@@ -7,19 +7,20 @@ use futures_util::StreamExt;
 
 use nanocl_error::{
   http::{HttpError, HttpResult},
-  io::IoResult,
+  io::{IoError, IoResult},
 };
 use nanocl_stubs::{
-  generic::GenericFilter,
-  job::{Job, JobPartial, JobSummary},
+  generic::{GenericClause, GenericFilter},
+  job::{Job, JobAttempt, JobPartial, JobStepState, JobSummary},
 };
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
   models::{
-    ColumnType, JobDb, JobUpdateDb, ObjPsStatusDb, Pool, ProcessDb, SystemState,
+    ColumnType, JobDb, JobRunDb, JobRunUpdateDb, JobUpdateDb, ObjPsStatusDb,
+    Pool, ProcessDb, SystemState,
   },
-  schema::jobs,
+  schema::{job_runs, jobs},
   utils,
 };
 
@@ -34,6 +35,8 @@ impl RepositoryBase for JobDb {
       ("metadata", (ColumnType::Json, "jobs.metadata")),
       ("created_at", (ColumnType::Timestamptz, "jobs.created_at")),
       ("updated_at", (ColumnType::Timestamptz, "jobs.updated_at")),
+      ("next_run_at", (ColumnType::Timestamptz, "jobs.next_run_at")),
+      ("last_run_at", (ColumnType::Timestamptz, "jobs.last_run_at")),
       (
         "status.wanted",
         (ColumnType::Text, "object_process_statuses.wanted"),
@@ -110,7 +113,61 @@ impl RepositoryReadByTransform for JobDb {
 }
 
 impl JobDb {
+  /// Claim the due run of a job by moving its next run,
+  /// only one node succeeds for a given `due` date.
+  /// Returns whether the run was claimed.
+  pub async fn claim_run(
+    key: &str,
+    due: chrono::NaiveDateTime,
+    next_run_at: Option<chrono::NaiveDateTime>,
+    pool: &Pool,
+  ) -> IoResult<bool> {
+    let pool = pool.clone();
+    let key = key.to_owned();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let count = diesel::update(
+        jobs::table
+          .filter(jobs::key.eq(key))
+          .filter(jobs::next_run_at.eq(due)),
+      )
+      .set(jobs::next_run_at.eq(next_run_at))
+      .execute(&mut conn)
+      .map_err(Self::map_err)?;
+      Ok::<_, IoError>(count == 1)
+    })
+    .await?
+  }
+
+  /// Count the jobs of a namespace
+  pub async fn count_by_namespace(
+    namespace: &str,
+    pool: &Pool,
+  ) -> IoResult<usize> {
+    let pool = pool.clone();
+    let data = ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let data = jobs::table
+        .select(jobs::data)
+        .load::<serde_json::Value>(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(data)
+    })
+    .await??;
+    // Jobs created without a namespace belong to `global`
+    let count = data
+      .iter()
+      .filter(|data| {
+        data["Namespace"].as_str().unwrap_or("global") == namespace
+      })
+      .count();
+    Ok(count)
+  }
+
   pub async fn clear_by_pk(pk: &str, pool: &Pool) -> IoResult<()> {
+    let filter =
+      GenericFilter::new().r#where("job_key", GenericClause::Eq(pk.to_owned()));
+    JobRunDb::del_by(&filter, pool).await?;
     JobDb::del_by_pk(pk, pool).await?;
     ObjPsStatusDb::del_by_pk(pk, pool).await?;
     Ok(())
@@ -125,6 +182,8 @@ impl JobDb {
       updated_at: chrono::Utc::now().naive_utc(),
       metadata: p.metadata.clone(),
       data,
+      next_run_at: None,
+      last_run_at: None,
     })
   }
 
@@ -132,14 +191,24 @@ impl JobDb {
     let p = serde_json::from_value::<JobPartial>(self.data.clone())?;
     Ok(Job {
       name: self.key.clone(),
+      namespace: p.namespace.clone(),
       created_at: self.created_at,
       updated_at: self.updated_at,
       metadata: self.metadata.clone(),
       secrets: p.secrets.clone(),
       schedule: p.schedule.clone(),
+      time_zone: p.time_zone.clone(),
+      concurrency_policy: p.concurrency_policy,
+      catch_up: p.catch_up,
+      retries: p.retries,
+      backoff: p.backoff,
+      active_deadline: p.active_deadline,
+      container_timeout: p.container_timeout,
+      continue_on_failure: p.continue_on_failure,
       ttl: p.ttl,
       status: status.clone().try_into()?,
       containers: p.containers.clone(),
+      steps: p.steps.clone(),
       image_pull_secret: p.image_pull_secret.clone(),
       image_pull_policy: p.image_pull_policy.clone(),
     })
@@ -179,3 +248,106 @@ impl JobDb {
     Ok(job_summaries)
   }
 }
+
+impl RepositoryBase for JobRunDb {
+  fn get_columns<'a>(
+  ) -> std::collections::HashMap<&'a str, (ColumnType, &'a str)> {
+    HashMap::from([
+      ("key", (ColumnType::Text, "job_runs.key")),
+      ("job_key", (ColumnType::Text, "job_runs.job_key")),
+      (
+        "created_at",
+        (ColumnType::Timestamptz, "job_runs.created_at"),
+      ),
+    ])
+  }
+}
+
+impl RepositoryCreate for JobRunDb {}
+
+impl RepositoryUpdate for JobRunDb {
+  type UpdateItem = JobRunUpdateDb;
+}
+
+impl RepositoryDelBy for JobRunDb {
+  fn gen_del_query(
+    filter: &GenericFilter,
+  ) -> diesel::query_builder::BoxedDeleteStatement<
+    'static,
+    diesel::pg::Pg,
+    <Self as diesel::associations::HasTable>::Table,
+  >
+  where
+    Self: diesel::associations::HasTable,
+  {
+    let mut query = diesel::delete(job_runs::table).into_boxed();
+    let columns = Self::get_columns();
+    gen_sql_query!(query, filter, columns)
+  }
+}
+
+impl RepositoryReadBy for JobRunDb {
+  type Output = JobRunDb;
+
+  fn get_pk() -> &'static str {
+    "key"
+  }
+
+  fn gen_read_query(
+    filter: &GenericFilter,
+    is_multiple: bool,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<
+    'static,
+    diesel::PgConnection,
+    Self::Output,
+  > {
+    let mut query = job_runs::table.into_boxed();
+    let columns = Self::get_columns();
+    query = gen_sql_query!(query, filter, columns);
+    if let Some(orders) = &filter.order_by {
+      query = gen_sql_order_by!(query, orders, columns);
+    } else {
+      query = query.order(job_runs::created_at.desc());
+    }
+    if is_multiple {
+      gen_sql_multiple!(query, filter);
+    }
+    query
+  }
+}
+
+impl JobRunDb {
+  pub fn new(key: &str, job_key: &str) -> Self {
+    JobRunDb {
+      key: key.to_owned(),
+      job_key: job_key.to_owned(),
+      created_at: chrono::Utc::now().naive_utc(),
+      attempts: serde_json::Value::Array(Vec::new()),
+      steps: serde_json::Value::Array(Vec::new()),
+    }
+  }
+
+  /// Latest run of a job
+  pub async fn read_last(
+    job_key: &str,
+    pool: &Pool,
+  ) -> IoResult<Option<JobRunDb>> {
+    let filter = GenericFilter::new()
+      .r#where("job_key", GenericClause::Eq(job_key.to_owned()))
+      .limit(1);
+    let runs = JobRunDb::read_by(&filter, pool).await?;
+    Ok(runs.into_iter().next())
+  }
+
+  /// Attempts of the containers during the run
+  pub fn try_to_attempts(&self) -> IoResult<Vec<JobAttempt>> {
+    let attempts = serde_json::from_value(self.attempts.clone())?;
+    Ok(attempts)
+  }
+
+  /// State of the steps during the run
+  pub fn try_to_step_states(&self) -> IoResult<Vec<JobStepState>> {
+    let steps = serde_json::from_value(self.steps.clone())?;
+    Ok(steps)
+  }
+}
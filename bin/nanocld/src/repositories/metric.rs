@@ -1,12 +1,20 @@
-use diesel::{prelude::*, sql_query};
-use nanocl_error::io::{IoError, IoResult};
-use nanocl_stubs::generic::{GenericClause, GenericFilter};
+use diesel::{prelude::*, sql_query, sql_types};
+use nanocl_error::io::{FromIo, IoError, IoResult};
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter},
+  metric::{MetricAggregate, MetricFieldAggregate},
+};
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
-  models::{ColumnType, MetricDb, MetricNodeDb, NodeDb, Pool},
+  models::{
+    ColumnType, MetricAggregateDb, MetricDb, MetricNodeDb, NodeDb, Pool,
+  },
   schema::metrics,
-  utils,
+  utils::{
+    self,
+    metric::{MetricAggregatePlan, MetricGroupField},
+  },
 };
 
 use super::generic::*;
@@ -29,6 +37,23 @@ impl RepositoryBase for MetricDb {
 
 impl RepositoryCreate for MetricDb {}
 
+impl RepositoryDelBy for MetricDb {
+  fn gen_del_query(
+    filter: &GenericFilter,
+  ) -> diesel::query_builder::BoxedDeleteStatement<
+    'static,
+    diesel::pg::Pg,
+    <Self as diesel::associations::HasTable>::Table,
+  >
+  where
+    Self: diesel::associations::HasTable,
+  {
+    let mut query = diesel::delete(metrics::table).into_boxed();
+    let columns = Self::get_columns();
+    gen_sql_query!(query, filter, columns)
+  }
+}
+
 impl RepositoryReadBy for MetricDb {
   type Output = MetricDb;
 
@@ -126,3 +151,241 @@ impl MetricDb {
     NodeDb::read_by(&filter, pool).await
   }
 }
+
+/// Text values of the data that can be cast to a number
+const NUMERIC_PATTERN: &str = r"^\s*-?[0-9]+(\.[0-9]+)?([eE][-+]?[0-9]+)?\s*$";
+
+/// Start of the time bucket of a timestamp column
+/// for the interval bound at `$4`
+fn gen_bucket_sql(column: &str) -> String {
+  format!("to_timestamp(floor(extract(epoch FROM {column})::float8 / $4) * $4)")
+}
+
+/// Array of the values of the grouped fields
+fn gen_labels_sql(labels: Vec<String>) -> String {
+  if labels.is_empty() {
+    "ARRAY[]::text[]".to_owned()
+  } else {
+    format!("ARRAY[{}]", labels.join(", "))
+  }
+}
+
+/// Array of the aggregated values of the fields
+fn gen_fields_sql(aggregates: Vec<String>) -> String {
+  if aggregates.is_empty() {
+    "'[]'::jsonb".to_owned()
+  } else {
+    format!("jsonb_build_array({})", aggregates.join(", "))
+  }
+}
+
+/// Generate the query of an aggregation of the raw metrics,
+/// the paths of the fields are bound after the kind, the time range,
+/// the interval and the node in the order of the plan
+fn gen_aggregate_sql(plan: &MetricAggregatePlan) -> String {
+  let mut index = 5;
+  let mut next_param = || {
+    index += 1;
+    format!("${index}::text[]")
+  };
+  let labels = plan
+    .group_by
+    .iter()
+    .map(|(_, field)| match field {
+      MetricGroupField::NodeName => "node_name".to_owned(),
+      MetricGroupField::Data(_) => format!("data #>> {}", next_param()),
+    })
+    .collect::<Vec<_>>();
+  let mut columns = vec![
+    format!("{} AS bucket", gen_bucket_sql("created_at")),
+    format!("{} AS labels", gen_labels_sql(labels)),
+  ];
+  columns.extend((0..plan.fields.len()).map(|i| {
+    let param = next_param();
+    format!(
+      "CASE WHEN (data #>> {param}) ~ '{NUMERIC_PATTERN}' \
+        THEN (data #>> {param})::float8 END AS v{i}"
+    )
+  }));
+  let aggregates = (0..plan.fields.len())
+    .map(|i| {
+      format!(
+        "jsonb_build_object(\
+        'Count', count(v{i}), 'Sum', sum(v{i}), 'Avg', avg(v{i}), \
+        'Min', min(v{i}), 'Max', max(v{i}), \
+        'P50', percentile_cont(0.50) WITHIN GROUP (ORDER BY v{i}), \
+        'P95', percentile_cont(0.95) WITHIN GROUP (ORDER BY v{i}), \
+        'P99', percentile_cont(0.99) WITHIN GROUP (ORDER BY v{i}))"
+      )
+    })
+    .collect::<Vec<_>>();
+  format!(
+    "WITH points AS (\
+      SELECT {} \
+      FROM metrics \
+      WHERE kind = $1 AND created_at >= $2 AND created_at < $3 \
+        AND ($5::text IS NULL OR node_name = $5)\
+    ) \
+    SELECT bucket, labels, count(*) AS count, \
+      {} AS fields \
+    FROM points \
+    GROUP BY bucket, labels \
+    ORDER BY bucket, labels",
+    columns.join(", "),
+    gen_fields_sql(aggregates),
+  )
+}
+
+/// Generate the query of an aggregation of the rollups of a resolution,
+/// the names of the fields are bound after the kind, the time range,
+/// the interval, the node and the resolution in the order of the plan.
+/// The percentiles are averages of the percentiles of the rollup buckets
+/// weighted by their number of values so they are approximate.
+fn gen_rollup_aggregate_sql(plan: &MetricAggregatePlan) -> String {
+  let mut index = 6;
+  let mut next_param = || {
+    index += 1;
+    format!("${index}::text")
+  };
+  let labels = plan
+    .group_by
+    .iter()
+    .map(|(_, field)| match field {
+      MetricGroupField::NodeName => "node_name".to_owned(),
+      MetricGroupField::Data(_) => format!("labels ->> {}", next_param()),
+    })
+    .collect::<Vec<_>>();
+  let mut columns = vec![
+    format!("{} AS bucket", gen_bucket_sql("bucket")),
+    format!("{} AS labels", gen_labels_sql(labels)),
+    "count".to_owned(),
+  ];
+  for i in 0..plan.fields.len() {
+    let param = next_param();
+    columns.extend(
+      [
+        ("Count", "c"),
+        ("Sum", "s"),
+        ("Min", "mn"),
+        ("Max", "mx"),
+        ("P50", "pa"),
+        ("P95", "pb"),
+        ("P99", "pc"),
+      ]
+      .map(|(key, alias)| {
+        format!("(fields -> {param} ->> '{key}')::float8 AS {alias}{i}")
+      }),
+    );
+  }
+  let aggregates = (0..plan.fields.len())
+    .map(|i| {
+      format!(
+        "jsonb_build_object(\
+        'Count', coalesce(sum(c{i}), 0)::int8, 'Sum', sum(s{i}), \
+        'Avg', sum(s{i}) / nullif(sum(c{i}), 0), \
+        'Min', min(mn{i}), 'Max', max(mx{i}), \
+        'P50', sum(pa{i} * c{i}) / nullif(sum(c{i}), 0), \
+        'P95', sum(pb{i} * c{i}) / nullif(sum(c{i}), 0), \
+        'P99', sum(pc{i} * c{i}) / nullif(sum(c{i}), 0))"
+      )
+    })
+    .collect::<Vec<_>>();
+  format!(
+    "WITH points AS (\
+      SELECT {} \
+      FROM metric_rollups \
+      WHERE kind = $1 AND bucket >= $2 AND bucket < $3 \
+        AND ($5::text IS NULL OR node_name = $5) AND resolution = $6\
+    ) \
+    SELECT bucket, labels, sum(count)::int8 AS count, \
+      {} AS fields \
+    FROM points \
+    GROUP BY bucket, labels \
+    ORDER BY bucket, labels",
+    columns.join(", "),
+    gen_fields_sql(aggregates),
+  )
+}
+
+impl MetricDb {
+  /// Aggregate the metrics of a kind by time bucket and group in the store,
+  /// from the rollups of the resolution of the plan when it's set
+  pub async fn aggregate(
+    plan: &MetricAggregatePlan,
+    pool: &Pool,
+  ) -> IoResult<Vec<MetricAggregate>> {
+    let pool = pool.clone();
+    let group_names =
+      plan
+        .group_by
+        .iter()
+        .filter_map(|(name, field)| match field {
+          MetricGroupField::Data(path) => Some((name.clone(), path.clone())),
+          MetricGroupField::NodeName => None,
+        });
+    let fields = group_names
+      .chain(plan.fields.iter().cloned())
+      .collect::<Vec<_>>();
+    let since = plan.since.and_utc();
+    let until = plan.until.and_utc();
+    let interval = plan.interval as f64;
+    let kind = plan.kind.clone();
+    let node_name = plan.node_name.clone();
+    let resolution = plan.resolution;
+    let sql = match resolution {
+      Some(_) => gen_rollup_aggregate_sql(plan),
+      None => gen_aggregate_sql(plan),
+    };
+    let rows = ntex::rt::spawn_blocking(move || {
+      let mut query = sql_query(sql)
+        .into_boxed()
+        .bind::<sql_types::Text, _>(kind)
+        .bind::<sql_types::Timestamptz, _>(since)
+        .bind::<sql_types::Timestamptz, _>(until)
+        .bind::<sql_types::Double, _>(interval)
+        .bind::<sql_types::Nullable<sql_types::Text>, _>(node_name);
+      if let Some(resolution) = resolution {
+        query = query.bind::<sql_types::BigInt, _>(resolution as i64);
+        for (name, _) in fields {
+          query = query.bind::<sql_types::Text, _>(name);
+        }
+      } else {
+        for (_, path) in fields {
+          query = query.bind::<sql_types::Array<sql_types::Text>, _>(path);
+        }
+      }
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let rows = query
+        .get_results::<MetricAggregateDb>(&mut conn)
+        .map_err(|err| IoError::interrupted("Metric", &err.to_string()))?;
+      Ok::<_, IoError>(rows)
+    })
+    .await
+    .map_err(|err| IoError::interrupted("Metric", &err.to_string()))??;
+    rows
+      .into_iter()
+      .map(|row| {
+        let group = plan
+          .group_by
+          .iter()
+          .map(|(name, _)| name.clone())
+          .zip(row.labels)
+          .collect();
+        let mut fields = row.fields;
+        let aggregates = fields.as_array_mut().into_iter().flatten();
+        for (aggregate, (name, _)) in aggregates.zip(plan.fields.iter()) {
+          aggregate["Field"] = serde_json::Value::String(name.clone());
+        }
+        let fields =
+          serde_json::from_value::<Vec<MetricFieldAggregate>>(fields)
+            .map_err(|err| err.map_err_context(|| "Metric"))?;
+        Ok(MetricAggregate {
+          bucket: row.bucket,
+          group,
+          count: row.count,
+          fields,
+        })
+      })
+      .collect()
+  }
+}
@@ -0,0 +1,90 @@
+use diesel::prelude::*;
+
+use nanocl_error::io::{IoError, IoResult};
+use nanocl_stubs::generic::GenericFilter;
+
+use crate::{
+  gen_sql_query,
+  models::{ColumnType, MetricRollupDb, Pool},
+  schema::metric_rollups,
+  utils,
+};
+
+use super::generic::*;
+
+impl RepositoryBase for MetricRollupDb {
+  fn get_columns<'a>(
+  ) -> std::collections::HashMap<&'a str, (ColumnType, &'a str)> {
+    std::collections::HashMap::from([
+      ("key", (ColumnType::Uuid, "metric_rollups.key")),
+      ("node_name", (ColumnType::Text, "metric_rollups.node_name")),
+      ("kind", (ColumnType::Text, "metric_rollups.kind")),
+      ("bucket", (ColumnType::Timestamptz, "metric_rollups.bucket")),
+      (
+        "expires_at",
+        (ColumnType::Timestamptz, "metric_rollups.expires_at"),
+      ),
+    ])
+  }
+}
+
+impl RepositoryDelBy for MetricRollupDb {
+  fn gen_del_query(
+    filter: &GenericFilter,
+  ) -> diesel::query_builder::BoxedDeleteStatement<
+    'static,
+    diesel::pg::Pg,
+    <Self as diesel::associations::HasTable>::Table,
+  >
+  where
+    Self: diesel::associations::HasTable,
+  {
+    let mut query = diesel::delete(metric_rollups::table).into_boxed();
+    let columns = Self::get_columns();
+    gen_sql_query!(query, filter, columns)
+  }
+}
+
+impl MetricRollupDb {
+  /// Insert the buckets of a rollup at once
+  /// so a failure doesn't leave a partial rollup
+  pub async fn create_many(items: Vec<Self>, pool: &Pool) -> IoResult<()> {
+    if items.is_empty() {
+      return Ok(());
+    }
+    let pool = pool.clone();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      diesel::insert_into(metric_rollups::table)
+        .values(items)
+        .execute(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(())
+    })
+    .await?
+  }
+
+  /// Start of the last bucket of a rollup saved by a node
+  pub async fn last_bucket(
+    kind: &str,
+    resolution: u64,
+    node_name: &str,
+    pool: &Pool,
+  ) -> IoResult<Option<chrono::NaiveDateTime>> {
+    let pool = pool.clone();
+    let kind = kind.to_owned();
+    let node_name = node_name.to_owned();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let bucket = metric_rollups::table
+        .filter(metric_rollups::kind.eq(kind))
+        .filter(metric_rollups::resolution.eq(resolution as i64))
+        .filter(metric_rollups::node_name.eq(node_name))
+        .select(diesel::dsl::max(metric_rollups::bucket))
+        .first::<Option<chrono::NaiveDateTime>>(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(bucket)
+    })
+    .await?
+  }
+}
@@ -1,13 +1,17 @@
+mod api_token;
 mod cargo;
 mod event;
+mod identity;
 mod job;
 mod metric;
+mod metric_rollup;
 mod namespace;
 mod node;
 mod object_process_status;
 mod process;
 mod resource;
 mod resource_kind;
+mod role;
 mod secret;
 mod spec;
 mod vm;
@@ -2,13 +2,20 @@ use std::collections::HashMap;
 
 use diesel::prelude::*;
 
-use nanocl_error::http::HttpResult;
+use nanocl_error::{
+  http::HttpResult,
+  io::{IoError, IoResult},
+};
 use nanocl_stubs::{generic::GenericFilter, namespace::NamespaceSummary};
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
-  models::{CargoDb, ColumnType, NamespaceDb, ProcessDb, SystemState},
-  schema::namespaces,
+  models::{
+    CargoDb, ColumnType, NamespaceDb, NamespaceQuotaLockDb, Pool, ProcessDb,
+    SystemState,
+  },
+  schema::{namespace_quota_locks, namespaces},
+  utils,
 };
 
 use super::generic::*;
@@ -92,3 +99,57 @@ impl NamespaceDb {
     Ok(new_items)
   }
 }
+
+impl NamespaceDb {
+  /// Take the lease on the quota of a namespace if it's free or expired.
+  /// Return false when another holder has it.
+  pub async fn try_lock_quota(
+    lock: &NamespaceQuotaLockDb,
+    pool: &Pool,
+  ) -> IoResult<bool> {
+    let pool = pool.clone();
+    let lock = lock.clone();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let now = chrono::Utc::now().naive_utc();
+      let upsert = diesel::insert_into(namespace_quota_locks::table)
+        .values(&lock)
+        .on_conflict(namespace_quota_locks::namespace_name)
+        .do_update()
+        .set((
+          namespace_quota_locks::token.eq(lock.token),
+          namespace_quota_locks::expires_at.eq(lock.expires_at),
+        ));
+      // Only an expired lease is taken over
+      let count = diesel::query_dsl::methods::FilterDsl::filter(
+        upsert,
+        namespace_quota_locks::expires_at.lt(now),
+      )
+      .execute(&mut conn)
+      .map_err(Self::map_err)?;
+      Ok::<_, IoError>(count == 1)
+    })
+    .await?
+  }
+
+  /// Release the lease on the quota of a namespace if it's still held
+  pub async fn unlock_quota(
+    namespace: &str,
+    token: &uuid::Uuid,
+    pool: &Pool,
+  ) -> IoResult<()> {
+    let pool = pool.clone();
+    let namespace = namespace.to_owned();
+    let token = *token;
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      diesel::delete(namespace_quota_locks::table)
+        .filter(namespace_quota_locks::namespace_name.eq(namespace))
+        .filter(namespace_quota_locks::token.eq(token))
+        .execute(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(())
+    })
+    .await?
+  }
+}
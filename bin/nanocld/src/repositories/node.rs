@@ -8,9 +8,9 @@ use nanocl_stubs::generic::GenericFilter;
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
-  models::{ColumnType, NodeDb, Pool, SystemState},
-  schema::nodes,
-  vars,
+  models::{ColumnType, NodeDb, NodeLeaseDb, Pool, SystemState},
+  schema::{node_group_links, node_leases, nodes},
+  utils, vars,
 };
 
 use super::generic::*;
@@ -103,3 +103,89 @@ impl NodeDb {
     Ok(())
   }
 }
+
+impl NodeDb {
+  /// Take or renew the lease of a node on a task.
+  /// Returns whether the node holds the lease.
+  pub async fn try_lease(lease: &NodeLeaseDb, pool: &Pool) -> IoResult<bool> {
+    let pool = pool.clone();
+    let lease = lease.clone();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let now = chrono::Utc::now().naive_utc();
+      let upsert = diesel::insert_into(node_leases::table)
+        .values(&lease)
+        .on_conflict(node_leases::name)
+        .do_update()
+        .set((
+          node_leases::node_name.eq(&lease.node_name),
+          node_leases::expires_at.eq(lease.expires_at),
+        ));
+      // Renewed by its holder or taken over once expired
+      let count = diesel::query_dsl::methods::FilterDsl::filter(
+        upsert,
+        node_leases::node_name
+          .eq(&lease.node_name)
+          .or(node_leases::expires_at.lt(now)),
+      )
+      .execute(&mut conn)
+      .map_err(Self::map_err)?;
+      Ok::<_, IoError>(count == 1)
+    })
+    .await?
+  }
+
+  /// Delete the lease on a task that no longer exists
+  pub async fn del_lease(name: &str, pool: &Pool) -> IoResult<()> {
+    let pool = pool.clone();
+    let name = name.to_owned();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      diesel::delete(node_leases::table.filter(node_leases::name.eq(name)))
+        .execute(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(())
+    })
+    .await?
+  }
+
+  /// List the names of every node registered in the cluster
+  pub async fn list_names(pool: &Pool) -> IoResult<Vec<String>> {
+    let pool = pool.clone();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let names = nodes::table
+        .select(nodes::name)
+        .order(nodes::name.asc())
+        .load::<String>(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(names)
+    })
+    .await?
+  }
+
+  /// List node groups with the names of the nodes they contain
+  pub async fn list_groups(
+    pool: &Pool,
+  ) -> IoResult<HashMap<String, Vec<String>>> {
+    let pool = pool.clone();
+    let links = ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let links = node_group_links::table
+        .select((
+          node_group_links::node_group_name,
+          node_group_links::node_name,
+        ))
+        .order(node_group_links::node_name.asc())
+        .load::<(String, String)>(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(links)
+    })
+    .await??;
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for (group, node) in links {
+      groups.entry(group).or_default().push(node);
+    }
+    Ok(groups)
+  }
+}
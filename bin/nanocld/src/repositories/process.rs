@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use diesel::prelude::*;
 
-use nanocl_error::io::IoResult;
+use nanocl_error::io::{IoError, IoResult};
 
 use nanocl_stubs::{
   generic::{GenericClause, GenericFilter},
@@ -13,6 +13,7 @@ use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
   models::{ColumnType, Pool, ProcessDb, ProcessUpdateDb},
   schema::processes,
+  utils,
 };
 
 use super::generic::*;
@@ -140,3 +141,74 @@ impl ProcessDb {
     ProcessDb::transform_read_by(&filter, pool).await
   }
 }
+
+impl ProcessDb {
+  /// Namespace of the cargo or vm running the process
+  pub fn namespace_of(process: &Process) -> Option<&str> {
+    process
+      .data
+      .config
+      .as_ref()?
+      .labels
+      .as_ref()?
+      .get("io.nanocl.n")
+      .map(String::as_str)
+  }
+
+  /// Processes matching the filter without its limit and offset
+  /// that run in one of the namespaces
+  async fn read_all_in_namespaces(
+    filter: &GenericFilter,
+    namespaces: &[String],
+    pool: &Pool,
+  ) -> IoResult<Vec<Process>> {
+    let pool = pool.clone();
+    let filter = filter.clone();
+    let items = ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let items = Self::gen_read_query(&filter, false)
+        .get_results::<Self>(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(items)
+    })
+    .await??;
+    let mut processes = Vec::new();
+    for item in items {
+      let process = Self::transform(item)?;
+      let visible = Self::namespace_of(&process)
+        .is_some_and(|namespace| namespaces.iter().any(|n| n == namespace));
+      if visible {
+        processes.push(process);
+      }
+    }
+    Ok(processes)
+  }
+
+  /// List the processes matching the filter that run in one of the namespaces
+  pub async fn read_in_namespaces(
+    filter: &GenericFilter,
+    namespaces: &[String],
+    pool: &Pool,
+  ) -> IoResult<Vec<Process>> {
+    let processes =
+      Self::read_all_in_namespaces(filter, namespaces, pool).await?;
+    Ok(
+      processes
+        .into_iter()
+        .skip(filter.offset.unwrap_or(0))
+        .take(filter.limit.unwrap_or(100))
+        .collect(),
+    )
+  }
+
+  /// Count the processes matching the filter that run in one of the namespaces
+  pub async fn count_in_namespaces(
+    filter: &GenericFilter,
+    namespaces: &[String],
+    pool: &Pool,
+  ) -> IoResult<i64> {
+    let processes =
+      Self::read_all_in_namespaces(filter, namespaces, pool).await?;
+    Ok(processes.len() as i64)
+  }
+}
@@ -5,20 +5,23 @@ use diesel::prelude::*;
 use jsonschema::{Draft, Validator};
 use nanocl_error::{
   http::{HttpError, HttpResult},
-  io::IoResult,
+  http_client::HttpClientError,
+  io::{IoError, IoResult},
 };
 
 use nanocl_stubs::{
-  generic::GenericFilter,
-  resource::{Resource, ResourcePartial},
+  generic::{GenericClause, GenericFilter, GenericFilterNsp},
+  resource::{Resource, ResourcePartial, ResourceStatus},
   resource_kind::ResourceKind,
 };
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
   models::{
-    ColumnType, Pool, ResourceDb, ResourceKindDb, ResourceUpdateDb, SpecDb,
+    ColumnType, NamespaceDb, Pool, ResourceDb, ResourceKindDb,
+    ResourceStatusDb, ResourceUpdateDb, SpecDb, SystemState,
   },
+  objects::generic::*,
   schema::resources,
   utils,
 };
@@ -34,7 +37,13 @@ impl RepositoryBase for ResourceDb {
         (ColumnType::Timestamptz, "resources.created_at"),
       ),
       ("kind", (ColumnType::Text, "resources.kind")),
+      ("name", (ColumnType::Text, "resources.name")),
+      (
+        "namespace_name",
+        (ColumnType::Text, "resources.namespace_name"),
+      ),
       ("spec_key", (ColumnType::Text, "resources.spec_key")),
+      ("status", (ColumnType::Text, "resources.status")),
       ("data", (ColumnType::Json, "specs.data")),
       ("metadata", (ColumnType::Json, "specs.metadata")),
     ])
@@ -109,9 +118,19 @@ impl WithSpec for ResourceDb {
 
   fn with_spec(self, r: &Self::Relation) -> Self::Output {
     Self::Output {
+      name: self.name,
+      namespace_name: self.namespace_name,
       created_at: self.created_at,
       kind: self.kind,
       spec: r.clone().into(),
+      status: ResourceStatus {
+        phase: self.status.parse().unwrap_or_default(),
+        message: self.status_message,
+        observed_spec_key: self.observed_spec_key,
+        attempts: self.attempts.max(0) as u32,
+        next_attempt_at: self.next_attempt_at,
+        updated_at: self.status_updated_at,
+      },
     }
   }
 }
@@ -133,40 +152,56 @@ impl ResourceDb {
     }
   }
 
-  /// Create a new resource from a spec.
+  /// Create a new resource from a spec in a namespace.
+  /// Its status is pending when its controller couldn't apply it.
   pub async fn create_from_spec(
+    namespace: &str,
     item: &ResourcePartial,
+    error: Option<&HttpClientError>,
     pool: &Pool,
   ) -> IoResult<Resource> {
     let (kind, version) = ResourceDb::parse_kind(&item.kind, pool).await?;
+    let key = utils::key::gen_key(namespace, &item.name);
     let spec = SpecDb {
       key: uuid::Uuid::new_v4(),
       created_at: chrono::Utc::now().naive_utc(),
       kind_name: "Resource".to_owned(),
-      kind_key: item.name.to_owned(),
+      kind_key: key.clone(),
       version: version.to_owned(),
       data: item.data.clone(),
       metadata: item.metadata.clone(),
     };
     let spec = SpecDb::create_from(spec, pool).await?;
+    let status =
+      utils::resource::gen_status(None, 0, spec.key, error.map_or(Ok(()), Err));
     let new_item = ResourceDb {
-      key: item.name.to_owned(),
+      key,
       created_at: chrono::Utc::now().naive_utc(),
       kind,
       spec_key: spec.key.to_owned(),
+      name: item.name.to_owned(),
+      namespace_name: namespace.to_owned(),
+      status: status.status,
+      status_message: status.status_message,
+      observed_spec_key: status.observed_spec_key,
+      attempts: status.attempts,
+      next_attempt_at: status.next_attempt_at,
+      status_updated_at: status.status_updated_at,
     };
     let resource_db = ResourceDb::create_from(new_item, pool).await?;
     let item = resource_db.with_spec(&spec);
     Ok(item)
   }
 
-  /// Update a resource from a spec.
+  /// Update a resource by key from a spec.
+  /// Its status is pending when its controller couldn't apply it.
   pub async fn update_from_spec(
+    key: &str,
     item: &ResourcePartial,
+    error: Option<&HttpClientError>,
     pool: &Pool,
   ) -> IoResult<Resource> {
-    let key = item.name.clone();
-    let resource = ResourceDb::transform_read_by_pk(&item.name, pool).await?;
+    let resource = ResourceDb::transform_read_by_pk(key, pool).await?;
     let (_, version) = ResourceDb::parse_kind(&item.kind, pool).await?;
     let spec = SpecDb {
       key: uuid::Uuid::new_v4(),
@@ -182,21 +217,84 @@ impl ResourceDb {
       key: None,
       spec_key: Some(spec.key.to_owned()),
     };
-    let resource_db =
-      ResourceDb::update_pk(&key, resource_update, pool).await?;
+    let resource_db = ResourceDb::update_pk(key, resource_update, pool).await?;
+    let status = utils::resource::gen_status(
+      resource.status.observed_spec_key,
+      0,
+      spec.key,
+      error.map_or(Ok(()), Err),
+    );
+    let resource_db = ResourceDb::update_status(key, &spec.key, status, pool)
+      .await?
+      .unwrap_or(resource_db);
     let item = resource_db.with_spec(&spec);
     Ok(item)
   }
 
-  /// This hook is called when a resource is created.
+  /// Count the resources of a kind or of a version of a kind
+  pub async fn count_by_kind(
+    kind: &str,
+    version: Option<&str>,
+    pool: &Pool,
+  ) -> IoResult<i64> {
+    let pool = pool.clone();
+    let kind = kind.to_owned();
+    let version = version.map(ToOwned::to_owned);
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let mut query = resources::table
+        .inner_join(crate::schema::specs::table)
+        .filter(resources::kind.eq(kind))
+        .into_boxed();
+      if let Some(version) = version {
+        query = query.filter(crate::schema::specs::version.eq(version));
+      }
+      let count = query.count().get_result(&mut conn).map_err(Self::map_err)?;
+      Ok::<_, IoError>(count)
+    })
+    .await?
+  }
+
+  /// Save the status of a resource if its spec didn't change
+  /// since it was applied
+  pub async fn update_status(
+    key: &str,
+    spec_key: &uuid::Uuid,
+    status: ResourceStatusDb,
+    pool: &Pool,
+  ) -> IoResult<Option<ResourceDb>> {
+    let pool = pool.clone();
+    let key = key.to_owned();
+    let spec_key = *spec_key;
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let item = diesel::update(
+        resources::table
+          .filter(resources::key.eq(key))
+          .filter(resources::spec_key.eq(spec_key)),
+      )
+      .set(status)
+      .get_result(&mut conn)
+      .optional()
+      .map_err(Self::map_err)?;
+      Ok::<_, IoError>(item)
+    })
+    .await?
+  }
+
+  /// This hook is called when a resource is created or updated.
   /// It call a custom controller at a specific url or just validate a schema.
+  /// A resource rejected by the controller is refused, the error of a controller
+  /// that couldn't be reached is returned so the resource is saved as pending.
   /// If the resource is a Kind Kind, it will create a resource Kind with an associated version.
   /// To call a custom controller, the resource Kind must have a Url field in his config.
   /// Unless it must have a Schema field in his config that is a Validator to validate the resource.
+  /// The controller receives the namespace of the resource with its name.
   pub async fn hook_create(
     resource: &ResourcePartial,
+    namespace: &str,
     pool: &Pool,
-  ) -> HttpResult<ResourcePartial> {
+  ) -> HttpResult<(ResourcePartial, Option<HttpClientError>)> {
     let mut resource = resource.clone();
     let (kind, version) = ResourceDb::parse_kind(&resource.kind, pool).await?;
     log::trace!("hook_create_resource kind: {kind} {version}");
@@ -216,12 +314,21 @@ impl ResourceDb {
     }
     if let Some(url) = &kind.data.url {
       let ctrl_client = utils::ctrl_client::CtrlClient::new(&kind.name, url);
-      let config = ctrl_client
-        .apply_rule(&version, &resource.name, &resource.data)
-        .await?;
-      resource.data = config;
+      match ctrl_client
+        .apply_rule(&version, &resource.name, namespace, &resource.data)
+        .await
+      {
+        Ok(config) => resource.data = config,
+        Err(err) if utils::resource::is_rejected(&err) => {
+          return Err(err.into())
+        }
+        Err(err) => {
+          log::warn!("hook_create_resource: {} {err}", resource.name);
+          return Ok((resource, Some(err)));
+        }
+      }
     }
-    Ok(resource)
+    Ok((resource, None))
   }
 
   /// This hook is called when a resource is deleted.
@@ -236,7 +343,43 @@ impl ResourceDb {
     if let Some(url) = &kind.data.url {
       let ctrl_client = utils::ctrl_client::CtrlClient::new(&kind.name, url);
       ctrl_client
-        .delete_rule(&resource.spec.version, &resource.spec.resource_key)
+        .delete_rule(
+          &resource.spec.version,
+          &resource.name,
+          &resource.namespace_name,
+        )
+        .await?;
+    }
+    Ok(())
+  }
+
+  /// List the resources of a namespace or of every namespace
+  pub async fn list(
+    query: &GenericFilterNsp,
+    state: &SystemState,
+  ) -> HttpResult<Vec<Resource>> {
+    let mut filter = query.filter.clone().unwrap_or_default();
+    if let Some(namespace) = &query.namespace {
+      NamespaceDb::read_by_pk(namespace, &state.inner.pool).await?;
+      filter =
+        filter.r#where("namespace_name", GenericClause::Eq(namespace.clone()));
+    }
+    let resources =
+      ResourceDb::transform_read_by(&filter, &state.inner.pool).await?;
+    Ok(resources)
+  }
+
+  /// Delete the resources of a namespace
+  pub async fn delete_by_namespace(
+    namespace: &str,
+    state: &SystemState,
+  ) -> HttpResult<()> {
+    let filter = GenericFilter::new()
+      .r#where("namespace_name", GenericClause::Eq(namespace.to_owned()));
+    let resources =
+      ResourceDb::transform_read_by(&filter, &state.inner.pool).await?;
+    for resource in resources {
+      ResourceDb::del_obj_by_pk(&resource.spec.resource_key, &(), state)
         .await?;
     }
     Ok(())
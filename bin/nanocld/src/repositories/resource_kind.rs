@@ -9,13 +9,18 @@ use nanocl_error::{
 
 use nanocl_stubs::{
   generic::{GenericClause, GenericFilter},
-  resource_kind::{ResourceKind, ResourceKindInspect, ResourceKindPartial},
+  resource_kind::{
+    ResourceKind, ResourceKindInspect, ResourceKindPartial, ResourceKindVersion,
+  },
 };
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
-  models::{ColumnType, Pool, ResourceKindDb, ResourceKindDbUpdate, SpecDb},
+  models::{
+    ColumnType, Pool, ResourceDb, ResourceKindDb, ResourceKindDbUpdate, SpecDb,
+  },
   schema::resource_kinds,
+  utils,
 };
 
 use super::generic::*;
@@ -125,6 +130,9 @@ impl ResourceKindDb {
         &item.version, &item.name
       )));
     }
+    if let Some(conversion) = &item.data.conversion {
+      utils::resource::check_conversion(conversion)?;
+    }
     let kind_version: SpecDb = item.try_into()?;
     let version = SpecDb::create_from(kind_version, pool).await?;
     match ResourceKindDb::transform_read_by_pk(&item.name, pool).await {
@@ -146,4 +154,82 @@ impl ResourceKindDb {
     let item: ResourceKind = version.try_into()?;
     Ok(item)
   }
+
+  /// List the versions of a kind from the oldest to the newest
+  pub async fn list_versions(
+    name: &str,
+    pool: &Pool,
+  ) -> IoResult<Vec<ResourceKindVersion>> {
+    let mut versions = SpecDb::read_by_kind_key(name, pool)
+      .await?
+      .into_iter()
+      .map(ResourceKindVersion::try_from)
+      .collect::<IoResult<Vec<_>>>()?;
+    versions.sort_by_key(|version| version.created_at);
+    Ok(versions)
+  }
+
+  /// Ensure no resource use a kind or a version of a kind
+  pub async fn ensure_unused(
+    name: &str,
+    version: Option<&str>,
+    pool: &Pool,
+  ) -> HttpResult<()> {
+    let count = ResourceDb::count_by_kind(name, version, pool).await?;
+    if count > 0 {
+      let kind = match version {
+        Some(version) => format!("Version {version} of {name}"),
+        None => format!("Kind {name}"),
+      };
+      return Err(HttpError::conflict(format!(
+        "{kind} is still used by {count} resources, migrate or delete them first"
+      )));
+    }
+    Ok(())
+  }
+
+  /// Delete a version of a kind not used by any resource.
+  /// A version between two others can't be deleted while an older version
+  /// is used, its conversion is needed to migrate the older resources.
+  /// The kind moves to its newest version left or is deleted with its last version.
+  pub async fn delete_version(
+    name: &str,
+    version: &str,
+    pool: &Pool,
+  ) -> HttpResult<()> {
+    let (kind, _) = ResourceKindDb::read_by_pk(name, pool).await?;
+    let spec = SpecDb::get_version(name, version, pool).await?;
+    ResourceKindDb::ensure_unused(name, Some(version), pool).await?;
+    let versions = ResourceKindDb::list_versions(name, pool).await?;
+    let position = versions.iter().position(|item| item.key == spec.key);
+    if let Some(position) = position.filter(|pos| pos + 1 < versions.len()) {
+      for older in &versions[..position] {
+        let count =
+          ResourceDb::count_by_kind(name, Some(&older.version), pool).await?;
+        if count > 0 {
+          return Err(HttpError::conflict(format!(
+            "Version {version} of {name} is needed to migrate {count} resources from {}, migrate or delete them first",
+            older.version
+          )));
+        }
+      }
+    }
+    let Some(newest) = versions.iter().rev().find(|item| item.key != spec.key)
+    else {
+      ResourceKindDb::del_by_pk(name, pool).await?;
+      SpecDb::del_by_kind_key(name, pool).await?;
+      return Ok(());
+    };
+    if kind.spec_key == spec.key {
+      let update = ResourceKindDbUpdate {
+        spec_key: newest.key,
+      };
+      ResourceKindDb::update_pk(name, update, pool).await?;
+    }
+    let filter = GenericFilter::new()
+      .r#where("kind_key", GenericClause::Eq(name.to_owned()))
+      .r#where("version", GenericClause::Eq(version.to_owned()));
+    SpecDb::del_by(&filter, pool).await?;
+    Ok(())
+  }
 }
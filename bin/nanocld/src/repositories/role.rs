@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use nanocl_stubs::generic::GenericFilter;
+
+use nanocl_stubs::auth::Role;
+
+use crate::{
+  gen_sql_multiple, gen_sql_order_by, gen_sql_query,
+  models::{ColumnType, RoleDb, RoleUpdateDb},
+  schema::roles,
+};
+
+use super::generic::*;
+
+impl RepositoryBase for RoleDb {
+  fn get_columns<'a>() -> HashMap<&'a str, (ColumnType, &'a str)> {
+    HashMap::from([
+      ("key", (ColumnType::Text, "roles.key")),
+      ("created_at", (ColumnType::Timestamptz, "roles.created_at")),
+      ("updated_at", (ColumnType::Timestamptz, "roles.updated_at")),
+      ("rules", (ColumnType::Json, "roles.rules")),
+    ])
+  }
+}
+
+impl RepositoryCreate for RoleDb {}
+
+impl RepositoryDelByPk for RoleDb {}
+
+impl RepositoryUpdate for RoleDb {
+  type UpdateItem = RoleUpdateDb;
+}
+
+impl RepositoryReadBy for RoleDb {
+  type Output = RoleDb;
+
+  fn get_pk() -> &'static str {
+    "key"
+  }
+
+  fn gen_read_query(
+    filter: &GenericFilter,
+    is_multiple: bool,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<
+    'static,
+    diesel::pg::PgConnection,
+    Self::Output,
+  > {
+    let mut query = roles::table.into_boxed();
+    let columns = Self::get_columns();
+    query = gen_sql_query!(query, filter, columns);
+    if let Some(orders) = &filter.order_by {
+      query = gen_sql_order_by!(query, orders, columns);
+    } else {
+      query = query.order(roles::created_at.desc());
+    }
+    if is_multiple {
+      gen_sql_multiple!(query, filter);
+    }
+    query
+  }
+}
+
+impl RepositoryCountBy for RoleDb {
+  fn gen_count_query(
+    filter: &GenericFilter,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<'static, diesel::PgConnection, i64>
+  {
+    let mut query = roles::table.into_boxed();
+    let columns = Self::get_columns();
+    gen_sql_query!(query, filter, columns).count()
+  }
+}
+
+impl RepositoryReadByTransform for RoleDb {
+  type NewOutput = Role;
+
+  fn transform(
+    input: Self::Output,
+  ) -> nanocl_error::io::IoResult<Self::NewOutput> {
+    input.try_into()
+  }
+}
@@ -2,14 +2,16 @@ use std::collections::HashMap;
 
 use diesel::prelude::*;
 
+use nanocl_error::io::{IoError, IoResult};
 use nanocl_stubs::generic::GenericFilter;
 
 use nanocl_stubs::secret::Secret;
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
-  models::{ColumnType, SecretDb, SecretUpdateDb},
+  models::{ColumnType, Pool, SecretDb, SecretUpdateDb, SystemState},
   schema::secrets,
+  utils,
 };
 
 use super::generic::*;
@@ -29,6 +31,7 @@ impl RepositoryBase for SecretDb {
       ),
       ("data", (ColumnType::Json, "secrets.data")),
       ("metadata", (ColumnType::Json, "secrets.metadata")),
+      ("key_id", (ColumnType::Text, "secrets.key_id")),
     ])
   }
 }
@@ -91,3 +94,42 @@ impl RepositoryReadByTransform for SecretDb {
     input.try_into()
   }
 }
+
+impl SecretDb {
+  /// Read a secret by its key and decrypt its data
+  pub async fn read_decrypted_by_pk(
+    pk: &str,
+    state: &SystemState,
+  ) -> IoResult<Secret> {
+    let secret = SecretDb::read_by_pk(pk, &state.inner.pool).await?;
+    utils::secret::decrypt(secret, state)
+  }
+
+  /// List the ids of the keys used to encrypt the stored secrets
+  pub async fn list_key_ids(pool: &Pool) -> IoResult<Vec<String>> {
+    let pool = pool.clone();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      let key_ids = secrets::table
+        .select(secrets::key_id)
+        .filter(secrets::key_id.is_not_null())
+        .distinct()
+        .load::<Option<String>>(&mut conn)
+        .map_err(Self::map_err)?;
+      Ok::<_, IoError>(key_ids.into_iter().flatten().collect())
+    })
+    .await?
+  }
+
+  /// Read secrets matching the filter and decrypt their data
+  pub async fn read_decrypted_by(
+    filter: &GenericFilter,
+    state: &SystemState,
+  ) -> IoResult<Vec<Secret>> {
+    SecretDb::read_by(filter, &state.inner.pool)
+      .await?
+      .into_iter()
+      .map(|secret| utils::secret::decrypt(secret, state))
+      .collect()
+  }
+}
@@ -34,6 +34,8 @@ impl RepositoryBase for SpecDb {
 
 impl RepositoryCreate for SpecDb {}
 
+impl RepositoryDelByPk for SpecDb {}
+
 impl RepositoryDelBy for SpecDb {
   fn gen_del_query(
     filter: &GenericFilter,
@@ -153,6 +155,8 @@ impl SpecDb {
       secrets: p.secrets,
       container: p.container,
       replication: p.replication,
+      update_strategy: p.update_strategy,
+      autoscale: p.autoscale,
       image_pull_secret: p.image_pull_secret,
       image_pull_policy: p.image_pull_policy,
     };
@@ -1,5 +1,15 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_tokens (key) {
+        key -> Uuid,
+        created_at -> Timestamptz,
+        identity_key -> Varchar,
+        token_hash -> Varchar,
+        expires_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     cargoes (key) {
         key -> Varchar,
@@ -28,6 +38,26 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    identities (key) {
+        key -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        subject -> Nullable<Varchar>,
+        roles -> Jsonb,
+    }
+}
+
+diesel::table! {
+    job_runs (key) {
+        key -> Varchar,
+        job_key -> Varchar,
+        created_at -> Timestamptz,
+        attempts -> Jsonb,
+        steps -> Jsonb,
+    }
+}
+
 diesel::table! {
     jobs (key) {
         key -> Varchar,
@@ -36,6 +66,23 @@ diesel::table! {
         status_key -> Varchar,
         data -> Jsonb,
         metadata -> Nullable<Jsonb>,
+        next_run_at -> Nullable<Timestamptz>,
+        last_run_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    metric_rollups (key) {
+        key -> Uuid,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        node_name -> Varchar,
+        kind -> Varchar,
+        resolution -> Int8,
+        bucket -> Timestamptz,
+        labels -> Jsonb,
+        count -> Int8,
+        fields -> Jsonb,
     }
 }
 
@@ -51,11 +98,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    namespace_quota_locks (namespace_name) {
+        namespace_name -> Varchar,
+        token -> Uuid,
+        expires_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     namespaces (name) {
         name -> Varchar,
         created_at -> Timestamptz,
         metadata -> Nullable<Jsonb>,
+        network -> Nullable<Jsonb>,
+        quota -> Nullable<Jsonb>,
+        limit_range -> Nullable<Jsonb>,
     }
 }
 
@@ -73,6 +131,14 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    node_leases (name) {
+        name -> Varchar,
+        node_name -> Varchar,
+        expires_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     nodes (name) {
         name -> Varchar,
@@ -123,6 +189,23 @@ diesel::table! {
         created_at -> Timestamptz,
         kind -> Varchar,
         spec_key -> Uuid,
+        name -> Varchar,
+        namespace_name -> Varchar,
+        status -> Varchar,
+        status_message -> Nullable<Varchar>,
+        observed_spec_key -> Nullable<Uuid>,
+        attempts -> Int4,
+        next_attempt_at -> Nullable<Timestamptz>,
+        status_updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    roles (key) {
+        key -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        rules -> Jsonb,
     }
 }
 
@@ -135,6 +218,7 @@ diesel::table! {
         immutable -> Bool,
         data -> Jsonb,
         metadata -> Nullable<Jsonb>,
+        key_id -> Nullable<Varchar>,
     }
 }
 
@@ -175,6 +259,7 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(api_tokens -> identities (identity_key));
 diesel::joinable!(cargoes -> namespaces (namespace_name));
 diesel::joinable!(cargoes -> object_process_statuses (status_key));
 diesel::joinable!(cargoes -> specs (spec_key));
@@ -183,6 +268,7 @@ diesel::joinable!(node_group_links -> node_groups (node_group_name));
 diesel::joinable!(node_group_links -> nodes (node_name));
 diesel::joinable!(processes -> nodes (node_name));
 diesel::joinable!(resource_kinds -> specs (spec_key));
+diesel::joinable!(resources -> namespaces (namespace_name));
 diesel::joinable!(resources -> specs (spec_key));
 diesel::joinable!(vm_images -> nodes (node_name));
 diesel::joinable!(vms -> namespaces (namespace_name));
@@ -190,18 +276,25 @@ diesel::joinable!(vms -> object_process_statuses (status_key));
 diesel::joinable!(vms -> specs (spec_key));
 
 diesel::allow_tables_to_appear_in_same_query!(
+  api_tokens,
   cargoes,
   events,
+  identities,
+  job_runs,
   jobs,
+  metric_rollups,
   metrics,
+  namespace_quota_locks,
   namespaces,
   node_group_links,
   node_groups,
+  node_leases,
   nodes,
   object_process_statuses,
   processes,
   resource_kinds,
   resources,
+  roles,
   secrets,
   specs,
   vm_images,
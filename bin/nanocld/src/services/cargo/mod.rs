@@ -41,7 +41,7 @@ mod tests {
     cargo::{
       Cargo, CargoDeleteQuery, CargoInspect, CargoKillOptions, CargoSummary,
     },
-    cargo_spec::{CargoSpec, CargoSpecPartial},
+    cargo_spec::{CargoAutoscale, CargoSpec, CargoSpecPartial},
     proxy::ProxySslConfig,
     secret::SecretPartial,
     system::{EventActorKind, EventCondition, EventKind, NativeEventAction},
@@ -398,4 +398,67 @@ mod tests {
     ntex::time::sleep(std::time::Duration::from_secs(1)).await;
     system.state.wait_event_loop().await;
   }
+
+  #[ntex::test]
+  async fn autoscale() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let spec = |autoscale| CargoSpecPartial {
+      name: "test-cargo-autoscale".to_owned(),
+      container: bollard_next::container::Config {
+        image: Some("ghcr.io/next-hat/nanocl-get-started:latest".to_owned()),
+        ..Default::default()
+      },
+      autoscale: Some(autoscale),
+      ..Default::default()
+    };
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(spec(CargoAutoscale {
+          min_replicas: 3,
+          max_replicas: 2,
+          target_cpu: Some(50.0),
+          ..Default::default()
+        })),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create cargo with min replicas above max"
+    );
+    let autoscale = CargoAutoscale {
+      min_replicas: 1,
+      max_replicas: 3,
+      target_rps: Some(20.0),
+      ..Default::default()
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(spec(autoscale.clone())), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create cargo with autoscale"
+    );
+    let cargo = TestClient::res_json::<Cargo>(res).await;
+    assert_eq!(cargo.spec.autoscale, Some(autoscale));
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/test-cargo-autoscale"),
+        Some(CargoDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete cargo with autoscale"
+    );
+    system.state.wait_event_loop().await;
+  }
 }
@@ -3,9 +3,10 @@ use ntex::web;
 use nanocl_error::http::HttpResult;
 use nanocl_stubs::system::EventCondition;
 
-use crate::models::SystemState;
+use crate::{models::SystemState, utils::auth::ApiScope};
 
-/// Watch on new events of all peer nodes with optional condition to stop the stream
+/// Watch on new events of all peer nodes with optional condition to stop the stream.
+/// Only the events of the namespaces the identity can see are sent.
 #[cfg_attr(feature = "dev", utoipa::path(
   post,
   tag = "Events",
@@ -17,11 +18,12 @@ use crate::models::SystemState;
 ))]
 #[web::post("/events/watch")]
 pub async fn watch_event(
+  req: web::HttpRequest,
   state: web::types::State<SystemState>,
   condition: Option<web::types::Json<Vec<EventCondition>>>,
 ) -> HttpResult<web::HttpResponse> {
   let stream = state
-    .subscribe_raw(condition.map(|c| c.into_inner()))
+    .subscribe_raw(condition.map(|c| c.into_inner()), ApiScope::of(&req))
     .await?;
   Ok(
     web::HttpResponse::Ok()
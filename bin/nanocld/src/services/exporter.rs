@@ -0,0 +1,52 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{models::SystemState, utils};
+
+/// Export the metrics of the daemon in the prometheus text exposition format.
+/// Served outside of the versioned api to be scraped on `/metrics`.
+pub async fn export_metrics(
+  state: web::types::State<SystemState>,
+) -> HttpResult<web::HttpResponse> {
+  let body = utils::exporter::export(&state).await?;
+  Ok(
+    web::HttpResponse::Ok()
+      .content_type(utils::exporter::CONTENT_TYPE)
+      .body(body),
+  )
+}
+
+pub fn ntex_config(config: &mut web::ServiceConfig) {
+  config.service(
+    web::resource("/metrics")
+      .wrap(
+        nanocl_utils::ntex::middlewares::Authentication::new(
+          crate::utils::auth::ApiAuthorizer,
+        )
+        .finish(),
+      )
+      .route(web::get().to(export_metrics)),
+  );
+}
+
+#[cfg(test)]
+mod tests {
+  use ntex::http;
+
+  use crate::utils::tests::*;
+
+  #[ntex::test]
+  async fn export() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let mut res = client.get_unversioned("/metrics").send().await.unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "export metrics");
+    let body = res.body().await.unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("# TYPE nanocl_objects gauge"));
+    assert!(body.contains("# TYPE nanocl_event_loop_lag_seconds gauge"));
+    let res = client.send_get("/metrics", None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::OK, "list metrics");
+  }
+}
@@ -0,0 +1,31 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::auth::IdentityPartial;
+
+use crate::{
+  models::{IdentityDb, SystemState},
+  objects::generic::*,
+  utils,
+};
+
+/// Create a new identity
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  request_body = IdentityPartial,
+  tag = "Identities",
+  path = "/identities",
+  responses(
+    (status = 201, description = "The created identity", body = nanocl_stubs::auth::Identity),
+    (status = 409, description = "Identity already exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/identities")]
+pub async fn create_identity(
+  state: web::types::State<SystemState>,
+  payload: web::types::Json<IdentityPartial>,
+) -> HttpResult<web::HttpResponse> {
+  utils::key::validate_name(&payload.name)?;
+  let item = IdentityDb::create_obj(&payload, &state).await?;
+  Ok(web::HttpResponse::Created().json(&item))
+}
@@ -0,0 +1,30 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{
+  models::{IdentityDb, SystemState},
+  objects::generic::*,
+};
+
+/// Delete a identity
+#[cfg_attr(feature = "dev", utoipa::path(
+  delete,
+  tag = "Identities",
+  path = "/identities/{name}",
+  params(
+    ("name" = String, Path, description = "Name of the identity")
+  ),
+  responses(
+    (status = 202, description = "Identity have been deleted"),
+    (status = 404, description = "Identity doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::delete("/identities/{name}")]
+pub async fn delete_identity(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  IdentityDb::del_obj_by_pk(&path.1, &(), &state).await?;
+  Ok(web::HttpResponse::Accepted().into())
+}
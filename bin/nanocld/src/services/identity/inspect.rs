@@ -0,0 +1,31 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{
+  models::{IdentityDb, SystemState},
+  repositories::generic::*,
+};
+
+/// Get detailed information about a identity
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Identities",
+  path = "/identities/{name}/inspect",
+  params(
+    ("name" = String, Path, description = "Name of the identity"),
+  ),
+  responses(
+    (status = 200, description = "Detailed information about the identity", body = nanocl_stubs::auth::Identity),
+    (status = 404, description = "Identity doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/identities/{name}/inspect")]
+pub async fn inspect_identity(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let item =
+    IdentityDb::transform_read_by_pk(&path.1, &state.inner.pool).await?;
+  Ok(web::HttpResponse::Ok().json(&item))
+}
@@ -0,0 +1,32 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericListQuery;
+
+use crate::{
+  models::{IdentityDb, SystemState},
+  repositories::generic::*,
+  utils,
+};
+
+/// List identities with optional filter
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Identities",
+  path = "/identities",
+  params(
+    ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"key\": { \"eq\": \"admin\" } } } }"),
+  ),
+  responses(
+    (status = 200, description = "List of identities", body = [nanocl_stubs::auth::Identity]),
+  ),
+))]
+#[web::get("/identities")]
+pub async fn list_identity(
+  state: web::types::State<SystemState>,
+  qs: web::types::Query<GenericListQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let filter = utils::query_string::parse_qs_filter(&qs)?;
+  let items = IdentityDb::transform_read_by(&filter, &state.inner.pool).await?;
+  Ok(web::HttpResponse::Ok().json(&items))
+}
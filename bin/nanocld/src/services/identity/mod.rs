@@ -0,0 +1,137 @@
+use ntex::web;
+
+pub mod create;
+pub mod delete;
+pub mod inspect;
+pub mod list;
+pub mod patch;
+pub mod token;
+
+pub use create::*;
+pub use delete::*;
+pub use inspect::*;
+pub use list::*;
+pub use patch::*;
+pub use token::*;
+
+pub fn ntex_config(config: &mut web::ServiceConfig) {
+  config.service(list_identity);
+  config.service(create_identity);
+  config.service(inspect_identity);
+  config.service(patch_identity);
+  config.service(delete_identity);
+  config.service(create_identity_token);
+  config.service(list_identity_token);
+  config.service(delete_identity_token);
+}
+
+#[cfg(test)]
+mod test_identity {
+  use ntex::http;
+
+  use nanocl_stubs::auth::{
+    ApiToken, ApiTokenPartial, Identity, IdentityPartial, IdentityUpdate,
+    RolePartial, RoleRule, RoleVerb,
+  };
+
+  use crate::utils::tests::*;
+
+  const ENDPOINT: &str = "/identities";
+
+  #[ntex::test]
+  async fn basic() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let identity = IdentityPartial {
+      name: "test-identity".to_owned(),
+      roles: vec!["test-identity-role".to_owned()],
+      subject: None,
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&identity), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create identity with missing role"
+    );
+    let role = RolePartial {
+      name: "test-identity-role".to_owned(),
+      rules: vec![RoleRule {
+        kinds: vec!["*".to_owned()],
+        namespaces: vec!["*".to_owned()],
+        verbs: vec![RoleVerb::Read],
+      }],
+    };
+    let res = client
+      .send_post("/roles", Some(&role), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create role");
+    let mut res = client
+      .send_post(ENDPOINT, Some(&identity), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create identity"
+    );
+    let _ = res.json::<Identity>().await.unwrap();
+    let res = client
+      .send_delete("/roles/test-identity-role", None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "delete role bound to an identity"
+    );
+    let update = IdentityUpdate {
+      subject: Some("test-identity.nanocl.internal".to_owned()),
+      ..Default::default()
+    };
+    let mut res = client
+      .send_patch(
+        &format!("{ENDPOINT}/test-identity"),
+        Some(&update),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "patch identity");
+    let identity = res.json::<Identity>().await.unwrap();
+    assert_eq!(identity.roles, vec!["test-identity-role".to_owned()]);
+    let mut res = client
+      .send_post(
+        &format!("{ENDPOINT}/test-identity/tokens"),
+        Some(&ApiTokenPartial::default()),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create token");
+    let token = res.json::<ApiToken>().await.unwrap();
+    assert!(token.token.is_some());
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/test-identity/tokens"), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "list tokens");
+    let tokens = res.json::<Vec<ApiToken>>().await.unwrap();
+    assert!(tokens.iter().all(|token| token.token.is_none()));
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/test-identity/tokens/{}", token.key),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::ACCEPTED, "delete token");
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/test-identity"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete identity"
+    );
+    let res = client
+      .send_delete("/roles/test-identity-role", None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::ACCEPTED, "delete role");
+  }
+}
@@ -0,0 +1,33 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::auth::IdentityUpdate;
+
+use crate::{
+  models::{IdentityDb, SystemState},
+  objects::generic::*,
+};
+
+/// Update the roles or the certificate subject of an identity
+#[cfg_attr(feature = "dev", utoipa::path(
+  patch,
+  request_body = IdentityUpdate,
+  tag = "Identities",
+  path = "/identities/{name}",
+  params(
+    ("name" = String, Path, description = "Name of the identity")
+  ),
+  responses(
+    (status = 200, description = "Identity updated", body = nanocl_stubs::auth::Identity),
+    (status = 404, description = "Identity doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::patch("/identities/{name}")]
+pub async fn patch_identity(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<IdentityUpdate>,
+) -> HttpResult<web::HttpResponse> {
+  let item = IdentityDb::patch_obj_by_pk(&path.1, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&item))
+}
@@ -0,0 +1,90 @@
+use ntex::web;
+
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::{
+  auth::ApiTokenPartial,
+  generic::{GenericClause, GenericFilter},
+};
+
+use crate::{
+  models::{ApiTokenDb, SystemState},
+  repositories::generic::*,
+  utils,
+};
+
+/// Create an api token to authenticate as an identity
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  request_body = ApiTokenPartial,
+  tag = "Identities",
+  path = "/identities/{name}/tokens",
+  params(
+    ("name" = String, Path, description = "Name of the identity")
+  ),
+  responses(
+    (status = 201, description = "The created token, the token value is only returned once", body = nanocl_stubs::auth::ApiToken),
+    (status = 404, description = "Identity doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/identities/{name}/tokens")]
+pub async fn create_identity_token(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<ApiTokenPartial>,
+) -> HttpResult<web::HttpResponse> {
+  let token = utils::auth::create_token(&path.1, &payload, &state).await?;
+  Ok(web::HttpResponse::Created().json(&token))
+}
+
+/// List the api tokens of an identity
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Identities",
+  path = "/identities/{name}/tokens",
+  params(
+    ("name" = String, Path, description = "Name of the identity")
+  ),
+  responses(
+    (status = 200, description = "List of api tokens", body = [nanocl_stubs::auth::ApiToken]),
+  ),
+))]
+#[web::get("/identities/{name}/tokens")]
+pub async fn list_identity_token(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let filter = GenericFilter::new()
+    .r#where("identity_key", GenericClause::Eq(path.1.clone()));
+  let items = ApiTokenDb::transform_read_by(&filter, &state.inner.pool).await?;
+  Ok(web::HttpResponse::Ok().json(&items))
+}
+
+/// Revoke an api token of an identity
+#[cfg_attr(feature = "dev", utoipa::path(
+  delete,
+  tag = "Identities",
+  path = "/identities/{name}/tokens/{key}",
+  params(
+    ("name" = String, Path, description = "Name of the identity"),
+    ("key" = String, Path, description = "Key of the token"),
+  ),
+  responses(
+    (status = 202, description = "Token have been revoked"),
+    (status = 404, description = "Token doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::delete("/identities/{name}/tokens/{key}")]
+pub async fn delete_identity_token(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let token = ApiTokenDb::read_by_pk(&path.2, &state.inner.pool).await?;
+  if token.identity_key != path.1 {
+    return Err(HttpError::not_found(format!(
+      "Token {}: not found for identity {}",
+      path.2, path.1
+    )));
+  }
+  ApiTokenDb::del_by_pk(&token.key, &state.inner.pool).await?;
+  Ok(web::HttpResponse::Accepted().into())
+}
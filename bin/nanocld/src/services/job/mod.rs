@@ -22,7 +22,14 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
 
 #[cfg(test)]
 mod tests {
-  use nanocl_stubs::job::{Job, JobSummary};
+  use bollard_next::container::Config;
+  use nanocl_stubs::{
+    job::{
+      Job, JobConcurrencyPolicy, JobInspect, JobPartial, JobStep,
+      JobStepStatus, JobSummary,
+    },
+    system::ObjPsStatusKind,
+  };
   use ntex::http;
 
   use crate::utils::tests::*;
@@ -57,6 +64,168 @@ mod tests {
     );
   }
 
+  #[ntex::test]
+  async fn schedule() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let mut job = JobPartial {
+      name: "test-job-schedule".to_owned(),
+      schedule: Some("0 0 31 2 *".to_owned()),
+      ..Default::default()
+    };
+    let res = client.send_post(ENDPOINT, Some(&job), None::<String>).await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create job with a schedule that never match"
+    );
+    job.schedule = Some("0 9 * * 1-5".to_owned());
+    job.time_zone = Some("Mars/Olympus".to_owned());
+    let res = client.send_post(ENDPOINT, Some(&job), None::<String>).await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create job with an invalid time zone"
+    );
+    job.time_zone = Some("Europe/Paris".to_owned());
+    job.concurrency_policy = Some(JobConcurrencyPolicy::Forbid);
+    let res = client.send_post(ENDPOINT, Some(&job), None::<String>).await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create scheduled job"
+    );
+    let job_endpoint = format!("{ENDPOINT}/{}", &job.name);
+    let mut res = client
+      .send_get(&format!("{job_endpoint}/inspect"), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect job");
+    let inspect = res.json::<JobInspect>().await.unwrap();
+    let next_run_at = inspect.next_run_at.expect("Expect a next run");
+    assert!(next_run_at > chrono::Utc::now().naive_utc());
+    assert_eq!(inspect.spec.time_zone, job.time_zone);
+    let _ = client.send_delete(&job_endpoint, None::<String>).await;
+    ntex::time::sleep(std::time::Duration::from_secs(1)).await;
+    system.state.wait_event_loop().await;
+  }
+
+  #[ntex::test]
+  async fn retries() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let job = JobPartial {
+      name: "test-job-retries".to_owned(),
+      retries: Some(1),
+      backoff: Some(1),
+      containers: vec![Config {
+        image: Some("alpine:latest".to_owned()),
+        cmd: Some(vec!["sh".to_owned(), "-c".to_owned(), "exit 3".to_owned()]),
+        ..Default::default()
+      }],
+      ..Default::default()
+    };
+    let res = client.send_post(ENDPOINT, Some(&job), None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create job");
+    let job_endpoint = format!("{ENDPOINT}/{}", &job.name);
+    client
+      .send_post(
+        &format!("/processes/job/{}/start", &job.name),
+        None::<String>,
+        None::<String>,
+      )
+      .await;
+    let mut inspect = JobInspect::default();
+    for _ in 0..60 {
+      ntex::time::sleep(std::time::Duration::from_secs(1)).await;
+      let mut res = client
+        .send_get(&format!("{job_endpoint}/inspect"), None::<String>)
+        .await;
+      inspect = res.json::<JobInspect>().await.unwrap();
+      if inspect.spec.status.actual == ObjPsStatusKind::Fail {
+        break;
+      }
+    }
+    assert_eq!(inspect.spec.status.actual, ObjPsStatusKind::Fail);
+    assert_eq!(inspect.attempts.len(), 2);
+    assert_eq!(inspect.attempts[1].attempt, 2);
+    assert_eq!(inspect.attempts[1].exit_code, Some(3));
+    let _ = client.send_delete(&job_endpoint, None::<String>).await;
+    ntex::time::sleep(std::time::Duration::from_secs(1)).await;
+    system.state.wait_event_loop().await;
+  }
+
+  #[ntex::test]
+  async fn steps() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let step = |name: &str, depends_on: &[&str], cmd: &str| JobStep {
+      name: name.to_owned(),
+      depends_on: Some(depends_on.iter().map(|d| d.to_string()).collect()),
+      container: Config {
+        image: Some("alpine:latest".to_owned()),
+        cmd: Some(vec!["sh".to_owned(), "-c".to_owned(), cmd.to_owned()]),
+        ..Default::default()
+      },
+    };
+    let mut job = JobPartial {
+      name: "test-job-steps".to_owned(),
+      steps: Some(vec![step("a", &["b"], "true"), step("b", &["a"], "true")]),
+      ..Default::default()
+    };
+    let res = client.send_post(ENDPOINT, Some(&job), None::<String>).await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create job with a dependency cycle"
+    );
+    job.steps = Some(vec![
+      step("write", &[], "echo ok > /opt/nanocl.io/artifacts/out"),
+      step("read", &["write"], "grep ok /opt/nanocl.io/artifacts/out"),
+      step("fail", &[], "exit 1"),
+      step("after", &["fail"], "true"),
+    ]);
+    job.continue_on_failure = Some(true);
+    let res = client.send_post(ENDPOINT, Some(&job), None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create job");
+    let job_endpoint = format!("{ENDPOINT}/{}", &job.name);
+    client
+      .send_post(
+        &format!("/processes/job/{}/start", &job.name),
+        None::<String>,
+        None::<String>,
+      )
+      .await;
+    let mut inspect = JobInspect::default();
+    for _ in 0..60 {
+      ntex::time::sleep(std::time::Duration::from_secs(1)).await;
+      let mut res = client
+        .send_get(&format!("{job_endpoint}/inspect"), None::<String>)
+        .await;
+      inspect = res.json::<JobInspect>().await.unwrap();
+      if inspect.spec.status.actual == ObjPsStatusKind::Fail {
+        break;
+      }
+    }
+    assert_eq!(inspect.spec.status.actual, ObjPsStatusKind::Fail);
+    let statuses = inspect
+      .steps
+      .iter()
+      .map(|step| (step.name.as_str(), step.status))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      statuses,
+      vec![
+        ("write", JobStepStatus::Succeeded),
+        ("read", JobStepStatus::Succeeded),
+        ("fail", JobStepStatus::Failed),
+        ("after", JobStepStatus::Skipped),
+      ]
+    );
+    let _ = client.send_delete(&job_endpoint, None::<String>).await;
+    ntex::time::sleep(std::time::Duration::from_secs(1)).await;
+    system.state.wait_event_loop().await;
+  }
+
   #[ntex::test]
   async fn basic() {
     let system = gen_default_test_system().await;
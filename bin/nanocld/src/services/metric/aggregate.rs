@@ -0,0 +1,42 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::metric::MetricAggregateQuery;
+
+use crate::{
+  models::{MetricDb, SystemState},
+  utils,
+};
+
+/// Aggregate the metrics of a kind by time bucket and grouped fields
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Metrics",
+  path = "/metrics/aggregate",
+  params(
+    ("kind" = String, Query, description = "Kind of the metrics", example = "ncproxy.io/http"),
+    ("interval" = Option<u64>, Query, description = "Width of the time buckets in seconds default to 60"),
+    ("since" = Option<String>, Query, description = "Start of the time range in UTC default to one hour before until", example = "2024-12-24T00:00:00"),
+    ("until" = Option<String>, Query, description = "End of the time range in UTC default to now"),
+    ("group_by" = Option<String>, Query, description = "Comma separated fields to group by", example = "host,status,node_name"),
+    ("fields" = Option<String>, Query, description = "Comma separated numeric fields to aggregate", example = "request_time,bytes_sent"),
+  ),
+  responses(
+    (status = 200, description = "Aggregated metrics by time bucket", body = [nanocl_stubs::metric::MetricAggregate]),
+    (status = 400, description = "Invalid query", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/metrics/aggregate")]
+pub async fn aggregate_metric(
+  state: web::types::State<SystemState>,
+  qs: web::types::Query<MetricAggregateQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let mut plan = utils::metric::parse_aggregate_query(&qs)?;
+  plan.resolution = utils::metric::select_resolution(
+    &plan,
+    &state.inner.config.metric_retention,
+    chrono::Utc::now().naive_utc(),
+  );
+  let items = MetricDb::aggregate(&plan, &state.inner.pool).await?;
+  Ok(web::HttpResponse::Ok().json(&items))
+}
@@ -1,11 +1,12 @@
 use ntex::web;
 
 use nanocl_error::http::{HttpError, HttpResult};
-use nanocl_stubs::metric::MetricPartial;
+use nanocl_stubs::metric::{HttpMetric, MetricPartial};
 
 use crate::{
   models::{MetricDb, MetricNodePartial, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 /// Create a new metric
@@ -28,8 +29,21 @@ pub async fn create_metric(
   if payload.kind.starts_with("nanocl.io") {
     return Err(HttpError::bad_request("reserved kind nanocl.io"));
   }
-  let new_metric =
-    MetricNodePartial::try_new_node(&state.inner.config.hostname, &payload)?;
+  let expires_at = utils::metric::expires_at(
+    &payload.kind,
+    &state.inner.config.metric_retention,
+  );
+  let new_metric = MetricNodePartial::try_new_node(
+    &state.inner.config.hostname,
+    &payload,
+    expires_at,
+  )?;
   let metric = MetricDb::create_from(&new_metric, &state.inner.pool).await?;
+  if payload.kind == "ncproxy.io/http" {
+    match serde_json::from_value::<HttpMetric>(payload.data.clone()) {
+      Ok(http) => state.inner.exporter.observe_http(&http),
+      Err(err) => log::warn!("create_metric: invalid http metric {err}"),
+    }
+  }
   Ok(web::HttpResponse::Created().json(&metric))
 }
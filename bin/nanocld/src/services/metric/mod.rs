@@ -1,10 +1,12 @@
 use ntex::web;
 
+pub mod aggregate;
 pub mod count;
 pub mod create;
 pub mod inspect;
 pub mod list;
 
+pub use aggregate::*;
 pub use count::*;
 pub use create::*;
 pub use inspect::*;
@@ -15,13 +17,14 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(create_metric);
   config.service(inspect_metric);
   config.service(count_metric);
+  config.service(aggregate_metric);
 }
 
 #[cfg(test)]
 mod tests {
   use nanocl_stubs::{
     generic::{GenericClause, GenericFilter, GenericListQuery},
-    metric::{Metric, MetricPartial},
+    metric::{Metric, MetricAggregate, MetricAggregateQuery, MetricPartial},
   };
   use ntex::http;
 
@@ -77,5 +80,37 @@ mod tests {
       )
       .await;
     test_status_code!(res.status(), http::StatusCode::OK, "inspect metric");
+    let query = MetricAggregateQuery {
+      kind: "test.io/test".to_owned(),
+      group_by: Some("test,node_name".to_owned()),
+      fields: Some("value".to_owned()),
+      ..Default::default()
+    };
+    let res = client
+      .send_get(&format!("{ENDPOINT}/aggregate"), Some(&query))
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "aggregate metric");
+    let items = TestClient::res_json::<Vec<MetricAggregate>>(res).await;
+    assert!(
+      !items.is_empty(),
+      "Expect the created metric to be aggregated"
+    );
+    assert_eq!(items[0].group["test"].as_deref(), Some("test"));
+    assert_eq!(items[0].fields[0].field, "value");
+    assert_eq!(items[0].fields[0].count, 0);
+    let res = client
+      .send_get(
+        &format!("{ENDPOINT}/aggregate"),
+        Some(&MetricAggregateQuery {
+          fields: Some("value'".to_owned()),
+          ..query
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "aggregate metric with invalid field"
+    );
   }
 }
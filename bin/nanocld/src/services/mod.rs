@@ -8,6 +8,8 @@ pub mod openapi;
 mod cargo;
 mod event;
 mod exec;
+mod exporter;
+mod identity;
 mod job;
 mod metric;
 mod namespace;
@@ -15,7 +17,9 @@ mod node;
 mod process;
 mod resource;
 mod resource_kind;
+mod role;
 mod secret;
+mod service;
 mod system;
 mod vm;
 mod vm_image;
@@ -44,8 +48,15 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
         .configure(swagger::register),
     );
   }
+  exporter::ntex_config(config);
   config.service(
     web::scope("/{version}")
+      .wrap(
+        nanocl_utils::ntex::middlewares::Authentication::new(
+          crate::utils::auth::ApiAuthorizer,
+        )
+        .finish(),
+      )
       .wrap(
         nanocl_utils::ntex::middlewares::Versioning::new(crate::vars::VERSION)
           .finish(),
@@ -60,10 +71,13 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
       .configure(vm::ntex_config)
       .configure(metric::ntex_config)
       .configure(secret::ntex_config)
+      .configure(service::ntex_config)
       .configure(process::ntex_config)
       .configure(job::ntex_config)
       .configure(event::ntex_config)
-      .configure(resource_kind::ntex_config),
+      .configure(resource_kind::ntex_config)
+      .configure(role::ntex_config)
+      .configure(identity::ntex_config),
   );
 }
 
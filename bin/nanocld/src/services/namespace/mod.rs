@@ -22,9 +22,19 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
 
 #[cfg(test)]
 mod test_namespace {
+  use ntex::http;
+
   use serde_json::json;
 
-  use nanocl_stubs::namespace::{Namespace, NamespacePartial};
+  use nanocl_stubs::{
+    cargo::Cargo,
+    cargo_spec::CargoSpecPartial,
+    generic::GenericNspQuery,
+    namespace::{
+      Namespace, NamespaceInspect, NamespaceLimitRange, NamespaceNetwork,
+      NamespacePartial, NamespaceQuota,
+    },
+  };
 
   use crate::utils::tests::*;
 
@@ -40,6 +50,9 @@ mod test_namespace {
     let new_namespace = NamespacePartial {
       name: String::from("controller-default"),
       metadata: None,
+      network: None,
+      quota: None,
+      limit_range: None,
     };
     let res = client
       .send_post(ENDPOINT, Some(new_namespace), None::<String>)
@@ -94,4 +107,141 @@ mod test_namespace {
     delete(&client).await;
     system.state.wait_event_loop().await;
   }
+
+  #[ntex::test]
+  async fn network() {
+    const NAME: &str = "controller-network";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let mut new_namespace = NamespacePartial {
+      name: NAME.to_owned(),
+      metadata: None,
+      network: Some(NamespaceNetwork {
+        connect: vec!["controller-unknown".to_owned()],
+      }),
+      quota: None,
+      limit_range: None,
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&new_namespace), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create namespace connected to unknown"
+    );
+    new_namespace.network = Some(NamespaceNetwork {
+      connect: vec!["global".to_owned()],
+    });
+    let res = client
+      .send_post(ENDPOINT, Some(&new_namespace), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create namespace connected to global"
+    );
+    let namespace = TestClient::res_json::<Namespace>(res).await;
+    assert_eq!(namespace.network, new_namespace.network);
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{NAME}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete namespace"
+    );
+    system.state.wait_event_loop().await;
+  }
+
+  #[ntex::test]
+  async fn quota() {
+    const NAME: &str = "controller-quota";
+    const MEGABYTE: i64 = 1024 * 1024;
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let new_namespace = NamespacePartial {
+      name: NAME.to_owned(),
+      metadata: None,
+      network: None,
+      quota: Some(NamespaceQuota {
+        max_cargoes: Some(1),
+        ..Default::default()
+      }),
+      limit_range: Some(NamespaceLimitRange {
+        default_memory: Some(32 * MEGABYTE),
+        max_memory: Some(64 * MEGABYTE),
+        ..Default::default()
+      }),
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&new_namespace), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create namespace with quota"
+    );
+    let spec = |name: &str, memory: Option<i64>| CargoSpecPartial {
+      name: name.to_owned(),
+      container: bollard_next::container::Config {
+        image: Some("ghcr.io/next-hat/nanocl-get-started:latest".to_owned()),
+        host_config: Some(bollard_next::service::HostConfig {
+          memory,
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    let query = Some(GenericNspQuery::new(Some(NAME)));
+    let res = client
+      .send_post(
+        "/cargoes",
+        Some(spec("quota-1", Some(128 * MEGABYTE))),
+        query.clone(),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create cargo above the limit range"
+    );
+    let res = client
+      .send_post("/cargoes", Some(spec("quota-1", None)), query.clone())
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create cargo with default memory"
+    );
+    let cargo = TestClient::res_json::<Cargo>(res).await;
+    assert_eq!(
+      cargo.spec.container.host_config.unwrap().memory,
+      Some(32 * MEGABYTE)
+    );
+    let res = client
+      .send_post("/cargoes", Some(spec("quota-2", None)), query)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::FORBIDDEN,
+      "create cargo above the quota"
+    );
+    let res = client
+      .send_get(&format!("{ENDPOINT}/{NAME}/inspect"), None::<String>)
+      .await;
+    let namespace = TestClient::res_json::<NamespaceInspect>(res).await;
+    assert_eq!(namespace.usage.cargoes, 1);
+    assert_eq!(namespace.usage.memory, 32 * MEGABYTE);
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{NAME}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete namespace with quota"
+    );
+    system.state.wait_event_loop().await;
+  }
 }
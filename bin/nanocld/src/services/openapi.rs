@@ -7,8 +7,8 @@ use nanocl_stubs::{
 use crate::vars;
 
 use super::{
-  cargo, event, exec, job, metric, namespace, node, process, resource,
-  resource_kind, secret, system, vm, vm_image,
+  cargo, event, exec, identity, job, metric, namespace, node, process,
+  resource, resource_kind, role, secret, service, system, vm, vm_image,
 };
 
 /// When returning a [HttpError](nanocl_error::http::HttpError)
@@ -67,6 +67,7 @@ impl Modify for VersionModifier {
     secret::delete_secret,
     secret::patch_secret,
     secret::count_secret,
+    secret::rotate_secret_key,
     // Job
     job::list_job,
     job::delete_job,
@@ -106,6 +107,7 @@ impl Modify for VersionModifier {
     resource_kind::list_resource_kind,
     resource_kind::create_resource_kind,
     resource_kind::delete_resource_kind,
+    resource_kind::delete_resource_kind_version,
     resource_kind::inspect_resource_kind,
     resource_kind::count_resource_kind,
     resource_kind::inspect_resource_kind_version,
@@ -117,12 +119,14 @@ impl Modify for VersionModifier {
     resource::put_resource,
     resource::list_resource_history,
     resource::revert_resource,
+    resource::migrate_resource,
     resource::count_resource,
     // Metric
     metric::list_metric,
     metric::create_metric,
     metric::inspect_metric,
     metric::count_metric,
+    metric::aggregate_metric,
     // Process
     process::logs_processes,
     process::logs_process,
@@ -136,11 +140,29 @@ impl Modify for VersionModifier {
     process::count_processes,
     process::inspect_process,
     process::start_process_by_pk,
+    // Service
+    service::list_service,
+    service::inspect_service,
     // Event
     event::list_event,
     event::watch_event,
     event::inspect_event,
     event::count_event,
+    // Role
+    role::list_role,
+    role::create_role,
+    role::inspect_role,
+    role::put_role,
+    role::delete_role,
+    // Identity
+    identity::list_identity,
+    identity::create_identity,
+    identity::inspect_identity,
+    identity::patch_identity,
+    identity::delete_identity,
+    identity::create_identity_token,
+    identity::list_identity_token,
+    identity::delete_identity_token,
   ),
   components(schemas(Statefile, ResourceProxyRule, ResourceDnsRule)),
   tags(
@@ -153,8 +175,11 @@ impl Modify for VersionModifier {
     (name = "Metrics", description = "Metrics management endpoints."),
     (name = "Processes", description = "Processes management endpoints."),
     (name = "Secrets", description = "Secrets management endpoints."),
+    (name = "Services", description = "Service discovery endpoints."),
     (name = "Jobs", description = "Jobs management endpoints."),
     (name = "Events", description = "Events management endpoints."),
+    (name = "Roles", description = "Roles management endpoints."),
+    (name = "Identities", description = "Identities and api tokens management endpoints."),
   ),
   modifiers(&VersionModifier),
 )]
@@ -6,10 +6,10 @@ use nanocl_stubs::generic::{GenericCount, GenericListQuery};
 use crate::{
   models::{ProcessDb, SystemState},
   repositories::generic::*,
-  utils,
+  utils::{self, auth::ApiScope},
 };
 
-/// Count processes
+/// Count processes in the namespaces the identity can see
 #[cfg_attr(feature = "dev", utoipa::path(
   get,
   tag = "Processes",
@@ -23,10 +23,17 @@ use crate::{
 ))]
 #[web::get("/processes/count")]
 pub async fn count_processes(
+  req: web::HttpRequest,
   state: web::types::State<SystemState>,
   qs: web::types::Query<GenericListQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let filter = utils::query_string::parse_qs_filter(&qs)?;
-  let count = ProcessDb::count_by(&filter, &state.inner.pool).await?;
+  let count = match ApiScope::of(&req) {
+    Some(namespaces) => {
+      ProcessDb::count_in_namespaces(&filter, &namespaces, &state.inner.pool)
+        .await?
+    }
+    None => ProcessDb::count_by(&filter, &state.inner.pool).await?,
+  };
   Ok(web::HttpResponse::Ok().json(&GenericCount { count }))
 }
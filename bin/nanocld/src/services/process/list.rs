@@ -6,10 +6,11 @@ use nanocl_stubs::generic::GenericListQuery;
 use crate::{
   models::{ProcessDb, SystemState},
   repositories::generic::*,
-  utils,
+  utils::{self, auth::ApiScope},
 };
 
 /// List processes with optional filter
+/// in the namespaces the identity can see
 #[cfg_attr(feature = "dev", utoipa::path(
   get,
   tag = "Processes",
@@ -23,11 +24,17 @@ use crate::{
 ))]
 #[web::get("/processes")]
 pub async fn list_processes(
+  req: web::HttpRequest,
   state: web::types::State<SystemState>,
   qs: web::types::Query<GenericListQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let filter = utils::query_string::parse_qs_filter(&qs)?;
-  let processes =
-    ProcessDb::transform_read_by(&filter, &state.inner.pool).await?;
+  let processes = match ApiScope::of(&req) {
+    Some(namespaces) => {
+      ProcessDb::read_in_namespaces(&filter, &namespaces, &state.inner.pool)
+        .await?
+    }
+    None => ProcessDb::transform_read_by(&filter, &state.inner.pool).await?,
+  };
   Ok(web::HttpResponse::Ok().json(&processes))
 }
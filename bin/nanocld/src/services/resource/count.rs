@@ -1,7 +1,7 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::{GenericCount, GenericListQuery};
+use nanocl_stubs::generic::{GenericClause, GenericCount, GenericListQueryNsp};
 
 use crate::{
   models::{ResourceDb, SystemState},
@@ -16,6 +16,7 @@ use crate::{
   path = "/resources/count",
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"name\": { \"eq\": \"global\" } } } }"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resources default to every namespace"),
   ),
   responses(
     (status = 200, description = "Count result", body = GenericCount),
@@ -24,9 +25,13 @@ use crate::{
 #[web::get("/resources/count")]
 pub async fn count_resource(
   state: web::types::State<SystemState>,
-  qs: web::types::Query<GenericListQuery>,
+  qs: web::types::Query<GenericListQueryNsp>,
 ) -> HttpResult<web::HttpResponse> {
-  let filter = utils::query_string::parse_qs_filter(&qs)?;
+  let query = utils::query_string::parse_qs_nsp_filter(&qs)?;
+  let mut filter = query.filter.unwrap_or_default();
+  if let Some(namespace) = query.namespace {
+    filter = filter.r#where("namespace_name", GenericClause::Eq(namespace));
+  }
   let count = ResourceDb::count_by(&filter, &state.inner.pool).await?;
   Ok(web::HttpResponse::Ok().json(&GenericCount { count }))
 }
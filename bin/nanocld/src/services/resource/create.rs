@@ -1,11 +1,12 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::resource::ResourcePartial;
+use nanocl_stubs::{generic::GenericNspQuery, resource::ResourcePartial};
 
 use crate::{
-  models::{ResourceDb, SystemState},
+  models::{ResourceDb, ResourceObjCreateIn, SystemState},
   objects::generic::*,
+  utils,
 };
 
 /// Create a new resource
@@ -14,6 +15,9 @@ use crate::{
   request_body = ResourcePartial,
   tag = "Resources",
   path = "/resources",
+  params(
+    ("namespace" = Option<String>, Query, description = "Namespace where to create the resource default to 'global'"),
+  ),
   responses(
     (status = 200, description = "The created resource", body = nanocl_stubs::resource::Resource),
     (status = 409, description = "Resource already exist", body = crate::services::openapi::ApiError),
@@ -23,7 +27,12 @@ use crate::{
 pub async fn create_resource(
   state: web::types::State<SystemState>,
   payload: web::types::Json<ResourcePartial>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  let resource = ResourceDb::create_obj(&payload, &state).await?;
+  let obj = ResourceObjCreateIn {
+    namespace: utils::key::resolve_nsp(&qs.namespace),
+    resource: payload.into_inner(),
+  };
+  let resource = ResourceDb::create_obj(&obj, &state).await?;
   Ok(web::HttpResponse::Created().json(&resource))
 }
@@ -1,10 +1,12 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericNspQuery;
 
 use crate::{
   models::{ResourceDb, SystemState},
   objects::generic::*,
+  utils,
 };
 
 /// Delete a resource by name
@@ -13,7 +15,8 @@ use crate::{
   tag = "Resources",
   path = "/resources/{name}",
   params(
-    ("name" = String, Path, description = "The resource name to delete")
+    ("name" = String, Path, description = "The resource name to delete"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resource default to 'global'"),
   ),
   responses(
     (status = 202, description = "The resource and his history has been deleted"),
@@ -24,7 +27,10 @@ use crate::{
 pub async fn delete_resource(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  ResourceDb::del_obj_by_pk(&path.1, &(), &state).await?;
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  ResourceDb::del_obj_by_pk(&key, &(), &state).await?;
   Ok(web::HttpResponse::Accepted().finish())
 }
@@ -1,10 +1,12 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericNspQuery;
 
 use crate::{
   models::{ResourceDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 /// Get detailed information about a resource
@@ -13,7 +15,8 @@ use crate::{
   tag = "Resources",
   path = "/resources/{name}/inspect",
   params(
-    ("name" = String, Path, description = "The resource name to inspect")
+    ("name" = String, Path, description = "The resource name to inspect"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resource default to 'global'"),
   ),
   responses(
     (status = 200, description = "Detailed information about a resource", body = nanocl_stubs::resource::Resource),
@@ -24,8 +27,11 @@ use crate::{
 pub async fn inspect_resource(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
   let resource =
-    ResourceDb::transform_read_by_pk(&path.1, &state.inner.pool).await?;
+    ResourceDb::transform_read_by_pk(&key, &state.inner.pool).await?;
   Ok(web::HttpResponse::Ok().json(&resource))
 }
@@ -1,11 +1,10 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericListQuery;
+use nanocl_stubs::generic::GenericListQueryNsp;
 
 use crate::{
   models::{ResourceDb, SystemState},
-  repositories::generic::*,
   utils,
 };
 
@@ -16,6 +15,7 @@ use crate::{
   path = "/resources",
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"kind\": { \"eq\": \"ncproxy.io/rule\" } } } }"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resources default to every namespace"),
   ),
   responses(
     (status = 200, description = "List of resources", body = [nanocl_stubs::resource::Resource]),
@@ -24,9 +24,9 @@ use crate::{
 #[web::get("/resources")]
 pub async fn list_resource(
   state: web::types::State<SystemState>,
-  qs: web::types::Query<GenericListQuery>,
+  qs: web::types::Query<GenericListQueryNsp>,
 ) -> HttpResult<web::HttpResponse> {
-  let filter = utils::query_string::parse_qs_filter(&qs)?;
-  let items = ResourceDb::transform_read_by(&filter, &state.inner.pool).await?;
+  let query = utils::query_string::parse_qs_nsp_filter(&qs)?;
+  let items = ResourceDb::list(&query, &state).await?;
   Ok(web::HttpResponse::Ok().json(&items))
 }
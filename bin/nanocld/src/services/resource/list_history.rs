@@ -2,13 +2,14 @@ use ntex::web;
 
 use nanocl_error::http::HttpResult;
 use nanocl_stubs::{
-  generic::{GenericClause, GenericFilter},
+  generic::{GenericClause, GenericFilter, GenericNspQuery},
   resource::ResourceSpec,
 };
 
 use crate::{
   models::{SpecDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 /// List resource history
@@ -17,7 +18,8 @@ use crate::{
   tag = "Resources",
   path = "/resources/{name}/histories",
   params(
-    ("name" = String, Path, description = "The resource name to list history")
+    ("name" = String, Path, description = "The resource name to list history"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resource default to 'global'"),
   ),
   responses(
     (status = 200, description = "The resource history", body = [ResourceSpec]),
@@ -28,9 +30,11 @@ use crate::{
 pub async fn list_resource_history(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  let filter =
-    GenericFilter::new().r#where("kind_key", GenericClause::Eq(path.1.clone()));
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  let filter = GenericFilter::new().r#where("kind_key", GenericClause::Eq(key));
   let items = SpecDb::read_by(&filter, &state.inner.pool)
     .await?
     .into_iter()
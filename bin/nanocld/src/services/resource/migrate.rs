@@ -0,0 +1,35 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::{generic::GenericNspQuery, resource::ResourceMigrate};
+
+use crate::{models::SystemState, utils};
+
+/// Migrate a resource to another version of its kind
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  request_body = ResourceMigrate,
+  tag = "Resources",
+  path = "/resources/{name}/migrate",
+  params(
+    ("name" = String, Path, description = "The resource name to migrate"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resource default to 'global'"),
+  ),
+  responses(
+    (status = 200, description = "The resource has been migrated", body = nanocl_stubs::resource::Resource),
+    (status = 400, description = "The resource can't be converted to the version", body = crate::services::openapi::ApiError),
+    (status = 404, description = "Resource or version doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/resources/{name}/migrate")]
+pub async fn migrate_resource(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<ResourceMigrate>,
+  qs: web::types::Query<GenericNspQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  let resource = utils::resource::migrate(&key, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&resource))
+}
@@ -6,6 +6,7 @@ pub mod delete;
 pub mod inspect;
 pub mod list;
 pub mod list_history;
+pub mod migrate;
 pub mod put;
 pub mod revert;
 
@@ -15,6 +16,7 @@ pub use delete::*;
 pub use inspect::*;
 pub use list::*;
 pub use list_history::*;
+pub use migrate::*;
 pub use put::*;
 pub use revert::*;
 
@@ -26,6 +28,7 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(put_resource);
   config.service(count_resource);
   config.service(list_resource_history);
+  config.service(migrate_resource);
   config.service(revert_resource);
 }
 
@@ -33,8 +36,14 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
 mod tests {
   use nanocl_stubs::{
     generic::{GenericClause, GenericFilter, GenericListQuery},
-    resource::{Resource, ResourcePartial, ResourceUpdate},
-    resource_kind::{ResourceKindPartial, ResourceKindSpec},
+    resource::{
+      Resource, ResourceMigrate, ResourcePartial, ResourceStatusKind,
+      ResourceUpdate,
+    },
+    resource_kind::{
+      ResourceKindConversion, ResourceKindPartial, ResourceKindSpec,
+      ResourceKindTransform, ResourceKindTransformOp,
+    },
   };
   use ntex::http;
 
@@ -76,6 +85,7 @@ mod tests {
       data: ResourceKindSpec {
         schema: Some(spec),
         url: None,
+        conversion: None,
       },
     };
     let res = client
@@ -106,8 +116,12 @@ mod tests {
       "create resource"
     );
     let resource = res.json::<Resource>().await.unwrap();
-    assert_eq!(resource.spec.resource_key, TEST_RESOURCE);
+    assert_eq!(
+      resource.spec.resource_key,
+      format!("{TEST_RESOURCE}.global")
+    );
     assert_eq!(resource.kind, TEST_RESOURCE_KIND);
+    assert_eq!(resource.status.phase, ResourceStatusKind::Applied);
     // Basic list
     let mut res = client.send_get(ENDPOINT, None::<String>).await;
     test_status_code!(res.status(), http::StatusCode::OK, "list resource");
@@ -186,7 +200,10 @@ mod tests {
       .await;
     test_status_code!(res.status(), http::StatusCode::OK, "inspect resource");
     let resource = res.json::<Resource>().await.unwrap();
-    assert_eq!(resource.spec.resource_key, TEST_RESOURCE);
+    assert_eq!(
+      resource.spec.resource_key,
+      format!("{TEST_RESOURCE}.global")
+    );
     assert_eq!(&resource.kind, TEST_RESOURCE_KIND);
     assert_eq!(&resource.spec.data, &data);
     // History
@@ -217,7 +234,10 @@ mod tests {
       .await;
     test_status_code!(res.status(), http::StatusCode::OK, "patch resource");
     let resource = res.json::<Resource>().await.unwrap();
-    assert_eq!(resource.spec.resource_key, TEST_RESOURCE);
+    assert_eq!(
+      resource.spec.resource_key,
+      format!("{TEST_RESOURCE}.global")
+    );
     assert_eq!(&resource.kind, TEST_RESOURCE_KIND);
     // Delete
     let resp = client
@@ -240,4 +260,258 @@ mod tests {
       "delete resource kind"
     );
   }
+
+  #[ntex::test]
+  async fn migrate() {
+    const TEST_RESOURCE: &str = "test_resource_migrate";
+    const TEST_RESOURCE_KIND: &str = "test.io/test-resource-migrate";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let schema = serde_json::json!({
+      "type": "object",
+      "required": ["User"],
+      "properties": {
+        "User": {
+          "type": "object",
+          "required": ["Name"],
+        }
+      }
+    });
+    let kind_v1 = ResourceKindPartial {
+      name: TEST_RESOURCE_KIND.to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: Some(serde_json::json!({ "type": "object" })),
+        url: None,
+        conversion: None,
+      },
+    };
+    let res = client
+      .send_post("/resource/kinds", Some(&kind_v1), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource kind v1"
+    );
+    let resource = ResourcePartial {
+      name: TEST_RESOURCE.to_owned(),
+      kind: TEST_RESOURCE_KIND.to_owned(),
+      data: serde_json::json!({ "Username": "test", "Legacy": true }),
+      metadata: None,
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource"
+    );
+    let kind_v2 = ResourceKindPartial {
+      version: "v2".to_owned(),
+      data: ResourceKindSpec {
+        schema: Some(schema),
+        url: None,
+        conversion: Some(ResourceKindConversion::Transform(vec![
+          ResourceKindTransform {
+            op: ResourceKindTransformOp::Move,
+            path: "/User/Name".to_owned(),
+            from: Some("/Username".to_owned()),
+            value: None,
+          },
+          ResourceKindTransform {
+            op: ResourceKindTransformOp::Remove,
+            path: "/Legacy".to_owned(),
+            from: None,
+            value: None,
+          },
+        ])),
+      },
+      ..kind_v1.clone()
+    };
+    let res = client
+      .send_post("/resource/kinds", Some(&kind_v2), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource kind v2"
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}/version/v1"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "delete used resource kind version"
+    );
+    let mut res = client
+      .send_post(
+        &format!("{ENDPOINT}/{TEST_RESOURCE}/migrate"),
+        Some(&ResourceMigrate::default()),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "migrate resource");
+    let resource = res.json::<Resource>().await.unwrap();
+    assert_eq!(resource.spec.version, "v2");
+    assert_eq!(
+      resource.spec.data,
+      serde_json::json!({ "User": { "Name": "test" } })
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}/version/v1"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind version"
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "delete used resource kind"
+    );
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{TEST_RESOURCE}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource"
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind"
+    );
+  }
+
+  #[ntex::test]
+  async fn delete_intermediate_version() {
+    const TEST_RESOURCE: &str = "test_resource_intermediate";
+    const TEST_RESOURCE_KIND: &str = "test.io/test-resource-intermediate";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let kind_v1 = ResourceKindPartial {
+      name: TEST_RESOURCE_KIND.to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: Some(serde_json::json!({ "type": "object" })),
+        url: None,
+        conversion: None,
+      },
+    };
+    let res = client
+      .send_post("/resource/kinds", Some(&kind_v1), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource kind v1"
+    );
+    let resource = ResourcePartial {
+      name: TEST_RESOURCE.to_owned(),
+      kind: TEST_RESOURCE_KIND.to_owned(),
+      data: serde_json::json!({ "Username": "test" }),
+      metadata: None,
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource"
+    );
+    for (version, from, path) in
+      [("v2", "/Username", "/Name"), ("v3", "/Name", "/User")]
+    {
+      let kind = ResourceKindPartial {
+        version: version.to_owned(),
+        data: ResourceKindSpec {
+          conversion: Some(ResourceKindConversion::Transform(vec![
+            ResourceKindTransform {
+              op: ResourceKindTransformOp::Move,
+              path: path.to_owned(),
+              from: Some(from.to_owned()),
+              value: None,
+            },
+          ])),
+          ..kind_v1.data.clone()
+        },
+        ..kind_v1.clone()
+      };
+      let res = client
+        .send_post("/resource/kinds", Some(&kind), None::<String>)
+        .await;
+      test_status_code!(
+        res.status(),
+        http::StatusCode::CREATED,
+        format!("create resource kind {version}")
+      );
+    }
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}/version/v2"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "delete version needed by an older version"
+    );
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{TEST_RESOURCE}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource"
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}/version/v2"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete intermediate version"
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind"
+    );
+  }
 }
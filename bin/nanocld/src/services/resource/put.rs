@@ -1,12 +1,16 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::resource::{ResourcePartial, ResourceUpdate};
+use nanocl_stubs::{
+  generic::GenericNspQuery,
+  resource::{ResourcePartial, ResourceUpdate},
+};
 
 use crate::{
   models::{ResourceDb, SystemState},
   objects::generic::*,
   repositories::generic::*,
+  utils,
 };
 
 /// Create a new resource spec and add history entry
@@ -16,7 +20,8 @@ use crate::{
   tag = "Resources",
   path = "/resources/{name}",
   params(
-    ("name" = String, Path, description = "Name of the resource")
+    ("name" = String, Path, description = "Name of the resource"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resource default to 'global'"),
   ),
   responses(
     (status = 200, description = "Resource updated", body = nanocl_stubs::resource::Resource),
@@ -28,16 +33,18 @@ pub async fn put_resource(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
   payload: web::types::Json<ResourceUpdate>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
   let resource =
-    ResourceDb::transform_read_by_pk(&path.1, &state.inner.pool).await?;
+    ResourceDb::transform_read_by_pk(&key, &state.inner.pool).await?;
   let new_resource = ResourcePartial {
     name: path.1.clone(),
-    kind: resource.kind,
+    kind: format!("{}/{}", resource.kind, resource.spec.version),
     data: payload.data.clone(),
     metadata: payload.metadata.clone(),
   };
-  let resource =
-    ResourceDb::put_obj_by_pk(&path.1, &new_resource, &state).await?;
+  let resource = ResourceDb::put_obj_by_pk(&key, &new_resource, &state).await?;
   Ok(web::HttpResponse::Ok().json(&resource))
 }
@@ -1,12 +1,13 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::resource::ResourcePartial;
+use nanocl_stubs::{generic::GenericNspQuery, resource::ResourcePartial};
 
 use crate::{
   models::{ResourceDb, SpecDb, SystemState},
   objects::generic::*,
   repositories::generic::*,
+  utils,
 };
 
 /// Revert a resource to a specific history
@@ -16,7 +17,8 @@ use crate::{
   path = "/resources/{name}/histories/{id}/revert",
   params(
     ("name" = String, Path, description = "The resource name to revert"),
-    ("id" = String, Path, description = "The resource history id to revert to")
+    ("id" = String, Path, description = "The resource history id to revert to"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the resource default to 'global'"),
   ),
   responses(
     (status = 200, description = "The resource has been revert", body = nanocl_stubs::resource::Resource),
@@ -27,17 +29,19 @@ use crate::{
 pub async fn revert_resource(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String, uuid::Uuid)>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
   let history = SpecDb::read_by_pk(&path.2, &state.inner.pool).await?;
   let resource =
-    ResourceDb::transform_read_by_pk(&path.1, &state.inner.pool).await?;
+    ResourceDb::transform_read_by_pk(&key, &state.inner.pool).await?;
   let new_resource = ResourcePartial {
-    name: resource.spec.resource_key,
-    kind: resource.kind,
+    name: resource.name,
+    kind: format!("{}/{}", resource.kind, history.version),
     data: history.data,
     metadata: history.metadata,
   };
-  let resource =
-    ResourceDb::put_obj_by_pk(&path.1, &new_resource, &state).await?;
+  let resource = ResourceDb::put_obj_by_pk(&key, &new_resource, &state).await?;
   Ok(web::HttpResponse::Ok().json(&resource))
 }
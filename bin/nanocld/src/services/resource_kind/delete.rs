@@ -19,6 +19,7 @@ use crate::{
   responses(
     (status = 202, description = "Resource kind deleted"),
     (status = 404, description = "Resource kind doesn't exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "Resource kind is used by resources", body = crate::services::openapi::ApiError),
   ),
 ))]
 #[web::delete("/resource/kinds/{domain}/{name}")]
@@ -28,7 +29,34 @@ pub async fn delete_resource_kind(
 ) -> HttpResult<web::HttpResponse> {
   let key = format!("{}/{}", path.1, path.2);
   ResourceKindDb::read_by_pk(&key, &state.inner.pool).await?;
+  ResourceKindDb::ensure_unused(&key, None, &state.inner.pool).await?;
   ResourceKindDb::del_by_pk(&key, &state.inner.pool).await?;
   SpecDb::del_by_kind_key(&key, &state.inner.pool).await?;
   Ok(web::HttpResponse::Accepted().into())
 }
+
+/// Delete a version of a resource kind
+#[cfg_attr(feature = "dev", utoipa::path(
+  delete,
+  tag = "ResourceKinds",
+  path = "/resource/kinds/{domain}/{name}/version/{version}",
+  params(
+    ("domain" = String, Path, description = "Domain of the resource kind"),
+    ("name" = String, Path, description = "Name of the resource kind"),
+    ("version" = String, Path, description = "Version of the resource kind"),
+  ),
+  responses(
+    (status = 202, description = "Resource kind version deleted"),
+    (status = 404, description = "Resource kind version doesn't exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "Resource kind version is used by resources", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::delete("/resource/kinds/{domain}/{name}/version/{version}")]
+pub async fn delete_resource_kind_version(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let key = format!("{}/{}", path.1, path.2);
+  ResourceKindDb::delete_version(&key, &path.3, &state.inner.pool).await?;
+  Ok(web::HttpResponse::Accepted().into())
+}
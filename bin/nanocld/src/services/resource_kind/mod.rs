@@ -17,6 +17,7 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
     .service(list_resource_kind)
     .service(create_resource_kind)
     .service(delete_resource_kind)
+    .service(delete_resource_kind_version)
     .service(inspect_resource_kind)
     .service(count_resource_kind)
     .service(inspect_resource_kind_version);
@@ -63,6 +64,7 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+        conversion: None,
       },
     };
     let res = client
@@ -86,6 +88,7 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: None,
+        conversion: None,
       },
     };
     let res = client
@@ -109,6 +112,7 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+        conversion: None,
       },
     };
     let mut res = client
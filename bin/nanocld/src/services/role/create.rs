@@ -0,0 +1,31 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::auth::RolePartial;
+
+use crate::{
+  models::{RoleDb, SystemState},
+  objects::generic::*,
+  utils,
+};
+
+/// Create a new role
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  request_body = RolePartial,
+  tag = "Roles",
+  path = "/roles",
+  responses(
+    (status = 201, description = "The created role", body = nanocl_stubs::auth::Role),
+    (status = 409, description = "Role already exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/roles")]
+pub async fn create_role(
+  state: web::types::State<SystemState>,
+  payload: web::types::Json<RolePartial>,
+) -> HttpResult<web::HttpResponse> {
+  utils::key::validate_name(&payload.name)?;
+  let item = RoleDb::create_obj(&payload, &state).await?;
+  Ok(web::HttpResponse::Created().json(&item))
+}
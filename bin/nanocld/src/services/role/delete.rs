@@ -0,0 +1,30 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{
+  models::{RoleDb, SystemState},
+  objects::generic::*,
+};
+
+/// Delete a role
+#[cfg_attr(feature = "dev", utoipa::path(
+  delete,
+  tag = "Roles",
+  path = "/roles/{name}",
+  params(
+    ("name" = String, Path, description = "Name of the role")
+  ),
+  responses(
+    (status = 202, description = "Role have been deleted"),
+    (status = 404, description = "Role doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::delete("/roles/{name}")]
+pub async fn delete_role(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  RoleDb::del_obj_by_pk(&path.1, &(), &state).await?;
+  Ok(web::HttpResponse::Accepted().into())
+}
@@ -0,0 +1,32 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericListQuery;
+
+use crate::{
+  models::{RoleDb, SystemState},
+  repositories::generic::*,
+  utils,
+};
+
+/// List roles with optional filter
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Roles",
+  path = "/roles",
+  params(
+    ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"key\": { \"eq\": \"admin\" } } } }"),
+  ),
+  responses(
+    (status = 200, description = "List of roles", body = [nanocl_stubs::auth::Role]),
+  ),
+))]
+#[web::get("/roles")]
+pub async fn list_role(
+  state: web::types::State<SystemState>,
+  qs: web::types::Query<GenericListQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let filter = utils::query_string::parse_qs_filter(&qs)?;
+  let items = RoleDb::transform_read_by(&filter, &state.inner.pool).await?;
+  Ok(web::HttpResponse::Ok().json(&items))
+}
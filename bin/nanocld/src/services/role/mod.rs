@@ -0,0 +1,87 @@
+use ntex::web;
+
+pub mod create;
+pub mod delete;
+pub mod inspect;
+pub mod list;
+pub mod put;
+
+pub use create::*;
+pub use delete::*;
+pub use inspect::*;
+pub use list::*;
+pub use put::*;
+
+pub fn ntex_config(config: &mut web::ServiceConfig) {
+  config.service(list_role);
+  config.service(create_role);
+  config.service(inspect_role);
+  config.service(put_role);
+  config.service(delete_role);
+}
+
+#[cfg(test)]
+mod test_role {
+  use ntex::http;
+
+  use nanocl_stubs::auth::{Role, RolePartial, RoleRule, RoleUpdate, RoleVerb};
+
+  use crate::utils::tests::*;
+
+  const ENDPOINT: &str = "/roles";
+
+  #[ntex::test]
+  async fn basic() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let role = RolePartial {
+      name: "test-role".to_owned(),
+      rules: vec![RoleRule {
+        kinds: vec!["cargoes".to_owned()],
+        namespaces: vec!["*".to_owned()],
+        verbs: vec![RoleVerb::Read],
+      }],
+    };
+    let mut res = client
+      .send_post(ENDPOINT, Some(&role), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create role");
+    let _ = res.json::<Role>().await.unwrap();
+    let res = client
+      .send_post(ENDPOINT, Some(&role), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "create existing role"
+    );
+    let res = client.send_get(ENDPOINT, None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::OK, "list roles");
+    let update = RoleUpdate {
+      rules: vec![RoleRule {
+        kinds: vec!["*".to_owned()],
+        namespaces: vec!["dev".to_owned()],
+        verbs: vec![RoleVerb::All],
+      }],
+    };
+    let mut res = client
+      .send_put(
+        &format!("{ENDPOINT}/test-role"),
+        Some(&update),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "put role");
+    let role = res.json::<Role>().await.unwrap();
+    assert!(role.allows("secrets", "dev", RoleVerb::Write));
+    assert!(!role.allows("secrets", "global", RoleVerb::Read));
+    let res = client
+      .send_get(&format!("{ENDPOINT}/test-role/inspect"), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect role");
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/test-role"), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::ACCEPTED, "delete role");
+  }
+}
@@ -0,0 +1,33 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::auth::RoleUpdate;
+
+use crate::{
+  models::{RoleDb, SystemState},
+  objects::generic::*,
+};
+
+/// Replace the rules of a role
+#[cfg_attr(feature = "dev", utoipa::path(
+  put,
+  request_body = RoleUpdate,
+  tag = "Roles",
+  path = "/roles/{name}",
+  params(
+    ("name" = String, Path, description = "Name of the role")
+  ),
+  responses(
+    (status = 200, description = "Role updated", body = nanocl_stubs::auth::Role),
+    (status = 404, description = "Role doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::put("/roles/{name}")]
+pub async fn put_role(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<RoleUpdate>,
+) -> HttpResult<web::HttpResponse> {
+  let item = RoleDb::put_obj_by_pk(&path.1, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&item))
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use ntex::web;
 
 use bollard_next::auth::DockerCredentials;
@@ -36,6 +38,10 @@ pub async fn create_secret(
       serde_json::from_value::<Vec<String>>(payload.data.clone())
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
     }
+    "nanocl.io/basic-auth" => {
+      serde_json::from_value::<HashMap<String, String>>(payload.data.clone())
+        .map_err(|e| HttpError::bad_request(e.to_string()))?;
+    }
     "nanocl.io/container-registry" => {
       serde_json::from_value::<DockerCredentials>(payload.data.clone())
         .map_err(|e| HttpError::bad_request(e.to_string()))?;
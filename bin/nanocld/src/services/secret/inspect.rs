@@ -2,10 +2,7 @@ use ntex::web;
 
 use nanocl_error::http::HttpResult;
 
-use crate::{
-  models::{SecretDb, SystemState},
-  repositories::generic::*,
-};
+use crate::models::{SecretDb, SystemState};
 
 /// Get detailed information about a secret
 #[cfg_attr(feature = "dev", utoipa::path(
@@ -25,7 +22,6 @@ pub async fn inspect_secret(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
 ) -> HttpResult<web::HttpResponse> {
-  let secret =
-    SecretDb::transform_read_by_pk(&path.1, &state.inner.pool).await?;
+  let secret = SecretDb::read_decrypted_by_pk(&path.1, &state).await?;
   Ok(web::HttpResponse::Ok().json(&secret))
 }
@@ -5,7 +5,6 @@ use nanocl_stubs::generic::GenericListQuery;
 
 use crate::{
   models::{SecretDb, SystemState},
-  repositories::generic::*,
   utils,
 };
 
@@ -27,6 +26,6 @@ pub async fn list_secret(
   qs: web::types::Query<GenericListQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let filter = utils::query_string::parse_qs_filter(&qs)?;
-  let items = SecretDb::transform_read_by(&filter, &state.inner.pool).await?;
+  let items = SecretDb::read_decrypted_by(&filter, &state).await?;
   Ok(web::HttpResponse::Ok().json(&items))
 }
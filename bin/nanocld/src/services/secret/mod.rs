@@ -6,6 +6,7 @@ pub mod delete;
 pub mod inspect;
 pub mod list;
 pub mod patch;
+pub mod rotate_key;
 
 pub use count::*;
 pub use create::*;
@@ -13,6 +14,7 @@ pub use delete::*;
 pub use inspect::*;
 pub use list::*;
 pub use patch::*;
+pub use rotate_key::*;
 
 pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(list_secret);
@@ -21,6 +23,7 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(delete_secret);
   config.service(count_secret);
   config.service(patch_secret);
+  config.service(rotate_secret_key);
 }
 
 #[cfg(test)]
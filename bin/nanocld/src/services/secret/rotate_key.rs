@@ -0,0 +1,23 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{models::SystemState, utils};
+
+/// Generate a new key to encrypt secrets at rest and encrypt every secret with it
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "Secrets",
+  path = "/secrets/rotate-key",
+  responses(
+    (status = 200, description = "Rotation result", body = nanocl_stubs::secret::SecretKeyRotation),
+    (status = 400, description = "Key provider doesn't support rotation", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/secrets/rotate-key")]
+pub async fn rotate_secret_key(
+  state: web::types::State<SystemState>,
+) -> HttpResult<web::HttpResponse> {
+  let rotation = utils::secret::rotate_key(&state).await?;
+  Ok(web::HttpResponse::Ok().json(&rotation))
+}
@@ -0,0 +1,31 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::service::ServiceQuery;
+
+use crate::{models::SystemState, utils};
+
+/// Get the service of a cargo by its name
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Services",
+  path = "/services/{name}/inspect",
+  params(
+    ("name" = String, Path, description = "Name of the cargo"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the service belongs default to 'global'"),
+    ("all" = Option<bool>, Query, description = "Include the running instances that aren't healthy"),
+  ),
+  responses(
+    (status = 200, description = "Service details", body = nanocl_stubs::service::Service),
+    (status = 404, description = "Cargo doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/services/{name}/inspect")]
+pub async fn inspect_service(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  qs: web::types::Query<ServiceQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let service = utils::service::inspect(&path.1, &qs, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&service))
+}
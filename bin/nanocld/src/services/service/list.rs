@@ -0,0 +1,29 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::service::ServiceQuery;
+
+use crate::{models::SystemState, utils};
+
+/// List the services of the cargoes of a namespace with their healthy instances
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Services",
+  path = "/services",
+  params(
+    ("namespace" = Option<String>, Query, description = "Namespace where the services belongs default to 'global'"),
+    ("all" = Option<bool>, Query, description = "Include the running instances that aren't healthy"),
+  ),
+  responses(
+    (status = 200, description = "List of service", body = [nanocl_stubs::service::Service]),
+    (status = 404, description = "Namespace doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/services")]
+pub async fn list_service(
+  state: web::types::State<SystemState>,
+  qs: web::types::Query<ServiceQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let services = utils::service::list(&qs, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&services))
+}
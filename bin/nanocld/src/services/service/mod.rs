@@ -0,0 +1,66 @@
+use ntex::web;
+
+pub mod inspect;
+pub mod list;
+
+pub use inspect::*;
+pub use list::*;
+
+pub fn ntex_config(config: &mut web::ServiceConfig) {
+  config.service(list_service);
+  config.service(inspect_service);
+}
+
+#[cfg(test)]
+mod tests {
+  use ntex::http;
+
+  use nanocl_stubs::service::{Service, ServiceQuery};
+
+  use crate::utils::tests::*;
+
+  const ENDPOINT: &str = "/services";
+
+  #[ntex::test]
+  async fn basic() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let res = client
+      .send_get(ENDPOINT, Some(ServiceQuery::new(Some("system"))))
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "list services");
+    let services = TestClient::res_json::<Vec<Service>>(res).await;
+    assert!(
+      services.iter().any(|service| service.name == "nstore"),
+      "Expect nstore to be listed"
+    );
+    let res = client
+      .send_get(
+        &format!("{ENDPOINT}/nstore/inspect"),
+        Some(ServiceQuery {
+          namespace: Some("system".to_owned()),
+          all: Some(true),
+        }),
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect service");
+    let service = TestClient::res_json::<Service>(res).await;
+    assert_eq!(service.key, "nstore.system");
+    let res = client
+      .send_get(&format!("{ENDPOINT}/unknown/inspect"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "inspect unknown service"
+    );
+    let res = client
+      .send_get(ENDPOINT, Some(ServiceQuery::new(Some("unknown"))))
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "list services of unknown namespace"
+    );
+  }
+}
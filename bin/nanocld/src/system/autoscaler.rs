@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use ntex::{rt, time};
+
+use crate::{models::SystemState, utils};
+
+/// Interval between two evaluations of the autoscaling policies
+const TICK: Duration = Duration::from_secs(15);
+
+/// Spawn the autoscaler adjusting the number of replicas of the cargoes
+/// with an autoscaling policy from the load of their instances.
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    loop {
+      time::sleep(TICK).await;
+      if let Err(err) = utils::autoscale::run(&state).await {
+        log::warn!("autoscaler::spawn: {err}");
+      }
+    }
+  });
+}
@@ -154,8 +154,8 @@ async fn exec_docker(
       action.clone_into(&mut event.action);
     }
     "destroy" => {
-      state.spawn_emit_event(event);
       let _ = ProcessDb::del_by_pk(&id, &state.inner.pool).await;
+      state.spawn_emit_event(event);
       return Ok(());
     }
     "create" => {
@@ -167,11 +167,24 @@ async fn exec_docker(
       action.clone_into(&mut event.action);
     }
   }
+  let res = update_process(&id, name, state).await;
+  // Emitted once the process is updated so the watchers of the events
+  // like ncdns read the current state of the services,
+  // but even if the update failed so they aren't missed
   state.spawn_emit_event(event);
+  res
+}
+
+/// Refresh a process from the inspection of its container
+async fn update_process(
+  id: &str,
+  name: String,
+  state: &SystemState,
+) -> IoResult<()> {
   let instance = state
     .inner
     .docker_api
-    .inspect_container(&id, None::<InspectContainerOptions>)
+    .inspect_container(id, None::<InspectContainerOptions>)
     .await
     .map_err(|err| err.map_err_context(|| "Docker event"))?;
   let data = serde_json::to_value(instance)
@@ -182,7 +195,7 @@ async fn exec_docker(
     name: Some(name),
     ..Default::default()
   };
-  ProcessDb::update_pk(&id, new_instance, &state.inner.pool).await?;
+  ProcessDb::update_pk(id, new_instance, &state.inner.pool).await?;
   Ok(())
 }
 
@@ -1,7 +1,5 @@
 use std::str::FromStr;
 
-use ntex::rt;
-
 use nanocl_error::io::IoResult;
 use nanocl_stubs::{
   generic::{GenericClause, GenericFilter},
@@ -13,13 +11,13 @@ use nanocl_stubs::{
 
 use crate::{
   models::{CargoDb, JobDb, ObjPsStatusDb, ProcessDb, SystemState, VmDb},
-  objects::generic::*,
   repositories::generic::*,
   tasks::generic::*,
   utils,
 };
 
-/// Remove a job after when finished and ttl is set
+/// Set the status of a job when its instances died outside of its start task
+/// and remove it after its ttl if set
 async fn job_ttl(actor: &EventActor, state: &SystemState) -> IoResult<()> {
   let attributes = actor.attributes.clone().unwrap_or_default();
   let job_id = match attributes.get("io.nanocl.j") {
@@ -27,6 +25,13 @@ async fn job_ttl(actor: &EventActor, state: &SystemState) -> IoResult<()> {
     Some(job_id) => job_id.as_str().unwrap_or_default(),
   };
   log::debug!("event::job_ttl: {job_id}");
+  // The start task retries failed instances and sets the status itself
+  let task_key = format!("{}@{job_id}", EventActorKind::Job);
+  let task = state.inner.task_manager.get_task(&task_key).await;
+  if task.is_some_and(|task| task.kind == NativeEventAction::Starting) {
+    log::debug!("event::job_ttl: {job_id} is still running its start task");
+    return Ok(());
+  }
   let job = JobDb::transform_read_by_pk(job_id, &state.inner.pool).await?;
   match job.status.actual {
     ObjPsStatusKind::Finish | ObjPsStatusKind::Fail => {
@@ -47,38 +52,7 @@ async fn job_ttl(actor: &EventActor, state: &SystemState) -> IoResult<()> {
     return Ok(());
   }
   log::debug!("instance_failed: {instance_failed}");
-  if instance_failed > 0 {
-    ObjPsStatusDb::update_actual_status(
-      &job.name,
-      &ObjPsStatusKind::Fail,
-      &state.inner.pool,
-    )
-    .await?;
-    state
-      .emit_normal_native_action_sync(&job, NativeEventAction::Fail)
-      .await;
-  } else {
-    ObjPsStatusDb::update_actual_status(
-      &job.name,
-      &ObjPsStatusKind::Finish,
-      &state.inner.pool,
-    )
-    .await?;
-    state
-      .emit_normal_native_action_sync(&job, NativeEventAction::Finish)
-      .await;
-  }
-  let ttl = match job.ttl {
-    None => return Ok(()),
-    Some(ttl) => ttl,
-  };
-  let state = state.clone();
-  rt::spawn(async move {
-    log::debug!("event::job_ttl: {} will be deleted in {ttl}s", job.name);
-    ntex::time::sleep(std::time::Duration::from_secs(ttl as u64)).await;
-    let _ = JobDb::del_obj_by_pk(&job.name, &(), &state).await;
-  });
-  Ok(())
+  utils::container::job::finish(&job, instance_failed > 0, state).await
 }
 
 fn starting(
@@ -1,4 +1,4 @@
-use std::{os::unix::prelude::PermissionsExt, path::Path, process::Command};
+use std::{os::unix::prelude::PermissionsExt, path::Path};
 
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use ntex::rt;
@@ -81,30 +81,6 @@ fn set_uds_perm() {
   });
 }
 
-/// Create a new thread and spawn and manage a crond instance to run cron jobs
-fn spawn_crond() {
-  log::trace!("boot::spawn_crond: start thread");
-  rt::Arbiter::new().exec_fn(|| {
-    rt::spawn(async {
-      let task = ntex::web::block(move || {
-        match Command::new("crond").args(["-f"]).spawn() {
-          Ok(mut child) => {
-            child.wait()?;
-            Ok(())
-          }
-          Err(err) => Err(err),
-        }
-      })
-      .await;
-      if let Err(err) = task {
-        log::error!("boot::spawn_crond: {err}");
-      }
-      log::trace!("boot::spawn_crond: stop thread");
-      rt::Arbiter::current().stop();
-    });
-  });
-}
-
 /// Ensure that the state dir exists and is ready to use
 async fn ensure_state_dir(state_dir: &str) -> IoResult<()> {
   let vm_dir = format!("{state_dir}/vms/images");
@@ -122,16 +98,21 @@ async fn ensure_state_dir(state_dir: &str) -> IoResult<()> {
 /// Init function called before http server start.
 /// To boot and initialize our state and database.
 pub async fn init(conf: &DaemonConfig) -> IoResult<SystemState> {
-  spawn_crond();
   set_uds_perm();
   ensure_state_dir(&conf.state_dir).await?;
   let system_state = SystemState::new(conf).await?;
   let system_ptr = system_state.clone();
   NodeDb::register(&system_ptr).await?;
+  utils::secret::ensure_shared_keyring(&system_ptr).await?;
   utils::system::register_namespace("global", &system_ptr).await?;
   utils::system::register_namespace("system", &system_ptr).await?;
+  let count = utils::secret::encrypt_plain_secrets(&system_ptr).await?;
+  if count > 0 {
+    log::info!("boot::init: encrypted {count} plain text secret(s)");
+  }
   rt::spawn(async move {
     let fut = async move {
+      utils::container::network::sync(&system_ptr).await?;
       utils::system::sync_processes(&system_ptr).await?;
       utils::system::sync_vm_images(&system_ptr).await?;
       Ok::<_, IoError>(())
@@ -143,6 +124,10 @@ pub async fn init(conf: &DaemonConfig) -> IoResult<SystemState> {
   });
   super::docker_event::analyze(&system_state);
   super::metric::spawn(&system_state);
+  super::metric_rollup::spawn(&system_state);
+  super::scheduler::spawn(&system_state);
+  super::autoscaler::spawn(&system_state);
+  super::reconciler::spawn(&system_state);
   Ok(system_state)
 }
 
@@ -182,7 +167,7 @@ mod tests {
     // Test state
     let state = init(&config).await.unwrap();
     let state_ptr = state.clone();
-    let mut raw_sub = state.subscribe_raw(None).await.unwrap();
+    let mut raw_sub = state.subscribe_raw(None, None).await.unwrap();
     rt::spawn(async move {
       ntex::time::sleep(std::time::Duration::from_secs(1)).await;
       let actor = Resource::default();
@@ -8,20 +8,17 @@ use nanocl_error::io::IoResult;
 use metrsd_client::{stubs::MetrsdEvent, MetrsdClient};
 
 use crate::{
-  models::{MetricDb, MetricNodePartial, Pool, SystemState},
+  models::{MetricDb, MetricNodePartial, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 /// Save metric event send by [metrsd](http://github.com/next-hat/metrs) to the database
 /// The event can be a `CPU`, `MEMORY`, `DISK` or `NETWORK` event.
 /// The metric is saved for the current node.
 /// This allow us to know what node is the most used.
-async fn save_metric(
-  node: &str,
-  ev: &MetrsdEvent,
-  pool: &Pool,
-) -> IoResult<()> {
-  let node_name = node.to_owned();
+async fn save_metric(ev: &MetrsdEvent, state: &SystemState) -> IoResult<()> {
+  let node_name = state.inner.config.hostname.clone();
   let kind = "nanocl.io/metrs";
   let data = serde_json::to_value(ev)?;
   let mut cpu_percent = ev.cpus.iter().fold(0.0, |acc, cpu| acc + cpu.usage);
@@ -46,8 +43,12 @@ async fn save_metric(
     node_name,
     kind: kind.to_owned(),
     note: Some(display),
+    expires_at: utils::metric::expires_at(
+      kind,
+      &state.inner.config.metric_retention,
+    ),
   };
-  MetricDb::create_from(&metric, pool).await?;
+  MetricDb::create_from(&metric, &state.inner.pool).await?;
   Ok(())
 }
 
@@ -67,13 +68,7 @@ pub fn spawn(state: &SystemState) {
             while let Some(res) = stream.next().await {
               match res {
                 Ok(ev) => {
-                  if let Err(err) = save_metric(
-                    &state.inner.config.hostname,
-                    &ev,
-                    &state.inner.pool,
-                  )
-                  .await
-                  {
+                  if let Err(err) = save_metric(&ev, &state).await {
                     log::warn!("metrics::spawn_logger: {err}");
                   }
                 }
@@ -0,0 +1,24 @@
+use std::{collections::HashMap, time::Duration};
+
+use ntex::{rt, time};
+
+use crate::{models::SystemState, utils};
+
+/// Interval between two rollups of the metrics
+const TICK: Duration = Duration::from_secs(60);
+
+/// Spawn the task rolling up the metrics saved by this node
+/// and deleting the metrics and the rollups past their retention
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    let mut progress = HashMap::new();
+    loop {
+      utils::metric::rollup(&mut progress, &state).await;
+      if let Err(err) = utils::metric::enforce_retention(&state).await {
+        log::warn!("metric_rollup::spawn: {err}");
+      }
+      time::sleep(TICK).await;
+    }
+  });
+}
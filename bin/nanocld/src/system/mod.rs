@@ -1,7 +1,11 @@
+mod autoscaler;
 mod docker_event;
 mod event;
 mod init;
 mod metric;
+mod metric_rollup;
+mod reconciler;
+mod scheduler;
 mod system_state;
 
 pub use event::exec_event;
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use ntex::{rt, time};
+
+use nanocl_error::io::IoResult;
+
+use crate::{
+  models::{NodeDb, NodeLeaseDb, SystemState},
+  utils,
+};
+
+/// Interval between two reconciliations of the resources
+const TICK: Duration = Duration::from_secs(5);
+
+/// Name of the lease of the node reconciling the resources
+const LEASE: &str = "resource-reconciler";
+
+/// Seconds before another node can take the lease of a node that stopped
+const LEASE_TTL: i64 = 15;
+
+/// Whether this node reconciles the resources of the cluster,
+/// a single node does so their status doesn't depend on which node wrote it last
+async fn is_leader(state: &SystemState) -> IoResult<bool> {
+  let lease = NodeLeaseDb::new(LEASE, &state.inner.config.hostname, LEASE_TTL);
+  NodeDb::try_lease(&lease, &state.inner.pool).await
+}
+
+/// Spawn the task applying the pending resources with the controller
+/// of their kind and replaying the resources of the controllers coming back
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    let mut controllers = utils::resource::Controllers::new();
+    loop {
+      match is_leader(&state).await {
+        Err(err) => log::warn!("reconciler::spawn: {err}"),
+        // Seen again from scratch if this node becomes the leader
+        Ok(false) => controllers.clear(),
+        Ok(true) => {
+          if let Err(err) =
+            utils::resource::reconcile(&mut controllers, &state).await
+          {
+            log::warn!("reconciler::spawn: {err}");
+          }
+        }
+      }
+      time::sleep(TICK).await;
+    }
+  });
+}
@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+use ntex::{rt, time};
+
+use crate::{models::SystemState, utils};
+
+/// Interval between two checks of the scheduled jobs
+const TICK: Duration = Duration::from_secs(1);
+
+/// Spawn the scheduler starting jobs when their cron schedule is due.
+/// The first check handles the runs missed while the daemon was down.
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    let mut missed = true;
+    loop {
+      if let Err(err) = utils::cron::run_due_jobs(missed, &state).await {
+        log::warn!("scheduler::spawn: {err}");
+      }
+      missed = false;
+      time::sleep(TICK).await;
+    }
+  });
+}
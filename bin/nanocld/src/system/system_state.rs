@@ -16,8 +16,8 @@ use nanocl_stubs::{
 
 use crate::{
   models::{
-    EventDb, RawEventEmitter, RawEventReceiver, SystemState, SystemStateInner,
-    TaskManager,
+    EventDb, ExporterMetrics, RawEventEmitter, RawEventReceiver, SystemState,
+    SystemStateInner, TaskManager,
   },
   repositories::generic::*,
   utils, vars,
@@ -35,15 +35,19 @@ impl SystemState {
     )
     .map_err(|err| err.map_err_context(|| "Docker"))?;
     let pool = utils::store::init(conf).await?;
+    let secret_keyring = utils::secret::load_keyring(conf)?;
     let (sx, rx) = mpsc::unbounded();
     let system_state = SystemState {
       inner: Arc::new(SystemStateInner {
         pool,
         docker_api: docker.clone(),
         config: conf.to_owned(),
+        secret_keyring: std::sync::RwLock::new(secret_keyring),
+        secret_rotation: tokio::sync::RwLock::new(()),
         event_emitter: sx,
         event_emitter_raw: RawEventEmitter::new(),
         task_manager: TaskManager::new(),
+        exporter: ExporterMetrics::default(),
         arbiter: rt::Arbiter::new(),
       }),
     };
@@ -58,6 +62,10 @@ impl SystemState {
     self.inner.arbiter.clone().exec_fn(move || {
       rt::spawn(async move {
         while let Some(e) = rx.next().await {
+          self
+            .inner
+            .exporter
+            .observe_event(chrono::Utc::now().naive_utc() - e.created_at);
           if let Err(err) = super::exec_event(&e, &self).await {
             log::error!("system::run: exec_event {err}");
           }
@@ -99,12 +107,18 @@ impl SystemState {
     });
   }
 
-  /// Subscribe an http client to the event loop
+  /// Subscribe an http client to the event loop,
+  /// only the events of the given namespaces are sent when some are given
   pub async fn subscribe_raw(
     &self,
     condition: Option<Vec<EventCondition>>,
+    namespaces: Option<Vec<String>>,
   ) -> IoResult<RawEventReceiver> {
-    self.inner.event_emitter_raw.subscribe(condition).await
+    self
+      .inner
+      .event_emitter_raw
+      .subscribe(condition, namespaces)
+      .await
   }
 
   pub async fn emit_action_sync(
@@ -0,0 +1,490 @@
+use std::{collections::HashMap, fmt::Write};
+
+use ntex::{http::Method, tls::openssl::PeerCert, web};
+use openssl::nid::Nid;
+
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::{
+  auth::{ApiToken, ApiTokenPartial, Role, RoleRule, RoleVerb},
+  generic::{GenericClause, GenericFilter, GenericWhere},
+};
+use nanocl_utils::ntex::middlewares::{self, Authorizer};
+
+use crate::{
+  models::{ApiTokenDb, IdentityDb, ProcessDb, RoleDb, SystemState},
+  repositories::generic::*,
+};
+
+/// Routes reachable without authentication
+const PUBLIC_ROUTES: [&str; 2] = ["_ping", "version"];
+
+/// Namespace checked for objects that don't live in a namespace
+const CLUSTER_SCOPE: &str = "*";
+
+/// Where the namespace of the object of a request comes from
+#[derive(Debug, PartialEq, Eq)]
+pub enum ApiNamespace {
+  /// The namespace is known from the request
+  Name(String),
+  /// The namespace of the process with this name or key
+  Process(String),
+  /// The namespace of the process the exec instance with this id runs in
+  Exec(String),
+  /// Objects are listed across namespaces,
+  /// only those of the namespaces the identity can see are returned
+  Visible,
+}
+
+/// What a request want to do on the api
+#[derive(Debug, PartialEq, Eq)]
+pub struct ApiAccess {
+  /// Kind of object as named in the api path
+  pub kind: String,
+  /// Namespace of the object
+  pub namespace: ApiNamespace,
+  /// Verb performed on the object
+  pub verb: RoleVerb,
+}
+
+impl ApiAccess {
+  /// Resolve the access of a request from its method, its unversioned path
+  /// and its namespace query parameter.
+  /// Return none for public routes.
+  pub fn new(
+    method: &Method,
+    path: &str,
+    namespace: Option<String>,
+  ) -> Option<Self> {
+    let segments = path
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      .collect::<Vec<_>>();
+    let first = segments.first().copied().unwrap_or_default();
+    if PUBLIC_ROUTES.contains(&first) {
+      return None;
+    }
+    let second = segments.get(1).copied();
+    let kind = match (first, second) {
+      ("resource", Some("kinds")) => "resource_kinds".to_owned(),
+      ("vms", Some("images")) => "vm_images".to_owned(),
+      // Processes are controlled with the rights on their owner
+      ("processes", Some(kind @ ("cargo" | "vm" | "job"))) => {
+        format!("{kind}{}", if kind == "cargo" { "es" } else { "s" })
+      }
+      _ => first.to_owned(),
+    };
+    let verb = if segments.iter().any(|s| *s == "exec" || *s == "attach") {
+      RoleVerb::Exec
+    } else if middlewares::is_read_method(method)
+      || (first == "events" && second == Some("watch"))
+    {
+      RoleVerb::Read
+    } else {
+      RoleVerb::Write
+    };
+    let namespace = match (kind.as_str(), second) {
+      ("namespaces", Some(name)) if name != "count" => {
+        ApiNamespace::Name(name.to_owned())
+      }
+      // Resources are listed across every namespace without a namespace
+      ("resources", None | Some("count"))
+        if namespace.is_none() && verb == RoleVerb::Read =>
+      {
+        ApiNamespace::Name(CLUSTER_SCOPE.to_owned())
+      }
+      // The handlers of these kinds build the key of the object
+      // from the same namespace parameter
+      ("cargoes" | "vms" | "resources" | "services", _) => {
+        ApiNamespace::Name(namespace.unwrap_or("global".to_owned()))
+      }
+      ("events", Some("watch")) | ("processes", None | Some("count")) => {
+        ApiNamespace::Visible
+      }
+      ("processes", Some(name)) => ApiNamespace::Process(name.to_owned()),
+      ("exec", Some(id)) => ApiNamespace::Exec(id.to_owned()),
+      _ => ApiNamespace::Name(CLUSTER_SCOPE.to_owned()),
+    };
+    Some(Self {
+      kind,
+      namespace,
+      verb,
+    })
+  }
+}
+
+/// Namespaces an identity can see on a request listing objects
+/// across namespaces, set in the request extensions by the authorizer.
+/// Every namespace is visible when the request has none.
+#[derive(Clone, Debug)]
+pub struct ApiScope(pub Vec<String>);
+
+impl ApiScope {
+  /// Namespaces visible by the request if they are restricted
+  pub fn of(req: &web::HttpRequest) -> Option<Vec<String>> {
+    req.extensions().get::<Self>().map(|scope| scope.0.clone())
+  }
+}
+
+/// Encode bytes as a lowercase hexadecimal string
+fn to_hex(bytes: &[u8]) -> String {
+  bytes.iter().fold(String::new(), |mut hex, b| {
+    let _ = write!(hex, "{b:02x}");
+    hex
+  })
+}
+
+/// Hash a token to store or look it up in the database
+pub fn hash_token(token: &str) -> String {
+  to_hex(&openssl::sha::sha256(token.as_bytes()))
+}
+
+/// Create a new api token for an identity, the token is only returned once
+pub async fn create_token(
+  identity: &str,
+  payload: &ApiTokenPartial,
+  state: &SystemState,
+) -> HttpResult<ApiToken> {
+  IdentityDb::read_by_pk(identity, &state.inner.pool).await?;
+  let mut bytes = [0; 32];
+  openssl::rand::rand_bytes(&mut bytes)
+    .map_err(|err| HttpError::internal_server_error(err.to_string()))?;
+  let token = format!("ncl_{}", to_hex(&bytes));
+  let created_at = chrono::Utc::now().naive_utc();
+  let expires_at = payload
+    .expires_in
+    .map(|secs| created_at + chrono::Duration::seconds(secs as i64));
+  let item = ApiTokenDb {
+    key: uuid::Uuid::new_v4(),
+    created_at,
+    identity_key: identity.to_owned(),
+    token_hash: hash_token(&token),
+    expires_at,
+  };
+  let item: ApiToken = ApiTokenDb::create_from(item, &state.inner.pool)
+    .await?
+    .into();
+  Ok(ApiToken {
+    token: Some(token),
+    ..item
+  })
+}
+
+/// Common name of the client certificate of the connection
+fn peer_subject(req: &web::HttpRequest) -> Option<String> {
+  let cert = req.io()?.query::<PeerCert>();
+  let cert = cert.as_ref()?;
+  let entry = cert
+    .0
+    .subject_name()
+    .entries_by_nid(Nid::COMMONNAME)
+    .next()?;
+  entry.data().as_utf8().ok().map(|cn| cn.to_string())
+}
+
+/// Find the identity of a request from its api token or its client certificate
+async fn authenticate(
+  req: &web::HttpRequest,
+  state: &SystemState,
+) -> HttpResult<IdentityDb> {
+  if let Some(token) = middlewares::bearer_token(req) {
+    let filter = GenericFilter::new()
+      .r#where("token_hash", GenericClause::Eq(hash_token(&token)));
+    let token = ApiTokenDb::read_one_by(&filter, &state.inner.pool)
+      .await
+      .map_err(|_| HttpError::unauthorized("Invalid api token"))?;
+    if token.is_expired() {
+      return Err(HttpError::unauthorized("Api token expired"));
+    }
+    let identity =
+      IdentityDb::read_by_pk(&token.identity_key, &state.inner.pool).await?;
+    return Ok(identity);
+  }
+  if let Some(subject) = peer_subject(req) {
+    let filter = GenericFilter::new()
+      .r#where("subject", GenericClause::Eq(subject.clone()));
+    return IdentityDb::read_one_by(&filter, &state.inner.pool)
+      .await
+      .map_err(|_| {
+        HttpError::unauthorized(format!(
+          "No identity for certificate {subject}"
+        ))
+      });
+  }
+  Err(HttpError::unauthorized("Authentication required"))
+}
+
+/// Roles granted to an identity
+async fn identity_roles(
+  identity: &IdentityDb,
+  state: &SystemState,
+) -> HttpResult<Vec<Role>> {
+  let roles = serde_json::from_value::<Vec<String>>(identity.roles.clone())
+    .unwrap_or_default();
+  if roles.is_empty() {
+    return Err(HttpError::forbidden(format!(
+      "Identity {} has no role",
+      identity.key
+    )));
+  }
+  let filter = GenericFilter::new().r#where("key", GenericClause::In(roles));
+  let roles = RoleDb::transform_read_by(&filter, &state.inner.pool).await?;
+  Ok(roles)
+}
+
+/// Namespace of a process by its name or key,
+/// processes of jobs and unknown processes are cluster wide
+async fn process_namespace(name_or_key: &str, state: &SystemState) -> String {
+  let filter = GenericFilter {
+    r#where: Some(GenericWhere {
+      conditions: HashMap::from([(
+        "key".to_owned(),
+        GenericClause::Eq(name_or_key.to_owned()),
+      )]),
+      or: Some(vec![HashMap::from([(
+        "name".to_owned(),
+        GenericClause::Eq(name_or_key.to_owned()),
+      )])]),
+    }),
+    ..Default::default()
+  };
+  ProcessDb::transform_read_by(&filter, &state.inner.pool)
+    .await
+    .ok()
+    .and_then(|processes| {
+      let process = processes.first()?;
+      ProcessDb::namespace_of(process).map(str::to_owned)
+    })
+    .unwrap_or(CLUSTER_SCOPE.to_owned())
+}
+
+/// Namespace of the process an exec instance runs in
+async fn exec_namespace(id: &str, state: &SystemState) -> String {
+  match state.inner.docker_api.inspect_exec(id).await {
+    Ok(exec) => {
+      process_namespace(&exec.container_id.unwrap_or_default(), state).await
+    }
+    Err(_) => CLUSTER_SCOPE.to_owned(),
+  }
+}
+
+/// Namespaces where the roles grant the verb on the kind,
+/// none when every namespace is granted
+fn visible_namespaces(
+  roles: &[Role],
+  kind: &str,
+  verb: RoleVerb,
+) -> Option<Vec<String>> {
+  let mut namespaces = Vec::new();
+  let rules = roles.iter().flat_map(|role| role.rules.iter());
+  // A rule grants its own namespaces when it matches the kind and the verb
+  let grants = |rule: &&RoleRule| {
+    rule.namespaces.iter().any(|n| rule.allows(kind, n, verb))
+  };
+  for rule in rules.filter(grants) {
+    if rule.namespaces.iter().any(|n| n == CLUSTER_SCOPE) {
+      return None;
+    }
+    namespaces.extend(rule.namespaces.iter().cloned());
+  }
+  namespaces.sort();
+  namespaces.dedup();
+  Some(namespaces)
+}
+
+/// Check if the roles of an identity grant the access.
+/// The namespaces the identity can see are set in the request extensions
+/// for requests listing objects across namespaces.
+async fn authorize(
+  req: &web::HttpRequest,
+  identity: &IdentityDb,
+  access: &ApiAccess,
+  state: &SystemState,
+) -> HttpResult<()> {
+  let roles = identity_roles(identity, state).await?;
+  let namespace = match &access.namespace {
+    ApiNamespace::Name(name) => name.clone(),
+    ApiNamespace::Process(name) => process_namespace(name, state).await,
+    ApiNamespace::Exec(id) => exec_namespace(id, state).await,
+    ApiNamespace::Visible => {
+      match visible_namespaces(&roles, &access.kind, access.verb) {
+        None => return Ok(()),
+        Some(namespaces) if !namespaces.is_empty() => {
+          req.extensions_mut().insert(ApiScope(namespaces));
+          return Ok(());
+        }
+        Some(_) => CLUSTER_SCOPE.to_owned(),
+      }
+    }
+  };
+  if roles
+    .iter()
+    .any(|role| role.allows(&access.kind, &namespace, access.verb))
+  {
+    return Ok(());
+  }
+  Err(HttpError::forbidden(format!(
+    "Identity {} is not allowed to {:?} {} in namespace {namespace}",
+    identity.key, access.verb, access.kind
+  )))
+}
+
+/// Authorize requests of the api with the identities and roles of the store.
+/// Requests on the unix socket are trusted as they are protected by the socket permissions.
+pub struct ApiAuthorizer;
+
+impl Authorizer for ApiAuthorizer {
+  type Error = HttpError;
+
+  async fn authorize(&self, req: &web::HttpRequest) -> HttpResult<()> {
+    let Some(state) = req.app_state::<SystemState>() else {
+      return Err(HttpError::internal_server_error("Missing system state"));
+    };
+    if !state.inner.config.enable_auth || req.peer_addr().is_none() {
+      return Ok(());
+    }
+    // The prometheus exporter is served outside of the versioned api
+    let path = match req.path() {
+      "/metrics" => "/metrics",
+      _ => middlewares::unversioned_path(req),
+    };
+    let mut namespaces = middlewares::query_params(req, "namespace");
+    if namespaces.len() > 1 {
+      return Err(HttpError::bad_request(
+        "The namespace parameter can only be given once",
+      ));
+    }
+    let Some(access) = ApiAccess::new(req.method(), path, namespaces.pop())
+    else {
+      return Ok(());
+    };
+    let identity = authenticate(req, state).await?;
+    authorize(req, &identity, &access, state).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn access(method: Method, path: &str) -> Option<ApiAccess> {
+    ApiAccess::new(&method, path, None)
+  }
+
+  fn name(namespace: &str) -> ApiNamespace {
+    ApiNamespace::Name(namespace.to_owned())
+  }
+
+  fn role(kinds: &[&str], namespaces: &[&str]) -> Role {
+    let to_vec = |v: &[&str]| v.iter().map(|v| v.to_string()).collect();
+    let now = chrono::Utc::now().naive_utc();
+    Role {
+      name: "test".to_owned(),
+      created_at: now,
+      updated_at: now,
+      rules: vec![RoleRule {
+        kinds: to_vec(kinds),
+        namespaces: to_vec(namespaces),
+        verbs: vec![RoleVerb::All],
+      }],
+    }
+  }
+
+  #[test]
+  fn api_access() {
+    assert_eq!(access(Method::HEAD, "/_ping"), None);
+    assert_eq!(access(Method::GET, "/version"), None);
+    let res = access(Method::GET, "/cargoes/web/inspect").unwrap();
+    assert_eq!(res.kind, "cargoes");
+    assert_eq!(res.namespace, name("global"));
+    assert_eq!(res.verb, RoleVerb::Read);
+    let res =
+      ApiAccess::new(&Method::DELETE, "/cargoes/web", Some("dev".to_owned()))
+        .unwrap();
+    assert_eq!(res.namespace, name("dev"));
+    assert_eq!(res.verb, RoleVerb::Write);
+    let res = access(Method::POST, "/cargoes/web/exec").unwrap();
+    assert_eq!(res.verb, RoleVerb::Exec);
+    let res = access(Method::POST, "/processes/cargo/web/start").unwrap();
+    assert_eq!(res.kind, "cargoes");
+    let res = access(Method::GET, "/resource/kinds").unwrap();
+    assert_eq!(res.kind, "resource_kinds");
+    let res = access(Method::POST, "/events/watch").unwrap();
+    assert_eq!(res.verb, RoleVerb::Read);
+    let res = access(Method::DELETE, "/namespaces/dev").unwrap();
+    assert_eq!(res.namespace, name("dev"));
+    let res = access(Method::GET, "/resources").unwrap();
+    assert_eq!(res.namespace, name("*"));
+    let res = access(Method::POST, "/resources").unwrap();
+    assert_eq!(res.namespace, name("global"));
+  }
+
+  #[test]
+  fn api_access_cluster_scope() {
+    let dev = || Some("dev".to_owned());
+    for path in ["/roles", "/secrets/db", "/identities", "/jobs", "/nodes"] {
+      let res = ApiAccess::new(&Method::POST, path, dev()).unwrap();
+      assert_eq!(res.namespace, name("*"), "{path}");
+    }
+    let res = ApiAccess::new(&Method::GET, "/processes", dev()).unwrap();
+    assert_eq!(res.namespace, ApiNamespace::Visible);
+    let res = access(Method::POST, "/events/watch").unwrap();
+    assert_eq!(res.namespace, ApiNamespace::Visible);
+    let res = access(Method::GET, "/processes/web-1/logs").unwrap();
+    assert_eq!(res.namespace, ApiNamespace::Process("web-1".to_owned()));
+    let res = access(Method::POST, "/exec/abc/cargo/start").unwrap();
+    assert_eq!(res.namespace, ApiNamespace::Exec("abc".to_owned()));
+    let res =
+      ApiAccess::new(&Method::POST, "/processes/job/backup/start", dev())
+        .unwrap();
+    assert_eq!(res.kind, "jobs");
+    assert_eq!(res.namespace, name("*"));
+  }
+
+  #[test]
+  fn namespace_scoped_role() {
+    let role = role(&["*"], &["dev"]);
+    let dev = Some("dev".to_owned());
+    let roles = ApiAccess::new(&Method::POST, "/roles", dev.clone()).unwrap();
+    let ApiNamespace::Name(namespace) = roles.namespace else {
+      panic!("roles must be checked in a fixed namespace");
+    };
+    assert!(!role.allows(&roles.kind, &namespace, roles.verb));
+    let secrets =
+      ApiAccess::new(&Method::GET, "/secrets/db/inspect", dev.clone()).unwrap();
+    let ApiNamespace::Name(namespace) = secrets.namespace else {
+      panic!("secrets must be checked in a fixed namespace");
+    };
+    assert!(!role.allows(&secrets.kind, &namespace, secrets.verb));
+    let cargo = ApiAccess::new(&Method::POST, "/cargoes", dev).unwrap();
+    let ApiNamespace::Name(namespace) = cargo.namespace else {
+      panic!("cargoes must be checked in their namespace");
+    };
+    assert!(role.allows(&cargo.kind, &namespace, cargo.verb));
+  }
+
+  #[test]
+  fn visible_namespaces_of_roles() {
+    let roles = [role(&["processes"], &["dev"]), role(&["*"], &["prod"])];
+    assert_eq!(
+      visible_namespaces(&roles, "processes", RoleVerb::Read),
+      Some(vec!["dev".to_owned(), "prod".to_owned()])
+    );
+    assert_eq!(
+      visible_namespaces(&roles, "events", RoleVerb::Read),
+      Some(vec!["prod".to_owned()])
+    );
+    let roles = [role(&["events"], &["*"])];
+    assert_eq!(visible_namespaces(&roles, "events", RoleVerb::Read), None);
+    assert_eq!(
+      visible_namespaces(&roles, "processes", RoleVerb::Read),
+      Some(vec![])
+    );
+  }
+
+  #[test]
+  fn token_hash() {
+    assert_eq!(hash_token("token"), hash_token("token"));
+    assert_ne!(hash_token("token"), hash_token("other"));
+    assert_eq!(hash_token("token").len(), 64);
+  }
+}
@@ -0,0 +1,440 @@
+use futures::StreamExt;
+
+use bollard_next::container::{Stats, StatsOptions};
+use nanocl_error::io::{IoError, IoResult};
+use nanocl_stubs::{
+  cargo::Cargo,
+  cargo_spec::{
+    CargoAutoscale, CargoSpecPartial, ReplicationMode, ReplicationStatic,
+  },
+  generic::{GenericClause, GenericFilter},
+  metric::MetricAggregateQuery,
+  process::Process,
+  system::{EventKind, NativeEventAction, ObjPsStatusKind},
+};
+
+use crate::{
+  models::{
+    CargoDb, CargoObjPutIn, MetricDb, NodeDb, NodeLeaseDb, ProcessDb,
+    SystemState,
+  },
+  objects::generic::*,
+  repositories::generic::*,
+  utils,
+};
+
+/// Default seconds to wait after a change of the cargo before scaling up
+const DEFAULT_SCALE_UP_COOLDOWN: u64 = 60;
+/// Default seconds to wait after a change of the cargo before scaling down
+const DEFAULT_SCALE_DOWN_COOLDOWN: u64 = 300;
+/// Seconds of proxy metrics used to compute the requests per second
+const RPS_WINDOW: i64 = 60;
+/// Ratio to the target under which the number of replicas is kept
+const TOLERANCE: f64 = 0.1;
+/// Seconds before another node running the cargo can take the lease
+/// of a node that stopped evaluating its policy
+const LEASE_TTL: i64 = 45;
+
+/// Load of the instances of a cargo compared to its autoscaling targets
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CargoLoad {
+  /// Average CPU usage of an instance in percent of one CPU
+  pub cpu: Option<f64>,
+  /// Requests per second per instance
+  pub rps: Option<f64>,
+}
+
+/// Ensure an autoscaling policy can be applied to a cargo
+pub fn validate(
+  autoscale: Option<&CargoAutoscale>,
+  replication: Option<&ReplicationMode>,
+) -> IoResult<()> {
+  let Some(autoscale) = autoscale else {
+    return Ok(());
+  };
+  if autoscale.min_replicas == 0 {
+    return Err(IoError::invalid_input(
+      "Autoscale",
+      "MinReplicas must be at least 1",
+    ));
+  }
+  if autoscale.min_replicas > autoscale.max_replicas {
+    return Err(IoError::invalid_input(
+      "Autoscale",
+      "MinReplicas cannot be greater than MaxReplicas",
+    ));
+  }
+  let targets = [autoscale.target_cpu, autoscale.target_rps];
+  if targets.iter().all(Option::is_none) {
+    return Err(IoError::invalid_input(
+      "Autoscale",
+      "At least one of TargetCpu or TargetRps is required",
+    ));
+  }
+  if targets.iter().flatten().any(|target| *target <= 0.0) {
+    return Err(IoError::invalid_input(
+      "Autoscale",
+      "Targets must be greater than 0",
+    ));
+  }
+  replicas(replication)?;
+  Ok(())
+}
+
+/// Number of replicas set by a replication mode the autoscaler can adjust
+pub fn replicas(replication: Option<&ReplicationMode>) -> IoResult<usize> {
+  match replication {
+    None => Ok(1),
+    Some(ReplicationMode::Static(replication))
+    | Some(ReplicationMode::StaticByNodes(replication)) => {
+      Ok(replication.number)
+    }
+    Some(_) => Err(IoError::invalid_input(
+      "Autoscale",
+      "Replication mode must be Static or StaticByNodes",
+    )),
+  }
+}
+
+/// Replication mode with the number of replicas replaced
+pub fn with_replicas(
+  replication: Option<&ReplicationMode>,
+  number: usize,
+) -> ReplicationMode {
+  match replication {
+    Some(ReplicationMode::StaticByNodes(_)) => {
+      ReplicationMode::StaticByNodes(ReplicationStatic { number })
+    }
+    _ => ReplicationMode::Static(ReplicationStatic { number }),
+  }
+}
+
+/// Number of replicas needed to bring the load of the instances to the targets.
+/// The most loaded target wins and small deviations are ignored.
+pub fn desired_replicas(
+  autoscale: &CargoAutoscale,
+  current: usize,
+  load: &CargoLoad,
+) -> usize {
+  let ratios = [
+    (load.cpu, autoscale.target_cpu),
+    (load.rps, autoscale.target_rps),
+  ]
+  .into_iter()
+  .filter_map(|(usage, target)| Some(usage? / target?))
+  .collect::<Vec<_>>();
+  let desired = match ratios.into_iter().reduce(f64::max) {
+    None => current,
+    Some(ratio) if (ratio - 1.0).abs() <= TOLERANCE => current,
+    Some(ratio) => (current.max(1) as f64 * ratio).ceil() as usize,
+  };
+  desired.clamp(autoscale.min_replicas, autoscale.max_replicas)
+}
+
+/// Whether enough time passed since the last change of the cargo to scale it
+pub fn cooldown_elapsed(
+  autoscale: &CargoAutoscale,
+  current: usize,
+  desired: usize,
+  elapsed: u64,
+) -> bool {
+  let cooldown = if desired > current {
+    autoscale
+      .scale_up_cooldown
+      .unwrap_or(DEFAULT_SCALE_UP_COOLDOWN)
+  } else {
+    autoscale
+      .scale_down_cooldown
+      .unwrap_or(DEFAULT_SCALE_DOWN_COOLDOWN)
+  };
+  elapsed >= cooldown
+}
+
+/// CPU usage in percent of one CPU from docker stats
+fn cpu_percent(stats: &Stats) -> Option<f64> {
+  let cpu_delta = stats
+    .cpu_stats
+    .cpu_usage
+    .total_usage
+    .checked_sub(stats.precpu_stats.cpu_usage.total_usage)?;
+  let system_delta = stats
+    .cpu_stats
+    .system_cpu_usage?
+    .checked_sub(stats.precpu_stats.system_cpu_usage?)?;
+  if system_delta == 0 {
+    return None;
+  }
+  let cpus = stats.cpu_stats.online_cpus.unwrap_or(1);
+  Some(cpu_delta as f64 / system_delta as f64 * cpus as f64 * 100.0)
+}
+
+/// Average CPU usage of the running instances
+async fn cpu_usage(processes: &[Process], state: &SystemState) -> Option<f64> {
+  let mut usages = Vec::new();
+  for process in processes {
+    let opts = StatsOptions {
+      stream: false,
+      one_shot: false,
+    };
+    let stats = state
+      .inner
+      .docker_api
+      .stats(&process.key, Some(opts))
+      .next()
+      .await;
+    match stats {
+      Some(Ok(stats)) => usages.extend(cpu_percent(&stats)),
+      Some(Err(err)) => {
+        log::warn!("autoscale::cpu_usage: {}: {err}", process.key)
+      }
+      None => {}
+    }
+  }
+  if usages.is_empty() {
+    return None;
+  }
+  Some(usages.iter().sum::<f64>() / usages.len() as f64)
+}
+
+/// Check if an upstream of the proxy belongs to the cargo.
+/// The upstreams of a cargo are named `{cargo_key}-{port}-cargo`
+/// with a `-{hash}` suffix when they have options.
+fn is_cargo_upstream(upstream: &str, cargo_key: &str) -> bool {
+  let Some(rest) = upstream
+    .strip_prefix(cargo_key)
+    .and_then(|rest| rest.strip_prefix('-'))
+  else {
+    return false;
+  };
+  let parts = rest.split('-').collect::<Vec<_>>();
+  match parts.as_slice() {
+    [port, "cargo"] => port.parse::<u16>().is_ok(),
+    [port, "cargo", hash] => {
+      port.parse::<u16>().is_ok()
+        && !hash.is_empty()
+        && hash.chars().all(|c| c.is_ascii_hexdigit())
+    }
+    _ => false,
+  }
+}
+
+/// Requests per second per instance of a cargo from the proxy metrics
+/// counted by upstream in the store
+async fn rps_usage(
+  cargo_key: &str,
+  instances: usize,
+  state: &SystemState,
+) -> IoResult<Option<f64>> {
+  if instances == 0 {
+    return Ok(None);
+  }
+  let until = chrono::Utc::now().naive_utc();
+  let plan = utils::metric::parse_aggregate_query(&MetricAggregateQuery {
+    kind: "ncproxy.io/http".to_owned(),
+    interval: Some(RPS_WINDOW as u64),
+    since: Some(until - chrono::Duration::seconds(RPS_WINDOW)),
+    until: Some(until),
+    group_by: Some("proxy_host".to_owned()),
+    fields: None,
+  })?;
+  let requests = MetricDb::aggregate(&plan, &state.inner.pool)
+    .await?
+    .into_iter()
+    .filter(|aggregate| {
+      aggregate
+        .group
+        .get("proxy_host")
+        .and_then(Option::as_deref)
+        .is_some_and(|upstream| is_cargo_upstream(upstream, cargo_key))
+    })
+    .map(|aggregate| aggregate.count)
+    .sum::<i64>();
+  Ok(Some(requests as f64 / RPS_WINDOW as f64 / instances as f64))
+}
+
+/// Update the number of replicas of a cargo through a new version of its spec
+async fn scale(
+  cargo: &Cargo,
+  current: usize,
+  desired: usize,
+  load: &CargoLoad,
+  state: &SystemState,
+) -> IoResult<()> {
+  let mut spec = CargoSpecPartial::from(cargo.spec.clone());
+  spec.replication =
+    Some(with_replicas(cargo.spec.replication.as_ref(), desired));
+  let obj = CargoObjPutIn {
+    spec,
+    version: cargo.spec.version.clone(),
+  };
+  let cargo =
+    CargoDb::put_obj_by_pk(&cargo.spec.cargo_key, &obj, state).await?;
+  let mut usage = Vec::new();
+  if let Some(cpu) = load.cpu {
+    usage.push(format!("cpu {cpu:.1}%"));
+  }
+  if let Some(rps) = load.rps {
+    usage.push(format!("{rps:.2} req/s"));
+  }
+  state.emit_action(
+    &cargo.into(),
+    NativeEventAction::Scale,
+    EventKind::Normal,
+    "autoscale",
+    Some(format!(
+      "Scaled from {current} to {desired} replicas ({})",
+      usage.join(", ")
+    )),
+    Some(serde_json::json!({
+      "From": current,
+      "To": desired,
+      "Cpu": load.cpu,
+      "Rps": load.rps,
+    })),
+  );
+  Ok(())
+}
+
+/// Name of the lease of the node evaluating the autoscaling policy of a cargo
+pub fn lease_name(cargo_key: &str) -> String {
+  format!("cargo-autoscaler/{cargo_key}")
+}
+
+/// Evaluate the autoscaling policy of a cargo running on the current node
+async fn autoscale_cargo(cargo: &Cargo, state: &SystemState) -> IoResult<()> {
+  let Some(autoscale) = &cargo.spec.autoscale else {
+    return Ok(());
+  };
+  if cargo.status.actual != ObjPsStatusKind::Start {
+    return Ok(());
+  }
+  let processes =
+    ProcessDb::read_by_kind_key(&cargo.spec.cargo_key, None, &state.inner.pool)
+      .await?;
+  let (local, _): (Vec<_>, Vec<_>) = processes
+    .iter()
+    .cloned()
+    .partition(|process| process.node_name == state.inner.config.hostname);
+  // Only the nodes running the cargo can read the stats of its instances
+  if local.is_empty() {
+    return Ok(());
+  }
+  // A single node running the cargo evaluates its policy,
+  // otherwise each of them would scale it from the same load
+  let lease = NodeLeaseDb::new(
+    &lease_name(&cargo.spec.cargo_key),
+    &state.inner.config.hostname,
+    LEASE_TTL,
+  );
+  if !NodeDb::try_lease(&lease, &state.inner.pool).await? {
+    return Ok(());
+  }
+  let (_, _, _, running) = utils::container::generic::count_status(&processes);
+  let load = CargoLoad {
+    cpu: match autoscale.target_cpu {
+      None => None,
+      Some(_) => cpu_usage(&local, state).await,
+    },
+    rps: match autoscale.target_rps {
+      None => None,
+      Some(_) => rps_usage(&cargo.spec.cargo_key, running, state).await?,
+    },
+  };
+  let current = replicas(cargo.spec.replication.as_ref())?;
+  let desired = desired_replicas(autoscale, current, &load);
+  if desired == current {
+    return Ok(());
+  }
+  let elapsed = (chrono::Utc::now().naive_utc() - cargo.spec.created_at)
+    .num_seconds()
+    .max(0) as u64;
+  if !cooldown_elapsed(autoscale, current, desired, elapsed) {
+    log::debug!(
+      "autoscale::autoscale_cargo: {} waits its cooldown to scale to {desired}",
+      cargo.spec.cargo_key
+    );
+    return Ok(());
+  }
+  log::info!(
+    "autoscale::autoscale_cargo: scaling {} from {current} to {desired}",
+    cargo.spec.cargo_key
+  );
+  scale(cargo, current, desired, &load, state).await
+}
+
+/// Evaluate the autoscaling policy of the started cargoes
+pub async fn run(state: &SystemState) -> IoResult<()> {
+  let filter = GenericFilter::new()
+    .r#where("data", GenericClause::HasKey("Autoscale".to_owned()))
+    .r#where(
+      "status.wanted",
+      GenericClause::Eq(ObjPsStatusKind::Start.to_string()),
+    );
+  let cargoes = CargoDb::transform_read_by(&filter, &state.inner.pool).await?;
+  for cargo in cargoes {
+    if let Err(err) = autoscale_cargo(&cargo, state).await {
+      log::warn!("autoscale::run: {}: {err}", cargo.spec.cargo_key);
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy() -> CargoAutoscale {
+    CargoAutoscale {
+      min_replicas: 1,
+      max_replicas: 10,
+      target_cpu: Some(50.0),
+      target_rps: Some(10.0),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn cargo_upstream() {
+    assert!(is_cargo_upstream("web.global-80-cargo", "web.global"));
+    assert!(is_cargo_upstream("web.global-80-cargo-1f2e", "web.global"));
+    assert!(!is_cargo_upstream("web.global-x-80-cargo", "web.global"));
+    assert!(!is_cargo_upstream("web.global-80-8080-cargo", "web.global"));
+    assert!(!is_cargo_upstream("web.global-80-vm", "web.global"));
+    assert!(!is_cargo_upstream("web.globalx-80-cargo", "web.global"));
+    assert!(!is_cargo_upstream("web.global-80-cargo-", "web.global"));
+  }
+
+  #[test]
+  fn desired_replicas_from_load() {
+    let autoscale = policy();
+    let load = |cpu, rps| CargoLoad { cpu, rps };
+    assert_eq!(desired_replicas(&autoscale, 2, &load(None, None)), 2);
+    assert_eq!(desired_replicas(&autoscale, 2, &load(Some(100.0), None)), 4);
+    assert_eq!(desired_replicas(&autoscale, 2, &load(Some(52.0), None)), 2);
+    assert_eq!(
+      desired_replicas(&autoscale, 4, &load(Some(10.0), Some(5.0))),
+      2
+    );
+    assert_eq!(desired_replicas(&autoscale, 4, &load(Some(0.0), None)), 1);
+    assert_eq!(
+      desired_replicas(&autoscale, 4, &load(Some(1000.0), None)),
+      10
+    );
+    assert!(cooldown_elapsed(&autoscale, 2, 4, 60));
+    assert!(!cooldown_elapsed(&autoscale, 4, 2, 60));
+    assert!(validate(Some(&autoscale), None).is_ok());
+    let invalid = CargoAutoscale {
+      min_replicas: 3,
+      max_replicas: 2,
+      ..policy()
+    };
+    assert!(validate(Some(&invalid), None).is_err());
+    let invalid = CargoAutoscale {
+      target_cpu: None,
+      target_rps: None,
+      ..policy()
+    };
+    assert!(validate(Some(&invalid), None).is_err());
+    assert!(validate(Some(&autoscale), Some(&ReplicationMode::Auto)).is_err());
+  }
+}
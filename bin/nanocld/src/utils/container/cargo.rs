@@ -1,18 +1,17 @@
 use futures::{stream::FuturesUnordered, StreamExt};
-use ntex::rt;
 
 use bollard_next::{
   container::{
-    Config, RemoveContainerOptions, RenameContainerOptions,
-    StartContainerOptions, StopContainerOptions, WaitContainerOptions,
+    Config, RemoveContainerOptions, StartContainerOptions,
+    StopContainerOptions, WaitContainerOptions,
   },
   secret::{HostConfig, RestartPolicy, RestartPolicyNameEnum},
 };
 use nanocl_error::io::{FromIo, IoError, IoResult};
 use nanocl_stubs::{
   cargo::Cargo,
-  cargo_spec::ReplicationMode,
   generic::{GenericClause, GenericFilter},
+  namespace,
   process::{Process, ProcessKind},
   system::{NativeEventAction, ObjPsStatusKind},
 };
@@ -67,7 +66,9 @@ async fn create_init_container(
   init_container.host_config = Some(HostConfig {
     binds: Some(binds),
     network_mode: Some(
-      host_config.network_mode.unwrap_or("nanoclbr0".to_owned()),
+      host_config
+        .network_mode
+        .unwrap_or(namespace::network_name(&cargo.namespace_name)),
     ),
     ..host_config
   });
@@ -151,11 +152,12 @@ async fn start_init_container(
   Ok(())
 }
 
-/// Execute the cargo spec to create the cargo container
+/// Execute the cargo spec to create the cargo containers
+/// for the given range of instance ordinals
 ///
 pub async fn create(
   cargo: &Cargo,
-  number: usize,
+  ordinals: std::ops::Range<usize>,
   state: &SystemState,
 ) -> IoResult<Vec<Process>> {
   let data = serde_json::to_string(&cargo)?;
@@ -178,7 +180,7 @@ pub async fn create(
     state,
   )
   .await?;
-  let instances = (0..number)
+  let instances = ordinals
     .collect::<Vec<usize>>()
     .into_iter()
     .map(move |current| {
@@ -238,7 +240,7 @@ pub async fn create(
           env: Some(env),
           host_config: Some(HostConfig {
             restart_policy,
-            network_mode: Some("nanoclbr0".to_owned()),
+            network_mode: Some(namespace::network_name(&cargo.namespace_name)),
             binds: Some(binds),
             ..host_config
           }),
@@ -263,25 +265,39 @@ pub async fn create(
 }
 
 /// Start cargo instances
+/// Instances are created or removed on the current node
+/// to match its share of the replication mode
 ///
 pub async fn start(key: &str, state: &SystemState) -> IoResult<()> {
   let cargo = CargoDb::transform_read_by_pk(&key, &state.inner.pool).await?;
-  let filter = GenericFilter::new().r#where(
-    "data",
-    GenericClause::Contains(serde_json::json!({
-      "Config": {
-        "Labels": {
-          "io.nanocl.not-init-c": "true"
+  let number = super::replication::count(
+    cargo.spec.replication.as_ref(),
+    &cargo.spec.cargo_key,
+    state,
+  )
+  .await?;
+  let filter = GenericFilter::new()
+    .r#where(
+      "data",
+      GenericClause::Contains(serde_json::json!({
+        "Config": {
+          "Labels": {
+            "io.nanocl.not-init-c": "true"
+          }
         }
-      }
-    })),
-  );
-  let processes = ProcessDb::read_by_kind_key(
+      })),
+    )
+    .r#where(
+      "node_name",
+      GenericClause::Eq(state.inner.config.hostname.clone()),
+    );
+  let mut processes = ProcessDb::read_by_kind_key(
     &cargo.spec.cargo_key,
     Some(filter),
     &state.inner.pool,
   )
   .await?;
+  processes.sort_by(|a, b| a.created_at.cmp(&b.created_at));
   log::debug!(
     "processes {:?}",
     processes.iter().map(|p| p.name.clone()).collect::<Vec<_>>()
@@ -302,6 +318,27 @@ pub async fn start(key: &str, state: &SystemState) -> IoResult<()> {
     &state.inner.pool,
   )
   .await?;
+  if number < processes.len() {
+    // Remove the most recent instances the node doesn't need anymore
+    super::process::delete_instances(
+      &processes[number..]
+        .iter()
+        .map(|p| p.key.clone())
+        .collect::<Vec<_>>(),
+      state,
+    )
+    .await?;
+  }
+  if number == 0 {
+    log::debug!("cargo {key} has no instance to run on this node");
+    ObjPsStatusDb::update_actual_status(
+      key,
+      &ObjPsStatusKind::Start,
+      &state.inner.pool,
+    )
+    .await?;
+    return Ok(());
+  }
   if let Some(init_container) = &cargo.spec.init_container {
     if init_process.is_empty() {
       let process =
@@ -311,16 +348,8 @@ pub async fn start(key: &str, state: &SystemState) -> IoResult<()> {
       start_init_container(&init_process[0], state).await?;
     }
   }
-  // TODO: FIND BEST NODES TO RUN WORKLOAD
-  // let nodes =
-  //   MetricDb::find_best_nodes(90.0, 90.0, 100, &state.inner.pool).await?;
-  // log::debug!("BEST NODES FOR CARGO {key}: {nodes:?}");
-  if processes.is_empty() {
-    let number = match &cargo.spec.replication {
-      Some(ReplicationMode::Static(replication)) => replication.number,
-      _ => 1,
-    };
-    create(&cargo, number, state).await?;
+  if processes.len() < number {
+    create(&cargo, processes.len()..number, state).await?;
   }
   super::process::start_instances(
     &cargo.spec.cargo_key,
@@ -331,124 +360,235 @@ pub async fn start(key: &str, state: &SystemState) -> IoResult<()> {
   Ok(())
 }
 
-/// Function that update the cargo container by creating new instances before removing the old ones
+/// Replace the old instances by batches of new ones following the rollout plan
+/// Old instances are only retired once the new ones of the batch are healthy
+/// On failure the instances created by the rollout are removed
+///
+async fn rolling_update(
+  cargo: &Cargo,
+  plan: &super::rollout::RolloutPlan,
+  number: usize,
+  mut old: Vec<Process>,
+  state: &SystemState,
+) -> IoResult<()> {
+  let mut created: Vec<Process> = Vec::new();
+  while created.len() < number {
+    let size = plan.batch_size.min(number - created.len());
+    let retired = plan.retire_before(size).min(old.len());
+    super::rollout::retire_instances(
+      &old.drain(..retired).collect::<Vec<_>>(),
+      state,
+    )
+    .await?;
+    let res = async {
+      let batch =
+        create(cargo, created.len()..created.len() + size, state).await?;
+      created.extend(batch.clone());
+      batch
+        .iter()
+        .map(|process| async move {
+          state
+            .inner
+            .docker_api
+            .start_container(
+              &process.key,
+              None::<StartContainerOptions<String>>,
+            )
+            .await
+            .map_err(|err| err.map_err_context(|| "StartProcess"))?;
+          super::rollout::wait_healthy(process, plan.health_timeout, state)
+            .await
+        })
+        .collect::<FuturesUnordered<_>>()
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<IoResult<Vec<_>>>()
+    }
+    .await;
+    if let Err(err) = res {
+      let _ = super::process::delete_instances(
+        &created.iter().map(|p| p.key.clone()).collect::<Vec<_>>(),
+        state,
+      )
+      .await;
+      return Err(err);
+    }
+    let retired = (size - retired).min(old.len());
+    super::rollout::retire_instances(
+      &old.drain(..retired).collect::<Vec<_>>(),
+      state,
+    )
+    .await?;
+  }
+  // Retire the instances left when the number of replicas decreased
+  super::rollout::retire_instances(&old, state).await
+}
+
+/// Revert the cargo to its previous spec after a failed update
+/// and create the instances missing to match its replication
+async fn rollback(
+  cargo: &Cargo,
+  state: &SystemState,
+  reason: &str,
+) -> IoResult<()> {
+  let cargo = match super::rollout::rollback(cargo, state).await? {
+    Some(cargo) => {
+      state.emit_warning_native_action(
+        &cargo,
+        NativeEventAction::Rollback,
+        Some(format!(
+          "Rollback to version {}: {reason}",
+          cargo.spec.version
+        )),
+      );
+      cargo
+    }
+    // Already moved by another node, only restore the local instances
+    None => {
+      CargoDb::transform_read_by_pk(&cargo.spec.cargo_key, &state.inner.pool)
+        .await?
+    }
+  };
+  let number = super::replication::count(
+    cargo.spec.replication.as_ref(),
+    &cargo.spec.cargo_key,
+    state,
+  )
+  .await?;
+  let filter = GenericFilter::new()
+    .r#where(
+      "data",
+      GenericClause::Contains(serde_json::json!({
+        "Config": {
+          "Labels": {
+            "io.nanocl.not-init-c": "true"
+          }
+        }
+      })),
+    )
+    .r#where(
+      "node_name",
+      GenericClause::Eq(state.inner.config.hostname.clone()),
+    );
+  let processes = ProcessDb::read_by_kind_key(
+    &cargo.spec.cargo_key,
+    Some(filter),
+    &state.inner.pool,
+  )
+  .await?;
+  if processes.len() < number {
+    create(&cargo, processes.len()..number, state).await?;
+  }
+  super::process::start_instances(
+    &cargo.spec.cargo_key,
+    &ProcessKind::Cargo,
+    state,
+  )
+  .await
+}
+
+/// Function that update the cargo container by replacing the old instances
+/// with new ones following the rolling update strategy of the cargo
 /// This way we can have zero downtime deployment
 ///
 pub async fn update(key: &str, state: &SystemState) -> IoResult<()> {
   let cargo = CargoDb::transform_read_by_pk(&key, &state.inner.pool).await?;
+  let plan =
+    super::rollout::RolloutPlan::new(cargo.spec.update_strategy.as_ref())?;
+  let number = super::replication::count(
+    cargo.spec.replication.as_ref(),
+    &cargo.spec.cargo_key,
+    state,
+  )
+  .await?;
+  let filter = GenericFilter::new().r#where(
+    "node_name",
+    GenericClause::Eq(state.inner.config.hostname.clone()),
+  );
   let processes =
-    ProcessDb::read_by_kind_key(key, None, &state.inner.pool).await?;
-  // rename old instances to flag them for deletion
-  processes
-    .iter()
-    .map(|process| {
-      let docker_api = state.inner.docker_api.clone();
-      async move {
-        if process
-          .data
-          .state
-          .clone()
-          .unwrap_or_default()
-          .restarting
-          .unwrap_or_default()
-        {
-          docker_api
-            .stop_container(&process.name, None::<StopContainerOptions>)
-            .await
-            .map_err(|err| err.map_err_context(|| "StopContainer"))?;
-        }
-        let new_name = format!("tmp-{}", process.name);
-        docker_api
-          .rename_container(
-            &process.key,
-            RenameContainerOptions { name: &new_name },
-          )
-          .await
-          .map_err(|err| err.map_err_context(|| "RenameContainer"))?;
-        Ok::<_, IoError>(())
-      }
-    })
-    .collect::<FuturesUnordered<_>>()
-    .collect::<Vec<_>>()
-    .await
-    .into_iter()
-    .collect::<IoResult<Vec<_>>>()?;
-  let number = match &cargo.spec.replication {
-    Some(ReplicationMode::Static(replication)) => replication.number,
-    _ => 1,
-  };
+    ProcessDb::read_by_kind_key(key, Some(filter), &state.inner.pool).await?;
+  let (init_processes, processes): (Vec<_>, Vec<_>) =
+    processes.into_iter().partition(|process| {
+      process
+        .data
+        .config
+        .clone()
+        .unwrap_or_default()
+        .labels
+        .unwrap_or_default()
+        .contains_key("io.nanocl.init-c")
+    });
+  if number == 0 {
+    super::process::delete_instances(
+      &processes.iter().map(|p| p.key.clone()).collect::<Vec<_>>(),
+      state,
+    )
+    .await?;
+    let _ = super::process::delete_instances(
+      &init_processes
+        .iter()
+        .map(|p| p.key.clone())
+        .collect::<Vec<_>>(),
+      state,
+    )
+    .await;
+    ObjPsStatusDb::update_actual_status(
+      key,
+      &ObjPsStatusKind::Start,
+      &state.inner.pool,
+    )
+    .await?;
+    state
+      .emit_normal_native_action_sync(&cargo, NativeEventAction::Start)
+      .await;
+    return Ok(());
+  }
+  let mut res = Ok(());
   // Create instance with the new spec
   if let Some(init_container) = &cargo.spec.init_container {
-    let process = create_init_container(&cargo, init_container, state).await?;
-    start_init_container(&process, state).await?;
-  }
-  let new_instances = match create(&cargo, number, state).await {
-    Err(err) => {
-      log::error!(
-        "Unable to create cargo instance {} : {err}",
-        cargo.spec.cargo_key
-      );
-      return Err(err);
+    res = async {
+      let process =
+        create_init_container(&cargo, init_container, state).await?;
+      start_init_container(&process, state).await
     }
-    Ok(instances) => instances,
-  };
-  log::debug!("cargo new instances {new_instances:?}");
-  // start created containers
-  match super::process::start_instances(key, &ProcessKind::Cargo, state).await {
-    Err(err) => {
-      log::error!(
-        "Unable to start cargo instance {} : {err}",
-        cargo.spec.cargo_key
-      );
-      let state_ptr_ptr = state.clone();
-      let _ = super::process::delete_instances(
-        &new_instances
-          .iter()
-          .map(|p| p.key.clone())
-          .collect::<Vec<_>>(),
-        &state_ptr_ptr,
-      )
-      .await;
-      let res = processes
-        .iter()
-        .map(|process| {
-          let docker_api = state_ptr_ptr.inner.docker_api.clone();
-          async move {
-            docker_api
-              .rename_container(
-                &process.key,
-                RenameContainerOptions {
-                  name: &process.name,
-                },
-              )
-              .await
-              .map_err(|err| err.map_err_context(|| "RenameContainer"))?;
-            Ok::<_, IoError>(())
-          }
-        })
-        .collect::<FuturesUnordered<_>>()
-        .collect::<Vec<_>>()
-        .await
-        .into_iter()
-        .collect::<IoResult<Vec<_>>>();
-      if let Err(err) = res {
-        log::error!("Unable to rename containers back: {err}");
+    .await;
+  }
+  if res.is_ok() {
+    res = rolling_update(&cargo, &plan, number, processes, state).await;
+  }
+  if let Err(err) = res {
+    log::error!(
+      "Unable to update cargo instances {} : {err}",
+      cargo.spec.cargo_key
+    );
+    state.emit_error_native_action(
+      &cargo,
+      NativeEventAction::Fail,
+      Some(format!("Update failed: {err}")),
+    );
+    if plan.rollback {
+      if let Err(err) = rollback(&cargo, state, &err.to_string()).await {
+        log::error!("Unable to rollback cargo {key}: {err}");
       }
     }
-    Ok(_) => {
-      log::debug!("cargo instance {} started", cargo.spec.cargo_key);
-      // Delete old containers
-      let state_ptr_ptr = state.clone();
-      rt::spawn(async move {
-        ntex::time::sleep(std::time::Duration::from_secs(4)).await;
-        let _ = super::process::delete_instances(
-          &processes.iter().map(|p| p.key.clone()).collect::<Vec<_>>(),
-          &state_ptr_ptr,
-        )
-        .await;
-      });
-    }
+    ObjPsStatusDb::update_actual_status(
+      key,
+      &ObjPsStatusKind::Fail,
+      &state.inner.pool,
+    )
+    .await?;
+    return Err(err);
   }
+  log::debug!("cargo instance {} updated", cargo.spec.cargo_key);
+  let _ = super::process::delete_instances(
+    &init_processes
+      .iter()
+      .map(|p| p.key.clone())
+      .collect::<Vec<_>>(),
+    state,
+  )
+  .await;
   ObjPsStatusDb::update_actual_status(
     key,
     &ObjPsStatusKind::Start,
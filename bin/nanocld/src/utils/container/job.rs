@@ -1,33 +1,67 @@
-use futures::StreamExt;
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  time::{Duration, Instant},
+};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use ntex::rt;
+use tokio::fs;
 
 use bollard_next::{
-  container::{Config, StartContainerOptions, WaitContainerOptions},
+  container::{StartContainerOptions, WaitContainerOptions},
+  errors::Error as BollardError,
   secret::HostConfig,
 };
 use nanocl_error::io::{IoError, IoResult};
 use nanocl_stubs::{
-  job::Job,
+  generic::{GenericClause, GenericFilter},
+  job::{Job, JobAttempt, JobStepState, JobStepStatus},
+  namespace,
   process::{Process, ProcessKind},
-  system::{NativeEventAction, ObjPsStatusKind},
+  system::{EventKind, NativeEventAction, ObjPsStatusKind},
 };
 
 use crate::{
-  models::{JobDb, ObjPsStatusDb, ProcessDb, SystemState},
+  models::{
+    JobDb, JobRunDb, JobRunUpdateDb, ObjPsStatusDb, ProcessDb, SystemState,
+  },
+  objects::generic::*,
   repositories::generic::*,
   utils,
 };
 
-/// Create process (container) for a job
+use super::job_step::{PlannedStep, Readiness};
+
+/// Directory containing the artifacts of the runs of a job
+fn artifacts_dir(name: &str, state: &SystemState) -> String {
+  format!(
+    "{}/artifacts/{}/{name}",
+    state.inner.config.state_dir,
+    ProcessKind::Job
+  )
+}
+
+/// Directory shared by the steps of a job run to pass artifacts
+fn run_artifacts_dir(name: &str, run: &str, state: &SystemState) -> String {
+  format!("{}/{run}", artifacts_dir(name, state))
+}
+
+/// Create process (container) for a step of a job run
 ///
 async fn create_instance(
   job: &Job,
+  run: &str,
   index: usize,
-  container: &Config,
+  step: &PlannedStep,
   state: &SystemState,
 ) -> IoResult<Process> {
-  let mut container = container.clone();
+  let mut container = step.container.clone();
   let mut labels = container.labels.unwrap_or_default();
   labels.insert("io.nanocl.j".to_owned(), job.name.to_owned());
+  labels.insert("io.nanocl.n".to_owned(), job.namespace_name().to_owned());
+  labels.insert("io.nanocl.j.step".to_owned(), step.name.to_owned());
+  labels.insert("io.nanocl.j.run".to_owned(), run.to_owned());
   container.labels = Some(labels);
   let env_secrets =
     utils::secret::load_env_secrets(&job.secrets, state).await?;
@@ -38,6 +72,8 @@ async fn create_instance(
     state,
   )
   .await?;
+  let artifacts_dir = run_artifacts_dir(&job.name, run, state);
+  fs::create_dir_all(&artifacts_dir).await?;
   container.env = Some(
     container
       .env
@@ -49,9 +85,12 @@ async fn create_instance(
   let host_config = container.host_config.unwrap_or_default();
   let mut binds = host_config.binds.clone().unwrap_or_default();
   binds.push(format!("{}/:/opt/nanocl.io/secrets", secret_dir));
+  binds.push(format!("{artifacts_dir}:/opt/nanocl.io/artifacts"));
   container.host_config = Some(HostConfig {
     network_mode: Some(
-      host_config.network_mode.unwrap_or("nanoclbr0".to_owned()),
+      host_config
+        .network_mode
+        .unwrap_or(namespace::network_name(job.namespace_name())),
     ),
     binds: Some(binds),
     ..host_config
@@ -68,67 +107,435 @@ async fn create_instance(
   .await
 }
 
-/// Create processes (container) for a job
+/// Create processes (container) for the steps of a job run
 ///
 pub async fn create_instances(
   job: &Job,
+  run: &str,
+  steps: &[PlannedStep],
   state: &SystemState,
 ) -> IoResult<Vec<Process>> {
   let mut processes = Vec::new();
-  for (index, container) in job.containers.iter().enumerate() {
+  for (index, step) in steps.iter().enumerate() {
     super::image::download(
-      &container.image.clone().unwrap_or_default(),
+      &step.container.image.clone().unwrap_or_default(),
       job.image_pull_secret.clone(),
       job.image_pull_policy.clone().unwrap_or_default(),
       job,
       state,
     )
     .await?;
-    let process = create_instance(job, index, container, state).await?;
+    let process = create_instance(job, run, index, step, state).await?;
     processes.push(process);
   }
   Ok(processes)
 }
 
-/// Start job instances
+/// Find the process of a step by its label
+/// or by its name for instances created without it
+fn step_process<'a>(
+  job: &Job,
+  processes: &'a [Process],
+  index: usize,
+  step: &PlannedStep,
+) -> IoResult<&'a Process> {
+  let prefix = format!("{}-{index}-", job.name);
+  processes
+    .iter()
+    .find(|process| {
+      let labels = process
+        .data
+        .config
+        .as_ref()
+        .and_then(|config| config.labels.as_ref());
+      match labels.and_then(|labels| labels.get("io.nanocl.j.step")) {
+        Some(name) => name == &step.name,
+        None => process.name.starts_with(&prefix),
+      }
+    })
+    .ok_or_else(|| {
+      IoError::not_found("Step", &format!("{} has no instance", step.name))
+    })
+}
+
+/// Error of an attempt stopped or not started because of the active deadline
+const DEADLINE_EXCEEDED: &str = "active deadline exceeded";
+/// Delay in seconds before the first retry of a container when not set
+const DEFAULT_BACKOFF: u64 = 10;
+/// Maximum delay in seconds between two attempts of a container
+const MAX_BACKOFF: u64 = 3600;
+
+/// Delay in seconds before the given retry of a container, starting at 1
+fn backoff_delay(backoff: u64, retry: usize) -> u64 {
+  let exp = u32::try_from(retry.saturating_sub(1)).unwrap_or(u32::MAX);
+  backoff
+    .saturating_mul(2u64.saturating_pow(exp))
+    .min(MAX_BACKOFF)
+}
+
+/// Run of a job instance, empty for instances created before runs had their own
+fn instance_run(process: &Process) -> &str {
+  process
+    .data
+    .config
+    .as_ref()
+    .and_then(|config| config.labels.as_ref())
+    .and_then(|labels| labels.get("io.nanocl.j.run"))
+    .map(String::as_str)
+    .unwrap_or_default()
+}
+
+/// Delete the instances and the artifacts of the previous runs of a job
+/// that are done, runs with an instance still running are kept
+async fn delete_done_runs(job: &Job, state: &SystemState) -> IoResult<()> {
+  let processes =
+    ProcessDb::read_by_kind_key(&job.name, None, &state.inner.pool).await?;
+  let mut runs: HashMap<&str, Vec<&Process>> = HashMap::new();
+  for process in &processes {
+    runs.entry(instance_run(process)).or_default().push(process);
+  }
+  for (run, instances) in runs {
+    let running = instances.iter().any(|process| {
+      let container = process.data.state.clone().unwrap_or_default();
+      container.running.unwrap_or_default()
+        || container.restarting.unwrap_or_default()
+    });
+    if running {
+      continue;
+    }
+    super::process::delete_instances(
+      &instances.iter().map(|p| p.key.clone()).collect::<Vec<_>>(),
+      state,
+    )
+    .await?;
+    if !run.is_empty() {
+      let _ =
+        fs::remove_dir_all(run_artifacts_dir(&job.name, run, state)).await;
+      let filter =
+        GenericFilter::new().r#where("key", GenericClause::Eq(run.to_owned()));
+      JobRunDb::del_by(&filter, &state.inner.pool).await?;
+    }
+  }
+  Ok(())
+}
+
+/// Start a job instance and wait for it to exit.
+/// Return its exit code or why it couldn't run.
+async fn run_instance(key: &str, state: &SystemState) -> Result<i64, String> {
+  state
+    .inner
+    .docker_api
+    .start_container(key, None::<StartContainerOptions<String>>)
+    .await
+    .map_err(|err| err.to_string())?;
+  let mut stream = state.inner.docker_api.wait_container(
+    key,
+    Some(WaitContainerOptions {
+      condition: "not-running",
+    }),
+  );
+  match stream.next().await {
+    Some(Ok(res)) => Ok(res.status_code),
+    Some(Err(BollardError::DockerContainerWaitError { code, .. })) => Ok(code),
+    Some(Err(err)) => Err(err.to_string()),
+    None => Err("no exit status".to_owned()),
+  }
+}
+
+/// Settings and progress of a job run shared by its steps
+struct JobRun<'a> {
+  id: &'a str,
+  job: &'a Job,
+  started: Instant,
+  deadline: Option<Duration>,
+  timeout: Option<Duration>,
+  retries: usize,
+  backoff: u64,
+  attempts: RefCell<Vec<JobAttempt>>,
+  state: &'a SystemState,
+}
+
+impl JobRun<'_> {
+  /// Time left before the active deadline
+  fn remaining(&self) -> Option<Duration> {
+    self
+      .deadline
+      .map(|deadline| deadline.saturating_sub(self.started.elapsed()))
+  }
+
+  fn deadline_exceeded(&self) -> bool {
+    self
+      .remaining()
+      .is_some_and(|remaining| remaining.is_zero())
+  }
+
+  /// Save the attempts of the run and emit an event for the new one
+  async fn record_attempt(
+    &self,
+    attempt: JobAttempt,
+    note: String,
+  ) -> IoResult<()> {
+    let metadata = serde_json::to_value(&attempt)?;
+    let kind = match attempt.error {
+      None => EventKind::Normal,
+      Some(_) => EventKind::Warning,
+    };
+    self.attempts.borrow_mut().push(attempt);
+    let data = serde_json::to_value(&*self.attempts.borrow())?;
+    JobRunDb::update_pk(
+      self.id,
+      JobRunUpdateDb {
+        attempts: Some(data),
+        ..Default::default()
+      },
+      &self.state.inner.pool,
+    )
+    .await?;
+    self.state.emit_action(
+      &self.job.clone().into(),
+      NativeEventAction::Attempt,
+      kind,
+      "state_sync",
+      Some(note),
+      Some(metadata),
+    );
+    Ok(())
+  }
+}
+
+/// Save the state of the steps of a job run
+async fn save_step_states(
+  run: &str,
+  states: &[JobStepState],
+  state: &SystemState,
+) -> IoResult<()> {
+  JobRunDb::update_pk(
+    run,
+    JobRunUpdateDb {
+      steps: Some(serde_json::to_value(states)?),
+      ..Default::default()
+    },
+    &state.inner.pool,
+  )
+  .await?;
+  Ok(())
+}
+
+/// Run a step until it succeeds or fails all its attempts.
+/// Return true if it succeeded.
+async fn run_step(
+  run: &JobRun<'_>,
+  index: usize,
+  step: &PlannedStep,
+  process: &Process,
+) -> IoResult<bool> {
+  for attempt in 1..=run.retries + 1 {
+    let remaining = run.remaining();
+    let limit = match (run.timeout, remaining) {
+      (Some(timeout), Some(remaining)) => Some(timeout.min(remaining)),
+      (timeout, remaining) => timeout.or(remaining),
+    };
+    let started_at = chrono::Utc::now().naive_utc();
+    let result = match limit {
+      // The attempt isn't started but is recorded as failed
+      _ if run.deadline_exceeded() => Err(DEADLINE_EXCEEDED.to_owned()),
+      None => run_instance(&process.key, run.state).await,
+      Some(limit) => {
+        match ntex::time::timeout(limit, run_instance(&process.key, run.state))
+          .await
+        {
+          Ok(result) => result,
+          Err(_) => {
+            let _ = run
+              .state
+              .inner
+              .docker_api
+              .kill_container::<String>(&process.key, None)
+              .await;
+            if run.timeout.is_some_and(|timeout| timeout == limit) {
+              Err("container timeout exceeded".to_owned())
+            } else {
+              Err(DEADLINE_EXCEEDED.to_owned())
+            }
+          }
+        }
+      }
+    };
+    let (exit_code, error) = match result {
+      Ok(0) => (Some(0), None),
+      Ok(code) => (Some(code), Some(format!("exited with code {code}"))),
+      Err(err) => (None, Some(err)),
+    };
+    let name = &step.name;
+    let job_attempt = JobAttempt {
+      container: index,
+      step: run.job.steps.as_ref().map(|_| name.clone()),
+      attempt,
+      process: process.name.clone(),
+      started_at,
+      ended_at: Some(chrono::Utc::now().naive_utc()),
+      exit_code,
+      error: error.clone(),
+    };
+    let Some(error) = error else {
+      let note = format!("Step {name} attempt {attempt} succeeded");
+      run.record_attempt(job_attempt, note).await?;
+      return Ok(true);
+    };
+    if attempt > run.retries || run.deadline_exceeded() {
+      let note = format!("Step {name} attempt {attempt} failed: {error}");
+      run.record_attempt(job_attempt, note).await?;
+      break;
+    }
+    let delay = backoff_delay(run.backoff, attempt);
+    let note = format!(
+      "Step {name} attempt {attempt} failed: {error}, retrying in {delay}s"
+    );
+    run.record_attempt(job_attempt, note).await?;
+    let delay = Duration::from_secs(delay);
+    let delay = match run.remaining() {
+      Some(remaining) => delay.min(remaining),
+      None => delay,
+    };
+    ntex::time::sleep(delay).await;
+  }
+  Ok(false)
+}
+
+/// Start job instances.
+/// Each run creates its own instances, artifacts directory and state
+/// so overlapping runs don't interfere.
+/// Steps start once the steps they depend on succeeded, those that are ready
+/// run at the same time. A failed step is run again according to the retries
+/// and backoff of the job until it succeeds or the job reaches its active deadline.
+/// The final status is set however the run ends.
 ///
 pub async fn start(key: &str, state: &SystemState) -> IoResult<()> {
   let job = JobDb::transform_read_by_pk(&key, &state.inner.pool).await?;
-  let mut processes =
-    ProcessDb::read_by_kind_key(&job.name, None, &state.inner.pool).await?;
-  if processes.is_empty() {
-    processes = create_instances(&job, state).await?;
-  }
+  let res = run(&job, state).await;
+  finish(&job, res.as_ref().map_or(true, |failed| *failed), state).await?;
+  res.map(|_| ())
+}
+
+/// Run the steps of a job, return whether one of them failed
+async fn run(job: &Job, state: &SystemState) -> IoResult<bool> {
+  let steps = super::job_step::plan(&job.clone().into())?;
+  delete_done_runs(job, state).await?;
+  let run_id = uuid::Uuid::new_v4().to_string();
+  let mut states = super::job_step::init_states(&steps);
+  JobRunDb::create_from(
+    JobRunDb {
+      steps: serde_json::to_value(&states)?,
+      ..JobRunDb::new(&run_id, &job.name)
+    },
+    &state.inner.pool,
+  )
+  .await?;
+  let processes = create_instances(job, &run_id, &steps, state).await?;
   ObjPsStatusDb::update_actual_status(
-    key,
+    &job.name,
     &ObjPsStatusKind::Start,
     &state.inner.pool,
   )
   .await?;
   state
-    .emit_normal_native_action_sync(&job, NativeEventAction::Start)
+    .emit_normal_native_action_sync(job, NativeEventAction::Start)
     .await;
-  for process in processes {
-    let _ = state
-      .inner
-      .docker_api
-      .start_container(&process.key, None::<StartContainerOptions<String>>)
-      .await;
-    // We currently run a sequential order so we wait for the container to finish to start the next one.
-    let mut stream = state.inner.docker_api.wait_container(
-      &process.key,
-      Some(WaitContainerOptions {
-        condition: "not-running",
-      }),
-    );
-    while let Some(stream) = stream.next().await {
-      let result = stream
-        .map_err(|err| IoError::interrupted("JobCreate", &format!("{err}")))?;
-      if result.status_code == 0 {
-        break;
+  let run = &JobRun {
+    id: &run_id,
+    job,
+    started: Instant::now(),
+    deadline: job.active_deadline.map(Duration::from_secs),
+    timeout: job.container_timeout.map(Duration::from_secs),
+    retries: job.retries.unwrap_or_default(),
+    backoff: job.backoff.unwrap_or(DEFAULT_BACKOFF),
+    attempts: RefCell::new(Vec::new()),
+    state,
+  };
+  let continue_on_failure = job.continue_on_failure.unwrap_or_default();
+  let mut running = FuturesUnordered::new();
+  let mut failed = false;
+  let mut stopped = false;
+  loop {
+    if !stopped {
+      // A skipped step can skip its dependents so we loop until nothing changes
+      let mut changed = true;
+      while changed {
+        changed = false;
+        for (index, step) in steps.iter().enumerate() {
+          if states[index].status != JobStepStatus::Pending {
+            continue;
+          }
+          match super::job_step::readiness(step, &states) {
+            Readiness::Wait => {}
+            Readiness::Skip => {
+              states[index].status = JobStepStatus::Skipped;
+              changed = true;
+            }
+            Readiness::Ready => {
+              let process = step_process(job, &processes, index, step)?;
+              states[index].status = JobStepStatus::Running;
+              changed = true;
+              running.push(async move {
+                (index, run_step(run, index, step, process).await)
+              });
+            }
+          }
+        }
       }
+      save_step_states(&run_id, &states, state).await?;
     }
+    let Some((index, succeeded)) = running.next().await else {
+      break;
+    };
+    if succeeded? {
+      states[index].status = JobStepStatus::Succeeded;
+    } else {
+      states[index].status = JobStepStatus::Failed;
+      failed = true;
+      stopped = !continue_on_failure;
+    }
+    if run.deadline_exceeded() {
+      failed = true;
+      stopped = true;
+    }
+    save_step_states(&run_id, &states, state).await?;
   }
+  for step_state in states.iter_mut() {
+    if step_state.status == JobStepStatus::Pending {
+      step_state.status = JobStepStatus::Skipped;
+    }
+  }
+  save_step_states(&run_id, &states, state).await?;
+  Ok(failed)
+}
+
+/// Set the final status of a job once its instances are done
+/// and remove it after its ttl if set
+///
+pub async fn finish(
+  job: &Job,
+  failed: bool,
+  state: &SystemState,
+) -> IoResult<()> {
+  let (status, action) = if failed {
+    (ObjPsStatusKind::Fail, NativeEventAction::Fail)
+  } else {
+    (ObjPsStatusKind::Finish, NativeEventAction::Finish)
+  };
+  ObjPsStatusDb::update_actual_status(&job.name, &status, &state.inner.pool)
+    .await?;
+  state.emit_normal_native_action_sync(job, action).await;
+  let Some(ttl) = job.ttl else {
+    return Ok(());
+  };
+  let job = job.clone();
+  let state = state.clone();
+  rt::spawn(async move {
+    log::debug!("job::finish: {} will be deleted in {ttl}s", job.name);
+    ntex::time::sleep(Duration::from_secs(ttl as u64)).await;
+    let _ = JobDb::del_obj_by_pk(&job.name, &(), &state).await;
+  });
   Ok(())
 }
 
@@ -148,11 +555,24 @@ pub async fn delete(key: &str, state: &SystemState) -> IoResult<()> {
   .await?;
   log::debug!("JobDb::delete_by_pk({:?})", &job.name);
   JobDb::clear_by_pk(&job.name, &state.inner.pool).await?;
-  if job.schedule.is_some() {
-    utils::cron::remove_cron_rule(&job, state).await?;
-  }
+  let _ = fs::remove_dir_all(artifacts_dir(&job.name, state)).await;
   state
     .emit_normal_native_action_sync(&job, NativeEventAction::Destroy)
     .await;
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn backoff_delay_doubles() {
+    assert_eq!(backoff_delay(10, 1), 10);
+    assert_eq!(backoff_delay(10, 2), 20);
+    assert_eq!(backoff_delay(10, 3), 40);
+    assert_eq!(backoff_delay(10, 20), MAX_BACKOFF);
+    assert_eq!(backoff_delay(0, 5), 0);
+    assert_eq!(backoff_delay(u64::MAX, usize::MAX), MAX_BACKOFF);
+  }
+}
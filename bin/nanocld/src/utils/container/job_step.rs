@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use bollard_next::container::Config;
+
+use nanocl_error::io::{IoError, IoResult};
+use nanocl_stubs::job::{JobPartial, JobStepState, JobStepStatus};
+
+/// Step of a job resolved from its `Steps` or its `Containers`
+#[derive(Debug, Clone)]
+pub struct PlannedStep {
+  /// Name of the step
+  pub name: String,
+  /// Index of the steps it depends on
+  pub depends_on: Vec<usize>,
+  /// The step starts even if the steps it depends on failed
+  pub after_failure: bool,
+  /// Container to run
+  pub container: Config,
+}
+
+/// What to do with a pending step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Readiness {
+  Wait,
+  Ready,
+  Skip,
+}
+
+/// Resolve and validate the steps of a job.
+/// `Containers` become a chain of steps named by their index
+/// to keep running them in sequence.
+pub fn plan(job: &JobPartial) -> IoResult<Vec<PlannedStep>> {
+  let Some(steps) = &job.steps else {
+    let after_failure = job.continue_on_failure.unwrap_or_default();
+    return Ok(
+      job
+        .containers
+        .iter()
+        .enumerate()
+        .map(|(index, container)| PlannedStep {
+          name: index.to_string(),
+          depends_on: index.checked_sub(1).into_iter().collect(),
+          after_failure,
+          container: container.clone(),
+        })
+        .collect(),
+    );
+  };
+  if !job.containers.is_empty() {
+    return Err(IoError::invalid_input(
+      "Job",
+      "Containers and Steps cannot be used together",
+    ));
+  }
+  let mut indexes = HashMap::new();
+  for (index, step) in steps.iter().enumerate() {
+    if step.name.is_empty() {
+      return Err(IoError::invalid_input("Step", "name cannot be empty"));
+    }
+    if indexes.insert(step.name.as_str(), index).is_some() {
+      return Err(IoError::invalid_input(
+        "Step",
+        &format!("{} is defined more than once", step.name),
+      ));
+    }
+  }
+  let planned = steps
+    .iter()
+    .map(|step| {
+      let depends_on = step
+        .depends_on
+        .clone()
+        .unwrap_or_default()
+        .iter()
+        .map(|name| match indexes.get(name.as_str()) {
+          None => Err(IoError::invalid_input(
+            "Step",
+            &format!("{} depends on unknown step {name}", step.name),
+          )),
+          Some(index) => Ok(*index),
+        })
+        .collect::<IoResult<Vec<_>>>()?;
+      Ok(PlannedStep {
+        name: step.name.clone(),
+        depends_on,
+        after_failure: false,
+        container: step.container.clone(),
+      })
+    })
+    .collect::<IoResult<Vec<_>>>()?;
+  check_cycle(&planned)?;
+  Ok(planned)
+}
+
+/// Ensure the steps can all be started by removing the ready ones until none are left
+fn check_cycle(steps: &[PlannedStep]) -> IoResult<()> {
+  let mut remaining = steps
+    .iter()
+    .map(|step| step.depends_on.len())
+    .collect::<Vec<_>>();
+  let mut ready = (0..steps.len())
+    .filter(|index| remaining[*index] == 0)
+    .collect::<Vec<_>>();
+  let mut done = 0;
+  while let Some(index) = ready.pop() {
+    done += 1;
+    for (dependent, step) in steps.iter().enumerate() {
+      for dependency in &step.depends_on {
+        if *dependency == index {
+          remaining[dependent] -= 1;
+          if remaining[dependent] == 0 {
+            ready.push(dependent);
+          }
+        }
+      }
+    }
+  }
+  if done != steps.len() {
+    let names = steps
+      .iter()
+      .enumerate()
+      .filter(|(index, _)| remaining[*index] > 0)
+      .map(|(_, step)| step.name.clone())
+      .collect::<Vec<_>>()
+      .join(", ");
+    return Err(IoError::invalid_input(
+      "Step",
+      &format!("dependency cycle between {names}"),
+    ));
+  }
+  Ok(())
+}
+
+/// Initial state of the steps for a new run
+pub fn init_states(steps: &[PlannedStep]) -> Vec<JobStepState> {
+  steps
+    .iter()
+    .map(|step| JobStepState {
+      name: step.name.clone(),
+      depends_on: step
+        .depends_on
+        .iter()
+        .map(|index| steps[*index].name.clone())
+        .collect(),
+      status: JobStepStatus::Pending,
+    })
+    .collect()
+}
+
+/// Whether a pending step can start from the status of the steps it depends on
+pub fn readiness(step: &PlannedStep, states: &[JobStepState]) -> Readiness {
+  let mut readiness = Readiness::Ready;
+  for index in &step.depends_on {
+    match states[*index].status {
+      JobStepStatus::Succeeded => {}
+      JobStepStatus::Failed | JobStepStatus::Skipped if step.after_failure => {}
+      JobStepStatus::Failed | JobStepStatus::Skipped => {
+        return Readiness::Skip;
+      }
+      JobStepStatus::Pending | JobStepStatus::Running => {
+        readiness = Readiness::Wait;
+      }
+    }
+  }
+  readiness
+}
+
+#[cfg(test)]
+mod tests {
+  use nanocl_stubs::job::JobStep;
+
+  use super::*;
+
+  fn step(name: &str, depends_on: &[&str]) -> JobStep {
+    JobStep {
+      name: name.to_owned(),
+      depends_on: Some(depends_on.iter().map(|d| d.to_string()).collect()),
+      container: Config::default(),
+    }
+  }
+
+  #[test]
+  fn plan_steps() {
+    let job = JobPartial {
+      containers: vec![Config::default(), Config::default()],
+      ..Default::default()
+    };
+    let steps = plan(&job).unwrap();
+    assert_eq!(steps[0].depends_on, Vec::<usize>::new());
+    assert_eq!(steps[1].depends_on, vec![0]);
+    let mut job = JobPartial {
+      steps: Some(vec![
+        step("build", &[]),
+        step("lint", &[]),
+        step("test", &["build"]),
+        step("publish", &["test", "lint"]),
+      ]),
+      ..Default::default()
+    };
+    let steps = plan(&job).unwrap();
+    assert_eq!(steps[3].depends_on, vec![2, 1]);
+    let mut states = init_states(&steps);
+    assert_eq!(states[3].depends_on, vec!["test", "lint"]);
+    assert_eq!(readiness(&steps[2], &states), Readiness::Wait);
+    states[0].status = JobStepStatus::Succeeded;
+    states[1].status = JobStepStatus::Failed;
+    assert_eq!(readiness(&steps[2], &states), Readiness::Ready);
+    assert_eq!(readiness(&steps[3], &states), Readiness::Skip);
+    job.steps = Some(vec![step("a", &["b"]), step("b", &["a"])]);
+    assert!(plan(&job).is_err());
+    job.steps = Some(vec![step("a", &["c"])]);
+    assert!(plan(&job).is_err());
+    job.steps = Some(vec![step("a", &[]), step("a", &[])]);
+    assert!(plan(&job).is_err());
+    job.steps = Some(vec![step("a", &[])]);
+    job.containers = vec![Config::default()];
+    assert!(plan(&job).is_err());
+  }
+}
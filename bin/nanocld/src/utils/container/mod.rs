@@ -2,5 +2,9 @@ pub mod cargo;
 pub mod generic;
 pub mod image;
 pub mod job;
+pub mod job_step;
+pub mod network;
 pub mod process;
+pub mod replication;
+pub mod rollout;
 pub mod vm;
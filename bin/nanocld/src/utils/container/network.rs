@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use bollard_next::network::{
+  ConnectNetworkOptions, CreateNetworkOptions, InspectNetworkOptions,
+};
+use nanocl_error::io::{FromIo, IoError, IoResult};
+use nanocl_stubs::{
+  generic::GenericFilter,
+  namespace::{self, NamespaceNetwork, DEFAULT_NETWORK},
+};
+
+use crate::{
+  models::{NamespaceDb, SystemState},
+  repositories::generic::*,
+};
+
+/// Prefix of the interface of the namespace bridges, ncdns listens on them
+const BRIDGE_PREFIX: &str = "ncbr";
+
+/// Name of the host interface of the bridge of a namespace
+/// Interface names are limited to 15 characters so the namespace is hashed
+fn bridge_name(name: &str) -> String {
+  // FNV-1a, stable across releases unlike the std hasher
+  let hash = name.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+  });
+  format!("{BRIDGE_PREFIX}{:011x}", hash >> 20)
+}
+
+/// Ensure the namespaces a namespace connects to exist
+pub async fn validate(
+  name: &str,
+  network: Option<&NamespaceNetwork>,
+  state: &SystemState,
+) -> IoResult<()> {
+  let Some(network) = network else {
+    return Ok(());
+  };
+  for target in &network.connect {
+    if target == name {
+      return Err(IoError::invalid_input(
+        "Network",
+        &format!("Namespace {name} cannot connect to itself"),
+      ));
+    }
+    NamespaceDb::read_by_pk(target, &state.inner.pool)
+      .await
+      .map_err(|_| {
+        IoError::invalid_input(
+          "Network",
+          &format!("Namespace {target} doesn't exist"),
+        )
+      })?;
+  }
+  Ok(())
+}
+
+/// Create the bridge network of a namespace if it doesn't exist yet
+pub async fn ensure(name: &str, state: &SystemState) -> IoResult<()> {
+  let network = namespace::network_name(name);
+  // The default network is created by the installer
+  if network == DEFAULT_NETWORK {
+    return Ok(());
+  }
+  let bridge = bridge_name(name);
+  if state
+    .inner
+    .docker_api
+    .inspect_network(&network, None::<InspectNetworkOptions<String>>)
+    .await
+    .is_ok()
+  {
+    return Ok(());
+  }
+  state
+    .inner
+    .docker_api
+    .create_network(CreateNetworkOptions {
+      name: network.as_str(),
+      check_duplicate: true,
+      driver: "bridge",
+      attachable: true,
+      labels: HashMap::from([("io.nanocl.n", name)]),
+      options: HashMap::from([(
+        "com.docker.network.bridge.name",
+        bridge.as_str(),
+      )]),
+      ..Default::default()
+    })
+    .await
+    .map_err(|err| {
+      err.map_err_context(|| format!("Unable to create network {network}"))
+    })?;
+  log::debug!("network::ensure: {network} created");
+  Ok(())
+}
+
+/// Remove the bridge network of a namespace
+pub async fn remove(name: &str, state: &SystemState) -> IoResult<()> {
+  let network = namespace::network_name(name);
+  if network == DEFAULT_NETWORK {
+    return Ok(());
+  }
+  state
+    .inner
+    .docker_api
+    .remove_network(&network)
+    .await
+    .map_err(|err| {
+      err.map_err_context(|| format!("Unable to remove network {network}"))
+    })?;
+  Ok(())
+}
+
+/// Attach a container to the networks of the namespaces its namespace connects to
+pub async fn connect(
+  container: &str,
+  name: &str,
+  state: &SystemState,
+) -> IoResult<()> {
+  let Ok(namespace) = NamespaceDb::read_by_pk(name, &state.inner.pool).await
+  else {
+    return Ok(());
+  };
+  for target in namespace.try_to_network()?.connect {
+    // The target may have been deleted since
+    if NamespaceDb::read_by_pk(&target, &state.inner.pool)
+      .await
+      .is_err()
+    {
+      log::warn!("network::connect: namespace {target} doesn't exist");
+      continue;
+    }
+    let network = namespace::network_name(&target);
+    if let Err(err) = ensure(&target, state).await {
+      log::warn!("network::connect: {err}");
+      continue;
+    }
+    state
+      .inner
+      .docker_api
+      .connect_network(
+        &network,
+        ConnectNetworkOptions {
+          container,
+          endpoint_config: Default::default(),
+        },
+      )
+      .await
+      .map_err(|err| {
+        err.map_err_context(|| format!("Unable to connect to {network}"))
+      })?;
+  }
+  Ok(())
+}
+
+/// Ensure every namespace has its network
+pub async fn sync(state: &SystemState) -> IoResult<()> {
+  let namespaces =
+    NamespaceDb::read_by(&GenericFilter::default(), &state.inner.pool).await?;
+  for namespace in namespaces {
+    if let Err(err) = ensure(&namespace.name, state).await {
+      log::warn!("network::sync: {err}");
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bridge_name_fits_interface() {
+    let name = bridge_name("a-very-long-namespace-name");
+    assert_eq!(name.len(), 15);
+    assert!(name.starts_with(BRIDGE_PREFIX));
+    assert_eq!(name, bridge_name("a-very-long-namespace-name"));
+    assert_ne!(name, bridge_name("another"));
+  }
+}
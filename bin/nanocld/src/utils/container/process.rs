@@ -8,6 +8,7 @@ use nanocl_error::io::{FromIo, IoError, IoResult};
 use nanocl_stubs::{
   cargo::CargoKillOptions,
   generic::{GenericClause, GenericFilter},
+  namespace,
   process::{Process, ProcessKind, ProcessPartial},
   system::{NativeEventAction, ObjPsStatusKind},
 };
@@ -29,6 +30,14 @@ pub async fn create(
   let mut labels = item.labels.to_owned().unwrap_or_default();
   labels.insert("io.nanocl".to_owned(), "enabled".to_owned());
   labels.insert("io.nanocl.kind".to_owned(), kind.to_string());
+  // Workloads on the network of their namespace join the connected ones
+  let connect = labels.get("io.nanocl.n").cloned().filter(|name| {
+    config
+      .host_config
+      .as_ref()
+      .and_then(|host_config| host_config.network_mode.as_deref())
+      == Some(namespace::network_name(name).as_str())
+  });
   config.labels = Some(labels);
   let res = state
     .inner
@@ -42,6 +51,11 @@ pub async fn create(
     )
     .await
     .map_err(|err| err.map_err_context(|| "CreateProcess"))?;
+  if let Some(name) = connect {
+    if let Err(err) = super::network::connect(&res.id, &name, state).await {
+      log::warn!("process::create: {err}");
+    }
+  }
   let inspect = state
     .inner
     .docker_api
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+
+use nanocl_error::io::{IoError, IoResult};
+use nanocl_stubs::{
+  cargo_spec::ReplicationMode,
+  generic::{GenericClause, GenericFilter},
+};
+
+use crate::models::{NodeDb, ProcessDb, SystemState};
+
+/// Snapshot of the cluster topology used to place cargo instances
+#[derive(Debug, Default)]
+pub struct ReplicationCtx {
+  /// Name of the node computing its share of instances
+  pub node_name: String,
+  /// Names of every node in the cluster
+  pub nodes: Vec<String>,
+  /// Node groups with the names of the nodes they contain
+  pub groups: HashMap<String, Vec<String>>,
+  /// Names of the other nodes already running an instance of the cargo
+  pub running_on: Vec<String>,
+}
+
+impl ReplicationCtx {
+  /// Load the nodes and node groups of the cluster from the store
+  pub async fn cluster(state: &SystemState) -> IoResult<Self> {
+    Ok(Self {
+      node_name: state.inner.config.hostname.clone(),
+      nodes: NodeDb::list_names(&state.inner.pool).await?,
+      groups: NodeDb::list_groups(&state.inner.pool).await?,
+      running_on: Vec::new(),
+    })
+  }
+
+  /// Load the topology from the store for the given cargo
+  pub async fn load(cargo_key: &str, state: &SystemState) -> IoResult<Self> {
+    let ctx = Self::cluster(state).await?;
+    let filter = GenericFilter::new()
+      .r#where("node_name", GenericClause::Ne(ctx.node_name.clone()));
+    let mut running_on =
+      ProcessDb::read_by_kind_key(cargo_key, Some(filter), &state.inner.pool)
+        .await?
+        .into_iter()
+        .map(|process| process.node_name)
+        .collect::<Vec<_>>();
+    running_on.sort();
+    running_on.dedup();
+    Ok(Self { running_on, ..ctx })
+  }
+
+  fn get_group(&self, name: &str) -> IoResult<&Vec<String>> {
+    self.groups.get(name).ok_or_else(|| {
+      IoError::invalid_input(
+        "Replication",
+        &format!("Node group {name} doesn't exist"),
+      )
+    })
+  }
+
+  fn check_groups(&self, groups: &[String]) -> IoResult<()> {
+    if groups.is_empty() {
+      return Err(IoError::invalid_input(
+        "Replication",
+        "At least one node group is required",
+      ));
+    }
+    for group in groups {
+      self.get_group(group)?;
+    }
+    Ok(())
+  }
+
+  fn check_names(&self, names: &[String]) -> IoResult<()> {
+    if names.is_empty() {
+      return Err(IoError::invalid_input(
+        "Replication",
+        "At least one node name is required",
+      ));
+    }
+    for name in names {
+      if !self.nodes.contains(name) {
+        return Err(IoError::invalid_input(
+          "Replication",
+          &format!("Node {name} doesn't exist"),
+        ));
+      }
+    }
+    Ok(())
+  }
+
+  /// Position of the current node in the cluster,
+  /// a node that isn't registered yet comes first
+  fn position(&self) -> usize {
+    self
+      .nodes
+      .iter()
+      .position(|node| node == &self.node_name)
+      .unwrap_or_default()
+  }
+
+  /// Share of the current node of a number of instances
+  /// spread across the nodes of the cluster
+  fn share(&self, number: usize) -> usize {
+    let nodes = self.nodes.len().max(1);
+    number / nodes + usize::from(self.position() < number % nodes)
+  }
+
+  fn is_in_groups(&self, groups: &[String]) -> IoResult<bool> {
+    for group in groups {
+      if self.get_group(group)?.contains(&self.node_name) {
+        return Ok(true);
+      }
+    }
+    Ok(false)
+  }
+
+  /// Compute the number of instances the current node must run for a replication mode
+  /// Return an error when the mode can't be satisfied by the cluster
+  pub fn count(&self, mode: Option<&ReplicationMode>) -> IoResult<usize> {
+    let Some(mode) = mode else {
+      return Ok(1);
+    };
+    let number = match mode {
+      // One instance on every node
+      ReplicationMode::Auto | ReplicationMode::UniqueByNode => 1,
+      // Only the first node starts the instance so two nodes
+      // can't start it at the same time
+      ReplicationMode::Unique => {
+        usize::from(self.running_on.is_empty() && self.position() == 0)
+      }
+      ReplicationMode::UniqueByNodeGroups { groups } => {
+        self.check_groups(groups)?;
+        let mut number = 0;
+        for group in groups {
+          let nodes = self.get_group(group)?;
+          // The group is already covered by another node
+          if nodes.iter().any(|node| self.running_on.contains(node)) {
+            continue;
+          }
+          if nodes.contains(&self.node_name) {
+            number = 1;
+          }
+        }
+        number
+      }
+      ReplicationMode::UniqueByNodeNames { names } => {
+        self.check_names(names)?;
+        usize::from(names.contains(&self.node_name))
+      }
+      ReplicationMode::Static(replication) => self.share(replication.number),
+      ReplicationMode::StaticByNodes(replication) => replication.number,
+      ReplicationMode::StaticByNodeGroups { groups, number } => {
+        let number = parse_number(*number)?;
+        self.check_groups(groups)?;
+        if self.is_in_groups(groups)? {
+          number
+        } else {
+          0
+        }
+      }
+      ReplicationMode::StaticByNodeNames { names, number } => {
+        let number = parse_number(*number)?;
+        self.check_names(names)?;
+        if names.contains(&self.node_name) {
+          number
+        } else {
+          0
+        }
+      }
+    };
+    Ok(number)
+  }
+
+  /// Compute the number of instances in the whole cluster for a replication mode
+  pub fn total(&self, mode: Option<&ReplicationMode>) -> IoResult<usize> {
+    let Some(mode) = mode else {
+      return Ok(1);
+    };
+    let number = match mode {
+      ReplicationMode::Unique => 1,
+      ReplicationMode::Auto | ReplicationMode::UniqueByNode => {
+        self.nodes.len().max(1)
+      }
+      ReplicationMode::UniqueByNodeGroups { groups } => {
+        self.check_groups(groups)?;
+        groups.len()
+      }
+      ReplicationMode::UniqueByNodeNames { names } => {
+        self.check_names(names)?;
+        names.len()
+      }
+      ReplicationMode::Static(replication) => replication.number,
+      ReplicationMode::StaticByNodes(replication) => {
+        replication.number * self.nodes.len()
+      }
+      ReplicationMode::StaticByNodeGroups { groups, number } => {
+        let number = parse_number(*number)?;
+        self.check_groups(groups)?;
+        let mut nodes = Vec::new();
+        for group in groups {
+          nodes.extend(self.get_group(group)?);
+        }
+        nodes.sort();
+        nodes.dedup();
+        number * nodes.len()
+      }
+      ReplicationMode::StaticByNodeNames { names, number } => {
+        let number = parse_number(*number)?;
+        self.check_names(names)?;
+        number * names.len()
+      }
+    };
+    Ok(number)
+  }
+}
+
+fn parse_number(number: i64) -> IoResult<usize> {
+  usize::try_from(number).map_err(|_| {
+    IoError::invalid_input(
+      "Replication",
+      &format!("Invalid number of replicas {number}"),
+    )
+  })
+}
+
+/// Ensure the replication mode can be satisfied by the current cluster
+pub async fn validate(
+  mode: Option<&ReplicationMode>,
+  cargo_key: &str,
+  state: &SystemState,
+) -> IoResult<()> {
+  if mode.is_none() {
+    return Ok(());
+  }
+  ReplicationCtx::load(cargo_key, state).await?.count(mode)?;
+  Ok(())
+}
+
+/// Number of instances of a cargo the current node must run
+pub async fn count(
+  mode: Option<&ReplicationMode>,
+  cargo_key: &str,
+  state: &SystemState,
+) -> IoResult<usize> {
+  ReplicationCtx::load(cargo_key, state).await?.count(mode)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use nanocl_stubs::cargo_spec::ReplicationStatic;
+
+  fn ctx() -> ReplicationCtx {
+    ReplicationCtx {
+      node_name: "node1".to_owned(),
+      nodes: vec!["node1".to_owned(), "node2".to_owned()],
+      groups: HashMap::from([
+        ("front".to_owned(), vec!["node1".to_owned()]),
+        (
+          "back".to_owned(),
+          vec!["node1".to_owned(), "node2".to_owned()],
+        ),
+        ("db".to_owned(), vec!["node2".to_owned()]),
+      ]),
+      running_on: vec![],
+    }
+  }
+
+  #[test]
+  fn count_static() {
+    let ctx = ctx();
+    assert_eq!(ctx.count(None).unwrap(), 1);
+    let mode = ReplicationMode::Static(ReplicationStatic { number: 3 });
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 2);
+    let node2 = ReplicationCtx {
+      node_name: "node2".to_owned(),
+      ..self::ctx()
+    };
+    assert_eq!(node2.count(Some(&mode)).unwrap(), 1);
+    let mode = ReplicationMode::StaticByNodes(ReplicationStatic { number: 2 });
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 2);
+    let mode = ReplicationMode::StaticByNodeGroups {
+      groups: vec!["db".to_owned()],
+      number: 2,
+    };
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 0);
+    let mode = ReplicationMode::StaticByNodeGroups {
+      groups: vec!["db".to_owned(), "front".to_owned()],
+      number: 2,
+    };
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 2);
+    let mode = ReplicationMode::StaticByNodeNames {
+      names: vec!["node1".to_owned()],
+      number: 4,
+    };
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 4);
+  }
+
+  #[test]
+  fn count_unique() {
+    let mut ctx = ctx();
+    assert_eq!(ctx.count(Some(&ReplicationMode::Unique)).unwrap(), 1);
+    let node2 = ReplicationCtx {
+      node_name: "node2".to_owned(),
+      ..self::ctx()
+    };
+    assert_eq!(node2.count(Some(&ReplicationMode::Unique)).unwrap(), 0);
+    assert_eq!(ctx.count(Some(&ReplicationMode::Auto)).unwrap(), 1);
+    assert_eq!(ctx.count(Some(&ReplicationMode::UniqueByNode)).unwrap(), 1);
+    let mode = ReplicationMode::UniqueByNodeGroups {
+      groups: vec!["back".to_owned()],
+    };
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 1);
+    let mode = ReplicationMode::UniqueByNodeNames {
+      names: vec!["node2".to_owned()],
+    };
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 0);
+    ctx.running_on = vec!["node2".to_owned()];
+    assert_eq!(ctx.count(Some(&ReplicationMode::Unique)).unwrap(), 0);
+    let mode = ReplicationMode::UniqueByNodeGroups {
+      groups: vec!["back".to_owned()],
+    };
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 0);
+    let mode = ReplicationMode::UniqueByNodeGroups {
+      groups: vec!["back".to_owned(), "front".to_owned()],
+    };
+    assert_eq!(ctx.count(Some(&mode)).unwrap(), 1);
+  }
+
+  #[test]
+  fn count_unsatisfiable() {
+    let ctx = ctx();
+    let mode = ReplicationMode::UniqueByNodeGroups {
+      groups: vec!["unknown".to_owned()],
+    };
+    assert!(ctx.count(Some(&mode)).is_err());
+    let mode = ReplicationMode::UniqueByNodeNames { names: vec![] };
+    assert!(ctx.count(Some(&mode)).is_err());
+    let mode = ReplicationMode::StaticByNodeNames {
+      names: vec!["node3".to_owned()],
+      number: 1,
+    };
+    assert!(ctx.count(Some(&mode)).is_err());
+    let mode = ReplicationMode::StaticByNodeGroups {
+      groups: vec!["front".to_owned()],
+      number: -1,
+    };
+    assert!(ctx.count(Some(&mode)).is_err());
+  }
+
+  #[test]
+  fn total_in_cluster() {
+    let ctx = ctx();
+    assert_eq!(ctx.total(None).unwrap(), 1);
+    assert_eq!(ctx.total(Some(&ReplicationMode::UniqueByNode)).unwrap(), 2);
+    assert_eq!(ctx.total(Some(&ReplicationMode::Auto)).unwrap(), 2);
+    let mode = ReplicationMode::Static(ReplicationStatic { number: 3 });
+    assert_eq!(ctx.total(Some(&mode)).unwrap(), 3);
+    let mode = ReplicationMode::StaticByNodes(ReplicationStatic { number: 3 });
+    assert_eq!(ctx.total(Some(&mode)).unwrap(), 6);
+    let mode = ReplicationMode::StaticByNodeGroups {
+      groups: vec!["front".to_owned(), "back".to_owned()],
+      number: 2,
+    };
+    assert_eq!(ctx.total(Some(&mode)).unwrap(), 4);
+    let mode = ReplicationMode::StaticByNodeNames {
+      names: vec!["node2".to_owned()],
+      number: 3,
+    };
+    assert_eq!(ctx.total(Some(&mode)).unwrap(), 3);
+  }
+}
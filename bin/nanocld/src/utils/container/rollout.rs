@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use bollard_next::{
+  container::{InspectContainerOptions, RenameContainerOptions},
+  service::HealthStatusEnum,
+};
+use futures::{stream::FuturesUnordered, StreamExt};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+use nanocl_stubs::{
+  cargo::Cargo, cargo_spec::CargoUpdateStrategy, process::Process,
+};
+
+use crate::{
+  models::{CargoDb, SpecDb, SystemState},
+  repositories::generic::*,
+};
+
+/// Rolling update settings resolved from a cargo update strategy
+#[derive(Debug, PartialEq, Eq)]
+pub struct RolloutPlan {
+  /// Maximum number of instances created above the wanted number
+  pub max_surge: usize,
+  /// Maximum number of instances that can be unavailable
+  pub max_unavailable: usize,
+  /// Number of instances replaced at the same time
+  pub batch_size: usize,
+  /// Time to wait for a new instance to be healthy
+  pub health_timeout: Duration,
+  /// Rollback to the previous spec when new instances fail their checks
+  pub rollback: bool,
+}
+
+impl RolloutPlan {
+  /// Resolve the plan from an update strategy using the default values
+  /// for missing fields, return an error if the strategy can't progress
+  pub fn new(strategy: Option<&CargoUpdateStrategy>) -> IoResult<Self> {
+    let strategy = strategy.cloned().unwrap_or_default();
+    let max_surge = strategy.max_surge.unwrap_or(1);
+    let max_unavailable = strategy.max_unavailable.unwrap_or(0);
+    let max_batch = max_surge + max_unavailable;
+    if max_batch == 0 {
+      return Err(IoError::invalid_input(
+        "UpdateStrategy",
+        "MaxSurge and MaxUnavailable can't both be 0",
+      ));
+    }
+    let batch_size = strategy.batch_size.unwrap_or(max_batch);
+    if batch_size == 0 || batch_size > max_batch {
+      return Err(IoError::invalid_input(
+        "UpdateStrategy",
+        &format!("BatchSize must be between 1 and {max_batch}"),
+      ));
+    }
+    Ok(Self {
+      max_surge,
+      max_unavailable,
+      batch_size,
+      health_timeout: Duration::from_secs(
+        strategy.health_timeout.unwrap_or(60),
+      ),
+      rollback: strategy.rollback.unwrap_or(true),
+    })
+  }
+
+  /// Number of old instances to retire before creating a batch of new ones
+  pub fn retire_before(&self, size: usize) -> usize {
+    size.saturating_sub(self.max_surge)
+  }
+}
+
+/// Wait for an instance to report healthy.
+/// Instances without health check are considered healthy once running.
+pub async fn wait_healthy(
+  process: &Process,
+  timeout: Duration,
+  state: &SystemState,
+) -> IoResult<()> {
+  let started_at = std::time::Instant::now();
+  loop {
+    let inspect = state
+      .inner
+      .docker_api
+      .inspect_container(&process.key, None::<InspectContainerOptions>)
+      .await
+      .map_err(|err| err.map_err_context(|| "WaitHealthy"))?;
+    let container_state = inspect.state.unwrap_or_default();
+    if !container_state.running.unwrap_or_default()
+      && !container_state.restarting.unwrap_or_default()
+      && started_at.elapsed() > Duration::from_secs(1)
+    {
+      return Err(IoError::interrupted(
+        "WaitHealthy",
+        &format!("Instance {} is not running", process.name),
+      ));
+    }
+    match container_state.health.and_then(|health| health.status) {
+      Some(HealthStatusEnum::HEALTHY) => return Ok(()),
+      Some(HealthStatusEnum::UNHEALTHY) => {
+        return Err(IoError::interrupted(
+          "WaitHealthy",
+          &format!("Instance {} is unhealthy", process.name),
+        ));
+      }
+      Some(HealthStatusEnum::STARTING) => {}
+      _ => {
+        if container_state.running.unwrap_or_default() {
+          return Ok(());
+        }
+      }
+    }
+    if started_at.elapsed() > timeout {
+      return Err(IoError::interrupted(
+        "WaitHealthy",
+        &format!(
+          "Instance {} not healthy after {}s",
+          process.name,
+          timeout.as_secs()
+        ),
+      ));
+    }
+    ntex::time::sleep(Duration::from_secs(1)).await;
+  }
+}
+
+/// Remove old instances after flagging them with a `tmp-` prefix
+/// so they stop being used as targets
+pub async fn retire_instances(
+  processes: &[Process],
+  state: &SystemState,
+) -> IoResult<()> {
+  if processes.is_empty() {
+    return Ok(());
+  }
+  processes
+    .iter()
+    .map(|process| async move {
+      let new_name = format!("tmp-{}", process.name);
+      let res = state
+        .inner
+        .docker_api
+        .rename_container(
+          &process.key,
+          RenameContainerOptions { name: &new_name },
+        )
+        .await;
+      if let Err(err) = res {
+        log::warn!("Unable to rename container {}: {err}", process.name);
+      }
+    })
+    .collect::<FuturesUnordered<_>>()
+    .collect::<Vec<_>>()
+    .await;
+  super::process::delete_instances(
+    &processes.iter().map(|p| p.key.clone()).collect::<Vec<_>>(),
+    state,
+  )
+  .await
+}
+
+/// Revert the cargo to the spec preceding its current one in the history.
+/// Every node running the cargo fails the same rollout, only the first one
+/// moves the cargo away from the failed spec, the others get `None`.
+pub async fn rollback(
+  cargo: &Cargo,
+  state: &SystemState,
+) -> IoResult<Option<Cargo>> {
+  let key = &cargo.spec.cargo_key;
+  let specs = SpecDb::read_by_kind_key(key, &state.inner.pool).await?;
+  let previous = specs
+    .iter()
+    .skip_while(|spec| spec.key != cargo.spec.key)
+    .nth(1)
+    .ok_or_else(|| {
+      IoError::not_found(
+        "Rollback",
+        &format!("No previous spec for cargo {key}"),
+      )
+    })?
+    .try_to_cargo_spec()?;
+  let version = previous.version.clone();
+  let new_spec =
+    SpecDb::try_from_cargo_partial(key, &version, &previous.into())?;
+  let spec = SpecDb::create_from(new_spec, &state.inner.pool)
+    .await?
+    .try_to_cargo_spec()?;
+  if !CargoDb::update_spec_key_if(
+    key,
+    &cargo.spec.key,
+    &spec.key,
+    &state.inner.pool,
+  )
+  .await?
+  {
+    SpecDb::del_by_pk(&spec.key, &state.inner.pool).await?;
+    return Ok(None);
+  }
+  Ok(Some(Cargo {
+    spec,
+    ..cargo.clone()
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plan_defaults() {
+    let plan = RolloutPlan::new(None).unwrap();
+    assert_eq!(plan.max_surge, 1);
+    assert_eq!(plan.max_unavailable, 0);
+    assert_eq!(plan.batch_size, 1);
+    assert!(plan.rollback);
+    assert_eq!(plan.retire_before(1), 0);
+  }
+
+  #[test]
+  fn plan_limits() {
+    let strategy = CargoUpdateStrategy {
+      max_surge: Some(1),
+      max_unavailable: Some(2),
+      ..Default::default()
+    };
+    let plan = RolloutPlan::new(Some(&strategy)).unwrap();
+    assert_eq!(plan.batch_size, 3);
+    assert_eq!(plan.retire_before(3), 2);
+    let strategy = CargoUpdateStrategy {
+      max_surge: Some(0),
+      max_unavailable: Some(0),
+      ..Default::default()
+    };
+    assert!(RolloutPlan::new(Some(&strategy)).is_err());
+    let strategy = CargoUpdateStrategy {
+      batch_size: Some(2),
+      ..Default::default()
+    };
+    assert!(RolloutPlan::new(Some(&strategy)).is_err());
+  }
+}
@@ -1,82 +1,280 @@
-use ntex::web;
-use tokio::{fs, io::AsyncWriteExt};
-
-use nanocl_error::io::{FromIo, IoError, IoResult};
-use nanocl_stubs::job::Job;
-
-use crate::{models::SystemState, vars};
-
-/// Format the cron job command to start a job at a given time
-fn format_cron_job_command(job: &Job, state: &SystemState) -> String {
-  let host = state
-    .inner
-    .config
-    .hosts
-    .first()
-    .cloned()
-    .unwrap_or("unix:///run/nanocl/nanocl.sock".to_owned())
-    .replace("unix://", "");
-  format!(
-    "curl -X POST --unix {host} http://localhost/v{}/processes/job/{}/start",
-    vars::VERSION,
-    &job.name
-  )
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+
+use nanocl_error::io::{IoError, IoResult};
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter},
+  job::{Job, JobConcurrencyPolicy},
+  process::ProcessKind,
+  system::{EventActorKind, ObjPsStatusKind},
+};
+
+use crate::{
+  models::{JobDb, JobUpdateDb, ProcessDb, SystemState},
+  repositories::generic::*,
+  utils,
+};
+
+/// Schedule of a job parsed from its cron expression and its time zone
+#[derive(Debug, Clone)]
+pub struct JobSchedule {
+  cron: Cron,
+  tz: Tz,
+}
+
+impl JobSchedule {
+  /// Parse a cron expression (5 fields, 6 with seconds or an alias like `@daily`)
+  /// evaluated in the given time zone, UTC by default
+  pub fn new(schedule: &str, time_zone: Option<&str>) -> IoResult<Self> {
+    let cron = Cron::new(schedule)
+      .with_seconds_optional()
+      .parse()
+      .map_err(|err| {
+        IoError::invalid_input("Schedule", &format!("{schedule} {err}"))
+      })?;
+    let tz = match time_zone {
+      None => Tz::UTC,
+      Some(time_zone) => time_zone.parse::<Tz>().map_err(|err| {
+        IoError::invalid_input("Time zone", &format!("{time_zone} {err}"))
+      })?,
+    };
+    Ok(Self { cron, tz })
+  }
+
+  /// Schedule of the job if it has one
+  pub fn try_from_job(job: &Job) -> IoResult<Option<Self>> {
+    match &job.schedule {
+      None => Ok(None),
+      Some(schedule) => Self::new(schedule, job.time_zone.as_deref()).map(Some),
+    }
+  }
+
+  /// Next run strictly after the given date
+  pub fn next_after(&self, date: &DateTime<Utc>) -> IoResult<DateTime<Utc>> {
+    let date = date.with_timezone(&self.tz);
+    let next = self
+      .cron
+      .find_next_occurrence(&date, false)
+      .map_err(|err| IoError::invalid_data("Schedule", &format!("{err}")))?;
+    Ok(next.with_timezone(&Utc))
+  }
 }
 
-/// Execute the crontab command to update the cron jobs
-async fn exec_crontab() -> IoResult<()> {
-  web::block(|| {
-    std::process::Command::new("crontab")
-      .arg("/tmp/crontab")
-      .output()
-      .map_err(|err| err.map_err_context(|| "Cron job"))?;
-    Ok::<_, IoError>(())
-  })
+/// Format a date to be used in a generic filter
+fn format_filter_date(date: &DateTime<Utc>) -> String {
+  date.format("%Y-%m-%d %H:%M:%S%.f %:z").to_string()
+}
+
+/// Compute and save the next run of a job after the given date
+pub async fn schedule_job(
+  job: &Job,
+  after: &DateTime<Utc>,
+  state: &SystemState,
+) -> IoResult<Option<NaiveDateTime>> {
+  let next_run_at = match JobSchedule::try_from_job(job)? {
+    None => None,
+    Some(schedule) => Some(schedule.next_after(after)?.naive_utc()),
+  };
+  JobDb::update_pk(
+    &job.name,
+    JobUpdateDb {
+      next_run_at: Some(next_run_at),
+      ..Default::default()
+    },
+    &state.inner.pool,
+  )
   .await?;
-  Ok(())
+  Ok(next_run_at)
+}
+
+/// Check if a job is still running from its status and its instances
+async fn is_job_running(job: &Job, state: &SystemState) -> IoResult<bool> {
+  if matches!(
+    job.status.actual,
+    ObjPsStatusKind::Starting | ObjPsStatusKind::Start
+  ) {
+    return Ok(true);
+  }
+  let instances =
+    ProcessDb::read_by_kind_key(&job.name, None, &state.inner.pool).await?;
+  let (_, _, _, running) = utils::container::generic::count_status(&instances);
+  Ok(running > 0)
 }
 
-/// Add a cron rule to the crontab to start a job at a given time
-pub async fn add_cron_rule(
-  item: &Job,
-  schedule: &str,
+/// Start a scheduled job according to its concurrency policy.
+/// Return false if the run was skipped.
+async fn run_job(job: &Job, state: &SystemState) -> IoResult<bool> {
+  let policy = job.concurrency_policy.unwrap_or_default();
+  if policy != JobConcurrencyPolicy::Allow && is_job_running(job, state).await?
+  {
+    if policy == JobConcurrencyPolicy::Forbid {
+      log::info!("cron::run_job: {} is still running, skipping", job.name);
+      return Ok(false);
+    }
+    log::info!("cron::run_job: {} is still running, replacing", job.name);
+    let task_key = format!("{}@{}", EventActorKind::Job, job.name);
+    state.inner.task_manager.remove_task(&task_key).await;
+    utils::container::process::stop_instances(
+      &job.name,
+      &ProcessKind::Job,
+      state,
+    )
+    .await?;
+  }
+  utils::container::generic::emit_starting(&job.name, &ProcessKind::Job, state)
+    .await?;
+  Ok(true)
+}
+
+/// Schedule the jobs that have a schedule but no next run yet,
+/// like the jobs created before the scheduler was part of the daemon
+async fn schedule_unscheduled_jobs(
+  now: &DateTime<Utc>,
   state: &SystemState,
 ) -> IoResult<()> {
-  let cmd = format_cron_job_command(item, state);
-  let cron_rule = format!("{} {cmd}", schedule);
-  log::debug!("Creating cron rule: {cron_rule}");
-  fs::copy("/var/spool/cron/crontabs/root", "/tmp/crontab")
-    .await
-    .map_err(|err| err.map_err_context(|| "Cron job"))?;
-  let mut file = fs::OpenOptions::new()
-    .write(true)
-    .append(true)
-    .open("/tmp/crontab")
-    .await
-    .map_err(|err| err.map_err_context(|| "Cron job"))?;
-  file
-    .write_all(format!("{cron_rule}\n").as_bytes())
-    .await
-    .map_err(|err| err.map_err_context(|| "Cron job"))?;
-  exec_crontab().await?;
+  let filter =
+    GenericFilter::new().r#where("next_run_at", GenericClause::IsNull);
+  let jobs = JobDb::transform_read_by(&filter, &state.inner.pool).await?;
+  for job in jobs.iter().filter(|job| job.schedule.is_some()) {
+    if let Err(err) = schedule_job(job, now, state).await {
+      log::warn!("cron::schedule_unscheduled_jobs: {} {err}", job.name);
+    }
+  }
   Ok(())
 }
 
-/// Remove a cron rule from the crontab for the given job
-pub async fn remove_cron_rule(item: &Job, state: &SystemState) -> IoResult<()> {
-  let mut content = fs::read_to_string("/var/spool/cron/crontabs/root")
-    .await
-    .map_err(|err| err.map_err_context(|| "Cron job"))?;
-  let cmd = format_cron_job_command(item, state);
-  log::debug!("Removing cron rule: {cmd}");
-  content = content
-    .lines()
-    .filter(|line| !line.contains(&cmd))
-    .collect::<Vec<_>>()
-    .join("\n");
-  fs::write("/tmp/crontab", format!("{content}\n"))
+/// Start the jobs whose next run is due and schedule their following run.
+/// When `missed` is true the due runs were missed while the daemon was down
+/// and are only started for jobs with `catch_up` enabled.
+/// Every node checks the jobs, a due run is started by the node claiming it.
+/// Returns the names of the jobs started.
+pub async fn run_due_jobs(
+  missed: bool,
+  state: &SystemState,
+) -> IoResult<Vec<String>> {
+  let now = Utc::now();
+  if missed {
+    schedule_unscheduled_jobs(&now, state).await?;
+  }
+  let filter = GenericFilter::new()
+    .r#where("next_run_at", GenericClause::Le(format_filter_date(&now)));
+  let jobs = JobDb::read_by(&filter, &state.inner.pool).await?;
+  let mut started = vec![];
+  for (job, status) in jobs {
+    let Some(due) = job.next_run_at else {
+      continue;
+    };
+    let job = job.try_to_spec(&status)?;
+    let next_run_at = match JobSchedule::try_from_job(&job)
+      .and_then(|schedule| schedule.map(|s| s.next_after(&now)).transpose())
+    {
+      Ok(next_run_at) => next_run_at.map(|next| next.naive_utc()),
+      Err(err) => {
+        log::warn!("cron::run_due_jobs: {} {err}", job.name);
+        None
+      }
+    };
+    if !JobDb::claim_run(&job.name, due, next_run_at, &state.inner.pool).await?
+    {
+      continue;
+    }
+    if job.status.wanted == ObjPsStatusKind::Destroy {
+      continue;
+    }
+    let ran = if missed && !job.catch_up.unwrap_or_default() {
+      log::info!("cron::run_due_jobs: {} missed its schedule", job.name);
+      false
+    } else {
+      match run_job(&job, state).await {
+        Ok(ran) => ran,
+        Err(err) => {
+          log::warn!("cron::run_due_jobs: {} {err}", job.name);
+          false
+        }
+      }
+    };
+    if ran {
+      JobDb::update_pk(
+        &job.name,
+        JobUpdateDb {
+          last_run_at: Some(Some(now.naive_utc())),
+          ..Default::default()
+        },
+        &state.inner.pool,
+      )
+      .await?;
+      started.push(job.name.clone());
+    }
+  }
+  Ok(started)
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::TimeZone;
+
+  use super::*;
+
+  use nanocl_stubs::job::JobPartial;
+
+  use crate::{objects::generic::*, utils::tests::*};
+
+  #[test]
+  fn job_schedule() {
+    let schedule = JobSchedule::new("30 2 * * *", None).unwrap();
+    let date = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let next = schedule.next_after(&date).unwrap();
+    assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 1, 2, 30, 0).unwrap());
+    let next = schedule.next_after(&next).unwrap();
+    assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 2, 2, 30, 0).unwrap());
+    let schedule = JobSchedule::new("0 9 * * *", Some("Europe/Paris")).unwrap();
+    let next = schedule.next_after(&date).unwrap();
+    assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap());
+    let schedule = JobSchedule::new("@hourly", None).unwrap();
+    let next = schedule.next_after(&date).unwrap();
+    assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 1, 1, 0, 0).unwrap());
+    let schedule = JobSchedule::new("0 0 31 2 *", None).unwrap();
+    assert!(schedule.next_after(&date).is_err());
+    assert!(JobSchedule::new("* * *", None).is_err());
+    assert!(JobSchedule::new("* * * * *", Some("Mars/Olympus")).is_err());
+  }
+
+  #[ntex::test]
+  async fn concurrent_due_runs() {
+    let system = gen_default_test_system().await;
+    let state = system.state;
+    let job = JobPartial {
+      name: "test-job-concurrent-due-runs".to_owned(),
+      schedule: Some("@yearly".to_owned()),
+      concurrency_policy: Some(JobConcurrencyPolicy::Allow),
+      ..Default::default()
+    };
+    JobDb::create_obj(&job, &state).await.unwrap();
+    let due = Utc::now().naive_utc() - chrono::Duration::seconds(1);
+    JobDb::update_pk(
+      &job.name,
+      JobUpdateDb {
+        next_run_at: Some(Some(due)),
+        ..Default::default()
+      },
+      &state.inner.pool,
+    )
     .await
-    .map_err(|err| err.map_err_context(|| "Cron job"))?;
-  exec_crontab().await?;
-  Ok(())
+    .unwrap();
+    let (first, second) =
+      futures::join!(run_due_jobs(false, &state), run_due_jobs(false, &state));
+    let runs = first
+      .unwrap()
+      .into_iter()
+      .chain(second.unwrap())
+      .filter(|name| name == &job.name)
+      .count();
+    assert_eq!(runs, 1);
+    let (item, _) = JobDb::read_by_pk(&job.name, &state.inner.pool)
+      .await
+      .unwrap();
+    assert!(item.next_run_at.unwrap() > due);
+    JobDb::del_obj_by_pk(&job.name, &(), &state).await.unwrap();
+  }
 }
@@ -92,14 +92,30 @@ impl CtrlClient {
     Ok(body)
   }
 
+  /// Check if the controller is listening.
+  /// Any response means it's up, even an error as controllers
+  /// don't have to serve `/_ping`.
+  pub async fn ping(&self) -> Result<(), HttpClientError> {
+    let url = self.format_url("/_ping");
+    self
+      .client
+      .get(url)
+      .send()
+      .await
+      .map_err(|err| err.map_err_context(|| self.name.to_owned()))?;
+    Ok(())
+  }
+
   /// Call apply rule method on controller
   pub async fn apply_rule(
     &self,
     version: &str,
     name: &str,
+    namespace: &str,
     data: &serde_json::Value,
   ) -> Result<serde_json::Value, HttpClientError> {
-    let url = self.format_url(&format!("/{version}/rules/{name}"));
+    let url = self
+      .format_url(&format!("/{version}/rules/{name}?namespace={namespace}"));
     log::debug!("CtrlClient::apply_rule url: {}", url);
     let mut res = self
       .client
@@ -112,13 +128,40 @@ impl CtrlClient {
     self.res_json(&mut res).await
   }
 
+  /// Call convert rule method on controller to get the data of a rule
+  /// of a previous version in this version
+  pub async fn convert_rule(
+    &self,
+    version: &str,
+    from: &str,
+    name: &str,
+    namespace: &str,
+    data: &serde_json::Value,
+  ) -> Result<serde_json::Value, HttpClientError> {
+    let url = self.format_url(&format!(
+      "/{version}/rules/{name}/convert?namespace={namespace}&from={from}"
+    ));
+    log::debug!("CtrlClient::convert_rule url: {}", url);
+    let mut res = self
+      .client
+      .post(url)
+      .send_json(data)
+      .await
+      .map_err(|err| err.map_err_context(|| self.name.to_owned()))?;
+    let status = res.status();
+    self.is_api_error(&mut res, &status).await?;
+    self.res_json(&mut res).await
+  }
+
   /// Call delete rule method on controller
   pub async fn delete_rule(
     &self,
     version: &str,
     name: &str,
+    namespace: &str,
   ) -> Result<(), HttpClientError> {
-    let url = self.format_url(&format!("/{version}/rules/{name}"));
+    let url = self
+      .format_url(&format!("/{version}/rules/{name}?namespace={namespace}"));
     log::debug!("CtrlClient::delete_rule url: {}", url);
     let mut res = self
       .client
@@ -0,0 +1,522 @@
+use std::{collections::BTreeMap, fmt::Write, sync::atomic::Ordering};
+
+use futures::StreamExt;
+
+use bollard_next::container::StatsOptions;
+use metrsd_client::stubs::MetrsdEvent;
+use nanocl_error::io::IoResult;
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter},
+  process::Process,
+  system::ObjPsStatusKind,
+};
+
+use crate::{
+  models::{
+    CargoDb, HttpCounters, JobDb, MetricDb, NodeDb, ProcessDb, SystemState,
+    VmDb, HTTP_DURATION_BUCKETS,
+  },
+  repositories::generic::*,
+};
+
+/// Content type of the prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Name, help and value of a gauge read from the metrics of a node
+type NodeGauge = (&'static str, &'static str, fn(&MetrsdEvent) -> u64);
+
+/// Metrics written in the prometheus text exposition format
+#[derive(Debug, Default)]
+pub struct Exposition {
+  buf: String,
+}
+
+impl Exposition {
+  /// Start a new metric family
+  pub fn family(&mut self, name: &str, kind: &str, help: &str) -> &mut Self {
+    let _ = writeln!(self.buf, "# HELP {name} {help}");
+    let _ = writeln!(self.buf, "# TYPE {name} {kind}");
+    self
+  }
+
+  /// Add a sample to the current metric family
+  pub fn sample(
+    &mut self,
+    name: &str,
+    labels: &[(&str, &str)],
+    value: f64,
+  ) -> &mut Self {
+    self.buf.push_str(name);
+    if !labels.is_empty() {
+      let labels = labels
+        .iter()
+        .map(|(key, value)| format!("{key}=\"{}\"", escape(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+      let _ = write!(self.buf, "{{{labels}}}");
+    }
+    let _ = writeln!(self.buf, " {value}");
+    self
+  }
+
+  pub fn finish(self) -> String {
+    self.buf
+  }
+}
+
+/// Escape a label value
+fn escape(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+/// Last metrics of metrsd for every node of the cluster
+async fn write_nodes(
+  exp: &mut Exposition,
+  state: &SystemState,
+) -> IoResult<()> {
+  let mut nodes = Vec::new();
+  for node in NodeDb::list_names(&state.inner.pool).await? {
+    let filter = GenericFilter::new()
+      .r#where("kind", GenericClause::Eq("nanocl.io/metrs".to_owned()))
+      .r#where("node_name", GenericClause::Eq(node.clone()))
+      .limit(1);
+    let Some(metric) = MetricDb::read_by(&filter, &state.inner.pool)
+      .await?
+      .into_iter()
+      .next()
+    else {
+      continue;
+    };
+    if let Ok(ev) = serde_json::from_value::<MetrsdEvent>(metric.data) {
+      nodes.push((node, ev));
+    }
+  }
+  exp.family(
+    "nanocl_node_cpu_usage_percent",
+    "gauge",
+    "CPU usage of a node in percent",
+  );
+  for (node, ev) in &nodes {
+    for cpu in &ev.cpus {
+      exp.sample(
+        "nanocl_node_cpu_usage_percent",
+        &[("node", node), ("cpu", &cpu.name)],
+        cpu.usage as f64,
+      );
+    }
+  }
+  let memory: [NodeGauge; 4] = [
+    ("nanocl_node_memory_total_bytes", "Memory of a node", |ev| {
+      ev.memory.total
+    }),
+    (
+      "nanocl_node_memory_used_bytes",
+      "Memory used on a node",
+      |ev| ev.memory.used,
+    ),
+    ("nanocl_node_swap_total_bytes", "Swap of a node", |ev| {
+      ev.memory.swap_total
+    }),
+    ("nanocl_node_swap_used_bytes", "Swap used on a node", |ev| {
+      ev.memory.swap_used
+    }),
+  ];
+  for (name, help, value) in memory {
+    exp.family(name, "gauge", help);
+    for (node, ev) in &nodes {
+      exp.sample(name, &[("node", node)], value(ev) as f64);
+    }
+  }
+  exp.family(
+    "nanocl_node_disk_total_bytes",
+    "gauge",
+    "Size of a disk of a node",
+  );
+  for (node, ev) in &nodes {
+    for disk in &ev.disks {
+      exp.sample(
+        "nanocl_node_disk_total_bytes",
+        &[("node", node), ("mount_point", &disk.mount_point)],
+        disk.total_space as f64,
+      );
+    }
+  }
+  exp.family(
+    "nanocl_node_disk_available_bytes",
+    "gauge",
+    "Space available on a disk of a node",
+  );
+  for (node, ev) in &nodes {
+    for disk in &ev.disks {
+      exp.sample(
+        "nanocl_node_disk_available_bytes",
+        &[("node", node), ("mount_point", &disk.mount_point)],
+        disk.available_space as f64,
+      );
+    }
+  }
+  exp.family(
+    "nanocl_node_network_receive_bytes_total",
+    "counter",
+    "Bytes received by a network interface of a node",
+  );
+  for (node, ev) in &nodes {
+    for network in &ev.networks {
+      exp.sample(
+        "nanocl_node_network_receive_bytes_total",
+        &[("node", node), ("interface", &network.name)],
+        network.received as f64,
+      );
+    }
+  }
+  exp.family(
+    "nanocl_node_network_transmit_bytes_total",
+    "counter",
+    "Bytes transmitted by a network interface of a node",
+  );
+  for (node, ev) in &nodes {
+    for network in &ev.networks {
+      exp.sample(
+        "nanocl_node_network_transmit_bytes_total",
+        &[("node", node), ("interface", &network.name)],
+        network.transmitted as f64,
+      );
+    }
+  }
+  Ok(())
+}
+
+/// Container stats of the processes running on the current node
+async fn write_processes(
+  exp: &mut Exposition,
+  state: &SystemState,
+) -> IoResult<()> {
+  let filter = GenericFilter::new().r#where(
+    "node_name",
+    GenericClause::Eq(state.inner.config.hostname.clone()),
+  );
+  let processes: Vec<Process> =
+    ProcessDb::transform_read_by(&filter, &state.inner.pool).await?;
+  let mut samples = Vec::new();
+  for process in processes {
+    let opts = StatsOptions {
+      stream: false,
+      one_shot: true,
+    };
+    let stats = state
+      .inner
+      .docker_api
+      .stats(&process.key, Some(opts))
+      .next()
+      .await;
+    if let Some(Ok(stats)) = stats {
+      samples.push((process, stats));
+    }
+  }
+  exp.family(
+    "nanocl_process_cpu_seconds_total",
+    "counter",
+    "CPU time consumed by a process",
+  );
+  for (process, stats) in &samples {
+    let kind = process.kind.to_string();
+    exp.sample(
+      "nanocl_process_cpu_seconds_total",
+      &[
+        ("kind", &kind),
+        ("key", &process.kind_key),
+        ("process", &process.name),
+      ],
+      stats.cpu_stats.cpu_usage.total_usage as f64 / 1e9,
+    );
+  }
+  exp.family(
+    "nanocl_process_memory_usage_bytes",
+    "gauge",
+    "Memory used by a process",
+  );
+  for (process, stats) in &samples {
+    let kind = process.kind.to_string();
+    exp.sample(
+      "nanocl_process_memory_usage_bytes",
+      &[
+        ("kind", &kind),
+        ("key", &process.kind_key),
+        ("process", &process.name),
+      ],
+      stats.memory_stats.usage.unwrap_or_default() as f64,
+    );
+  }
+  exp.family(
+    "nanocl_process_network_receive_bytes_total",
+    "counter",
+    "Bytes received by a process",
+  );
+  for (process, stats) in &samples {
+    let kind = process.kind.to_string();
+    let received = stats
+      .networks
+      .iter()
+      .flat_map(|networks| networks.values())
+      .map(|network| network.rx_bytes)
+      .sum::<u64>();
+    exp.sample(
+      "nanocl_process_network_receive_bytes_total",
+      &[
+        ("kind", &kind),
+        ("key", &process.kind_key),
+        ("process", &process.name),
+      ],
+      received as f64,
+    );
+  }
+  exp.family(
+    "nanocl_process_network_transmit_bytes_total",
+    "counter",
+    "Bytes transmitted by a process",
+  );
+  for (process, stats) in &samples {
+    let kind = process.kind.to_string();
+    let transmitted = stats
+      .networks
+      .iter()
+      .flat_map(|networks| networks.values())
+      .map(|network| network.tx_bytes)
+      .sum::<u64>();
+    exp.sample(
+      "nanocl_process_network_transmit_bytes_total",
+      &[
+        ("kind", &kind),
+        ("key", &process.kind_key),
+        ("process", &process.name),
+      ],
+      transmitted as f64,
+    );
+  }
+  Ok(())
+}
+
+/// Number of objects by kind and actual status
+async fn write_objects(
+  exp: &mut Exposition,
+  state: &SystemState,
+) -> IoResult<()> {
+  let filter = GenericFilter::new();
+  let pool = &state.inner.pool;
+  let mut counts: BTreeMap<(&str, String), usize> = BTreeMap::new();
+  let mut count = |kind, status: ObjPsStatusKind| {
+    *counts.entry((kind, status.to_string())).or_default() += 1;
+  };
+  for cargo in CargoDb::transform_read_by(&filter, pool).await? {
+    count("cargo", cargo.status.actual);
+  }
+  for vm in VmDb::transform_read_by(&filter, pool).await? {
+    count("vm", vm.status.actual);
+  }
+  for job in JobDb::transform_read_by(&filter, pool).await? {
+    count("job", job.status.actual);
+  }
+  exp.family(
+    "nanocl_objects",
+    "gauge",
+    "Number of objects by kind and actual status",
+  );
+  for ((kind, status), count) in counts {
+    exp.sample(
+      "nanocl_objects",
+      &[("kind", kind), ("status", &status)],
+      count as f64,
+    );
+  }
+  Ok(())
+}
+
+/// Depth of the task manager and lag of the event loop
+async fn write_daemon(exp: &mut Exposition, state: &SystemState) {
+  let mut tasks: BTreeMap<String, usize> = BTreeMap::new();
+  for task in state.inner.task_manager.tasks.lock().await.values() {
+    *tasks.entry(task.kind.to_string()).or_default() += 1;
+  }
+  exp.family(
+    "nanocl_tasks",
+    "gauge",
+    "Number of tasks running in the task manager by action",
+  );
+  for (kind, count) in tasks {
+    exp.sample("nanocl_tasks", &[("action", &kind)], count as f64);
+  }
+  let exporter = &state.inner.exporter;
+  exp
+    .family(
+      "nanocl_events_total",
+      "counter",
+      "Events handled by the event loop",
+    )
+    .sample(
+      "nanocl_events_total",
+      &[],
+      exporter.events.load(Ordering::Relaxed) as f64,
+    )
+    .family(
+      "nanocl_event_loop_lag_seconds",
+      "gauge",
+      "Delay between the creation of the last event and its handling",
+    )
+    .sample(
+      "nanocl_event_loop_lag_seconds",
+      &[],
+      exporter.event_lag.load(Ordering::Relaxed) as f64 / 1e6,
+    );
+}
+
+/// Counters and latency histograms of the requests proxied by ncproxy
+pub fn write_http(exp: &mut Exposition, http: &HttpCounters) {
+  exp.family(
+    "nanocl_proxy_http_requests_total",
+    "counter",
+    "Requests proxied by ncproxy",
+  );
+  let mut requests = http.requests.iter().collect::<Vec<_>>();
+  requests.sort();
+  for (labels, count) in requests {
+    exp.sample(
+      "nanocl_proxy_http_requests_total",
+      &[
+        ("host", &labels.host),
+        ("method", &labels.method),
+        ("status", &labels.status.to_string()),
+      ],
+      *count as f64,
+    );
+  }
+  exp.family(
+    "nanocl_proxy_http_request_duration_seconds",
+    "histogram",
+    "Duration of the requests proxied by ncproxy",
+  );
+  let mut durations = http.durations.iter().collect::<Vec<_>>();
+  durations.sort_by(|a, b| a.0.cmp(b.0));
+  for (host, duration) in durations {
+    for (bound, count) in HTTP_DURATION_BUCKETS.iter().zip(duration.buckets) {
+      exp.sample(
+        "nanocl_proxy_http_request_duration_seconds_bucket",
+        &[("host", host), ("le", &bound.to_string())],
+        count as f64,
+      );
+    }
+    exp
+      .sample(
+        "nanocl_proxy_http_request_duration_seconds_bucket",
+        &[("host", host), ("le", "+Inf")],
+        duration.count as f64,
+      )
+      .sample(
+        "nanocl_proxy_http_request_duration_seconds_sum",
+        &[("host", host)],
+        duration.sum,
+      )
+      .sample(
+        "nanocl_proxy_http_request_duration_seconds_count",
+        &[("host", host)],
+        duration.count as f64,
+      );
+  }
+  exp.family(
+    "nanocl_proxy_http_response_bytes_total",
+    "counter",
+    "Bytes sent to the clients by ncproxy",
+  );
+  let mut bytes_sent = http.bytes_sent.iter().collect::<Vec<_>>();
+  bytes_sent.sort();
+  for (host, bytes) in bytes_sent {
+    exp.sample(
+      "nanocl_proxy_http_response_bytes_total",
+      &[("host", host)],
+      *bytes as f64,
+    );
+  }
+}
+
+/// Render the metrics of the daemon in the prometheus text exposition format
+pub async fn export(state: &SystemState) -> IoResult<String> {
+  let mut exp = Exposition::default();
+  write_nodes(&mut exp, state).await?;
+  write_processes(&mut exp, state).await?;
+  write_objects(&mut exp, state).await?;
+  write_daemon(&mut exp, state).await;
+  let http = state
+    .inner
+    .exporter
+    .http
+    .lock()
+    .map(|http| http.clone())
+    .unwrap_or_default();
+  write_http(&mut exp, &http);
+  Ok(exp.finish())
+}
+
+#[cfg(test)]
+mod tests {
+  use nanocl_stubs::metric::HttpMetric;
+
+  use super::*;
+  use crate::models::ExporterMetrics;
+
+  #[test]
+  fn exposition_format() {
+    let mut exp = Exposition::default();
+    exp.family("test_total", "counter", "Test counter").sample(
+      "test_total",
+      &[("name", "a\"b")],
+      2.0,
+    );
+    assert_eq!(
+      exp.finish(),
+      "# HELP test_total Test counter\n# TYPE test_total counter\ntest_total{name=\"a\\\"b\"} 2\n"
+    );
+    let metric = serde_json::from_value::<HttpMetric>(serde_json::json!({
+      "date_gmt": "2026-10-18T10:00:00+00:00",
+      "uri": "/",
+      "host": "example.com",
+      "remote_addr": "127.0.0.1",
+      "realip_remote_addr": "127.0.0.1",
+      "server_protocol": "HTTP/1.1",
+      "request_method": "GET",
+      "bytes_sent": "120",
+      "content_length": "0",
+      "status": "200",
+      "request_time": "0.030",
+      "body_bytes_sent": "20",
+      "proxy_host": "",
+      "upstream_addr": "",
+      "query_string": "",
+      "request_body": "",
+      "content_type": "",
+      "http_user_agent": "",
+      "http_referrer": "",
+      "http_accept_language": "",
+    }))
+    .unwrap();
+    let exporter = ExporterMetrics::default();
+    exporter.observe_http(&metric);
+    exporter.observe_http(&metric);
+    let mut exp = Exposition::default();
+    write_http(&mut exp, &exporter.http.lock().unwrap());
+    let res = exp.finish();
+    assert!(res.contains(
+      "nanocl_proxy_http_requests_total{host=\"example.com\",method=\"GET\",status=\"200\"} 2\n"
+    ));
+    assert!(res.contains(
+      "nanocl_proxy_http_request_duration_seconds_bucket{host=\"example.com\",le=\"0.025\"} 0\n"
+    ));
+    assert!(res.contains(
+      "nanocl_proxy_http_request_duration_seconds_bucket{host=\"example.com\",le=\"0.05\"} 2\n"
+    ));
+    assert!(res.contains(
+      "nanocl_proxy_http_response_bytes_total{host=\"example.com\"} 240\n"
+    ));
+  }
+}
@@ -0,0 +1,619 @@
+use std::collections::HashMap;
+
+use nanocl_error::io::{IoError, IoResult};
+use nanocl_stubs::{
+  config::{MetricRetention, MetricRollup},
+  generic::{GenericClause, GenericFilter},
+  metric::{MetricAggregate, MetricAggregateQuery},
+};
+
+use crate::{
+  models::{MetricDb, MetricRollupDb, SystemState},
+  repositories::generic::*,
+};
+
+/// Width of the time buckets in seconds when not set
+const DEFAULT_INTERVAL: u64 = 60;
+
+/// Time range in seconds when the start isn't set
+const DEFAULT_RANGE: i64 = 3600;
+
+/// Maximum number of time buckets of an aggregation
+const MAX_BUCKETS: i64 = 10_000;
+
+/// Maximum number of fields to group by or to aggregate
+const MAX_FIELDS: usize = 16;
+
+/// Retention in seconds of the metrics of a kind without policy
+const DEFAULT_RETENTION: i64 = 30 * 24 * 3600;
+
+/// Delay in seconds before a time bucket is rolled up,
+/// it lets the metrics sent late reach the store
+const ROLLUP_DELAY: i64 = 60;
+
+/// Field of a metric to group by
+#[derive(Debug, PartialEq)]
+pub enum MetricGroupField {
+  /// Node who saved the metric
+  NodeName,
+  /// Path of a field in the data of the metric
+  Data(Vec<String>),
+}
+
+/// Validated aggregation of the metrics of a kind
+#[derive(Debug)]
+pub struct MetricAggregatePlan {
+  pub kind: String,
+  pub since: chrono::NaiveDateTime,
+  pub until: chrono::NaiveDateTime,
+  /// Width of the time buckets in seconds
+  pub interval: u64,
+  /// Fields to group by with their name
+  pub group_by: Vec<(String, MetricGroupField)>,
+  /// Paths of the numeric fields to aggregate with their name
+  pub fields: Vec<(String, Vec<String>)>,
+  /// Only aggregate the metrics saved by this node
+  pub node_name: Option<String>,
+  /// Width of the buckets of the rollups to read from,
+  /// the raw metrics are read when not set
+  pub resolution: Option<u64>,
+}
+
+/// Split a dot separated field into its path
+fn parse_field(ctx: &str, field: &str) -> IoResult<Vec<String>> {
+  let path = field.split('.').map(str::to_owned).collect::<Vec<_>>();
+  let is_valid = path.iter().all(|segment| {
+    !segment.is_empty()
+      && segment
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+  });
+  if !is_valid {
+    return Err(IoError::invalid_input(
+      ctx,
+      &format!("{field} is not a valid field"),
+    ));
+  }
+  Ok(path)
+}
+
+/// Split a comma separated list of fields into their dot separated paths
+fn parse_fields(
+  ctx: &str,
+  list: &Option<String>,
+) -> IoResult<Vec<(String, Vec<String>)>> {
+  let Some(list) = list else {
+    return Ok(Vec::new());
+  };
+  let mut fields = Vec::new();
+  for field in list.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+    let path = parse_field(ctx, field)?;
+    if !fields.iter().any(|(name, _)| name == field) {
+      fields.push((field.to_owned(), path));
+    }
+  }
+  if fields.len() > MAX_FIELDS {
+    return Err(IoError::invalid_input(
+      ctx,
+      &format!("More than {MAX_FIELDS} fields"),
+    ));
+  }
+  Ok(fields)
+}
+
+/// Validate an aggregation query and fill its defaults
+pub fn parse_aggregate_query(
+  query: &MetricAggregateQuery,
+) -> IoResult<MetricAggregatePlan> {
+  if query.kind.is_empty() {
+    return Err(IoError::invalid_input("Kind", "Kind is required"));
+  }
+  let interval = query.interval.unwrap_or(DEFAULT_INTERVAL);
+  if interval == 0 {
+    return Err(IoError::invalid_input(
+      "Interval",
+      "Interval must be greater than 0",
+    ));
+  }
+  let until = query
+    .until
+    .unwrap_or_else(|| chrono::Utc::now().naive_utc());
+  let since = query
+    .since
+    .unwrap_or(until - chrono::Duration::seconds(DEFAULT_RANGE));
+  if since >= until {
+    return Err(IoError::invalid_input(
+      "Since",
+      "Since must be before until",
+    ));
+  }
+  let buckets = (until - since).num_seconds() / interval as i64;
+  if buckets > MAX_BUCKETS {
+    return Err(IoError::invalid_input(
+      "Interval",
+      &format!(
+        "{buckets} buckets exceed the maximum of {MAX_BUCKETS}, increase the interval"
+      ),
+    ));
+  }
+  let group_by = parse_fields("GroupBy", &query.group_by)?
+    .into_iter()
+    .map(|(name, path)| {
+      let field = if name == "node_name" {
+        MetricGroupField::NodeName
+      } else {
+        MetricGroupField::Data(path)
+      };
+      (name, field)
+    })
+    .collect();
+  let fields = parse_fields("Fields", &query.fields)?;
+  Ok(MetricAggregatePlan {
+    kind: query.kind.clone(),
+    since,
+    until,
+    interval,
+    group_by,
+    fields,
+    node_name: None,
+    resolution: None,
+  })
+}
+
+/// Retention policy of a kind of metrics
+pub fn policy_of<'a>(
+  kind: &str,
+  policies: &'a [MetricRetention],
+) -> Option<&'a MetricRetention> {
+  policies.iter().find(|policy| policy.kind == kind)
+}
+
+/// Expiration of a new metric of a kind from its retention policy,
+/// the metrics of a kind without policy expire after 30 days
+pub fn expires_at(
+  kind: &str,
+  policies: &[MetricRetention],
+) -> chrono::NaiveDateTime {
+  let retention = policy_of(kind, policies)
+    .map(|policy| policy.retention as i64)
+    .unwrap_or(DEFAULT_RETENTION);
+  chrono::Utc::now().naive_utc() + chrono::Duration::seconds(retention)
+}
+
+/// Ensure the retention policies can be applied.
+/// The rollups are computed from the raw metrics
+/// so they must be kept longer than the widest rollup interval.
+pub fn validate_retention(policies: &[MetricRetention]) -> IoResult<()> {
+  for (index, policy) in policies.iter().enumerate() {
+    let ctx = format!("MetricRetention {}", policy.kind);
+    super::key::ensure_kind(&policy.kind)?;
+    if policies[..index].iter().any(|p| p.kind == policy.kind) {
+      return Err(IoError::invalid_input(ctx.as_str(), "Kind is duplicated"));
+    }
+    if policy.retention == 0 {
+      return Err(IoError::invalid_input(
+        ctx.as_str(),
+        "Retention must be greater than 0",
+      ));
+    }
+    if policy.group_by.len() > MAX_FIELDS || policy.fields.len() > MAX_FIELDS {
+      return Err(IoError::invalid_input(
+        ctx.as_str(),
+        &format!("More than {MAX_FIELDS} fields"),
+      ));
+    }
+    for field in policy.group_by.iter().chain(policy.fields.iter()) {
+      parse_field(&ctx, field)?;
+    }
+    for (index, rollup) in policy.rollups.iter().enumerate() {
+      if rollup.interval == 0 || rollup.retention < rollup.interval {
+        return Err(IoError::invalid_input(
+          ctx.as_str(),
+          "Rollup interval must be greater than 0 and lower than its retention",
+        ));
+      }
+      if policy.retention as i64 <= rollup.interval as i64 + ROLLUP_DELAY {
+        return Err(IoError::invalid_input(
+          ctx.as_str(),
+          &format!(
+            "Retention must be greater than the rollup interval {} and a delay of {ROLLUP_DELAY}",
+            rollup.interval
+          ),
+        ));
+      }
+      if policy.rollups[..index]
+        .iter()
+        .any(|r| r.interval == rollup.interval)
+      {
+        return Err(IoError::invalid_input(
+          ctx.as_str(),
+          &format!("Rollup interval {} is duplicated", rollup.interval),
+        ));
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Choose the resolution to answer an aggregation at a given time.
+/// The raw metrics are used while they cover the time range,
+/// then the finest rollup covering it that has the grouped and aggregated
+/// fields and an interval dividing the requested one,
+/// or the one kept the longest when none covers it.
+pub fn select_resolution(
+  plan: &MetricAggregatePlan,
+  policies: &[MetricRetention],
+  now: chrono::NaiveDateTime,
+) -> Option<u64> {
+  let policy = policy_of(&plan.kind, policies)?;
+  let covers = |retention: u64| {
+    plan.since >= now - chrono::Duration::seconds(retention as i64)
+  };
+  if covers(policy.retention) {
+    return None;
+  }
+  let mut rollups = policy
+    .rollups
+    .iter()
+    .filter(|rollup| {
+      plan.interval % rollup.interval == 0
+        && plan.group_by.iter().all(|(name, field)| {
+          *field == MetricGroupField::NodeName || policy.group_by.contains(name)
+        })
+        && plan
+          .fields
+          .iter()
+          .all(|(name, _)| policy.fields.contains(name))
+    })
+    .collect::<Vec<_>>();
+  rollups.sort_by_key(|rollup| rollup.interval);
+  rollups
+    .iter()
+    .find(|rollup| covers(rollup.retention))
+    .or_else(|| rollups.iter().max_by_key(|rollup| rollup.retention))
+    .map(|rollup| rollup.interval)
+}
+
+/// Start of the time bucket of the given width containing a time
+fn floor_bucket(
+  time: chrono::NaiveDateTime,
+  interval: u64,
+) -> chrono::NaiveDateTime {
+  let secs = time.and_utc().timestamp();
+  let secs = secs - secs.rem_euclid(interval as i64);
+  chrono::DateTime::from_timestamp(secs, 0)
+    .unwrap_or_default()
+    .naive_utc()
+}
+
+/// Aggregation of the raw metrics of a node into the buckets of a rollup
+fn rollup_plan(
+  policy: &MetricRetention,
+  rollup: &MetricRollup,
+  node_name: &str,
+  since: chrono::NaiveDateTime,
+  until: chrono::NaiveDateTime,
+) -> IoResult<MetricAggregatePlan> {
+  let ctx = format!("MetricRetention {}", policy.kind);
+  let group_by = policy
+    .group_by
+    .iter()
+    .filter(|name| *name != "node_name")
+    .map(|name| {
+      let path = parse_field(&ctx, name)?;
+      Ok((name.clone(), MetricGroupField::Data(path)))
+    })
+    .collect::<IoResult<_>>()?;
+  let fields = policy
+    .fields
+    .iter()
+    .map(|name| Ok((name.clone(), parse_field(&ctx, name)?)))
+    .collect::<IoResult<_>>()?;
+  Ok(MetricAggregatePlan {
+    kind: policy.kind.clone(),
+    since,
+    until,
+    interval: rollup.interval,
+    group_by,
+    fields,
+    node_name: Some(node_name.to_owned()),
+    resolution: None,
+  })
+}
+
+/// Rows of a rollup from the aggregated metrics of its buckets
+fn rollup_rows(
+  plan: &MetricAggregatePlan,
+  rollup: &MetricRollup,
+  aggregates: Vec<MetricAggregate>,
+) -> IoResult<Vec<MetricRollupDb>> {
+  let now = chrono::Utc::now().naive_utc();
+  aggregates
+    .into_iter()
+    .map(|aggregate| {
+      let fields = aggregate
+        .fields
+        .iter()
+        .map(|field| Ok((field.field.clone(), serde_json::to_value(field)?)))
+        .collect::<IoResult<serde_json::Map<_, _>>>()?;
+      Ok(MetricRollupDb {
+        key: uuid::Uuid::new_v4(),
+        created_at: now,
+        expires_at: aggregate.bucket
+          + chrono::Duration::seconds(rollup.retention as i64),
+        node_name: plan.node_name.clone().unwrap_or_default(),
+        kind: plan.kind.clone(),
+        resolution: rollup.interval as i64,
+        bucket: aggregate.bucket,
+        labels: serde_json::to_value(&aggregate.group)?,
+        count: aggregate.count,
+        fields: serde_json::Value::Object(fields),
+      })
+    })
+    .collect()
+}
+
+/// Roll up the complete buckets of a rollup not computed yet
+/// from the raw metrics of the current node
+async fn rollup_metrics(
+  policy: &MetricRetention,
+  rollup: &MetricRollup,
+  since: Option<chrono::NaiveDateTime>,
+  state: &SystemState,
+) -> IoResult<chrono::NaiveDateTime> {
+  let node_name = &state.inner.config.hostname;
+  let interval = chrono::Duration::seconds(rollup.interval as i64);
+  let now = chrono::Utc::now().naive_utc();
+  let since = match since {
+    Some(since) => since,
+    None => match MetricRollupDb::last_bucket(
+      &policy.kind,
+      rollup.interval,
+      node_name,
+      &state.inner.pool,
+    )
+    .await?
+    {
+      Some(bucket) => bucket + interval,
+      None => {
+        let retention = policy.retention.min(rollup.retention) as i64;
+        floor_bucket(
+          now - chrono::Duration::seconds(retention),
+          rollup.interval,
+        )
+      }
+    },
+  };
+  let until = floor_bucket(
+    now - chrono::Duration::seconds(ROLLUP_DELAY),
+    rollup.interval,
+  )
+  .min(since + interval * MAX_BUCKETS as i32);
+  if until <= since {
+    return Ok(since);
+  }
+  let plan = rollup_plan(policy, rollup, node_name, since, until)?;
+  let aggregates = MetricDb::aggregate(&plan, &state.inner.pool).await?;
+  let rows = rollup_rows(&plan, rollup, aggregates)?;
+  MetricRollupDb::create_many(rows, &state.inner.pool).await?;
+  Ok(until)
+}
+
+/// Roll up the metrics of every policy, the end of the last rolled up bucket
+/// of each rollup is kept in `progress` so empty ranges aren't read again
+pub async fn rollup(
+  progress: &mut HashMap<(String, u64), chrono::NaiveDateTime>,
+  state: &SystemState,
+) {
+  for policy in &state.inner.config.metric_retention {
+    for rollup in &policy.rollups {
+      let key = (policy.kind.clone(), rollup.interval);
+      let since = progress.get(&key).cloned();
+      match rollup_metrics(policy, rollup, since, state).await {
+        Ok(until) => {
+          progress.insert(key, until);
+        }
+        Err(err) => {
+          log::warn!("metric::rollup: {} {}s {err}", key.0, key.1);
+        }
+      }
+    }
+  }
+}
+
+/// Delete the raw metrics older than the retention of their kind,
+/// the expiration of the stored metrics follows the retention in place
+/// when they were saved so a shorter retention is applied here
+pub async fn enforce_retention(state: &SystemState) -> IoResult<()> {
+  let now = chrono::Utc::now();
+  for policy in &state.inner.config.metric_retention {
+    let before = now - chrono::Duration::seconds(policy.retention as i64);
+    let filter = GenericFilter::new()
+      .r#where("kind", GenericClause::Eq(policy.kind.clone()))
+      .r#where("created_at", GenericClause::Lt(before.to_rfc3339()));
+    MetricDb::del_by(&filter, &state.inner.pool).await?;
+  }
+  let filter = GenericFilter::new()
+    .r#where("expires_at", GenericClause::Lt(now.to_rfc3339()));
+  MetricRollupDb::del_by(&filter, &state.inner.pool).await?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use nanocl_stubs::metric::MetricFieldAggregate;
+
+  #[test]
+  fn aggregate_query() {
+    let query = MetricAggregateQuery {
+      kind: "ncproxy.io/http".to_owned(),
+      group_by: Some("host, node_name,Upstream.Status".to_owned()),
+      fields: Some("request_time,request_time".to_owned()),
+      ..Default::default()
+    };
+    let plan = parse_aggregate_query(&query).unwrap();
+    assert_eq!(plan.interval, DEFAULT_INTERVAL);
+    assert_eq!((plan.until - plan.since).num_seconds(), DEFAULT_RANGE);
+    assert_eq!(
+      plan.group_by,
+      [
+        (
+          "host".to_owned(),
+          MetricGroupField::Data(vec!["host".to_owned()])
+        ),
+        ("node_name".to_owned(), MetricGroupField::NodeName),
+        (
+          "Upstream.Status".to_owned(),
+          MetricGroupField::Data(vec![
+            "Upstream".to_owned(),
+            "Status".to_owned()
+          ])
+        ),
+      ]
+    );
+    assert_eq!(plan.fields.len(), 1);
+    let invalid = [
+      MetricAggregateQuery {
+        fields: Some("request_time'".to_owned()),
+        ..query.clone()
+      },
+      MetricAggregateQuery {
+        group_by: Some("host..name".to_owned()),
+        ..query.clone()
+      },
+      MetricAggregateQuery {
+        interval: Some(0),
+        ..query.clone()
+      },
+      MetricAggregateQuery {
+        interval: Some(1),
+        since: Some(chrono::NaiveDateTime::default()),
+        ..query.clone()
+      },
+      MetricAggregateQuery {
+        kind: String::new(),
+        ..query.clone()
+      },
+    ];
+    for query in invalid {
+      assert!(parse_aggregate_query(&query).is_err(), "{query:?}");
+    }
+  }
+
+  #[test]
+  fn retention_policies() {
+    validate_retention(&MetricRetention::defaults()).unwrap();
+    let policy = MetricRetention::defaults().remove(0);
+    let invalid = [
+      vec![policy.clone(), policy.clone()],
+      vec![MetricRetention {
+        retention: 0,
+        ..policy.clone()
+      }],
+      vec![MetricRetention {
+        retention: 3600,
+        ..policy.clone()
+      }],
+      vec![MetricRetention {
+        fields: vec!["request_time'".to_owned()],
+        ..policy.clone()
+      }],
+      vec![MetricRetention {
+        rollups: vec![MetricRollup {
+          interval: 60,
+          retention: 30,
+        }],
+        ..policy.clone()
+      }],
+      vec![MetricRetention {
+        kind: "http".to_owned(),
+        ..policy.clone()
+      }],
+    ];
+    for policies in invalid {
+      assert!(validate_retention(&policies).is_err(), "{policies:?}");
+    }
+    let retention = chrono::Duration::days(7);
+    let now = chrono::Utc::now().naive_utc();
+    let expires_at = expires_at(&policy.kind, &[policy.clone()]);
+    assert!(expires_at >= now + retention);
+    assert!(expires_at < now + retention + chrono::Duration::minutes(1));
+  }
+
+  #[test]
+  fn resolution() {
+    let policies = MetricRetention::defaults();
+    let now = chrono::Utc::now().naive_utc();
+    let day = chrono::Duration::days(1);
+    let query = MetricAggregateQuery {
+      kind: "ncproxy.io/http".to_owned(),
+      interval: Some(3600),
+      until: Some(now),
+      group_by: Some("host,node_name".to_owned()),
+      fields: Some("request_time".to_owned()),
+      ..Default::default()
+    };
+    let plan = |since: chrono::NaiveDateTime, query: &MetricAggregateQuery| {
+      parse_aggregate_query(&MetricAggregateQuery {
+        since: Some(since),
+        ..query.clone()
+      })
+      .unwrap()
+    };
+    let select = |plan| select_resolution(&plan, &policies, now);
+    assert_eq!(select(plan(now - day, &query)), None);
+    assert_eq!(select(plan(now - day * 10, &query)), Some(60));
+    assert_eq!(select(plan(now - day * 100, &query)), Some(3600));
+    assert_eq!(select(plan(now - day * 400, &query)), Some(3600));
+    let query = MetricAggregateQuery {
+      interval: Some(90),
+      since: Some(now - day * 10),
+      ..query
+    };
+    assert_eq!(select(plan(now - day * 10, &query)), None);
+    let query = MetricAggregateQuery {
+      interval: Some(3600),
+      fields: Some("content_length".to_owned()),
+      ..query
+    };
+    assert_eq!(select(plan(now - day * 10, &query)), None);
+    let query = MetricAggregateQuery {
+      kind: "example.com/custom".to_owned(),
+      ..query
+    };
+    assert_eq!(select(plan(now - day * 10, &query)), None);
+  }
+
+  #[test]
+  fn rollup_buckets() {
+    let time = chrono::DateTime::from_timestamp(7_263, 0)
+      .unwrap()
+      .naive_utc();
+    let bucket = floor_bucket(time, 3600);
+    assert_eq!(bucket.and_utc().timestamp(), 7_200);
+    let policy = MetricRetention::defaults().remove(0);
+    let rollup = policy.rollups[0].clone();
+    let plan = rollup_plan(&policy, &rollup, "node-1", bucket, time).unwrap();
+    assert_eq!(plan.interval, 60);
+    assert_eq!(plan.node_name.as_deref(), Some("node-1"));
+    assert_eq!(plan.group_by.len(), 2);
+    let aggregate = MetricAggregate {
+      bucket,
+      group: [("host".to_owned(), Some("example.com".to_owned()))].into(),
+      count: 2,
+      fields: vec![MetricFieldAggregate {
+        field: "request_time".to_owned(),
+        count: 2,
+        sum: Some(0.5),
+        ..Default::default()
+      }],
+    };
+    let rows = rollup_rows(&plan, &rollup, vec![aggregate]).unwrap();
+    assert_eq!(rows[0].resolution, 60);
+    assert_eq!(rows[0].expires_at, bucket + chrono::Duration::days(30));
+    assert_eq!(rows[0].labels["host"], "example.com");
+    assert_eq!(rows[0].fields["request_time"]["Sum"], 0.5);
+  }
+}
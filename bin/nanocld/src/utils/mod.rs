@@ -2,13 +2,20 @@ pub mod key;
 pub mod stream;
 pub mod ws;
 
+pub mod auth;
+pub mod autoscale;
 pub mod container;
 pub mod cron;
 pub mod ctrl_client;
 pub mod exec;
+pub mod exporter;
+pub mod metric;
 pub mod query_string;
+pub mod quota;
+pub mod resource;
 pub mod secret;
 pub mod server;
+pub mod service;
 pub mod store;
 pub mod system;
 pub mod vm_image;
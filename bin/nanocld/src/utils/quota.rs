@@ -0,0 +1,406 @@
+use std::time::Duration;
+
+use bollard_next::container::Config;
+
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  io::IoResult,
+};
+use nanocl_stubs::{
+  cargo_spec::{CargoAutoscale, CargoSpecPartial, ReplicationMode},
+  job::JobPartial,
+  namespace::{NamespaceLimitRange, NamespaceQuota, NamespaceUsage},
+  vm_spec::VmHostConfig,
+};
+
+use crate::{
+  models::{
+    CargoDb, JobDb, NamespaceDb, NamespaceQuotaLockDb, Pool, SystemState, VmDb,
+  },
+  repositories::generic::*,
+  utils::{self, container::replication::ReplicationCtx},
+};
+
+/// Duration of the lease on the quota of a namespace
+const QUOTA_LOCK_TTL: i64 = 60;
+
+/// Maximum time to wait for the lease on the quota of a namespace in milliseconds
+const QUOTA_LOCK_WAIT: u64 = 30_000;
+
+/// Delay between two attempts to take the lease in milliseconds
+const QUOTA_LOCK_RETRY: u64 = 100;
+
+/// Number of nano cpus in a cpu
+const NANO_CPUS: i64 = 1_000_000_000;
+
+/// Number of bytes in a megabyte, the unit of the memory of a vm
+const MEGABYTE: i64 = 1024 * 1024;
+
+/// Resources reserved by a cargo or a vm
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Reservation {
+  instances: usize,
+  nano_cpus: i64,
+  memory: i64,
+}
+
+impl Reservation {
+  fn add_to(self, usage: &mut NamespaceUsage) {
+    usage.instances += self.instances;
+    usage.nano_cpus += self.nano_cpus;
+    usage.memory += self.memory;
+  }
+}
+
+/// Resources reserved by the instances of a cargo in the cluster.
+/// An autoscaled cargo reserves its maximum number of replicas.
+fn cargo_reservation(
+  replication: Option<&ReplicationMode>,
+  autoscale: Option<&CargoAutoscale>,
+  container: &Config,
+  ctx: &ReplicationCtx,
+) -> IoResult<Reservation> {
+  let mode = match autoscale {
+    Some(autoscale) => Some(utils::autoscale::with_replicas(
+      replication,
+      autoscale.max_replicas,
+    )),
+    None => replication.cloned(),
+  };
+  let instances = ctx.total(mode.as_ref())?;
+  let host_config = container.host_config.as_ref();
+  let nano_cpus = host_config.and_then(|h| h.nano_cpus).unwrap_or_default();
+  let memory = host_config.and_then(|h| h.memory).unwrap_or_default();
+  Ok(Reservation {
+    instances,
+    nano_cpus: nano_cpus * instances as i64,
+    memory: memory * instances as i64,
+  })
+}
+
+/// Resources reserved by a vm
+fn vm_reservation(host_config: &VmHostConfig) -> Reservation {
+  Reservation {
+    instances: 0,
+    nano_cpus: host_config.cpu as i64 * NANO_CPUS,
+    memory: host_config.memory as i64 * MEGABYTE,
+  }
+}
+
+/// Objects and resources reserved in a namespace,
+/// `exclude` is the key of an object being updated
+async fn reserved(
+  namespace: &str,
+  exclude: Option<&str>,
+  ctx: &ReplicationCtx,
+  state: &SystemState,
+) -> IoResult<NamespaceUsage> {
+  let mut usage = NamespaceUsage::default();
+  let cargoes =
+    CargoDb::read_by_namespace(namespace, &state.inner.pool).await?;
+  for cargo in cargoes {
+    if Some(cargo.spec.cargo_key.as_str()) == exclude {
+      continue;
+    }
+    usage.cargoes += 1;
+    // A cargo whose replication no longer fits the cluster reserves nothing
+    cargo_reservation(
+      cargo.spec.replication.as_ref(),
+      cargo.spec.autoscale.as_ref(),
+      &cargo.spec.container,
+      ctx,
+    )
+    .unwrap_or_default()
+    .add_to(&mut usage);
+  }
+  let vms = VmDb::read_by_namespace(namespace, &state.inner.pool).await?;
+  for vm in vms {
+    if Some(vm.spec.vm_key.as_str()) == exclude {
+      continue;
+    }
+    usage.vms += 1;
+    vm_reservation(&vm.spec.host_config).add_to(&mut usage);
+  }
+  usage.jobs = JobDb::count_by_namespace(namespace, &state.inner.pool).await?;
+  Ok(usage)
+}
+
+/// Current usage of the quota of a namespace
+pub async fn usage(
+  namespace: &str,
+  state: &SystemState,
+) -> IoResult<NamespaceUsage> {
+  let ctx = ReplicationCtx::cluster(state).await?;
+  reserved(namespace, None, &ctx, state).await
+}
+
+/// Lease on the quota of a namespace, objects checked against the quota
+/// are created while it's held so concurrent creations can't exceed it.
+/// It's released when dropped.
+#[derive(Default)]
+pub struct QuotaLock(Option<(NamespaceQuotaLockDb, Pool)>);
+
+impl Drop for QuotaLock {
+  fn drop(&mut self) {
+    let Some((lock, pool)) = self.0.take() else {
+      return;
+    };
+    ntex::rt::spawn(async move {
+      let namespace = &lock.namespace_name;
+      if let Err(err) =
+        NamespaceDb::unlock_quota(namespace, &lock.token, &pool).await
+      {
+        log::warn!("quota::unlock: {namespace} {err}");
+      }
+    });
+  }
+}
+
+/// Take the lease on the quota of a namespace,
+/// waiting for the other holders to release it
+async fn lock(namespace: &str, state: &SystemState) -> HttpResult<QuotaLock> {
+  let lock = NamespaceQuotaLockDb {
+    namespace_name: namespace.to_owned(),
+    token: uuid::Uuid::new_v4(),
+    expires_at: chrono::Utc::now().naive_utc()
+      + chrono::Duration::seconds(QUOTA_LOCK_TTL),
+  };
+  for _ in 0..QUOTA_LOCK_WAIT / QUOTA_LOCK_RETRY {
+    if NamespaceDb::try_lock_quota(&lock, &state.inner.pool).await? {
+      return Ok(QuotaLock(Some((lock, state.inner.pool.clone()))));
+    }
+    ntex::time::sleep(Duration::from_millis(QUOTA_LOCK_RETRY)).await;
+  }
+  Err(HttpError::conflict(format!(
+    "Namespace {namespace} quota is locked by another operation, try again"
+  )))
+}
+
+/// Ensure the usage of a namespace fits in its quota
+fn check_quota(
+  namespace: &str,
+  quota: &NamespaceQuota,
+  usage: &NamespaceUsage,
+) -> HttpResult<()> {
+  let checks = [
+    ("cargoes", usage.cargoes as i64, quota.max_cargoes),
+    ("instances", usage.instances as i64, quota.max_instances),
+    ("vms", usage.vms as i64, quota.max_vms),
+    ("jobs", usage.jobs as i64, quota.max_jobs),
+  ]
+  .map(|(name, used, max)| (name, used, max.map(|max| max as i64)));
+  let resources = [
+    ("nano cpus", usage.nano_cpus, quota.max_nano_cpus),
+    ("bytes of memory", usage.memory, quota.max_memory),
+  ];
+  for (name, used, max) in checks.into_iter().chain(resources) {
+    match max {
+      Some(max) if used > max => {
+        return Err(HttpError::forbidden(format!(
+          "Namespace {namespace} quota exceeded: {used} {name} for a maximum of {max}"
+        )));
+      }
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+/// Ensure a resource is set and doesn't exceed the maximum of a limit range
+fn check_limit(
+  namespace: &str,
+  name: &str,
+  value: Option<i64>,
+  max: Option<i64>,
+) -> HttpResult<()> {
+  let Some(max) = max else {
+    return Ok(());
+  };
+  match value {
+    Some(value) if value <= max => Ok(()),
+    Some(value) => Err(HttpError::bad_request(format!(
+      "Namespace {namespace} limit range exceeded: {value} {name} for a maximum of {max}"
+    ))),
+    None => Err(HttpError::bad_request(format!(
+      "Namespace {namespace} limit range requires {name} to be set with a maximum of {max}"
+    ))),
+  }
+}
+
+/// Set the default resources of a container and check them against the maximums
+fn apply_limit_range(
+  namespace: &str,
+  limit_range: &NamespaceLimitRange,
+  container: &mut Config,
+) -> HttpResult<()> {
+  let mut host_config = container.host_config.clone().unwrap_or_default();
+  let nano_cpus = host_config.nano_cpus.or(limit_range.default_nano_cpus);
+  let memory = host_config.memory.or(limit_range.default_memory);
+  if nano_cpus != host_config.nano_cpus || memory != host_config.memory {
+    host_config.nano_cpus = nano_cpus;
+    host_config.memory = memory;
+    container.host_config = Some(host_config);
+  }
+  check_limit(namespace, "nano cpus", nano_cpus, limit_range.max_nano_cpus)?;
+  check_limit(namespace, "bytes of memory", memory, limit_range.max_memory)?;
+  Ok(())
+}
+
+/// Apply the limit range of its namespace to a cargo
+/// and ensure it fits in the quota of the namespace.
+/// The cargo must be saved before the returned lock is dropped.
+pub async fn cargo(
+  namespace: &str,
+  key: &str,
+  spec: &CargoSpecPartial,
+  state: &SystemState,
+) -> HttpResult<(CargoSpecPartial, QuotaLock)> {
+  let mut spec = spec.clone();
+  let Ok(item) = NamespaceDb::read_by_pk(namespace, &state.inner.pool).await
+  else {
+    return Ok((spec, QuotaLock::default()));
+  };
+  if let Some(limit_range) = item.try_to_limit_range()? {
+    apply_limit_range(namespace, &limit_range, &mut spec.container)?;
+    if let Some(init_container) = spec.init_container.as_mut() {
+      apply_limit_range(namespace, &limit_range, init_container)?;
+    }
+  }
+  let Some(quota) = item.try_to_quota()? else {
+    return Ok((spec, QuotaLock::default()));
+  };
+  let lock = lock(namespace, state).await?;
+  let ctx = ReplicationCtx::cluster(state).await?;
+  let mut usage = reserved(namespace, Some(key), &ctx, state).await?;
+  usage.cargoes += 1;
+  cargo_reservation(
+    spec.replication.as_ref(),
+    spec.autoscale.as_ref(),
+    &spec.container,
+    &ctx,
+  )?
+  .add_to(&mut usage);
+  check_quota(namespace, &quota, &usage)?;
+  Ok((spec, lock))
+}
+
+/// Check a vm against the limit range and the quota of its namespace.
+/// The vm must be saved before the returned lock is dropped.
+pub async fn vm(
+  namespace: &str,
+  key: &str,
+  host_config: Option<&VmHostConfig>,
+  state: &SystemState,
+) -> HttpResult<QuotaLock> {
+  let Ok(item) = NamespaceDb::read_by_pk(namespace, &state.inner.pool).await
+  else {
+    return Ok(QuotaLock::default());
+  };
+  let host_config = host_config.cloned().unwrap_or_default();
+  let reservation = vm_reservation(&host_config);
+  if let Some(limit_range) = item.try_to_limit_range()? {
+    check_limit(
+      namespace,
+      "nano cpus",
+      Some(reservation.nano_cpus),
+      limit_range.max_nano_cpus,
+    )?;
+    check_limit(
+      namespace,
+      "bytes of memory",
+      Some(reservation.memory),
+      limit_range.max_memory,
+    )?;
+  }
+  let Some(quota) = item.try_to_quota()? else {
+    return Ok(QuotaLock::default());
+  };
+  let lock = lock(namespace, state).await?;
+  let ctx = ReplicationCtx::cluster(state).await?;
+  let mut usage = reserved(namespace, Some(key), &ctx, state).await?;
+  usage.vms += 1;
+  reservation.add_to(&mut usage);
+  check_quota(namespace, &quota, &usage)?;
+  Ok(lock)
+}
+
+/// Apply the limit range of its namespace to the containers of a job
+/// and ensure it fits in the quota of the namespace.
+/// The job must be saved before the returned lock is dropped.
+pub async fn job(
+  job: &JobPartial,
+  state: &SystemState,
+) -> HttpResult<(JobPartial, QuotaLock)> {
+  let mut job = job.clone();
+  let namespace = job.namespace_name().to_owned();
+  let Ok(item) = NamespaceDb::read_by_pk(&namespace, &state.inner.pool).await
+  else {
+    return Ok((job, QuotaLock::default()));
+  };
+  if let Some(limit_range) = item.try_to_limit_range()? {
+    for container in job.containers.iter_mut() {
+      apply_limit_range(&namespace, &limit_range, container)?;
+    }
+    for step in job.steps.iter_mut().flatten() {
+      apply_limit_range(&namespace, &limit_range, &mut step.container)?;
+    }
+  }
+  let Some(quota) = item.try_to_quota()? else {
+    return Ok((job, QuotaLock::default()));
+  };
+  let lock = lock(&namespace, state).await?;
+  let jobs = JobDb::count_by_namespace(&namespace, &state.inner.pool).await?;
+  let usage = NamespaceUsage {
+    jobs: jobs + 1,
+    ..Default::default()
+  };
+  check_quota(&namespace, &quota, &usage)?;
+  Ok((job, lock))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use bollard_next::service::HostConfig;
+
+  #[test]
+  fn limit_range_defaults_and_maximums() {
+    let limit_range = NamespaceLimitRange {
+      default_nano_cpus: Some(NANO_CPUS / 2),
+      default_memory: None,
+      max_nano_cpus: Some(NANO_CPUS),
+      max_memory: None,
+    };
+    let mut container = Config::default();
+    apply_limit_range("test", &limit_range, &mut container).unwrap();
+    let host_config = container.host_config.clone().unwrap();
+    assert_eq!(host_config.nano_cpus, Some(NANO_CPUS / 2));
+    assert_eq!(host_config.memory, None);
+    container.host_config = Some(HostConfig {
+      nano_cpus: Some(NANO_CPUS * 2),
+      ..Default::default()
+    });
+    let err =
+      apply_limit_range("test", &limit_range, &mut container).unwrap_err();
+    assert_eq!(err.status, ntex::http::StatusCode::BAD_REQUEST);
+  }
+
+  #[test]
+  fn quota_exceeded() {
+    let quota = NamespaceQuota {
+      max_cargoes: Some(2),
+      max_memory: Some(MEGABYTE),
+      ..Default::default()
+    };
+    let mut usage = NamespaceUsage {
+      cargoes: 2,
+      memory: MEGABYTE,
+      ..Default::default()
+    };
+    check_quota("test", &quota, &usage).unwrap();
+    usage.cargoes += 1;
+    let err = check_quota("test", &quota, &usage).unwrap_err();
+    assert_eq!(err.status, ntex::http::StatusCode::FORBIDDEN);
+  }
+}
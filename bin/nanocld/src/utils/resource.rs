@@ -0,0 +1,601 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  http_client::HttpClientError,
+  io::IoResult,
+};
+use nanocl_stubs::{
+  generic::GenericFilter,
+  resource::{
+    Resource, ResourceMigrate, ResourcePartial, ResourceStatus,
+    ResourceStatusKind,
+  },
+  resource_kind::{
+    ResourceKind, ResourceKindConversion, ResourceKindTransform,
+    ResourceKindTransformOp,
+  },
+};
+
+use crate::{
+  models::{ResourceDb, ResourceKindDb, ResourceStatusDb, SpecDb, SystemState},
+  objects::generic::*,
+  repositories::generic::*,
+  utils,
+};
+
+/// Seconds to wait before the first retry, doubled at each attempt
+const BACKOFF_BASE: i64 = 2;
+/// Maximum seconds to wait between two attempts
+const BACKOFF_MAX: i64 = 300;
+
+/// Reachability of the controllers by url,
+/// their resources are replayed when they come back
+pub type Controllers = HashMap<String, bool>;
+
+/// Delay before the next attempt after a number of failed attempts
+pub fn backoff(attempts: i32) -> chrono::Duration {
+  let exp = attempts.clamp(1, 16) - 1;
+  chrono::Duration::seconds((BACKOFF_BASE << exp).min(BACKOFF_MAX))
+}
+
+/// Whether the controller refused the resource,
+/// it's not retried until its spec changes or its controller comes back
+pub fn is_rejected(err: &HttpClientError) -> bool {
+  matches!(err, HttpClientError::HttpError(err) if err.status.is_client_error())
+}
+
+/// Status of a resource after a call to the controller of its kind
+/// from the spec it observed and its failed attempts before the call
+pub fn gen_status(
+  observed_spec_key: Option<uuid::Uuid>,
+  attempts: i32,
+  spec_key: uuid::Uuid,
+  result: Result<(), &HttpClientError>,
+) -> ResourceStatusDb {
+  let now = chrono::Utc::now().naive_utc();
+  let attempts = attempts + 1;
+  let (phase, next_attempt_at) = match result {
+    Ok(()) => {
+      return ResourceStatusDb {
+        status: ResourceStatusKind::Applied.to_string(),
+        status_message: None,
+        observed_spec_key: Some(spec_key),
+        attempts: 0,
+        next_attempt_at: None,
+        status_updated_at: now,
+      }
+    }
+    Err(err) if is_rejected(err) => (ResourceStatusKind::Failed, None),
+    Err(_) => (ResourceStatusKind::Pending, Some(now + backoff(attempts))),
+  };
+  ResourceStatusDb {
+    status: phase.to_string(),
+    status_message: result.err().map(|err| err.to_string()),
+    observed_spec_key,
+    attempts,
+    next_attempt_at,
+    status_updated_at: now,
+  }
+}
+
+/// Whether a pending resource should be applied again
+fn is_due(status: &ResourceStatus, now: chrono::NaiveDateTime) -> bool {
+  status.phase == ResourceStatusKind::Pending
+    && status.next_attempt_at.map_or(true, |at| at <= now)
+}
+
+/// Apply the current spec of a resource with the controller of its kind
+async fn apply(
+  kind: &ResourceKind,
+  resource: &Resource,
+) -> Result<(), HttpClientError> {
+  let Some(url) = &kind.data.url else {
+    return Ok(());
+  };
+  let ctrl_client = utils::ctrl_client::CtrlClient::new(&kind.name, url);
+  ctrl_client
+    .apply_rule(
+      &resource.spec.version,
+      &resource.name,
+      &resource.namespace_name,
+      &resource.spec.data,
+    )
+    .await?;
+  Ok(())
+}
+
+/// Whether a resource isn't applied at its current spec
+fn is_outdated(resource: &Resource) -> bool {
+  resource.status.phase != ResourceStatusKind::Applied
+    || resource.status.observed_spec_key != Some(resource.spec.key)
+}
+
+/// Apply the pending resources whose next attempt is due,
+/// and every resource of a kind whose controller came back.
+/// Controllers never seen get the resources not applied at their current spec,
+/// so a restart of the daemon doesn't replay the resources already applied.
+pub async fn reconcile(
+  controllers: &mut Controllers,
+  state: &SystemState,
+) -> IoResult<()> {
+  let resources =
+    ResourceDb::transform_read_by(&GenericFilter::new(), &state.inner.pool)
+      .await?;
+  let mut kinds = HashMap::<(String, String), Vec<Resource>>::new();
+  for resource in resources {
+    kinds
+      .entry((resource.kind.clone(), resource.spec.version.clone()))
+      .or_default()
+      .push(resource);
+  }
+  let now = chrono::Utc::now().naive_utc();
+  for ((kind, version), resources) in kinds {
+    let kind: ResourceKind =
+      match SpecDb::get_version(&kind, &version, &state.inner.pool)
+        .await
+        .and_then(ResourceKind::try_from)
+      {
+        Err(err) => {
+          log::warn!("resource::reconcile: {kind}/{version} {err}");
+          continue;
+        }
+        Ok(kind) => kind,
+      };
+    let (replay, first_seen) = match &kind.data.url {
+      None => (false, false),
+      Some(url) => {
+        let ctrl_client = utils::ctrl_client::CtrlClient::new(&kind.name, url);
+        let reachable = ctrl_client.ping().await.is_ok();
+        let was_reachable = controllers.insert(url.clone(), reachable);
+        (
+          reachable && was_reachable == Some(false),
+          reachable && was_reachable.is_none(),
+        )
+      }
+    };
+    if replay {
+      log::info!(
+        "resource::reconcile: replaying {} resources of {}/{version}",
+        resources.len(),
+        kind.name
+      );
+    }
+    for resource in resources {
+      let wanted = replay
+        || (first_seen && is_outdated(&resource))
+        || is_due(&resource.status, now);
+      if !wanted {
+        continue;
+      }
+      let key = &resource.spec.resource_key;
+      // The resource may have been deleted or updated since it was listed,
+      // its controller must not get a spec that's no longer current
+      let resource =
+        match ResourceDb::transform_read_by_pk(key, &state.inner.pool).await {
+          Ok(current) if current.spec.key == resource.spec.key => current,
+          _ => continue,
+        };
+      let result = apply(&kind, &resource).await;
+      if let Err(err) = &result {
+        log::warn!("resource::reconcile: {key} {err}");
+      }
+      let status = gen_status(
+        resource.status.observed_spec_key,
+        resource.status.attempts as i32,
+        resource.spec.key,
+        result.as_ref().copied(),
+      );
+      let updated = ResourceDb::update_status(
+        key,
+        &resource.spec.key,
+        status,
+        &state.inner.pool,
+      )
+      .await?;
+      if updated.is_none() && result.is_ok() {
+        forget_if_deleted(&kind, &resource, state).await;
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Delete a resource from its controller again
+/// when it was deleted while the controller applied it
+async fn forget_if_deleted(
+  kind: &ResourceKind,
+  resource: &Resource,
+  state: &SystemState,
+) {
+  let key = &resource.spec.resource_key;
+  if ResourceDb::transform_read_by_pk(key, &state.inner.pool)
+    .await
+    .is_ok()
+  {
+    return;
+  }
+  let Some(url) = &kind.data.url else {
+    return;
+  };
+  let ctrl_client = utils::ctrl_client::CtrlClient::new(&kind.name, url);
+  if let Err(err) = ctrl_client
+    .delete_rule(
+      &resource.spec.version,
+      &resource.name,
+      &resource.namespace_name,
+    )
+    .await
+  {
+    log::warn!("resource::reconcile: {key} {err}");
+  }
+}
+
+/// Tokens of a JSON pointer
+fn path_tokens(path: &str) -> HttpResult<Vec<String>> {
+  let Some(tokens) = path.strip_prefix('/') else {
+    return Err(HttpError::bad_request(format!(
+      "Invalid path {path} it must start with /"
+    )));
+  };
+  Ok(
+    tokens
+      .split('/')
+      .map(|token| token.replace("~1", "/").replace("~0", "~"))
+      .collect(),
+  )
+}
+
+/// Set a value at a path creating the missing objects
+fn set_at(data: &mut Value, path: &str, value: Value) -> HttpResult<()> {
+  let mut tokens = path_tokens(path)?;
+  let last = tokens.pop().unwrap_or_default();
+  let not_object =
+    || HttpError::bad_request(format!("Can't set {path} in a scalar value"));
+  let mut current = data;
+  for token in tokens {
+    if current.is_null() {
+      *current = Value::Object(Default::default());
+    }
+    current = match current {
+      Value::Object(map) => map.entry(token).or_insert(Value::Null),
+      Value::Array(items) => token
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| items.get_mut(index))
+        .ok_or_else(not_object)?,
+      _ => return Err(not_object()),
+    };
+  }
+  if current.is_null() {
+    *current = Value::Object(Default::default());
+  }
+  match current {
+    Value::Object(map) => {
+      map.insert(last, value);
+    }
+    Value::Array(items) if last == "-" => items.push(value),
+    Value::Array(items) => match last.parse::<usize>() {
+      Ok(index) if index < items.len() => items[index] = value,
+      Ok(index) if index == items.len() => items.push(value),
+      _ => return Err(not_object()),
+    },
+    _ => return Err(not_object()),
+  }
+  Ok(())
+}
+
+/// Remove the value at a path if it exists
+fn take_at(data: &mut Value, path: &str) -> HttpResult<Option<Value>> {
+  let mut tokens = path_tokens(path)?;
+  let last = tokens.pop().unwrap_or_default();
+  let mut current = data;
+  for token in tokens {
+    let next = match current {
+      Value::Object(map) => map.get_mut(&token),
+      Value::Array(items) => token
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| items.get_mut(index)),
+      _ => None,
+    };
+    let Some(next) = next else {
+      return Ok(None);
+    };
+    current = next;
+  }
+  let value = match current {
+    Value::Object(map) => map.remove(&last),
+    Value::Array(items) => last
+      .parse::<usize>()
+      .ok()
+      .filter(|index| *index < items.len())
+      .map(|index| items.remove(index)),
+    _ => None,
+  };
+  Ok(value)
+}
+
+/// Path of the value to move or copy of a step
+fn from_of(step: &ResourceKindTransform) -> HttpResult<&str> {
+  step.from.as_deref().ok_or_else(|| {
+    HttpError::bad_request(format!(
+      "Missing From to {:?} {}",
+      step.op, step.path
+    ))
+  })
+}
+
+/// Apply the steps of a declarative transform to the data of a resource
+pub fn transform(
+  data: &Value,
+  steps: &[ResourceKindTransform],
+) -> HttpResult<Value> {
+  let mut data = data.clone();
+  for step in steps {
+    match step.op {
+      ResourceKindTransformOp::Set => {
+        let value = step.value.clone().ok_or_else(|| {
+          HttpError::bad_request(format!("Missing Value to set {}", step.path))
+        })?;
+        set_at(&mut data, &step.path, value)?;
+      }
+      ResourceKindTransformOp::Move => {
+        if let Some(value) = take_at(&mut data, from_of(step)?)? {
+          set_at(&mut data, &step.path, value)?;
+        }
+      }
+      ResourceKindTransformOp::Copy => {
+        let from = from_of(step)?;
+        path_tokens(from)?;
+        if let Some(value) = data.pointer(from).cloned() {
+          set_at(&mut data, &step.path, value)?;
+        }
+      }
+      ResourceKindTransformOp::Remove => {
+        take_at(&mut data, &step.path)?;
+      }
+    }
+  }
+  Ok(data)
+}
+
+/// Ensure the conversion of a kind version can be applied
+pub fn check_conversion(conversion: &ResourceKindConversion) -> HttpResult<()> {
+  match conversion {
+    ResourceKindConversion::Url(url) => {
+      if !["unix://", "http://", "https://"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+      {
+        return Err(HttpError::bad_request(format!(
+          "Invalid conversion url {url}"
+        )));
+      }
+    }
+    ResourceKindConversion::Transform(steps) => {
+      for step in steps {
+        path_tokens(&step.path)?;
+        match step.op {
+          ResourceKindTransformOp::Move | ResourceKindTransformOp::Copy => {
+            path_tokens(from_of(step)?)?;
+          }
+          ResourceKindTransformOp::Set if step.value.is_none() => {
+            return Err(HttpError::bad_request(format!(
+              "Missing Value to set {}",
+              step.path
+            )));
+          }
+          _ => {}
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+/// Convert the data of a resource to a version of its kind
+/// with the conversion of each version after its current one.
+async fn convert(
+  resource: &Resource,
+  version: &str,
+  state: &SystemState,
+) -> HttpResult<Value> {
+  let versions =
+    ResourceKindDb::list_versions(&resource.kind, &state.inner.pool).await?;
+  let position = |version: &str| {
+    versions
+      .iter()
+      .position(|item| item.version == version)
+      .ok_or_else(|| {
+        HttpError::not_found(format!(
+          "Version {version} of {} doesn't exist",
+          resource.kind
+        ))
+      })
+  };
+  let from = position(&resource.spec.version)?;
+  let to = position(version)?;
+  if to < from {
+    return Err(HttpError::bad_request(format!(
+      "Resource {} can't be migrated from {} to the older version {version}",
+      resource.name, resource.spec.version
+    )));
+  }
+  let mut data = resource.spec.data.clone();
+  for versions in versions[from..=to].windows(2) {
+    let (previous, next) = (&versions[0], &versions[1]);
+    let Some(conversion) = &next.data.conversion else {
+      return Err(HttpError::bad_request(format!(
+        "Version {} of {} doesn't declare a conversion from {}",
+        next.version, resource.kind, previous.version
+      )));
+    };
+    data = match conversion {
+      ResourceKindConversion::Transform(steps) => transform(&data, steps)?,
+      ResourceKindConversion::Url(url) => {
+        let ctrl_client =
+          utils::ctrl_client::CtrlClient::new(&resource.kind, url);
+        ctrl_client
+          .convert_rule(
+            &next.version,
+            &previous.version,
+            &resource.name,
+            &resource.namespace_name,
+            &data,
+          )
+          .await?
+      }
+    };
+  }
+  Ok(data)
+}
+
+/// Migrate a resource to a version of its kind, the current version
+/// of the kind by default. The converted resource is applied like an update.
+pub async fn migrate(
+  key: &str,
+  payload: &ResourceMigrate,
+  state: &SystemState,
+) -> HttpResult<Resource> {
+  let resource =
+    ResourceDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  let version = match &payload.version {
+    Some(version) => version.clone(),
+    None => {
+      ResourceKindDb::transform_read_by_pk(&resource.kind, &state.inner.pool)
+        .await?
+        .version
+    }
+  };
+  if version == resource.spec.version {
+    return Ok(resource);
+  }
+  let data = convert(&resource, &version, state).await?;
+  let new_resource = ResourcePartial {
+    name: resource.name.clone(),
+    kind: format!("{}/{version}", resource.kind),
+    data,
+    metadata: resource.spec.metadata.clone(),
+  };
+  ResourceDb::put_obj_by_pk(key, &new_resource, state).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use nanocl_error::http::HttpError;
+
+  #[test]
+  fn backoff_delay() {
+    assert_eq!(backoff(0).num_seconds(), 2);
+    assert_eq!(backoff(1).num_seconds(), 2);
+    assert_eq!(backoff(3).num_seconds(), 8);
+    assert_eq!(backoff(9).num_seconds(), BACKOFF_MAX);
+    assert_eq!(backoff(i32::MAX).num_seconds(), BACKOFF_MAX);
+  }
+
+  #[test]
+  fn status_of_attempt() {
+    let spec_key = uuid::Uuid::new_v4();
+    let observed_spec_key = Some(uuid::Uuid::new_v4());
+    let status = gen_status(observed_spec_key, 2, spec_key, Ok(()));
+    assert_eq!(status.status, "applied");
+    assert_eq!(status.observed_spec_key, Some(spec_key));
+    assert_eq!(status.attempts, 0);
+    assert!(status.next_attempt_at.is_none());
+    let down = HttpClientError::HttpError(HttpError::internal_server_error(
+      "controller down",
+    ));
+    let status = gen_status(observed_spec_key, 2, spec_key, Err(&down));
+    assert_eq!(status.status, "pending");
+    assert_eq!(status.observed_spec_key, observed_spec_key);
+    assert_eq!(status.attempts, 3);
+    assert!(status.status_message.unwrap().contains("controller down"));
+    let next_attempt_at = status.next_attempt_at.unwrap();
+    assert!(next_attempt_at > status.status_updated_at);
+    let rejected =
+      HttpClientError::HttpError(HttpError::bad_request("invalid rule"));
+    let status = gen_status(observed_spec_key, 2, spec_key, Err(&rejected));
+    assert_eq!(status.status, "failed");
+    assert!(status.next_attempt_at.is_none());
+  }
+
+  #[test]
+  fn transform_data() {
+    let step =
+      |op, path: &str, from: Option<&str>, value| ResourceKindTransform {
+        op,
+        path: path.to_owned(),
+        from: from.map(ToOwned::to_owned),
+        value,
+      };
+    let data = serde_json::json!({
+      "Username": "test",
+      "Ports": [80],
+      "Legacy": true,
+    });
+    let steps = [
+      step(
+        ResourceKindTransformOp::Move,
+        "/User/Name",
+        Some("/Username"),
+        None,
+      ),
+      step(
+        ResourceKindTransformOp::Copy,
+        "/User/Ports",
+        Some("/Ports"),
+        None,
+      ),
+      step(
+        ResourceKindTransformOp::Set,
+        "/Ports/-",
+        None,
+        Some(serde_json::json!(443)),
+      ),
+      step(ResourceKindTransformOp::Remove, "/Legacy", None, None),
+      step(
+        ResourceKindTransformOp::Move,
+        "/Group",
+        Some("/Missing"),
+        None,
+      ),
+    ];
+    let data = transform(&data, &steps).unwrap();
+    assert_eq!(
+      data,
+      serde_json::json!({
+        "User": { "Name": "test", "Ports": [80] },
+        "Ports": [80, 443],
+      })
+    );
+    let invalid = step(
+      ResourceKindTransformOp::Set,
+      "/User/Name/First",
+      None,
+      Some(Value::Null),
+    );
+    assert!(transform(&data, &[invalid]).is_err());
+    let conversion = ResourceKindConversion::Transform(vec![step(
+      ResourceKindTransformOp::Move,
+      "/User",
+      None,
+      None,
+    )]);
+    assert!(check_conversion(&conversion).is_err());
+    let conversion = ResourceKindConversion::Transform(vec![step(
+      ResourceKindTransformOp::Remove,
+      "User",
+      None,
+      None,
+    )]);
+    assert!(check_conversion(&conversion).is_err());
+    let conversion =
+      ResourceKindConversion::Url("unix:///run/nanocl/proxy.sock".to_owned());
+    assert!(check_conversion(&conversion).is_ok());
+  }
+}
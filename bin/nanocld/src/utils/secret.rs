@@ -1,18 +1,383 @@
+use std::{collections::HashMap, io::Write, os::unix::fs::OpenOptionsExt};
+
 use futures::{stream::FuturesUnordered, StreamExt};
+use openssl::{
+  base64,
+  error::ErrorStack,
+  symm::{self, Cipher},
+};
 
-use nanocl_error::io::{IoError, IoResult};
+use nanocl_error::io::{FromIo, IoError, IoResult};
 use nanocl_stubs::{
+  config::DaemonConfig,
   generic::{GenericClause, GenericFilter},
   process::ProcessKind,
   proxy::ProxySslConfig,
+  secret::{Secret, SecretKeyRotation},
 };
 use tokio::fs;
 
 use crate::{
-  models::{SecretDb, SystemState},
+  models::{
+    NodeDb, SecretDb, SecretEnvelope, SecretKeyring, SecretUpdateDb,
+    SystemState,
+  },
   repositories::generic::*,
+  utils,
 };
 
+/// Size of the keys used to encrypt secrets (AES-256)
+const KEY_LEN: usize = 32;
+/// Size of the nonce of AES-GCM
+const NONCE_LEN: usize = 12;
+/// Size of the authentication tag of AES-GCM
+const TAG_LEN: usize = 16;
+
+/// Where the keyring used to encrypt secrets at rest is loaded from
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeyProvider {
+  /// A keyring file managed by the daemon
+  File(String),
+  /// A single base64 encoded key read from an environment variable
+  Env(String),
+}
+
+impl KeyProvider {
+  /// Resolve the key provider from the daemon config
+  pub fn from_config(config: &DaemonConfig) -> IoResult<Self> {
+    let Some(secret_key) = &config.secret_key else {
+      return Ok(Self::File(format!("{}/secret.key", config.state_dir)));
+    };
+    if let Some(path) = secret_key.strip_prefix("file://") {
+      return Ok(Self::File(path.to_owned()));
+    }
+    if let Some(var) = secret_key.strip_prefix("env://") {
+      return Ok(Self::Env(var.to_owned()));
+    }
+    Err(IoError::invalid_input(
+      "SecretKey",
+      &format!("Unsupported key provider {secret_key}"),
+    ))
+  }
+}
+
+fn decode_key(key: &str) -> IoResult<Vec<u8>> {
+  let key = base64::decode_block(key).map_err(ssl_err("SecretKey"))?;
+  if key.len() != KEY_LEN {
+    return Err(IoError::invalid_data(
+      "SecretKey",
+      &format!("Key must be {KEY_LEN} bytes long"),
+    ));
+  }
+  Ok(key)
+}
+
+fn ssl_err(context: &str) -> impl FnOnce(ErrorStack) -> IoError + '_ {
+  move |err| IoError::invalid_data(context, &err.to_string())
+}
+
+/// Generate a new random key
+fn gen_key() -> IoResult<Vec<u8>> {
+  let mut key = vec![0; KEY_LEN];
+  openssl::rand::rand_bytes(&mut key).map_err(ssl_err("SecretKey"))?;
+  Ok(key)
+}
+
+/// Encrypt the data with AES-256-GCM and return `nonce|cipher|tag` base64 encoded
+fn seal(key: &[u8], data: &[u8]) -> IoResult<String> {
+  let mut nonce = [0; NONCE_LEN];
+  openssl::rand::rand_bytes(&mut nonce).map_err(ssl_err("SecretEncrypt"))?;
+  let mut tag = [0; TAG_LEN];
+  let cipher = symm::encrypt_aead(
+    Cipher::aes_256_gcm(),
+    key,
+    Some(&nonce),
+    &[],
+    data,
+    &mut tag,
+  )
+  .map_err(ssl_err("SecretEncrypt"))?;
+  let mut sealed = nonce.to_vec();
+  sealed.extend(cipher);
+  sealed.extend(tag);
+  Ok(base64::encode_block(&sealed))
+}
+
+/// Decrypt data encrypted with [seal](seal)
+fn open(key: &[u8], sealed: &str) -> IoResult<Vec<u8>> {
+  let sealed =
+    base64::decode_block(sealed).map_err(ssl_err("SecretDecrypt"))?;
+  if sealed.len() < NONCE_LEN + TAG_LEN {
+    return Err(IoError::invalid_data("SecretDecrypt", "Truncated data"));
+  }
+  let (nonce, rest) = sealed.split_at(NONCE_LEN);
+  let (cipher, tag) = rest.split_at(rest.len() - TAG_LEN);
+  symm::decrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), &[], cipher, tag)
+    .map_err(ssl_err("SecretDecrypt"))
+}
+
+impl SecretKeyring {
+  fn get_key(&self, id: &str) -> IoResult<Vec<u8>> {
+    let key = self.keys.get(id).ok_or_else(|| {
+      IoError::not_found("SecretKey", &format!("Key {id} not in the keyring"))
+    })?;
+    decode_key(key)
+  }
+
+  /// Encrypt the data with a new data key wrapped by the active key
+  pub fn encrypt(
+    &self,
+    data: &serde_json::Value,
+  ) -> IoResult<(String, serde_json::Value)> {
+    let data_key = gen_key()?;
+    let envelope = SecretEnvelope {
+      key: seal(&self.get_key(&self.active)?, &data_key)?,
+      data: seal(&data_key, &serde_json::to_vec(data)?)?,
+    };
+    Ok((self.active.clone(), serde_json::to_value(envelope)?))
+  }
+
+  /// Decrypt data encrypted by the key `key_id`
+  pub fn decrypt(
+    &self,
+    key_id: &str,
+    data: &serde_json::Value,
+  ) -> IoResult<serde_json::Value> {
+    let envelope = serde_json::from_value::<SecretEnvelope>(data.clone())?;
+    let data_key = open(&self.get_key(key_id)?, &envelope.key)?;
+    let data = open(&data_key, &envelope.data)?;
+    Ok(serde_json::from_slice(&data)?)
+  }
+
+  /// Wrap the data key of an envelope encrypted by `key_id` with the active key
+  pub fn rewrap(
+    &self,
+    key_id: &str,
+    data: &serde_json::Value,
+  ) -> IoResult<serde_json::Value> {
+    let envelope = serde_json::from_value::<SecretEnvelope>(data.clone())?;
+    let data_key = open(&self.get_key(key_id)?, &envelope.key)?;
+    let envelope = SecretEnvelope {
+      key: seal(&self.get_key(&self.active)?, &data_key)?,
+      ..envelope
+    };
+    Ok(serde_json::to_value(envelope)?)
+  }
+}
+
+fn write_keyring(path: &str, keyring: &SecretKeyring) -> IoResult<()> {
+  let content = serde_json::to_vec_pretty(keyring)?;
+  let tmp_path = format!("{path}.tmp");
+  // A leftover from an interrupted write could have any mode
+  if std::path::Path::new(&tmp_path).exists() {
+    std::fs::remove_file(&tmp_path)
+      .map_err(|err| err.map_err_context(|| tmp_path.clone()))?;
+  }
+  std::fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .mode(0o600)
+    .open(&tmp_path)
+    .and_then(|mut file| file.write_all(&content))
+    .map_err(|err| err.map_err_context(|| tmp_path.clone()))?;
+  std::fs::rename(&tmp_path, path)
+    .map_err(|err| err.map_err_context(|| path.to_owned()))?;
+  Ok(())
+}
+
+/// Load the keyring used to encrypt secrets at rest,
+/// a new keyring file is generated if it doesn't exist
+pub fn load_keyring(config: &DaemonConfig) -> IoResult<SecretKeyring> {
+  match KeyProvider::from_config(config)? {
+    KeyProvider::Env(var) => {
+      let key = std::env::var(&var).map_err(|err| {
+        IoError::not_found("SecretKey", &format!("{var}: {err}"))
+      })?;
+      decode_key(&key)?;
+      Ok(SecretKeyring {
+        active: "env".to_owned(),
+        keys: HashMap::from([("env".to_owned(), key)]),
+      })
+    }
+    KeyProvider::File(path) => {
+      if !std::path::Path::new(&path).exists() {
+        let id = utils::key::generate_short_id(8);
+        let keyring = SecretKeyring {
+          active: id.clone(),
+          keys: HashMap::from([(id, base64::encode_block(&gen_key()?))]),
+        };
+        write_keyring(&path, &keyring)?;
+        log::info!("secret::load_keyring: generated a new keyring at {path}");
+        return Ok(keyring);
+      }
+      let content = std::fs::read(&path)
+        .map_err(|err| err.map_err_context(|| path.clone()))?;
+      let keyring = serde_json::from_slice::<SecretKeyring>(&content)
+        .map_err(|err| err.map_err_context(|| path.clone()))?;
+      keyring.get_key(&keyring.active)?;
+      Ok(keyring)
+    }
+  }
+}
+
+/// Refuse to start a node of a cluster with the default keyring,
+/// it lives in the state directory of each node while the secrets are shared,
+/// so a secret encrypted by a node couldn't be decrypted by the others
+pub async fn ensure_shared_keyring(state: &SystemState) -> IoResult<()> {
+  if state.inner.config.secret_key.is_some() {
+    return Ok(());
+  }
+  let nodes = NodeDb::list_names(&state.inner.pool).await?;
+  if nodes.len() > 1 {
+    return Err(IoError::invalid_input(
+      "SecretKey",
+      "A cluster of nodes requires a shared key provider, \
+      use --secret-key env://VARIABLE or file:// on a shared path",
+    ));
+  }
+  Ok(())
+}
+
+/// Reload the keyring file when another daemon sharing it rotated the key
+fn refresh_keyring(state: &SystemState) -> IoResult<()> {
+  let KeyProvider::File(path) = KeyProvider::from_config(&state.inner.config)?
+  else {
+    return Ok(());
+  };
+  // A missing file would be generated again with a new key
+  if !std::path::Path::new(&path).exists() {
+    return Ok(());
+  }
+  let keyring = load_keyring(&state.inner.config)?;
+  let mut current = state.inner.secret_keyring.write()?;
+  if current.active != keyring.active || current.keys != keyring.keys {
+    *current = keyring;
+  }
+  Ok(())
+}
+
+/// Decrypt the data of a secret read from the database
+pub fn decrypt(secret: SecretDb, state: &SystemState) -> IoResult<Secret> {
+  let data = match &secret.key_id {
+    None => secret.data.clone(),
+    Some(key_id) => {
+      if !state.inner.secret_keyring.read()?.keys.contains_key(key_id) {
+        refresh_keyring(state)?;
+      }
+      let keyring = state.inner.secret_keyring.read()?;
+      keyring.decrypt(key_id, &secret.data)?
+    }
+  };
+  let secret: Secret = secret.try_into()?;
+  Ok(Secret { data, ..secret })
+}
+
+/// Encrypt the data of a secret before saving it in the database
+/// with the active key of the keyring file
+pub fn encrypt(
+  data: &serde_json::Value,
+  state: &SystemState,
+) -> IoResult<(String, serde_json::Value)> {
+  refresh_keyring(state)?;
+  let keyring = state.inner.secret_keyring.read()?;
+  keyring.encrypt(data)
+}
+
+/// Encrypt every secret with the active key of the keyring,
+/// plain text secrets are encrypted and the others have their data key wrapped again.
+/// Secrets are paged by key so the updated ones are never skipped.
+/// Return the number of updated secrets.
+async fn reencrypt_secrets(
+  keyring: &SecretKeyring,
+  only_plain: bool,
+  state: &SystemState,
+) -> IoResult<usize> {
+  let mut count = 0;
+  let mut last_key: Option<String> = None;
+  loop {
+    let mut filter = GenericFilter {
+      order_by: Some(vec!["key asc".to_owned()]),
+      ..GenericFilter::new().limit(100)
+    };
+    if only_plain {
+      filter = filter.r#where("key_id", GenericClause::IsNull);
+    }
+    if let Some(last_key) = &last_key {
+      filter = filter.r#where("key", GenericClause::Gt(last_key.clone()));
+    }
+    let secrets = SecretDb::read_by(&filter, &state.inner.pool).await?;
+    let len = secrets.len();
+    last_key = secrets.last().map(|secret| secret.key.clone());
+    for secret in secrets {
+      let (key_id, data) = match &secret.key_id {
+        None => keyring.encrypt(&secret.data)?,
+        Some(key_id) if key_id == &keyring.active => continue,
+        Some(key_id) => (
+          keyring.active.clone(),
+          keyring.rewrap(key_id, &secret.data)?,
+        ),
+      };
+      let update = SecretUpdateDb {
+        data: Some(data),
+        key_id: Some(key_id),
+        ..Default::default()
+      };
+      SecretDb::update_pk(&secret.key, update, &state.inner.pool).await?;
+      count += 1;
+    }
+    if len < 100 {
+      break;
+    }
+  }
+  Ok(count)
+}
+
+/// Encrypt the secrets stored in plain text
+/// by daemons running before the encryption at rest was added
+pub async fn encrypt_plain_secrets(state: &SystemState) -> IoResult<usize> {
+  let _rotation = state.inner.secret_rotation.write().await;
+  let keyring = state.inner.secret_keyring.read()?.clone();
+  reencrypt_secrets(&keyring, true, state).await
+}
+
+/// Generate a new active key and encrypt every secret with it,
+/// old keys are removed from the keyring once no stored secret use them.
+/// Secrets can't be written during the rotation.
+pub async fn rotate_key(state: &SystemState) -> IoResult<SecretKeyRotation> {
+  let KeyProvider::File(path) = KeyProvider::from_config(&state.inner.config)?
+  else {
+    return Err(IoError::invalid_input(
+      "SecretKey",
+      "Key rotation is only supported with a file key provider",
+    ));
+  };
+  let _rotation = state.inner.secret_rotation.write().await;
+  refresh_keyring(state)?;
+  let mut keyring = state.inner.secret_keyring.read()?.clone();
+  let id = utils::key::generate_short_id(8);
+  keyring
+    .keys
+    .insert(id.clone(), base64::encode_block(&gen_key()?));
+  keyring.active = id.clone();
+  // Save the new key before using it so data are never encrypted by a lost key
+  write_keyring(&path, &keyring)?;
+  *state.inner.secret_keyring.write()? = keyring.clone();
+  let count = reencrypt_secrets(&keyring, false, state).await?;
+  let used = SecretDb::list_key_ids(&state.inner.pool).await?;
+  keyring
+    .keys
+    .retain(|key_id, _| key_id == &id || used.contains(key_id));
+  if keyring.keys.len() > 1 {
+    log::warn!(
+      "secret::rotate_key: {} old keys are still used by secrets",
+      keyring.keys.len() - 1
+    );
+  }
+  write_keyring(&path, &keyring)?;
+  *state.inner.secret_keyring.write()? = keyring;
+  Ok(SecretKeyRotation { key_id: id, count })
+}
+
 /// Transform and optional vector of secrets to a vector of envs from the database
 ///
 pub async fn load_env_secrets(
@@ -24,7 +389,7 @@ pub async fn load_env_secrets(
     let filter = GenericFilter::new()
       .r#where("key", GenericClause::In(secrets.clone()))
       .r#where("kind", GenericClause::Eq("nanocl.io/env".to_owned()));
-    let secrets = SecretDb::transform_read_by(&filter, &state.inner.pool)
+    let secrets = SecretDb::read_decrypted_by(&filter, state)
       .await?
       .into_iter()
       .map(|secret| {
@@ -52,8 +417,7 @@ pub async fn create_tls_secrets(
     let filter = GenericFilter::new()
       .r#where("key", GenericClause::In(secrets.clone()))
       .r#where("kind", GenericClause::Eq("nanocl.io/tls".to_owned()));
-    let secrets =
-      SecretDb::transform_read_by(&filter, &state.inner.pool).await?;
+    let secrets = SecretDb::read_decrypted_by(&filter, state).await?;
     secrets
       .into_iter()
       .map(|secret| {
@@ -86,3 +450,60 @@ pub async fn create_tls_secrets(
   }
   Ok(secret_dir)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keyring(ids: &[&str]) -> SecretKeyring {
+    SecretKeyring {
+      active: ids[0].to_owned(),
+      keys: ids
+        .iter()
+        .map(|id| (id.to_string(), base64::encode_block(&gen_key().unwrap())))
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn seal_open() {
+    let key = gen_key().unwrap();
+    let sealed = seal(&key, b"hello").unwrap();
+    assert_eq!(open(&key, &sealed).unwrap(), b"hello");
+    assert!(open(&gen_key().unwrap(), &sealed).is_err());
+  }
+
+  #[test]
+  fn keyring_rotation() {
+    let data = serde_json::json!({ "Password": "secret" });
+    let mut keyring = keyring(&["old", "new"]);
+    let (key_id, encrypted) = keyring.encrypt(&data).unwrap();
+    assert_eq!(key_id, "old");
+    assert_ne!(encrypted, data);
+    assert_eq!(keyring.decrypt(&key_id, &encrypted).unwrap(), data);
+    keyring.active = "new".to_owned();
+    let rewrapped = keyring.rewrap(&key_id, &encrypted).unwrap();
+    keyring.keys.remove("old");
+    assert_eq!(keyring.decrypt("new", &rewrapped).unwrap(), data);
+    assert!(keyring.decrypt("old", &encrypted).is_err());
+  }
+
+  #[test]
+  fn key_provider() {
+    let mut config = DaemonConfig {
+      state_dir: "/var/lib/nanocl".to_owned(),
+      ..Default::default()
+    };
+    assert_eq!(
+      KeyProvider::from_config(&config).unwrap(),
+      KeyProvider::File("/var/lib/nanocl/secret.key".to_owned())
+    );
+    config.secret_key = Some("env://NANOCL_SECRET_KEY".to_owned());
+    assert_eq!(
+      KeyProvider::from_config(&config).unwrap(),
+      KeyProvider::Env("NANOCL_SECRET_KEY".to_owned())
+    );
+    config.secret_key = Some("vault://secret".to_owned());
+    assert!(KeyProvider::from_config(&config).is_err());
+  }
+}
@@ -0,0 +1,209 @@
+use bollard_next::service::HealthStatusEnum;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::{
+  cargo::Cargo,
+  namespace,
+  process::Process,
+  service::{Service, ServiceEndpoint, ServicePort, ServiceQuery},
+};
+
+use crate::{
+  models::{CargoDb, NamespaceDb, ProcessDb, SystemState},
+  repositories::generic::*,
+  utils,
+};
+
+/// Endpoint of a running instance on the network of its namespace.
+/// Init containers and instances being retired aren't endpoints.
+fn endpoint_of(process: &Process, namespace: &str) -> Option<ServiceEndpoint> {
+  if process.name.starts_with("tmp-") || process.name.starts_with("init-") {
+    return None;
+  }
+  let state = process.data.state.clone().unwrap_or_default();
+  if !state.running.unwrap_or_default() {
+    return None;
+  }
+  let networks = process
+    .data
+    .network_settings
+    .clone()
+    .unwrap_or_default()
+    .networks
+    .unwrap_or_default();
+  let network = networks.get(&namespace::network_name(namespace))?;
+  let ip_address = network.ip_address.clone().filter(|ip| !ip.is_empty())?;
+  let healthy = !matches!(
+    state.health.and_then(|health| health.status),
+    Some(HealthStatusEnum::STARTING | HealthStatusEnum::UNHEALTHY)
+  );
+  Some(ServiceEndpoint {
+    name: process.name.clone(),
+    node_name: process.node_name.clone(),
+    ip_address,
+    gateway: network.gateway.clone().filter(|ip| !ip.is_empty()),
+    healthy,
+  })
+}
+
+/// Discovery view of a cargo from its instances
+fn from_cargo(cargo: &Cargo, processes: &[Process], all: bool) -> Service {
+  let mut endpoints = processes
+    .iter()
+    .filter_map(|process| endpoint_of(process, &cargo.namespace_name))
+    .filter(|endpoint| all || endpoint.healthy)
+    .collect::<Vec<_>>();
+  endpoints.sort_by(|a, b| a.name.cmp(&b.name));
+  Service {
+    key: cargo.spec.cargo_key.clone(),
+    name: cargo.spec.name.clone(),
+    namespace_name: cargo.namespace_name.clone(),
+    ports: ServicePort::of_container(&cargo.spec.container),
+    endpoints,
+  }
+}
+
+/// Discovery view of a cargo, the processes are read from the store
+/// where they are kept in sync with the docker events of every node
+async fn read_service(
+  cargo: &Cargo,
+  all: bool,
+  state: &SystemState,
+) -> HttpResult<Service> {
+  let processes =
+    ProcessDb::read_by_kind_key(&cargo.spec.cargo_key, None, &state.inner.pool)
+      .await?;
+  Ok(from_cargo(cargo, &processes, all))
+}
+
+/// List the services of the cargoes of a namespace
+pub async fn list(
+  query: &ServiceQuery,
+  state: &SystemState,
+) -> HttpResult<Vec<Service>> {
+  let namespace = utils::key::resolve_nsp(&query.namespace);
+  NamespaceDb::read_by_pk(&namespace, &state.inner.pool).await?;
+  let cargoes =
+    CargoDb::read_by_namespace(&namespace, &state.inner.pool).await?;
+  let all = query.all.unwrap_or_default();
+  let mut services = Vec::new();
+  for cargo in &cargoes {
+    services.push(read_service(cargo, all, state).await?);
+  }
+  Ok(services)
+}
+
+/// Get the service of a cargo by its name
+pub async fn inspect(
+  name: &str,
+  query: &ServiceQuery,
+  state: &SystemState,
+) -> HttpResult<Service> {
+  let namespace = utils::key::resolve_nsp(&query.namespace);
+  let key = utils::key::gen_key(&namespace, name);
+  let cargo = CargoDb::transform_read_by_pk(&key, &state.inner.pool).await?;
+  read_service(&cargo, query.all.unwrap_or_default(), state).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use bollard_next::container::Config;
+  use bollard_next::service::{
+    ContainerInspectResponse, ContainerState, EndpointSettings, Health,
+    HostConfig, NetworkSettings,
+  };
+  use nanocl_stubs::{cargo_spec::CargoSpec, process::ProcessKind};
+
+  fn process(
+    name: &str,
+    ip: &str,
+    health: Option<HealthStatusEnum>,
+  ) -> Process {
+    let endpoint = EndpointSettings {
+      ip_address: Some(ip.to_owned()),
+      gateway: Some("10.1.0.1".to_owned()),
+      ..Default::default()
+    };
+    Process {
+      key: name.to_owned(),
+      created_at: Default::default(),
+      updated_at: Default::default(),
+      name: name.to_owned(),
+      kind: ProcessKind::Cargo,
+      node_name: "node-1".to_owned(),
+      kind_key: "api.prod".to_owned(),
+      data: ContainerInspectResponse {
+        state: Some(ContainerState {
+          running: Some(true),
+          health: health.map(|status| Health {
+            status: Some(status),
+            ..Default::default()
+          }),
+          ..Default::default()
+        }),
+        network_settings: Some(NetworkSettings {
+          networks: Some([("nanocl.prod".to_owned(), endpoint)].into()),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+    }
+  }
+
+  #[test]
+  fn service_of_cargo() {
+    let cargo = Cargo {
+      namespace_name: "prod".to_owned(),
+      spec: CargoSpec {
+        cargo_key: "api.prod".to_owned(),
+        name: "api".to_owned(),
+        container: Config {
+          exposed_ports: Some(
+            [("53/udp".to_owned(), Default::default())].into(),
+          ),
+          host_config: Some(HostConfig {
+            port_bindings: Some(
+              [("80/tcp".to_owned(), None), ("9000".to_owned(), None)].into(),
+            ),
+            ..Default::default()
+          }),
+          ..Default::default()
+        },
+        ..Default::default()
+      },
+      ..Default::default()
+    };
+    let mut stopped = process("api-4.prod.c", "10.1.0.5", None);
+    stopped.data.state = None;
+    let processes = [
+      process("api-2.prod.c", "10.1.0.3", Some(HealthStatusEnum::HEALTHY)),
+      process("api-1.prod.c", "10.1.0.2", None),
+      process(
+        "api-3.prod.c",
+        "10.1.0.4",
+        Some(HealthStatusEnum::UNHEALTHY),
+      ),
+      process("tmp-api-5.prod.c", "10.1.0.6", None),
+      process("init-api-6.prod.c", "10.1.0.7", None),
+      stopped,
+    ];
+    let service = from_cargo(&cargo, &processes, false);
+    let ports = service
+      .ports
+      .iter()
+      .map(|port| (port.port, port.protocol.as_str()))
+      .collect::<Vec<_>>();
+    assert_eq!(ports, [(53, "udp"), (80, "tcp"), (9000, "tcp")]);
+    let ips = service
+      .endpoints
+      .iter()
+      .map(|endpoint| endpoint.ip_address.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(ips, ["10.1.0.2", "10.1.0.3"]);
+    let service = from_cargo(&cargo, &processes, true);
+    assert_eq!(service.endpoints.len(), 3);
+    assert!(!service.endpoints[2].healthy);
+  }
+}
@@ -105,7 +105,11 @@ pub async fn register_namespace(
   let new_nsp = NamespacePartial {
     name: name.to_owned(),
     metadata: None,
+    network: None,
+    quota: None,
+    limit_range: None,
   };
+  utils::container::network::ensure(name, state).await?;
   NamespaceDb::create_from(&new_nsp, &state.inner.pool).await?;
   Ok(())
 }
@@ -3,12 +3,12 @@ use clap::Parser;
 /// Nanocl Controller Daemon DNS
 #[derive(Debug, Parser)]
 pub(crate) struct Cli {
-  /// Path to the config directory
-  #[clap(long)]
-  pub(crate) state_dir: String,
   /// Dns server address to resolve domain name if not existing in local
   #[clap(long)]
   pub(crate) dns: Vec<String>,
+  /// Port of the dns server
+  #[clap(long, default_value = "53")]
+  pub(crate) dns_port: u16,
   /// Server address to listen on (default: unix:///run/nanocl/dns.sock)
   #[clap(long, default_value = "unix:///run/nanocl/dns.sock")]
   pub(crate) host: String,
@@ -1,26 +1,29 @@
+use futures::StreamExt;
 use ntex::rt;
 
 use nanocl_error::io::IoResult;
 
 use nanocl_utils::versioning;
 
-use nanocld_client::stubs::resource_kind::{
-  ResourceKindPartial, ResourceKindSpec,
+use nanocld_client::stubs::{
+  resource_kind::{ResourceKindPartial, ResourceKindSpec},
+  system::{Event, EventActorKind},
 };
 
 use nanocld_client::NanocldClient;
 
-use crate::vars;
+use crate::{nameserver::Nameserver, utils, vars};
 
 async fn ensure_self_config(client: &NanocldClient) -> IoResult<()> {
   let formatted_version = versioning::format_version(vars::VERSION);
   let resource_kind = ResourceKindPartial {
-    name: "ncdns.io/rule".to_owned(),
+    name: vars::RULE_KEY.to_owned(),
     version: format!("v{formatted_version}"),
     metadata: None,
     data: ResourceKindSpec {
       schema: None,
       url: Some("unix:///run/nanocl/dns.sock".to_owned()),
+      conversion: None,
     },
   };
   if client
@@ -34,17 +37,60 @@ async fn ensure_self_config(client: &NanocldClient) -> IoResult<()> {
   Ok(())
 }
 
-async fn r#loop(client: &NanocldClient) {
+/// Update the records of the instances when a cargo or a process changes
+async fn on_event(
+  event: &Event,
+  nameserver: &Nameserver,
+  client: &NanocldClient,
+) -> IoResult<()> {
+  let Some(actor) = &event.actor else {
+    return Ok(());
+  };
+  match actor.kind {
+    // The services of the rules use the port mappings of the cargoes
+    EventActorKind::Cargo => {
+      log::trace!("event::on_event: {} {}", actor.kind, event.action);
+      utils::load_rules(nameserver, client).await?;
+      utils::sync_instances(nameserver, client).await
+    }
+    EventActorKind::Process | EventActorKind::Namespace => {
+      log::trace!("event::on_event: {} {}", actor.kind, event.action);
+      utils::sync_instances(nameserver, client).await
+    }
+    _ => Ok(()),
+  }
+}
+
+async fn r#loop(nameserver: &Nameserver, client: &NanocldClient) {
   loop {
     log::info!("event::loop: subscribing to nanocld events");
     match client.watch_events(None).await {
       Err(err) => {
         log::warn!("event::loop: {err}");
       }
-      Ok(_) => {
+      Ok(mut stream) => {
+        if let Err(err) = ensure_self_config(client).await {
+          log::warn!("event::loop: {err}");
+          continue;
+        }
+        if let Err(err) = utils::load_rules(nameserver, client).await {
+          log::warn!("event::loop: {err}");
+        }
+        if let Err(err) = utils::sync_instances(nameserver, client).await {
+          log::warn!("event::loop: {err}");
+        }
         log::info!("event::loop: subscribed to nanocld events");
-        if ensure_self_config(client).await.is_ok() {
-          break;
+        while let Some(event) = stream.next().await {
+          let event = match event {
+            Err(err) => {
+              log::warn!("event::loop: {err}");
+              continue;
+            }
+            Ok(event) => event,
+          };
+          if let Err(err) = on_event(&event, nameserver, client).await {
+            log::warn!("event::loop: {err}");
+          }
         }
       }
     }
@@ -54,11 +100,12 @@ async fn r#loop(client: &NanocldClient) {
 }
 
 /// Spawn new thread with event loop to watch for nanocld events
-pub(crate) fn spawn(client: &NanocldClient) {
+pub(crate) fn spawn(nameserver: &Nameserver, client: &NanocldClient) {
+  let nameserver = nameserver.clone();
   let client = client.clone();
   rt::Arbiter::new().exec_fn(move || {
     ntex::rt::spawn(async move {
-      r#loop(&client).await;
+      r#loop(&nameserver, &client).await;
       rt::Arbiter::current().stop();
     });
   });
@@ -4,23 +4,22 @@ use nanocl_error::io::IoResult;
 use nanocl_utils::logger;
 
 mod cli;
-mod dnsmasq;
 mod event;
+mod nameserver;
+mod record;
 mod server;
 mod services;
 mod utils;
 mod vars;
+mod zone;
 
 use nanocld_client::NanocldClient;
 
 use cli::Cli;
-use dnsmasq::Dnsmasq;
+use nameserver::Nameserver;
 
 async fn run(cli: &Cli) -> IoResult<()> {
-  // Spawn a new thread to listen events from nanocld
-  let dnsmasq = Dnsmasq::new(&cli.state_dir)
-    .with_dns(cli.dns.clone())
-    .ensure()?;
+  let nameserver = Nameserver::new(&cli.dns, cli.dns_port)?;
   #[allow(unused)]
   let mut client = NanocldClient::connect_with_unix_default();
   #[cfg(any(feature = "dev", feature = "test"))]
@@ -31,8 +30,10 @@ async fn run(cli: &Cli) -> IoResult<()> {
       ..Default::default()
     })?;
   }
-  event::spawn(&client);
-  let server = server::gen(&cli.host, &dnsmasq, &client)?;
+  let server = server::gen(&cli.host, &nameserver, &client)?;
+  nameserver.spawn();
+  // Spawn a new thread to listen events from nanocld
+  event::spawn(&nameserver, &client);
   server.await?;
   Ok(())
 }
@@ -61,15 +62,8 @@ mod tests {
 
   #[ntex::test]
   async fn run_wrong_host() -> IoResult<()> {
-    let cli = Cli::parse_from([
-      "ncdns",
-      "--host",
-      "wrong://dadas",
-      "--state-dir",
-      "/tmp/ncdns",
-      "--dns",
-      "1.1.1.1",
-    ]);
+    let cli =
+      Cli::parse_from(["ncdns", "--host", "wrong://dadas", "--dns", "1.1.1.1"]);
     let server = run(&cli).await;
     assert!(server.is_err());
     Ok(())
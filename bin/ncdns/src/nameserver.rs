@@ -0,0 +1,394 @@
+use std::{
+  collections::HashSet,
+  net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+  rc::Rc,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use futures::{channel::mpsc, StreamExt};
+use hickory_proto::op::{Edns, Message, MessageType, OpCode, ResponseCode};
+use ntex::rt;
+use tokio::{
+  io::{AsyncReadExt, AsyncWriteExt},
+  net::{TcpListener, TcpStream, UdpSocket},
+};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+
+use crate::zone::{Lookup, Zone};
+
+/// Time to wait for the answer of an upstream server
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Time to wait for a query on an idle tcp connection
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum size of an udp response advertised with EDNS
+const EDNS_MAX_PAYLOAD: u16 = 1232;
+
+/// Transport of a query, the forwarded query uses the same one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+  Udp,
+  Tcp,
+}
+
+/// Embedded dns server answering from the zone
+/// and forwarding the other queries to the upstream servers
+#[derive(Clone)]
+pub struct Nameserver {
+  pub(crate) zone: Zone,
+  port: u16,
+  upstreams: Arc<Vec<SocketAddr>>,
+  /// Addresses the server listens on or is about to
+  listening: Arc<Mutex<HashSet<IpAddr>>>,
+  sender: mpsc::UnboundedSender<IpAddr>,
+  receiver: Arc<Mutex<Option<mpsc::UnboundedReceiver<IpAddr>>>>,
+}
+
+/// Parse the address of an upstream server, the port defaults to 53
+fn parse_upstream(upstream: &str) -> IoResult<SocketAddr> {
+  if let Ok(addr) = upstream.parse::<SocketAddr>() {
+    return Ok(addr);
+  }
+  let ip = upstream.parse::<IpAddr>().map_err(|_| {
+    IoError::invalid_input(
+      "Dns",
+      &format!("{upstream} is not a valid ip address"),
+    )
+  })?;
+  Ok(SocketAddr::new(ip, 53))
+}
+
+impl Nameserver {
+  /// Create a new nameserver, it doesn't listen until spawned
+  pub(crate) fn new(upstreams: &[String], port: u16) -> IoResult<Self> {
+    let upstreams = upstreams
+      .iter()
+      .map(|upstream| parse_upstream(upstream))
+      .collect::<IoResult<Vec<_>>>()?;
+    let (sender, receiver) = mpsc::unbounded();
+    Ok(Self {
+      zone: Zone::default(),
+      port,
+      upstreams: Arc::new(upstreams),
+      listening: Default::default(),
+      sender,
+      receiver: Arc::new(Mutex::new(Some(receiver))),
+    })
+  }
+
+  /// Listen on an address, nothing is done if it's already the case
+  pub(crate) fn listen(&self, ip: IpAddr) {
+    if !self.listening.lock().unwrap().insert(ip) {
+      return;
+    }
+    let _ = self.sender.unbounded_send(ip);
+  }
+
+  /// Spawn a new thread binding the requested addresses
+  pub(crate) fn spawn(&self) {
+    let Some(mut receiver) = self.receiver.lock().unwrap().take() else {
+      return;
+    };
+    let nameserver = self.clone();
+    rt::Arbiter::new().exec_fn(move || {
+      rt::spawn(async move {
+        while let Some(ip) = receiver.next().await {
+          let addr = SocketAddr::new(ip, nameserver.port);
+          if let Err(err) = nameserver.bind(addr).await {
+            log::warn!("nameserver::spawn: {err}");
+            // Allow a new attempt when the address is requested again
+            nameserver.listening.lock().unwrap().remove(&ip);
+          }
+        }
+      });
+    });
+  }
+
+  /// Bind the udp and tcp sockets of an address and serve them
+  async fn bind(&self, addr: SocketAddr) -> IoResult<()> {
+    let udp = UdpSocket::bind(addr)
+      .await
+      .map_err(|err| err.map_err_context(|| format!("udp {addr}")))?;
+    let tcp = TcpListener::bind(addr)
+      .await
+      .map_err(|err| err.map_err_context(|| format!("tcp {addr}")))?;
+    log::info!("nameserver::bind: listening on {addr}");
+    rt::spawn(self.clone().serve_udp(Rc::new(udp)));
+    rt::spawn(self.clone().serve_tcp(tcp));
+    Ok(())
+  }
+
+  async fn serve_udp(self, socket: Rc<UdpSocket>) {
+    let mut buf = vec![0; 4096];
+    loop {
+      let (size, peer) = match socket.recv_from(&mut buf).await {
+        Err(err) => {
+          log::warn!("nameserver::serve_udp: {err}");
+          continue;
+        }
+        Ok(res) => res,
+      };
+      let query = buf[..size].to_vec();
+      let socket = Rc::clone(&socket);
+      let nameserver = self.clone();
+      rt::spawn(async move {
+        let Some(response) = nameserver.handle(&query, Protocol::Udp).await
+        else {
+          return;
+        };
+        if let Err(err) = socket.send_to(&response, peer).await {
+          log::warn!("nameserver::serve_udp: {peer} {err}");
+        }
+      });
+    }
+  }
+
+  async fn serve_tcp(self, listener: TcpListener) {
+    loop {
+      let (stream, peer) = match listener.accept().await {
+        Err(err) => {
+          log::warn!("nameserver::serve_tcp: {err}");
+          continue;
+        }
+        Ok(res) => res,
+      };
+      let nameserver = self.clone();
+      rt::spawn(async move {
+        if let Err(err) = nameserver.serve_connection(stream).await {
+          log::debug!("nameserver::serve_tcp: {peer} {err}");
+        }
+      });
+    }
+  }
+
+  /// Answer the queries of a tcp connection until it's closed or idle
+  async fn serve_connection(&self, mut stream: TcpStream) -> IoResult<()> {
+    loop {
+      let Ok(query) =
+        ntex::time::timeout(TCP_IDLE_TIMEOUT, read_frame(&mut stream)).await
+      else {
+        return Ok(());
+      };
+      let Some(query) = query? else {
+        return Ok(());
+      };
+      let Some(response) = self.handle(&query, Protocol::Tcp).await else {
+        return Ok(());
+      };
+      write_frame(&mut stream, &response).await?;
+    }
+  }
+
+  /// Answer a query from the zone or forward it to the upstream servers,
+  /// None when the query can't be parsed
+  pub(crate) async fn handle(
+    &self,
+    data: &[u8],
+    protocol: Protocol,
+  ) -> Option<Vec<u8>> {
+    let request = Message::from_vec(data).ok()?;
+    if request.message_type() != MessageType::Query {
+      return None;
+    }
+    if request.op_code() != OpCode::Query {
+      return error_response(&request, ResponseCode::NotImp);
+    }
+    let Some(query) = request.query() else {
+      return error_response(&request, ResponseCode::FormErr);
+    };
+    log::trace!(
+      "nameserver::handle: {} {}",
+      query.name(),
+      query.query_type()
+    );
+    let (code, answers) =
+      match self.zone.lookup(query.name(), query.query_type()) {
+        Lookup::Found(records) => (ResponseCode::NoError, records),
+        Lookup::NoData => (ResponseCode::NoError, vec![]),
+        Lookup::NxDomain => (ResponseCode::NXDomain, vec![]),
+        Lookup::NotFound => {
+          return match self.forward(data, protocol).await {
+            Ok(response) => Some(response),
+            Err(err) => {
+              log::warn!("nameserver::handle: {} {err}", query.name());
+              error_response(&request, ResponseCode::ServFail)
+            }
+          };
+        }
+      };
+    let mut response = response_for(&request, code);
+    response.set_authoritative(true).add_answers(answers);
+    let bytes = response.to_vec().ok()?;
+    if protocol == Protocol::Udp && bytes.len() > request.max_payload() as usize
+    {
+      return response.truncate().to_vec().ok();
+    }
+    Some(bytes)
+  }
+
+  /// Send a query to the upstream servers and return the first answer
+  async fn forward(
+    &self,
+    data: &[u8],
+    protocol: Protocol,
+  ) -> IoResult<Vec<u8>> {
+    if self.upstreams.is_empty() {
+      return Err(IoError::not_found("Dns", "No upstream server configured"));
+    }
+    let mut last_err = None;
+    for upstream in self.upstreams.iter() {
+      let res = match protocol {
+        Protocol::Udp => {
+          ntex::time::timeout(FORWARD_TIMEOUT, forward_udp(upstream, data))
+            .await
+        }
+        Protocol::Tcp => {
+          ntex::time::timeout(FORWARD_TIMEOUT, forward_tcp(upstream, data))
+            .await
+        }
+      };
+      match res {
+        Ok(Ok(response)) => return Ok(response),
+        Ok(Err(err)) => last_err = Some(err),
+        Err(_) => {
+          last_err = Some(IoError::other(
+            "Dns",
+            &format!("{upstream} didn't answer in time"),
+          ))
+        }
+      }
+    }
+    Err(last_err.unwrap_or_else(|| IoError::other("Dns", "No answer")))
+  }
+}
+
+/// Response header matching a request
+fn response_for(request: &Message, code: ResponseCode) -> Message {
+  let mut response = Message::new();
+  response
+    .set_id(request.id())
+    .set_message_type(MessageType::Response)
+    .set_op_code(request.op_code())
+    .set_recursion_desired(request.recursion_desired())
+    .set_recursion_available(true)
+    .set_response_code(code)
+    .add_queries(request.queries().to_vec());
+  if request.extensions().is_some() {
+    let mut edns = Edns::new();
+    edns.set_max_payload(EDNS_MAX_PAYLOAD);
+    response.set_edns(edns);
+  }
+  response
+}
+
+fn error_response(request: &Message, code: ResponseCode) -> Option<Vec<u8>> {
+  response_for(request, code).to_vec().ok()
+}
+
+async fn forward_udp(upstream: &SocketAddr, data: &[u8]) -> IoResult<Vec<u8>> {
+  let local: IpAddr = match upstream {
+    SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+    SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+  };
+  let socket = UdpSocket::bind(SocketAddr::new(local, 0)).await?;
+  socket.connect(upstream).await?;
+  socket.send(data).await?;
+  let mut buf = vec![0; 4096];
+  loop {
+    let size = socket.recv(&mut buf).await?;
+    // Ignore the answers that don't belong to the query
+    if size >= 2 && data.len() >= 2 && buf[..2] == data[..2] {
+      return Ok(buf[..size].to_vec());
+    }
+  }
+}
+
+async fn forward_tcp(upstream: &SocketAddr, data: &[u8]) -> IoResult<Vec<u8>> {
+  let mut stream = TcpStream::connect(upstream).await?;
+  write_frame(&mut stream, data).await?;
+  read_frame(&mut stream)
+    .await?
+    .ok_or_else(|| IoError::other("Dns", "Connection closed by upstream"))
+}
+
+/// Read a length prefixed message, None when the connection is closed
+async fn read_frame(stream: &mut TcpStream) -> IoResult<Option<Vec<u8>>> {
+  let mut len = [0; 2];
+  if stream.read_exact(&mut len).await.is_err() {
+    return Ok(None);
+  }
+  let mut data = vec![0; u16::from_be_bytes(len) as usize];
+  stream.read_exact(&mut data).await?;
+  Ok(Some(data))
+}
+
+/// Write a length prefixed message
+async fn write_frame(stream: &mut TcpStream, data: &[u8]) -> IoResult<()> {
+  let len = u16::try_from(data.len())
+    .map_err(|_| IoError::invalid_data("Dns", "Message too large"))?;
+  stream.write_all(&len.to_be_bytes()).await?;
+  stream.write_all(data).await?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use hickory_proto::{
+    op::Query,
+    rr::{Name, RecordType},
+  };
+
+  fn query(name: &str, record_type: RecordType) -> Vec<u8> {
+    let mut message = Message::new();
+    message
+      .set_id(42)
+      .set_recursion_desired(true)
+      .add_query(Query::query(Name::from_ascii(name).unwrap(), record_type));
+    message.to_vec().unwrap()
+  }
+
+  #[ntex::test]
+  async fn answer_from_zone() {
+    let nameserver = Nameserver::new(&[], 53).unwrap();
+    let name = Zone::parse_name("app.test").unwrap();
+    let ip = "10.0.0.2".parse().unwrap();
+    nameserver
+      .zone
+      .set_rule("test", vec![Zone::address_record(&name, &ip, 30)]);
+    let data = query("app.test.", RecordType::A);
+    let response = nameserver.handle(&data, Protocol::Udp).await.unwrap();
+    let response = Message::from_vec(&response).unwrap();
+    assert_eq!(response.id(), 42);
+    assert!(response.authoritative());
+    assert_eq!(response.response_code(), ResponseCode::NoError);
+    assert_eq!(response.answers().len(), 1);
+    let data = query("missing.app.global.nanocl.internal.", RecordType::A);
+    let response = nameserver.handle(&data, Protocol::Tcp).await.unwrap();
+    let response = Message::from_vec(&response).unwrap();
+    assert_eq!(response.response_code(), ResponseCode::NXDomain);
+    // Without upstream servers the other names can't be resolved
+    let data = query("example.com.", RecordType::A);
+    let response = nameserver.handle(&data, Protocol::Udp).await.unwrap();
+    let response = Message::from_vec(&response).unwrap();
+    assert_eq!(response.response_code(), ResponseCode::ServFail);
+    assert!(nameserver.handle(&[0, 1], Protocol::Udp).await.is_none());
+  }
+
+  #[test]
+  fn upstreams() {
+    assert_eq!(
+      parse_upstream("1.1.1.1").unwrap(),
+      "1.1.1.1:53".parse().unwrap()
+    );
+    assert_eq!(
+      parse_upstream("10.0.0.1:5353").unwrap(),
+      "10.0.0.1:5353".parse().unwrap()
+    );
+    assert!(parse_upstream("dns.example").is_err());
+  }
+}
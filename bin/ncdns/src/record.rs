@@ -0,0 +1,303 @@
+use std::collections::{BTreeSet, HashSet};
+
+use hickory_proto::rr::{rdata, Name, RData, Record, RecordType};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+
+use nanocld_client::bollard_next::container::Config;
+use nanocld_client::stubs::dns::{DnsEntry, DnsSrv};
+use nanocld_client::stubs::service::ServicePort;
+use nanocld_client::NanocldClient;
+
+use crate::{
+  utils,
+  zone::{Zone, DEFAULT_TTL},
+};
+
+/// Preference of a mail exchange without one
+const DEFAULT_MX_PREFERENCE: u16 = 10;
+
+/// Maximum length of a string of a TXT record
+const MAX_TXT_LEN: usize = 255;
+
+/// Split a cargo key `<name>.<namespace>`, the namespace defaults to global
+fn split_cargo_key(key: &str) -> (&str, &str) {
+  key.rsplit_once('.').unwrap_or((key, "global"))
+}
+
+/// Protocol of a service name `_<service>._<protocol>.<domain>`
+fn srv_protocol(name: &Name) -> Option<String> {
+  let label = name.iter().nth(1)?;
+  let label = String::from_utf8_lossy(label).to_lowercase();
+  label.strip_prefix('_').map(|protocol| protocol.to_owned())
+}
+
+/// Container ports of the port mappings and the exposed ports of a cargo
+/// matching a protocol, tcp when a port doesn't specify one
+fn mapped_ports(container: &Config, protocol: Option<&str>) -> Vec<u16> {
+  let ports = ServicePort::of_container(container)
+    .into_iter()
+    .filter(|port| protocol.map_or(true, |protocol| protocol == port.protocol))
+    .map(|port| port.port)
+    .collect::<BTreeSet<_>>();
+  ports.into_iter().collect()
+}
+
+/// Records of a service, one for each port of its cargo without a fixed port
+async fn srv_records(
+  name: &Name,
+  srv: &DnsSrv,
+  ttl: u32,
+  client: &NanocldClient,
+) -> IoResult<Vec<Record>> {
+  let target = match (&srv.target, &srv.cargo) {
+    (Some(target), _) => Zone::parse_name(target)?,
+    (None, Some(cargo)) => {
+      let (cargo, namespace) = split_cargo_key(cargo);
+      Zone::instance_name(&format!("{cargo}.{namespace}"))?
+    }
+    (None, None) => {
+      return Err(IoError::invalid_input(
+        "Srv",
+        &format!("{name} requires a Target or a Cargo"),
+      ))
+    }
+  };
+  let ports = match (srv.port, &srv.cargo) {
+    (Some(port), _) => vec![port],
+    (None, Some(key)) => {
+      let (cargo, namespace) = split_cargo_key(key);
+      let cargo = client
+        .inspect_cargo(cargo, Some(namespace))
+        .await
+        .map_err(|err| err.map_err_context(|| format!("Srv {name}")))?;
+      let protocol = srv_protocol(name);
+      let ports = mapped_ports(&cargo.spec.container, protocol.as_deref());
+      if ports.is_empty() {
+        return Err(IoError::invalid_input(
+          "Srv",
+          &format!("Cargo {key} has no port mapping for {name}"),
+        ));
+      }
+      ports
+    }
+    (None, None) => {
+      return Err(IoError::invalid_input(
+        "Srv",
+        &format!("{name} requires a Port or a Cargo"),
+      ))
+    }
+  };
+  let records = ports
+    .into_iter()
+    .map(|port| {
+      let rdata = RData::SRV(rdata::SRV::new(
+        srv.priority.unwrap_or_default(),
+        srv.weight.unwrap_or_default(),
+        port,
+        target.clone(),
+      ));
+      Record::from_rdata(name.clone(), ttl, rdata)
+    })
+    .collect();
+  Ok(records)
+}
+
+/// Records of an entry of a dns rule
+pub(crate) async fn from_entry(
+  entry: &DnsEntry,
+  client: &NanocldClient,
+) -> IoResult<Vec<Record>> {
+  let name = Zone::parse_name(&entry.name)?;
+  let ttl = entry.ttl.unwrap_or(DEFAULT_TTL);
+  let kinds = [
+    entry.ip_address.is_some(),
+    entry.ipv4.is_some(),
+    entry.ipv6.is_some(),
+    entry.cname.is_some(),
+    entry.txt.is_some(),
+    entry.srv.is_some(),
+    entry.mx.is_some(),
+  ];
+  if kinds.iter().filter(|kind| **kind).count() != 1 {
+    return Err(IoError::invalid_input(
+      "DnsEntry",
+      &format!(
+        "{name} must define exactly one of IpAddress, Ipv4, Ipv6, Cname, Txt, Srv or Mx"
+      ),
+    ));
+  }
+  if let Some(network) = &entry.ip_address {
+    let ip = utils::get_entry_addr(network, client).await?;
+    return Ok(vec![Zone::address_record(&name, &ip, ttl)]);
+  }
+  if let Some(srv) = &entry.srv {
+    return srv_records(&name, srv, ttl, client).await;
+  }
+  let rdata = if let Some(ip) = entry.ipv4 {
+    RData::A(rdata::A(ip))
+  } else if let Some(ip) = entry.ipv6 {
+    RData::AAAA(rdata::AAAA(ip))
+  } else if let Some(target) = &entry.cname {
+    RData::CNAME(rdata::CNAME(Zone::parse_name(target)?))
+  } else if let Some(txt) = &entry.txt {
+    if let Some(data) = txt.iter().find(|data| data.len() > MAX_TXT_LEN) {
+      return Err(IoError::invalid_input(
+        "Txt",
+        &format!("{name} string is longer than {MAX_TXT_LEN} bytes: {data}"),
+      ));
+    }
+    RData::TXT(rdata::TXT::new(txt.clone()))
+  } else if let Some(mx) = &entry.mx {
+    RData::MX(rdata::MX::new(
+      mx.preference.unwrap_or(DEFAULT_MX_PREFERENCE),
+      Zone::parse_name(&mx.exchange)?,
+    ))
+  } else {
+    unreachable!("the kind of the entry is checked above")
+  };
+  Ok(vec![Record::from_rdata(name, ttl, rdata)])
+}
+
+/// Ensure an alias isn't declared with other records for the same name
+pub(crate) fn validate(records: &[Record]) -> IoResult<()> {
+  let mut aliases = HashSet::new();
+  for record in records {
+    if record.record_type() == RecordType::CNAME
+      && !aliases.insert(record.name())
+    {
+      return Err(IoError::invalid_input(
+        "Cname",
+        &format!("{} has more than one alias", record.name()),
+      ));
+    }
+  }
+  for record in records {
+    if record.record_type() != RecordType::CNAME
+      && aliases.contains(record.name())
+    {
+      return Err(IoError::invalid_input(
+        "Cname",
+        &format!("{} cannot have other records than its alias", record.name()),
+      ));
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use nanocld_client::bollard_next::service::HostConfig;
+  use nanocld_client::stubs::dns::DnsMx;
+  use nanocld_client::ConnectOpts;
+
+  fn client() -> NanocldClient {
+    NanocldClient::connect_to(&ConnectOpts {
+      url: "http://nanocl.internal:8585".into(),
+      ..Default::default()
+    })
+    .unwrap()
+  }
+
+  #[test]
+  fn ports_of_cargo() {
+    let container = Config {
+      exposed_ports: Some([("53/udp".to_owned(), Default::default())].into()),
+      host_config: Some(HostConfig {
+        port_bindings: Some(
+          [
+            ("80/tcp".to_owned(), None),
+            ("443".to_owned(), None),
+            ("53/udp".to_owned(), None),
+          ]
+          .into(),
+        ),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    assert_eq!(mapped_ports(&container, Some("tcp")), [80, 443]);
+    assert_eq!(mapped_ports(&container, Some("udp")), [53]);
+    assert_eq!(mapped_ports(&container, None), [53, 80, 443]);
+    let name = Zone::parse_name("_http._tcp.app.test").unwrap();
+    assert_eq!(srv_protocol(&name).as_deref(), Some("tcp"));
+    let name = Zone::parse_name("app.test").unwrap();
+    assert_eq!(srv_protocol(&name), None);
+    assert_eq!(split_cargo_key("app.prod"), ("app", "prod"));
+    assert_eq!(split_cargo_key("app"), ("app", "global"));
+  }
+
+  #[ntex::test]
+  async fn entries() {
+    let client = client();
+    let entry = DnsEntry {
+      name: "mail.test".to_owned(),
+      mx: Some(DnsMx {
+        exchange: "mx.test".to_owned(),
+        preference: None,
+      }),
+      ttl: Some(300),
+      ..Default::default()
+    };
+    let records = from_entry(&entry, &client).await.unwrap();
+    assert_eq!(records[0].record_type(), RecordType::MX);
+    assert_eq!(records[0].ttl(), 300);
+    let entry = DnsEntry {
+      name: "_http._tcp.app.test".to_owned(),
+      srv: Some(DnsSrv {
+        target: Some("app.test".to_owned()),
+        port: Some(8080),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let records = from_entry(&entry, &client).await.unwrap();
+    assert_eq!(records[0].record_type(), RecordType::SRV);
+    let entry = DnsEntry {
+      name: "v6.test".to_owned(),
+      ipv6: Some("::1".parse().unwrap()),
+      ..Default::default()
+    };
+    let records = from_entry(&entry, &client).await.unwrap();
+    assert_eq!(records[0].record_type(), RecordType::AAAA);
+    let entry = DnsEntry {
+      name: "both.test".to_owned(),
+      ipv6: Some("::1".parse().unwrap()),
+      cname: Some("app.test".to_owned()),
+      ..Default::default()
+    };
+    assert!(from_entry(&entry, &client).await.is_err());
+    let entry = DnsEntry {
+      name: "none.test".to_owned(),
+      ..Default::default()
+    };
+    assert!(from_entry(&entry, &client).await.is_err());
+    let entry = DnsEntry {
+      name: "txt.test".to_owned(),
+      txt: Some(vec!["a".repeat(256)]),
+      ..Default::default()
+    };
+    assert!(from_entry(&entry, &client).await.is_err());
+  }
+
+  #[ntex::test]
+  async fn alias_conflicts() {
+    let client = client();
+    let alias = DnsEntry {
+      name: "www.test".to_owned(),
+      cname: Some("app.test".to_owned()),
+      ..Default::default()
+    };
+    let txt = DnsEntry {
+      name: "www.test".to_owned(),
+      txt: Some(vec!["hello".to_owned()]),
+      ..Default::default()
+    };
+    let mut records = from_entry(&alias, &client).await.unwrap();
+    validate(&records).unwrap();
+    records.extend(from_entry(&txt, &client).await.unwrap());
+    assert!(validate(&records).is_err());
+  }
+}
@@ -4,19 +4,19 @@ use nanocl_error::io::{IoError, IoResult};
 use nanocl_utils::ntex::middlewares;
 use nanocld_client::NanocldClient;
 
-use crate::dnsmasq::Dnsmasq;
+use crate::nameserver::Nameserver;
 use crate::services;
 
 pub fn gen(
   host: &str,
-  dnsmasq: &Dnsmasq,
+  nameserver: &Nameserver,
   client: &NanocldClient,
 ) -> IoResult<ntex::server::Server> {
-  let dnsmasq = dnsmasq.clone();
+  let nameserver = nameserver.clone();
   let client = client.clone();
   let mut server = web::HttpServer::new(move || {
     web::App::new()
-      .state(dnsmasq.clone())
+      .state(nameserver.clone())
       .state(client.clone())
       .wrap(middlewares::SerializeError)
       .configure(services::ntex_config)
@@ -51,32 +51,31 @@ pub fn gen(
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::dnsmasq::Dnsmasq;
   use nanocl_error::io::IoResult;
   use nanocld_client::ConnectOpts;
 
   #[ntex::test]
   async fn generate_unix_and_tcp() -> IoResult<()> {
-    let dnsmasq = Dnsmasq::new("/tmp/ncdns");
+    let nameserver = Nameserver::new(&[], 53)?;
     let client = NanocldClient::connect_to(&ConnectOpts {
       url: "http://nanocl.internal:8585".into(),
       ..Default::default()
     })?;
-    let server = gen("unix:///tmp/ncdns.sock", &dnsmasq, &client)?;
+    let server = gen("unix:///tmp/ncdns.sock", &nameserver, &client)?;
     server.stop(true).await;
-    let server = gen("tcp://0.0.0.0:9987", &dnsmasq, &client)?;
+    let server = gen("tcp://0.0.0.0:9987", &nameserver, &client)?;
     server.stop(true).await;
     Ok(())
   }
 
   #[test]
   fn generate_wrong_host() -> IoResult<()> {
-    let dnsmasq = Dnsmasq::new("/tmp/ncdns");
+    let nameserver = Nameserver::new(&[], 53)?;
     let client = NanocldClient::connect_to(&ConnectOpts {
       url: "http://nanocl.internal:8585".into(),
       ..Default::default()
     })?;
-    let server = gen("wrong://dsadsa", &dnsmasq, &client);
+    let server = gen("wrong://dsadsa", &nameserver, &client);
     assert!(server.is_err());
     Ok(())
   }
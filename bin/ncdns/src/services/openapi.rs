@@ -1,6 +1,6 @@
 use utoipa::OpenApi;
 
-use nanocld_client::stubs::dns::{DnsEntry, ResourceDnsRule};
+use nanocld_client::stubs::dns::{DnsEntry, DnsMx, DnsSrv, ResourceDnsRule};
 
 use super::rule;
 
@@ -36,6 +36,8 @@ impl utoipa::Modify for VersionModifier {
   components(schemas(
     ResourceDnsRule,
     DnsEntry,
+    DnsSrv,
+    DnsMx,
   )),
   tags(
     (name = "Rules", description = "Rules management endpoints."),
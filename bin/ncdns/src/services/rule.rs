@@ -2,10 +2,16 @@ use ntex::web;
 
 use nanocl_error::http::HttpError;
 
-use nanocld_client::stubs::dns::ResourceDnsRule;
+use nanocld_client::stubs::{dns::ResourceDnsRule, generic::GenericNspQuery};
 use nanocld_client::NanocldClient;
 
-use crate::{dnsmasq, utils};
+use crate::{nameserver::Nameserver, utils};
+
+/// Key of the rule `<name>.<namespace>` like the key of its resource
+fn rule_key(name: &str, qs: &GenericNspQuery) -> String {
+  let namespace = qs.namespace.as_deref().unwrap_or("global");
+  format!("{name}.{namespace}")
+}
 
 /// Create/Update a new DnsRule
 #[cfg_attr(feature = "dev", utoipa::path(
@@ -15,6 +21,7 @@ use crate::{dnsmasq, utils};
   request_body = ResourceDnsRule,
   params(
     ("name" = String, Path, description = "Name of the rule"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the rule default to 'global'"),
   ),
   responses(
     (status = 200, description = "The created rule", body = ResourceDnsRule),
@@ -24,40 +31,40 @@ use crate::{dnsmasq, utils};
 pub(crate) async fn apply_rule(
   // To follow the ressource service convention, we have to use a tuple
   client: web::types::State<NanocldClient>,
-  dnsmasq: web::types::State<dnsmasq::Dnsmasq>,
+  nameserver: web::types::State<Nameserver>,
   path: web::types::Path<(String, String)>,
   payload: web::types::Json<ResourceDnsRule>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> Result<web::HttpResponse, HttpError> {
-  utils::update_entries(&path.1, &payload, &dnsmasq, &client).await?;
-  utils::reload_service(&client).await?;
+  let key = rule_key(&path.1, &qs);
+  utils::apply_rule(&key, &payload, &nameserver, &client).await?;
   Ok(web::HttpResponse::Ok().json(&payload.into_inner()))
 }
 
-/// Delete a ProxyRule
+/// Delete a DnsRule
 #[cfg_attr(feature = "dev", utoipa::path(
   delete,
   tag = "Rules",
   path = "/rules/{name}",
   params(
     ("name" = String, Path, description = "Name of the rule"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the rule default to 'global'"),
   ),
   responses(
     (status = 200, description = "Rule has been deleted"),
+    (status = 404, description = "Rule does not exist"),
   ),
 ))]
 #[web::delete("/rules/{name}")]
 pub(crate) async fn remove_rule(
-  client: web::types::State<NanocldClient>,
-  dnsmasq: web::types::State<dnsmasq::Dnsmasq>,
+  nameserver: web::types::State<Nameserver>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> Result<web::HttpResponse, HttpError> {
-  let rule = client.inspect_resource(&path.1).await?;
-  let dns_rule = serde_json::from_value::<ResourceDnsRule>(rule.spec.data)
-    .map_err(|err| {
-      HttpError::bad_request(format!("Unable to serialize the DnsRule: {err}"))
-    })?;
-  utils::remove_entries(&dns_rule, &dnsmasq, &client).await?;
-  utils::reload_service(&client).await?;
+  let key = rule_key(&path.1, &qs);
+  if !nameserver.zone.remove_rule(&key) {
+    return Err(HttpError::not_found(format!("DnsRule {key} not found")));
+  }
   Ok(web::HttpResponse::Ok().finish())
 }
 
@@ -1,12 +1,21 @@
+use std::net::IpAddr;
+
+use hickory_proto::rr::{rdata, RData, Record};
+
 use nanocl_error::io::{FromIo, IoError, IoResult};
 
 use nanocld_client::stubs::dns::ResourceDnsRule;
 use nanocld_client::stubs::generic::{
-  GenericClause, GenericFilter, NetworkKind,
+  GenericClause, GenericFilter, GenericFilterNsp, NetworkKind,
 };
+use nanocld_client::stubs::service::{Service, ServiceQuery};
 use nanocld_client::NanocldClient;
 
-use crate::dnsmasq::Dnsmasq;
+use crate::{
+  nameserver::Nameserver,
+  record, vars,
+  zone::{Zone, DEFAULT_TTL},
+};
 
 /// Get public address of host
 async fn get_host_addr(client: &NanocldClient) -> IoResult<String> {
@@ -53,100 +62,159 @@ async fn get_network_addr(
   Ok(addr)
 }
 
-/// Reload the dns service
-/// TODO: use a better way to reload the service, we may have to move from dnsmasq to something else
-pub(crate) async fn reload_service(client: &NanocldClient) -> IoResult<()> {
-  client
-    .restart_process("cargo", "ndns", Some("system"))
-    .await?;
-  Ok(())
+/// Address of an entry
+pub(crate) async fn get_entry_addr(
+  network: &NetworkKind,
+  client: &NanocldClient,
+) -> IoResult<IpAddr> {
+  let addr = match network {
+    NetworkKind::Other(ip) => return Ok(*ip),
+    NetworkKind::All => {
+      return Err(IoError::invalid_input(
+        "Network",
+        "All network is not supported",
+      ))
+    }
+    _ => get_network_addr(network, client).await?,
+  };
+  parse_ip(&addr)
 }
 
-pub(crate) async fn update_entries(
+fn parse_ip(addr: &str) -> IoResult<IpAddr> {
+  addr.parse::<IpAddr>().map_err(|_| {
+    IoError::invalid_data("Network", &format!("{addr} is not a valid ip"))
+  })
+}
+
+/// Serve the records of a dns rule and listen on its network
+pub(crate) async fn apply_rule(
   key: &str,
   dns_rule: &ResourceDnsRule,
-  dnsmasq: &Dnsmasq,
+  nameserver: &Nameserver,
+  client: &NanocldClient,
+) -> IoResult<()> {
+  let listen_address = match &dns_rule.network {
+    NetworkKind::Other(ip) => *ip,
+    network => parse_ip(&get_network_addr(network, client).await?)?,
+  };
+  let mut records = Vec::new();
+  for entry in &dns_rule.entries {
+    let entry_records = record::from_entry(entry, client).await?;
+    for record in &entry_records {
+      log::debug!("utils::apply_rule: {record}");
+    }
+    records.extend(entry_records);
+  }
+  record::validate(&records)?;
+  nameserver.zone.set_rule(key, records);
+  nameserver.listen(listen_address);
+  Ok(())
+}
+
+/// Serve the dns rules stored by nanocld
+pub(crate) async fn load_rules(
+  nameserver: &Nameserver,
   client: &NanocldClient,
 ) -> IoResult<()> {
   let filter = GenericFilter::new()
-    .r#where("kind", GenericClause::Eq("DnsRule".to_owned()))
-    .r#where("key", GenericClause::Ne(key.to_owned()))
-    .r#where(
-      "data",
-      GenericClause::Contains(
-        serde_json::json!({ "Network": dns_rule.network }),
-      ),
-    );
-  let resources = client.list_resource(Some(&filter)).await.map_err(|err| {
+    .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()));
+  let query = GenericFilterNsp {
+    filter: Some(filter),
+    namespace: None,
+  };
+  let resources = client.list_resource(Some(&query)).await.map_err(|err| {
     err.map_err_context(|| "Unable to list resources from nanocl daemon")
   })?;
-  log::debug!("utils::update_entries: {} resources", resources.len());
-  let mut entries = dns_rule.entries.clone();
   for resource in resources {
-    let mut dns_rule = serde_json::from_value::<ResourceDnsRule>(
-      resource.spec.data,
-    )
-    .map_err(|err| err.map_err_context(|| "Unable to serialize the DnsRule"))?;
-    entries.append(&mut dns_rule.entries);
-  }
-  let listen_address = get_network_addr(&dns_rule.network, client).await?;
-  let mut file_content =
-    format!("bind-dynamic\nlisten-address={listen_address}\n");
-  for entry in &entries {
-    let ip_address = match &entry.ip_address {
-      NetworkKind::Local => "127.0.0.1".to_owned(),
-      NetworkKind::Public => get_host_addr(client).await?,
-      NetworkKind::Internal => get_bridge_addr(client).await?,
-      NetworkKind::Other(ip) => ip.to_string(),
-      NetworkKind::All => {
-        return Err(IoError::invalid_input(
-          "Network",
-          "All network is not supported",
-        ))
-      }
-    };
-    let entry = &format!("address=/{}/{}", entry.name, ip_address);
-    file_content += &format!("{entry}\n");
-    log::debug!("utils::update_entries: {entry}");
+    let dns_rule =
+      match serde_json::from_value::<ResourceDnsRule>(resource.spec.data) {
+        Err(err) => {
+          log::warn!("utils::load_rules: {} {err}", resource.spec.resource_key);
+          continue;
+        }
+        Ok(dns_rule) => dns_rule,
+      };
+    let key = &resource.spec.resource_key;
+    if let Err(err) = apply_rule(key, &dns_rule, nameserver, client).await {
+      log::warn!("utils::load_rules: {key} {err}");
+    }
   }
-  dnsmasq
-    .write_config(&dns_rule.network.to_string(), &file_content)
-    .await?;
   Ok(())
 }
 
-pub(crate) async fn remove_entries(
-  dns_rule: &ResourceDnsRule,
-  dnsmasq: &Dnsmasq,
-  client: &NanocldClient,
-) -> IoResult<()> {
-  let content = dnsmasq.read_config(&dns_rule.network.to_string()).await?;
-  log::debug!("{}", content);
-  let mut file_content = String::new();
-  let lines = content.lines();
-  let listen_address = get_network_addr(&dns_rule.network, client).await?;
-  let empty_entries = format!("listen-address={listen_address}\n");
-  for line in lines {
-    let mut found = false;
-    for entry in &dns_rule.entries {
-      if line.starts_with(&format!("address=/{}/", entry.name)) {
-        found = true;
-        log::debug!("Found {}", line);
-        break;
+/// Records of the healthy instances of the services, of their ports
+/// and the gateways of the networks of their running instances
+fn instance_records(services: &[Service]) -> (Vec<Record>, Vec<IpAddr>) {
+  let mut records = Vec::new();
+  let mut gateways = Vec::new();
+  for service in services {
+    let Ok(name) = Zone::instance_name(&service.key) else {
+      continue;
+    };
+    let mut healthy = false;
+    for endpoint in &service.endpoints {
+      let gateway = endpoint.gateway.as_deref().unwrap_or_default();
+      if let Ok(gateway) = gateway.parse::<IpAddr>() {
+        if !gateways.contains(&gateway) {
+          gateways.push(gateway);
+        }
+      }
+      if !endpoint.healthy {
+        continue;
+      }
+      let Ok(ip) = endpoint.ip_address.parse::<IpAddr>() else {
+        continue;
+      };
+      let record = Zone::address_record(&name, &ip, DEFAULT_TTL);
+      if !records.contains(&record) {
+        records.push(record);
       }
+      healthy = true;
     }
-    if !found {
-      file_content.push_str(&format!("{line}\n"));
+    // The ports are only announced while an instance can receive traffic
+    if !healthy {
+      continue;
     }
+    for port in &service.ports {
+      let srv_name = format!("_{}._{}.{}", port.port, port.protocol, name);
+      let Ok(srv_name) = Zone::parse_name(&srv_name) else {
+        continue;
+      };
+      let rdata = RData::SRV(rdata::SRV::new(0, 0, port.port, name.clone()));
+      records.push(Record::from_rdata(srv_name, DEFAULT_TTL, rdata));
+    }
+  }
+  (records, gateways)
+}
+
+/// Update the records of the services of every namespace
+/// and listen on the bridges their instances are attached to
+pub(crate) async fn sync_instances(
+  nameserver: &Nameserver,
+  client: &NanocldClient,
+) -> IoResult<()> {
+  let namespaces = client.list_namespace(None).await.map_err(|err| {
+    err.map_err_context(|| "Unable to list namespaces from nanocl daemon")
+  })?;
+  let mut services = Vec::new();
+  for namespace in namespaces {
+    let query = ServiceQuery {
+      namespace: Some(namespace.name),
+      all: Some(true),
+    };
+    let namespace_services =
+      client.list_service(Some(&query)).await.map_err(|err| {
+        err.map_err_context(|| "Unable to list services from nanocl daemon")
+      })?;
+    services.extend(namespace_services);
   }
-  log::debug!("{}", file_content);
-  if file_content == empty_entries {
-    dnsmasq.remove_config(&dns_rule.network.to_string()).await?;
-    return Ok(());
+  let (records, gateways) = instance_records(&services);
+  log::debug!("utils::sync_instances: {} records", records.len());
+  nameserver.zone.set_instances(records);
+  nameserver.listen(parse_ip(&get_bridge_addr(client).await?)?);
+  for gateway in gateways {
+    nameserver.listen(gateway);
   }
-  dnsmasq
-    .write_config(&dns_rule.network.to_string(), &file_content)
-    .await?;
   Ok(())
 }
 
@@ -155,7 +223,9 @@ pub mod tests {
   pub use nanocl_utils::ntex::test_client::*;
   use nanocld_client::{ConnectOpts, NanocldClient};
 
-  use crate::{dnsmasq, services, vars};
+  use nanocld_client::stubs::service::{Service, ServiceEndpoint, ServicePort};
+
+  use crate::{nameserver::Nameserver, services, vars};
 
   // Before a test
   pub fn before() {
@@ -166,10 +236,8 @@ pub mod tests {
   // Generate a test server
   pub fn gen_default_test_client() -> TestClient {
     before();
-    let dnsmasq = dnsmasq::Dnsmasq::new("/tmp/dnsmasq");
-    dnsmasq
-      .ensure()
-      .expect("Expect to setup minimal dnsmasq config");
+    let nameserver =
+      Nameserver::new(&[], 53).expect("Expect to create a nameserver");
     let client = NanocldClient::connect_to(&ConnectOpts {
       url: "http://nanocl.internal:8585".into(),
       ..Default::default()
@@ -178,10 +246,59 @@ pub mod tests {
     // Create test server
     let srv = ntex::web::test::server(move || {
       ntex::web::App::new()
-        .state(dnsmasq.clone())
+        .state(nameserver.clone())
         .state(client.clone())
         .configure(services::ntex_config)
     });
     TestClient::new(srv, vars::VERSION)
   }
+
+  fn service(key: &str, endpoints: &[(&str, bool)]) -> Service {
+    let (name, namespace) = key.split_once('.').unwrap();
+    Service {
+      key: key.to_owned(),
+      name: name.to_owned(),
+      namespace_name: namespace.to_owned(),
+      ports: vec![ServicePort {
+        port: 80,
+        protocol: "tcp".to_owned(),
+      }],
+      endpoints: endpoints
+        .iter()
+        .enumerate()
+        .map(|(index, (ip, healthy))| ServiceEndpoint {
+          name: format!("{name}-{index}.{namespace}.c"),
+          node_name: "test".to_owned(),
+          ip_address: ip.to_string(),
+          gateway: Some("10.1.0.1".to_owned()),
+          healthy: *healthy,
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn instance_records() {
+    let services = [
+      service("app.global", &[("10.0.0.2", true), ("10.0.0.3", true)]),
+      service("api.prod", &[("10.1.0.2", true), ("10.1.0.3", false)]),
+      service("other.prod", &[("10.1.0.4", false)]),
+    ];
+    let (records, gateways) = super::instance_records(&services);
+    let names = records
+      .iter()
+      .map(|record| format!("{} {}", record.record_type(), record.name()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      names,
+      [
+        "A app.global.nanocl.internal.",
+        "A app.global.nanocl.internal.",
+        "SRV _80._tcp.app.global.nanocl.internal.",
+        "A api.prod.nanocl.internal.",
+        "SRV _80._tcp.api.prod.nanocl.internal.",
+      ]
+    );
+    assert_eq!(gateways, ["10.1.0.1".parse::<std::net::IpAddr>().unwrap()]);
+  }
 }
@@ -0,0 +1,275 @@
+use std::{
+  collections::HashMap,
+  net::IpAddr,
+  sync::{Arc, RwLock},
+};
+
+use hickory_proto::rr::{rdata, Name, RData, Record, RecordType};
+
+use nanocl_error::io::{IoError, IoResult};
+
+/// Domain of the records generated for the running instances,
+/// ncdns is authoritative for it and never forwards its queries
+pub const INTERNAL_DOMAIN: &str = "nanocl.internal.";
+
+/// Time to live of the records in seconds
+pub const DEFAULT_TTL: u32 = 30;
+
+/// Maximum number of aliases followed to answer a query
+const MAX_CNAME_CHAIN: usize = 8;
+
+/// Result of a lookup in the zone
+#[derive(Debug, PartialEq)]
+pub enum Lookup {
+  /// Records matching the name and the type of the query
+  Found(Vec<Record>),
+  /// The name exists without records of the queried type
+  NoData,
+  /// The name doesn't exist and ncdns is authoritative for it
+  NxDomain,
+  /// The name doesn't exist, the query must be forwarded
+  NotFound,
+}
+
+#[derive(Default)]
+struct ZoneInner {
+  /// Records of the dns rules by resource key
+  rules: HashMap<String, Vec<Record>>,
+  /// Records of the running instances
+  instances: Vec<Record>,
+  /// Records of the rules and the instances by lowercase name
+  names: HashMap<Name, Vec<Record>>,
+}
+
+impl ZoneInner {
+  /// Records of a name, the nearest wildcard matches when it doesn't exist
+  fn records_of(&self, name: &Name) -> Option<Vec<Record>> {
+    if let Some(records) = self.names.get(name) {
+      return Some(records.clone());
+    }
+    let wildcard = Name::from_ascii("*").ok()?;
+    let mut parent = name.base_name();
+    while !parent.is_root() {
+      let candidate = wildcard.clone().append_domain(&parent).ok()?;
+      if let Some(records) = self.names.get(&candidate) {
+        let records = records
+          .iter()
+          .map(|record| {
+            let mut record = record.clone();
+            record.set_name(name.clone());
+            record
+          })
+          .collect();
+        return Some(records);
+      }
+      parent = parent.base_name();
+    }
+    None
+  }
+
+  /// Rebuild the index of the records by name
+  fn index(&mut self) {
+    let mut names: HashMap<Name, Vec<Record>> = HashMap::new();
+    let records = self.rules.values().flatten().chain(self.instances.iter());
+    for record in records {
+      let entry = names.entry(record.name().to_lowercase()).or_default();
+      // The same record can be declared by multiple rules
+      if !entry.contains(record) {
+        entry.push(record.clone());
+      }
+    }
+    self.names = names;
+  }
+}
+
+/// In-memory records served by ncdns, updated without restarting the server
+#[derive(Clone, Default)]
+pub struct Zone {
+  inner: Arc<RwLock<ZoneInner>>,
+}
+
+impl Zone {
+  /// Parse a domain name as a fully qualified name
+  pub fn parse_name(name: &str) -> IoResult<Name> {
+    let mut parsed = Name::from_ascii(name).map_err(|err| {
+      IoError::invalid_input("Name", &format!("{name} is invalid: {err}"))
+    })?;
+    if parsed.num_labels() == 0 {
+      return Err(IoError::invalid_input("Name", "Name cannot be empty"));
+    }
+    parsed.set_fqdn(true);
+    Ok(parsed.to_lowercase())
+  }
+
+  /// Create an address record, A or AAAA depending on the ip
+  pub fn address_record(name: &Name, ip: &IpAddr, ttl: u32) -> Record {
+    let rdata = match ip {
+      IpAddr::V4(ip) => RData::A(rdata::A(*ip)),
+      IpAddr::V6(ip) => RData::AAAA(rdata::AAAA(*ip)),
+    };
+    Record::from_rdata(name.clone(), ttl, rdata)
+  }
+
+  /// Name of the record of the instances of a cargo
+  pub fn instance_name(cargo_key: &str) -> IoResult<Name> {
+    Self::parse_name(&format!("{cargo_key}.{INTERNAL_DOMAIN}"))
+  }
+
+  /// Whether ncdns is authoritative for a name
+  fn is_authoritative(name: &Name) -> bool {
+    Name::from_ascii(INTERNAL_DOMAIN)
+      .map(|domain| domain.zone_of(name))
+      .unwrap_or_default()
+  }
+
+  /// Set the records of a dns rule
+  pub fn set_rule(&self, key: &str, records: Vec<Record>) {
+    let mut inner = self.inner.write().unwrap();
+    inner.rules.insert(key.to_owned(), records);
+    inner.index();
+  }
+
+  /// Remove the records of a dns rule, return false if it doesn't exist
+  pub fn remove_rule(&self, key: &str) -> bool {
+    let mut inner = self.inner.write().unwrap();
+    if inner.rules.remove(key).is_none() {
+      return false;
+    }
+    inner.index();
+    true
+  }
+
+  /// Replace the records of the running instances
+  pub fn set_instances(&self, records: Vec<Record>) {
+    let mut inner = self.inner.write().unwrap();
+    if inner.instances == records {
+      return;
+    }
+    inner.instances = records;
+    inner.index();
+  }
+
+  /// Find the records of a name by type, the aliases are followed
+  pub fn lookup(&self, name: &Name, record_type: RecordType) -> Lookup {
+    let inner = self.inner.read().unwrap();
+    let name = name.to_lowercase();
+    let Some(records) = inner.records_of(&name) else {
+      if Self::is_authoritative(&name) {
+        return Lookup::NxDomain;
+      }
+      return Lookup::NotFound;
+    };
+    let matches = |record: &Record| {
+      record_type == RecordType::ANY || record.record_type() == record_type
+    };
+    let mut answers = Vec::new();
+    let mut records = records;
+    for _ in 0..MAX_CNAME_CHAIN {
+      let alias = records
+        .iter()
+        .find(|record| record.record_type() == RecordType::CNAME)
+        .filter(|_| {
+          !matches!(record_type, RecordType::CNAME | RecordType::ANY)
+        });
+      let Some(alias) = alias else {
+        answers.extend(records.into_iter().filter(matches));
+        break;
+      };
+      answers.push(alias.clone());
+      let Some(RData::CNAME(target)) = alias.data() else {
+        break;
+      };
+      // The client resolves the aliases outside of the zone
+      let Some(target_records) = inner.records_of(&target.0.to_lowercase())
+      else {
+        break;
+      };
+      records = target_records;
+    }
+    if answers.is_empty() {
+      return Lookup::NoData;
+    }
+    Lookup::Found(answers)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn lookup() {
+    let zone = Zone::default();
+    let name = Zone::parse_name("Test.com").unwrap();
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+    zone.set_rule("test", vec![Zone::address_record(&name, &ip, DEFAULT_TTL)]);
+    let query = Name::from_ascii("test.COM.").unwrap();
+    let Lookup::Found(records) = zone.lookup(&query, RecordType::A) else {
+      panic!("Expect test.com to be found");
+    };
+    assert_eq!(records.len(), 1);
+    assert_eq!(
+      records[0].data(),
+      Some(&RData::A(rdata::A([10, 0, 0, 1].into())))
+    );
+    assert_eq!(zone.lookup(&query, RecordType::AAAA), Lookup::NoData);
+    let unknown = Name::from_ascii("unknown.com.").unwrap();
+    assert_eq!(zone.lookup(&unknown, RecordType::A), Lookup::NotFound);
+    let instance = Zone::instance_name("app.global").unwrap();
+    assert_eq!(zone.lookup(&instance, RecordType::A), Lookup::NxDomain);
+    zone.set_instances(vec![Zone::address_record(&instance, &ip, DEFAULT_TTL)]);
+    assert!(matches!(
+      zone.lookup(&instance, RecordType::A),
+      Lookup::Found(_)
+    ));
+    assert!(zone.remove_rule("test"));
+    assert!(!zone.remove_rule("test"));
+    assert_eq!(zone.lookup(&query, RecordType::A), Lookup::NotFound);
+  }
+
+  #[test]
+  fn invalid_name() {
+    assert!(Zone::parse_name("").is_err());
+    assert!(Zone::parse_name("bad..name").is_err());
+  }
+
+  #[test]
+  fn wildcard_and_alias() {
+    let zone = Zone::default();
+    let ip: IpAddr = "10.0.0.1".parse().unwrap();
+    let wildcard = Zone::parse_name("*.staging.test").unwrap();
+    let app = Zone::parse_name("app.test").unwrap();
+    let www = Zone::parse_name("www.test").unwrap();
+    let cname = RData::CNAME(rdata::CNAME(app.clone()));
+    zone.set_rule(
+      "test",
+      vec![
+        Zone::address_record(&wildcard, &ip, 60),
+        Zone::address_record(&app, &ip, DEFAULT_TTL),
+        Record::from_rdata(www.clone(), DEFAULT_TTL, cname),
+      ],
+    );
+    let query = Name::from_ascii("pr-1.staging.test.").unwrap();
+    let Lookup::Found(records) = zone.lookup(&query, RecordType::A) else {
+      panic!("Expect the wildcard to match");
+    };
+    assert_eq!(records[0].name(), &query);
+    assert_eq!(records[0].ttl(), 60);
+    let query = Name::from_ascii("a.pr-1.staging.test.").unwrap();
+    assert!(matches!(
+      zone.lookup(&query, RecordType::A),
+      Lookup::Found(_)
+    ));
+    let query = Name::from_ascii("staging.test.").unwrap();
+    assert_eq!(zone.lookup(&query, RecordType::A), Lookup::NotFound);
+    let Lookup::Found(records) = zone.lookup(&www, RecordType::A) else {
+      panic!("Expect the alias to be followed");
+    };
+    let types = records.iter().map(|r| r.record_type()).collect::<Vec<_>>();
+    assert_eq!(types, [RecordType::CNAME, RecordType::A]);
+    let Lookup::Found(records) = zone.lookup(&www, RecordType::CNAME) else {
+      panic!("Expect the alias to be found");
+    };
+    assert_eq!(records.len(), 1);
+  }
+}
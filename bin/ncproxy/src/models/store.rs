@@ -7,7 +7,7 @@ use nanocld_client::stubs::proxy::ProxyRule;
 /// Kind of rule configuration:
 /// * Site for HTTP/HTTPS
 /// * Stream for TCP/UDP
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NginxRuleKind {
   Site,
   Stream,
@@ -48,6 +48,38 @@ impl FromStr for NginxRuleKind {
   }
 }
 
+impl NginxRuleKind {
+  /// Name of the directory of the enabled configurations
+  pub fn enabled_dir(&self) -> &'static str {
+    match self {
+      Self::Site => "sites-enabled",
+      Self::Stream => "streams-enabled",
+    }
+  }
+}
+
+/// Configuration files of a rule staged until nginx validates them
+#[derive(Debug, Default)]
+pub struct StagedConf {
+  pub files: Vec<(String, NginxRuleKind, String)>,
+  /// Files of the secrets directory referenced by the configuration
+  pub secrets: Vec<(String, String)>,
+}
+
+impl StagedConf {
+  /// Stage a file, replacing a previous version of it
+  pub fn write(&mut self, name: &str, data: &str, kind: &NginxRuleKind) {
+    self.files.retain(|(n, k, _)| n != name || k != kind);
+    self.files.push((name.to_owned(), *kind, data.to_owned()));
+  }
+
+  /// Stage a file of the secrets directory, replacing a previous version of it
+  pub fn write_secret(&mut self, name: &str, data: &str) {
+    self.secrets.retain(|(n, _)| n != name);
+    self.secrets.push((name.to_owned(), data.to_owned()));
+  }
+}
+
 #[derive(Clone)]
 pub struct Store {
   pub dir: String,
@@ -81,7 +113,12 @@ impl Store {
     kind: &NginxRuleKind,
   ) -> IoResult<()> {
     let path = self.gen_path(name, kind);
-    tokio::fs::write(&path.0, data).await.map_err(|err| {
+    // Renaming the file replaces it atomically for nginx
+    let tmp_path = format!("{}.tmp", path.0);
+    tokio::fs::write(&tmp_path, data).await.map_err(|err| {
+      err.map_err_context(|| format!("Unable to create {} file", path.0))
+    })?;
+    tokio::fs::rename(&tmp_path, &path.0).await.map_err(|err| {
       err.map_err_context(|| format!("Unable to create {} file", path.0))
     })?;
     let _ = tokio::fs::symlink(&path.0, &path.1).await.map_err(|err| {
@@ -90,9 +127,50 @@ impl Store {
     Ok(())
   }
 
-  pub async fn delete_conf_file(&self, name: &str, kind: &NginxRuleKind) {
+  /// Write a file of the secrets directory only readable by its owner
+  pub async fn write_secret_file(
+    &self,
+    name: &str,
+    data: &str,
+  ) -> IoResult<()> {
+    let path = format!("{}/secrets/{name}", self.dir);
+    Self::write_private_file(&path, data).await
+  }
+
+  /// Write a file only readable by its owner
+  pub async fn write_private_file(
+    path: &str,
+    data: impl AsRef<[u8]>,
+  ) -> IoResult<()> {
+    let tmp_path = format!("{path}.tmp");
+    let _ = tokio::fs::remove_file(&tmp_path).await;
+    // Created with its mode before the data is written so it's never readable
+    tokio::fs::OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .mode(0o600)
+      .open(&tmp_path)
+      .await
+      .map_err(|err| {
+        err.map_err_context(|| format!("Unable to create {path} file"))
+      })?;
+    tokio::fs::write(&tmp_path, data).await.map_err(|err| {
+      err.map_err_context(|| format!("Unable to create {path} file"))
+    })?;
+    tokio::fs::rename(&tmp_path, path).await.map_err(|err| {
+      err.map_err_context(|| format!("Unable to create {path} file"))
+    })?;
+    Ok(())
+  }
+
+  /// Delete a configuration file, returns whether it was enabled
+  pub async fn delete_conf_file(
+    &self,
+    name: &str,
+    kind: &NginxRuleKind,
+  ) -> bool {
     let path = self.gen_path(name, kind);
     let _ = tokio::fs::remove_file(&path.0).await;
-    let _ = tokio::fs::remove_file(&path.1).await;
+    tokio::fs::remove_file(&path.1).await.is_ok()
   }
 }
@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+  collections::HashSet,
+  sync::{Arc, Mutex},
+};
 
 use futures::{channel::mpsc, SinkExt, StreamExt};
 use ntex::rt;
@@ -18,6 +21,10 @@ pub struct SystemState {
   pub client: NanocldClient,
   pub event_emitter: EventEmitter,
   pub nginx_dir: String,
+  /// Domains with an ACME order in progress
+  pub acme_orders: Arc<Mutex<HashSet<String>>>,
+  /// Serialize the changes of the nginx configuration
+  pub conf_lock: Arc<futures::lock::Mutex<()>>,
 }
 
 pub type SystemStateRef = Arc<SystemState>;
@@ -1,4 +1,7 @@
-use nanocld_client::stubs::proxy::{LimitReq, ProxySslConfig};
+use nanocld_client::stubs::proxy::{
+  LimitReq, ProxyHealthCheck, ProxyHttpLocation, ProxyLoadBalancing,
+  ProxySslConfig, ProxyStickySession, ProxyTimeouts,
+};
 use serde::{Deserialize, Serialize};
 
 use nanocl_error::io::{IoError, IoResult};
@@ -14,6 +17,49 @@ pub struct LocationTemplate {
   pub version: Option<f64>,
   pub headers: Option<Vec<String>>,
   pub ssl: Option<ProxySslConfig>,
+  #[serde(flatten)]
+  pub options: LocationOptions,
+}
+
+/// Directives of a location block generated from its options
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LocationOptions {
+  pub add_headers: Vec<String>,
+  pub hide_headers: Vec<String>,
+  pub preflight: Option<Vec<String>>,
+  pub rewrites: Vec<String>,
+  pub auth_basic: Option<String>,
+  pub auth_basic_user_file: Option<String>,
+  pub max_body_size: Option<String>,
+  pub gzip: Option<bool>,
+  pub websocket: bool,
+  pub timeouts: Option<ProxyTimeouts>,
+}
+
+/// Options of an upstream set by the location targeting it
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct UpstreamOptions {
+  pub load_balancing: Option<ProxyLoadBalancing>,
+  pub sticky_session: Option<ProxyStickySession>,
+  pub health_check: Option<ProxyHealthCheck>,
+}
+
+impl UpstreamOptions {
+  pub fn is_default(&self) -> bool {
+    self.load_balancing.is_none()
+      && self.sticky_session.is_none()
+      && self.health_check.is_none()
+  }
+}
+
+impl From<&ProxyHttpLocation> for UpstreamOptions {
+  fn from(location: &ProxyHttpLocation) -> Self {
+    Self {
+      load_balancing: location.load_balancing.clone(),
+      sticky_session: location.sticky_session.clone(),
+      health_check: location.health_check.clone(),
+    }
+  }
 }
 
 pub struct Template<'a> {
@@ -55,6 +101,10 @@ pub const UPSTREAM_TEMPLATE: &Template = &Template {
   data: include_str!("templates/upstream.conf"),
 };
 
+pub const SPLIT_TEMPLATE: &Template = &Template {
+  data: include_str!("templates/split.conf"),
+};
+
 pub const UNIX_UPSTREAM_TEMPLATE: &Template = &Template {
   data: include_str!("templates/unix_upstream.conf"),
 };
@@ -2,10 +2,18 @@ use ntex::web;
 
 use nanocl_error::http::HttpError;
 
-use nanocld_client::stubs::proxy::ResourceProxyRule;
+use nanocld_client::stubs::{
+  generic::GenericNspQuery, proxy::ResourceProxyRule,
+};
 
 use crate::{models::SystemStateRef, utils};
 
+/// Key of the rule `<name>.<namespace>` like the key of its resource
+fn rule_key(name: &str, qs: &GenericNspQuery) -> String {
+  let namespace = qs.namespace.as_deref().unwrap_or("global");
+  format!("{name}.{namespace}")
+}
+
 /// Create/Update a new ProxyRule
 #[cfg_attr(feature = "dev", utoipa::path(
   put,
@@ -14,6 +22,7 @@ use crate::{models::SystemStateRef, utils};
   request_body = ResourceProxyRule,
   params(
     ("name" = String, Path, description = "Name of the rule"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the rule default to 'global'"),
   ),
   responses(
     (status = 200, description = "The created rule", body = ResourceProxyRule),
@@ -24,9 +33,11 @@ pub async fn apply_rule(
   state: web::types::State<SystemStateRef>,
   path: web::types::Path<(String, String)>,
   payload: web::types::Json<ResourceProxyRule>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> Result<web::HttpResponse, HttpError> {
-  log::info!("apply_rule: {}", path.1);
-  utils::nginx::add_rule(&path.1, &payload, &state).await?;
+  let key = rule_key(&path.1, &qs);
+  log::info!("apply_rule: {key}");
+  utils::nginx::add_rule(&key, &payload, &state).await?;
   state.event_emitter.emit_reload().await;
   Ok(web::HttpResponse::Ok().json(&payload.into_inner()))
 }
@@ -38,6 +49,7 @@ pub async fn apply_rule(
   path = "/rules/{name}",
   params(
     ("name" = String, Path, description = "Name of the rule"),
+    ("namespace" = Option<String>, Query, description = "Namespace of the rule default to 'global'"),
   ),
   responses(
     (status = 200, description = "Rule has been deleted"),
@@ -47,9 +59,11 @@ pub async fn apply_rule(
 pub async fn remove_rule(
   state: web::types::State<SystemStateRef>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
 ) -> Result<web::HttpResponse, HttpError> {
-  log::info!("remove_rule: {}", path.1);
-  utils::nginx::del_rule(&path.1, &state).await;
+  let key = rule_key(&path.1, &qs);
+  log::info!("remove_rule: {key}");
+  utils::nginx::del_rule(&key, &state).await;
   state.event_emitter.emit_reload().await;
   Ok(web::HttpResponse::Ok().finish())
 }
@@ -0,0 +1,27 @@
+/// Periodically renew the certificates managed with ACME
+use std::{sync::Arc, time::Duration};
+
+use ntex::rt;
+
+use crate::{models::SystemStateRef, utils};
+
+/// Delay before the first check to let nanocld and nginx start
+const INITIAL_DELAY: Duration = Duration::from_secs(30);
+
+/// Delay between two checks of the certificates
+const RENEW_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+
+pub(crate) fn spawn(state: &SystemStateRef) {
+  let state = Arc::clone(state);
+  rt::Arbiter::new().exec_fn(move || {
+    rt::spawn(async move {
+      ntex::time::sleep(INITIAL_DELAY).await;
+      loop {
+        if let Err(err) = utils::acme::renew(&state).await {
+          log::warn!("acme::spawn: {err}");
+        }
+        ntex::time::sleep(RENEW_INTERVAL).await;
+      }
+    });
+  });
+}
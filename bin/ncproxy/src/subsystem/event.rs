@@ -8,7 +8,6 @@ use nanocl_error::io::{IoError, IoResult};
 use nanocl_utils::versioning;
 use nanocld_client::{
   stubs::{
-    resource::ResourcePartial,
     resource_kind::{ResourceKindPartial, ResourceKindSpec},
     system::Event,
     system::{EventActorKind, NativeEventAction},
@@ -49,12 +48,11 @@ async fn update_cargo_rule(
   )
   .await?;
   resources
-    .into_iter()
+    .iter()
     .map(|resource| async {
-      let resource: ResourcePartial = resource.into();
-      let rule = utils::resource::serialize(&resource.data)?;
+      let rule = utils::resource::serialize(&resource.spec.data)?;
       if let Err(err) =
-        utils::nginx::add_rule(&resource.name, &rule, state).await
+        utils::nginx::add_rule(&resource.spec.resource_key, &rule, state).await
       {
         log::warn!("event::update_cargo_rule: {err}");
       }
@@ -132,6 +130,7 @@ async fn ensure_self_config(client: &NanocldClient) -> IoResult<()> {
     data: ResourceKindSpec {
       schema: None,
       url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+      conversion: None,
     },
   };
   if client
@@ -158,6 +157,9 @@ async fn r#loop(state: &SystemStateRef) {
           continue;
         }
         let _ = utils::nginx::ensure_conf(state).await;
+        if let Err(err) = utils::resource::del_legacy_rules(state).await {
+          log::warn!("event::loop: {err}");
+        }
         log::info!("event::loop: subscribed to nanocld events");
         while let Some(event) = stream.next().await {
           let event = match event {
@@ -9,7 +9,7 @@ use crate::{
   models::{EventEmitter, Store, SystemState, SystemStateRef},
 };
 
-use super::{event, metric};
+use super::{acme, event, metric};
 
 pub async fn init(cli: &Cli) -> IoResult<SystemStateRef> {
   #[allow(unused)]
@@ -28,8 +28,11 @@ pub async fn init(cli: &Cli) -> IoResult<SystemStateRef> {
     event_emitter,
     store: Store::new(&cli.state_dir),
     nginx_dir: cli.nginx_dir.clone(),
+    acme_orders: Default::default(),
+    conf_lock: Default::default(),
   });
   event::spawn(&state);
   metric::spawn(&state);
+  acme::spawn(&state);
   Ok(state)
 }
@@ -0,0 +1,667 @@
+use std::{sync::Arc, time::Duration};
+
+use ntex::{
+  http::client::{Client, Connector},
+  rt,
+  time::Millis,
+  util::Bytes,
+};
+use openssl::{
+  asn1::Asn1Time,
+  bn::{BigNum, BigNumContext},
+  ec::{EcGroup, EcKey},
+  ecdsa::EcdsaSig,
+  hash::MessageDigest,
+  nid::Nid,
+  pkey::{PKey, Private},
+  ssl::{SslConnector, SslMethod, SslVerifyMode},
+  stack::Stack,
+  x509::{extension::SubjectAlternativeName, X509NameBuilder, X509ReqBuilder},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+
+use nanocld_client::stubs::{
+  generic::{GenericClause, GenericFilter, GenericFilterNsp},
+  proxy::{
+    ProxyRule, ProxySsl, ProxySslAcme, ProxySslConfig, ResourceProxyRule,
+  },
+  secret::{SecretPartial, SecretUpdate},
+};
+
+use crate::{
+  models::{Store, SystemStateRef},
+  vars,
+};
+
+/// Production directory of Let's Encrypt
+pub const LETS_ENCRYPT_DIRECTORY: &str =
+  "https://acme-v02.api.letsencrypt.org/directory";
+
+/// Default number of days before the expiration to renew a certificate
+const DEFAULT_RENEW_BEFORE: u32 = 30;
+
+/// Number of times an order or an authorization is polled
+const POLL_ATTEMPTS: usize = 30;
+
+/// Delay between two polls
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Name of the secret storing the certificate of a domain
+pub fn secret_name(domain: &str) -> String {
+  format!("acme.{domain}")
+}
+
+/// Directory where the HTTP-01 challenges are served from by nginx
+pub fn challenge_dir(state: &SystemStateRef) -> String {
+  format!("{}/acme/challenges", state.store.dir)
+}
+
+/// Token of a HTTP-01 challenge, used as a file name in the challenge directory
+fn validate_token(token: &str) -> IoResult<&str> {
+  let valid = !token.is_empty()
+    && token
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+  if !valid {
+    return Err(IoError::invalid_data(
+      "Acme challenge",
+      &format!("invalid token {token}"),
+    ));
+  }
+  Ok(token)
+}
+
+/// Domain a certificate can be requested for with the HTTP-01 challenge
+pub fn validate_domain(domain: Option<&String>) -> IoResult<&str> {
+  match domain {
+    None => Err(IoError::invalid_input(
+      "Acme",
+      "A domain is required to obtain a certificate",
+    )),
+    Some(domain) if domain.contains('*') => Err(IoError::invalid_input(
+      "Acme",
+      "Wildcard domains can't be validated with the HTTP-01 challenge",
+    )),
+    Some(domain) => Ok(domain),
+  }
+}
+
+/// Encode in base64url without padding as required by JWS
+fn b64(data: &[u8]) -> String {
+  openssl::base64::encode_block(data)
+    .replace('+', "-")
+    .replace('/', "_")
+    .trim_end_matches('=')
+    .to_owned()
+}
+
+fn ssl_err(err: openssl::error::ErrorStack) -> IoError {
+  IoError::invalid_data("Acme", &err.to_string())
+}
+
+/// Key of the ACME account signing the requests with ES256
+struct AccountKey {
+  key: PKey<Private>,
+}
+
+impl AccountKey {
+  /// Load the account key from the state directory or create it
+  async fn load(state: &SystemStateRef) -> IoResult<Self> {
+    let path = format!("{}/acme/account.key", state.store.dir);
+    if let Ok(pem) = tokio::fs::read(&path).await {
+      let key = PKey::private_key_from_pem(&pem).map_err(ssl_err)?;
+      return Ok(Self { key });
+    }
+    let key = gen_key()?;
+    let pem = key.private_key_to_pem_pkcs8().map_err(ssl_err)?;
+    Store::write_private_file(&path, pem).await?;
+    Ok(Self { key })
+  }
+
+  /// Public key as a JSON web key with the members in lexicographic order
+  fn jwk(&self) -> IoResult<serde_json::Value> {
+    let ec_key = self.key.ec_key().map_err(ssl_err)?;
+    let mut ctx = BigNumContext::new().map_err(ssl_err)?;
+    let mut x = BigNum::new().map_err(ssl_err)?;
+    let mut y = BigNum::new().map_err(ssl_err)?;
+    ec_key
+      .public_key()
+      .affine_coordinates(ec_key.group(), &mut x, &mut y, &mut ctx)
+      .map_err(ssl_err)?;
+    Ok(serde_json::json!({
+      "crv": "P-256",
+      "kty": "EC",
+      "x": b64(&x.to_vec_padded(32).map_err(ssl_err)?),
+      "y": b64(&y.to_vec_padded(32).map_err(ssl_err)?),
+    }))
+  }
+
+  /// Thumbprint of the key used in the key authorization of the challenges
+  fn thumbprint(&self) -> IoResult<String> {
+    // serde_json keeps the keys sorted which gives the canonical form
+    let jwk = self.jwk()?.to_string();
+    Ok(b64(&openssl::sha::sha256(jwk.as_bytes())))
+  }
+
+  /// Sign a request in the flattened JWS JSON serialization
+  fn sign(
+    &self,
+    protected: &serde_json::Value,
+    payload: &str,
+  ) -> IoResult<serde_json::Value> {
+    let protected = b64(protected.to_string().as_bytes());
+    let input = format!("{protected}.{payload}");
+    let mut signer =
+      openssl::sign::Signer::new(MessageDigest::sha256(), &self.key)
+        .map_err(ssl_err)?;
+    let der = signer
+      .sign_oneshot_to_vec(input.as_bytes())
+      .map_err(ssl_err)?;
+    // JWS expects the raw r and s values instead of the DER signature
+    let sig = EcdsaSig::from_der(&der).map_err(ssl_err)?;
+    let mut signature = sig.r().to_vec_padded(32).map_err(ssl_err)?;
+    signature.extend(sig.s().to_vec_padded(32).map_err(ssl_err)?);
+    Ok(serde_json::json!({
+      "protected": protected,
+      "payload": payload,
+      "signature": b64(&signature),
+    }))
+  }
+}
+
+fn gen_key() -> IoResult<PKey<Private>> {
+  let group =
+    EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).map_err(ssl_err)?;
+  let ec_key = EcKey::generate(&group).map_err(ssl_err)?;
+  PKey::from_ec_key(ec_key).map_err(ssl_err)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+  new_nonce: String,
+  new_account: String,
+  new_order: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Order {
+  status: String,
+  #[serde(default)]
+  authorizations: Vec<String>,
+  finalize: String,
+  certificate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Authorization {
+  status: String,
+  #[serde(default)]
+  challenges: Vec<Challenge>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Challenge {
+  #[serde(rename = "type")]
+  kind: String,
+  url: String,
+  #[serde(default)]
+  token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Problem {
+  #[serde(rename = "type")]
+  kind: Option<String>,
+  detail: Option<String>,
+}
+
+struct AcmeResponse {
+  location: Option<String>,
+  body: Bytes,
+}
+
+impl AcmeResponse {
+  fn json<T: DeserializeOwned>(&self) -> IoResult<T> {
+    serde_json::from_slice(&self.body)
+      .map_err(|err| err.map_err_context(|| "Acme response").into())
+  }
+}
+
+/// Minimal ACME (RFC 8555) client to order certificates
+struct AcmeClient {
+  http: Client,
+  directory: Directory,
+  key: AccountKey,
+  kid: Option<String>,
+}
+
+impl AcmeClient {
+  async fn new(config: &ProxySslAcme, key: AccountKey) -> IoResult<Self> {
+    let mut builder =
+      SslConnector::builder(SslMethod::tls()).map_err(ssl_err)?;
+    if config.insecure.unwrap_or_default() {
+      builder.set_verify(SslVerifyMode::NONE);
+    }
+    let http = Client::build()
+      .connector(Connector::default().openssl(builder.build()).finish())
+      .timeout(Millis::from_secs(30))
+      .finish();
+    let url = config
+      .directory
+      .clone()
+      .unwrap_or(LETS_ENCRYPT_DIRECTORY.to_owned());
+    let mut res = http
+      .get(&url)
+      .send()
+      .await
+      .map_err(|err| IoError::other("Acme directory", &err.to_string()))?;
+    let directory = res.json::<Directory>().await.map_err(|err| {
+      IoError::invalid_data("Acme directory", &err.to_string())
+    })?;
+    Ok(Self {
+      http,
+      directory,
+      key,
+      kid: None,
+    })
+  }
+
+  async fn nonce(&self) -> IoResult<String> {
+    let res = self
+      .http
+      .head(&self.directory.new_nonce)
+      .send()
+      .await
+      .map_err(|err| IoError::other("Acme nonce", &err.to_string()))?;
+    res
+      .header("Replay-Nonce")
+      .and_then(|nonce| nonce.to_str().ok())
+      .map(|nonce| nonce.to_owned())
+      .ok_or_else(|| IoError::invalid_data("Acme nonce", "missing"))
+  }
+
+  /// Send a signed request, without payload it's a POST-as-GET
+  async fn post(
+    &self,
+    url: &str,
+    payload: Option<&serde_json::Value>,
+  ) -> IoResult<AcmeResponse> {
+    let mut protected = serde_json::json!({
+      "alg": "ES256",
+      "nonce": self.nonce().await?,
+      "url": url,
+    });
+    match &self.kid {
+      Some(kid) => protected["kid"] = serde_json::json!(kid),
+      None => protected["jwk"] = self.key.jwk()?,
+    }
+    let payload = payload
+      .map(|payload| b64(payload.to_string().as_bytes()))
+      .unwrap_or_default();
+    let body = self.key.sign(&protected, &payload)?;
+    let mut res = self
+      .http
+      .post(url)
+      .header("Content-Type", "application/jose+json")
+      .send_body(body.to_string())
+      .await
+      .map_err(|err| IoError::other("Acme", &err.to_string()))?;
+    let location = res
+      .header("Location")
+      .and_then(|location| location.to_str().ok())
+      .map(|location| location.to_owned());
+    let body = res
+      .body()
+      .limit(1024 * 1024)
+      .await
+      .map_err(|err| IoError::other("Acme", &err.to_string()))?;
+    if !res.status().is_success() {
+      let problem = serde_json::from_slice::<Problem>(&body).ok();
+      let msg = problem
+        .map(|problem| {
+          format!(
+            "{}: {}",
+            problem.kind.unwrap_or_default(),
+            problem.detail.unwrap_or_default()
+          )
+        })
+        .unwrap_or_else(|| res.status().to_string());
+      return Err(IoError::other("Acme", &format!("{url} {msg}")));
+    }
+    Ok(AcmeResponse { location, body })
+  }
+
+  /// Register the account or fetch the existing one for the key
+  async fn account(&mut self, email: Option<&str>) -> IoResult<()> {
+    let mut payload = serde_json::json!({ "termsOfServiceAgreed": true });
+    if let Some(email) = email {
+      payload["contact"] = serde_json::json!([format!("mailto:{email}")]);
+    }
+    let res = self
+      .post(&self.directory.new_account, Some(&payload))
+      .await?;
+    self.kid = Some(res.location.ok_or_else(|| {
+      IoError::invalid_data("Acme account", "missing location")
+    })?);
+    Ok(())
+  }
+
+  /// Solve the HTTP-01 challenge of an authorization
+  async fn authorize(&self, url: &str, state: &SystemStateRef) -> IoResult<()> {
+    let authorization = self.post(url, None).await?.json::<Authorization>()?;
+    if authorization.status == "valid" {
+      return Ok(());
+    }
+    let challenge = authorization
+      .challenges
+      .iter()
+      .find(|challenge| challenge.kind == "http-01")
+      .ok_or_else(|| {
+        IoError::invalid_data("Acme authorization", "no http-01 challenge")
+      })?;
+    let token = validate_token(&challenge.token)?;
+    let path = format!("{}/{token}", challenge_dir(state));
+    let key_authorization = format!("{token}.{}", self.key.thumbprint()?);
+    tokio::fs::write(&path, key_authorization).await?;
+    let res = self.validate(url, &challenge.url).await;
+    let _ = tokio::fs::remove_file(&path).await;
+    res
+  }
+
+  async fn validate(&self, url: &str, challenge_url: &str) -> IoResult<()> {
+    self
+      .post(challenge_url, Some(&serde_json::json!({})))
+      .await?;
+    for _ in 0..POLL_ATTEMPTS {
+      ntex::time::sleep(POLL_INTERVAL).await;
+      let authorization =
+        self.post(url, None).await?.json::<Authorization>()?;
+      match authorization.status.as_str() {
+        "valid" => return Ok(()),
+        "pending" | "processing" => continue,
+        status => {
+          return Err(IoError::other(
+            "Acme authorization",
+            &format!("{url} is {status}"),
+          ))
+        }
+      }
+    }
+    Err(IoError::other(
+      "Acme authorization",
+      &format!("{url} timed out"),
+    ))
+  }
+
+  /// Order a certificate for a domain and return the certificate chain in PEM
+  async fn order(
+    &self,
+    domain: &str,
+    key: &PKey<Private>,
+    state: &SystemStateRef,
+  ) -> IoResult<String> {
+    let payload = serde_json::json!({
+      "identifiers": [{ "type": "dns", "value": domain }],
+    });
+    let res = self.post(&self.directory.new_order, Some(&payload)).await?;
+    let url = res
+      .location
+      .clone()
+      .ok_or_else(|| IoError::invalid_data("Acme order", "missing location"))?;
+    let order = res.json::<Order>()?;
+    for authorization in &order.authorizations {
+      self.authorize(authorization, state).await?;
+    }
+    let csr = gen_csr(domain, key)?;
+    self
+      .post(
+        &order.finalize,
+        Some(&serde_json::json!({ "csr": b64(&csr) })),
+      )
+      .await?;
+    for _ in 0..POLL_ATTEMPTS {
+      let order = self.post(&url, None).await?.json::<Order>()?;
+      match (order.status.as_str(), order.certificate) {
+        ("valid", Some(certificate)) => {
+          let res = self.post(&certificate, None).await?;
+          return String::from_utf8(res.body.to_vec()).map_err(|err| {
+            IoError::invalid_data("Acme certificate", &err.to_string())
+          });
+        }
+        ("pending" | "ready" | "processing" | "valid", _) => {
+          ntex::time::sleep(POLL_INTERVAL).await;
+        }
+        (status, _) => {
+          return Err(IoError::other(
+            "Acme order",
+            &format!("{url} is {status}"),
+          ))
+        }
+      }
+    }
+    Err(IoError::other("Acme order", &format!("{url} timed out")))
+  }
+}
+
+/// Certificate signing request of a domain in DER
+fn gen_csr(domain: &str, key: &PKey<Private>) -> IoResult<Vec<u8>> {
+  let mut name = X509NameBuilder::new().map_err(ssl_err)?;
+  name
+    .append_entry_by_nid(Nid::COMMONNAME, domain)
+    .map_err(ssl_err)?;
+  let name = name.build();
+  let mut req = X509ReqBuilder::new().map_err(ssl_err)?;
+  req.set_subject_name(&name).map_err(ssl_err)?;
+  req.set_pubkey(key).map_err(ssl_err)?;
+  let san = SubjectAlternativeName::new()
+    .dns(domain)
+    .build(&req.x509v3_context(None))
+    .map_err(ssl_err)?;
+  let mut extensions = Stack::new().map_err(ssl_err)?;
+  extensions.push(san).map_err(ssl_err)?;
+  req.add_extensions(&extensions).map_err(ssl_err)?;
+  req.sign(key, MessageDigest::sha256()).map_err(ssl_err)?;
+  req.build().to_der().map_err(ssl_err)
+}
+
+/// Number of days before the first certificate of a PEM chain expires
+fn days_left(certificate: &str) -> IoResult<i32> {
+  let cert =
+    openssl::x509::X509::from_pem(certificate.as_bytes()).map_err(ssl_err)?;
+  let now = Asn1Time::days_from_now(0).map_err(ssl_err)?;
+  let diff = now.diff(cert.not_after()).map_err(ssl_err)?;
+  Ok(diff.days)
+}
+
+/// Obtain a certificate for a domain
+async fn issue(
+  domain: &str,
+  config: &ProxySslAcme,
+  state: &SystemStateRef,
+) -> IoResult<ProxySslConfig> {
+  tokio::fs::create_dir_all(challenge_dir(state)).await?;
+  let account_key = AccountKey::load(state).await?;
+  let mut client = AcmeClient::new(config, account_key).await?;
+  client.account(config.email.as_deref()).await?;
+  let key = gen_key()?;
+  let certificate = client.order(domain, &key, state).await?;
+  let certificate_key = key.private_key_to_pem_pkcs8().map_err(ssl_err)?;
+  Ok(ProxySslConfig {
+    certificate,
+    certificate_key: String::from_utf8_lossy(&certificate_key).into_owned(),
+    certificate_client: None,
+    verify_client: None,
+    dhparam: None,
+  })
+}
+
+/// Create or update the `nanocl.io/tls` secret of a domain
+async fn store(
+  domain: &str,
+  ssl: &ProxySslConfig,
+  state: &SystemStateRef,
+) -> IoResult<()> {
+  let name = secret_name(domain);
+  let data = serde_json::to_value(ssl)
+    .map_err(|err| err.map_err_context(|| "Acme certificate"))?;
+  let metadata = Some(serde_json::json!({ "Domain": domain }));
+  if state.client.inspect_secret(&name).await.is_ok() {
+    state
+      .client
+      .patch_secret(&name, &SecretUpdate { metadata, data })
+      .await?;
+    return Ok(());
+  }
+  let secret = SecretPartial {
+    name,
+    kind: "nanocl.io/tls".to_owned(),
+    immutable: false,
+    metadata,
+    data,
+  };
+  state.client.create_secret(&secret).await?;
+  Ok(())
+}
+
+/// Whether the certificate of a domain is missing or about to expire
+pub async fn needs_renewal(
+  domain: &str,
+  config: &ProxySslAcme,
+  state: &SystemStateRef,
+) -> bool {
+  let Ok(secret) = state.client.inspect_secret(&secret_name(domain)).await
+  else {
+    return true;
+  };
+  let renew_before = config.renew_before.unwrap_or(DEFAULT_RENEW_BEFORE);
+  match serde_json::from_value::<ProxySslConfig>(secret.data)
+    .map_err(|err| err.map_err_context(|| "Acme certificate").into())
+    .and_then(|ssl| days_left(&ssl.certificate))
+  {
+    Ok(days) => days < renew_before as i32,
+    Err(err) => {
+      log::warn!("acme::needs_renewal: {domain} {err}");
+      true
+    }
+  }
+}
+
+/// Obtain or renew the certificate of a domain in the background
+/// then apply the rule again to serve it
+pub fn spawn(
+  name: &str,
+  rule: &ResourceProxyRule,
+  domain: &str,
+  config: &ProxySslAcme,
+  state: &SystemStateRef,
+) {
+  {
+    let Ok(mut orders) = state.acme_orders.lock() else {
+      return;
+    };
+    if !orders.insert(domain.to_owned()) {
+      return;
+    }
+  }
+  let name = name.to_owned();
+  let rule = rule.clone();
+  let domain = domain.to_owned();
+  let config = config.clone();
+  let state = Arc::clone(state);
+  rt::spawn(async move {
+    log::info!("acme::spawn: ordering a certificate for {domain}");
+    let res = async {
+      let ssl = issue(&domain, &config, &state).await?;
+      store(&domain, &ssl, &state).await?;
+      super::nginx::add_rule(&name, &rule, &state).await?;
+      state.event_emitter.emit_reload().await;
+      Ok::<_, IoError>(())
+    }
+    .await;
+    match res {
+      Ok(_) => log::info!("acme::spawn: certificate of {domain} installed"),
+      Err(err) => log::warn!("acme::spawn: {domain} {err}"),
+    }
+    if let Ok(mut orders) = state.acme_orders.lock() {
+      orders.remove(&domain);
+    }
+  });
+}
+
+/// Renew the certificates of every rule using ACME
+pub async fn renew(state: &SystemStateRef) -> IoResult<()> {
+  let filter = GenericFilter::new()
+    .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()));
+  let query = GenericFilterNsp {
+    filter: Some(filter),
+    namespace: None,
+  };
+  let resources = state.client.list_resource(Some(&query)).await?;
+  for resource in resources {
+    let Ok(rule) = super::resource::serialize(&resource.spec.data) else {
+      continue;
+    };
+    for http_rule in &rule.rules {
+      let ProxyRule::Http(http_rule) = http_rule else {
+        continue;
+      };
+      let Some(ProxySsl::Acme { acme }) = &http_rule.ssl else {
+        continue;
+      };
+      let Ok(domain) = validate_domain(http_rule.domain.as_ref()) else {
+        continue;
+      };
+      if needs_renewal(domain, acme, state).await {
+        spawn(&resource.spec.resource_key, &rule, domain, acme, state);
+      }
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn jws() {
+    assert_eq!(b64(&[0xfb, 0xff]), "-_8");
+    let account = AccountKey {
+      key: gen_key().unwrap(),
+    };
+    let jwk = account.jwk().unwrap();
+    assert_eq!(jwk["kty"], "EC");
+    assert_eq!(jwk["x"].as_str().unwrap().len(), 43);
+    assert_eq!(account.thumbprint().unwrap().len(), 43);
+    let jws = account
+      .sign(&serde_json::json!({ "alg": "ES256" }), "")
+      .unwrap();
+    assert_eq!(jws["signature"].as_str().unwrap().len(), 86);
+  }
+
+  #[test]
+  fn csr_and_domain() {
+    let key = gen_key().unwrap();
+    let csr = gen_csr("example.com", &key).unwrap();
+    let req = openssl::x509::X509Req::from_der(&csr).unwrap();
+    assert!(req.verify(&key).unwrap());
+    assert!(validate_domain(None).is_err());
+    assert!(validate_domain(Some(&"*.example.com".to_owned())).is_err());
+    assert_eq!(
+      validate_domain(Some(&"example.com".to_owned())).unwrap(),
+      "example.com"
+    );
+  }
+
+  #[test]
+  fn challenge_token() {
+    assert!(
+      validate_token("LoqXcYV8q5ONbJQxbmR7SCTNo3tiAXDfowyjxAjEuX0").is_ok()
+    );
+    assert!(validate_token("").is_err());
+    assert!(validate_token("../../nginx/sites-enabled/x").is_err());
+  }
+}
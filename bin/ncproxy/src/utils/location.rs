@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+
+use nanocld_client::stubs::proxy::{
+  ProxyBasicAuth, ProxyCors, ProxyHttpLocation, ProxyResponseHeaders,
+  ProxyRewrite,
+};
+
+use crate::models::{LocationOptions, StagedConf, SystemStateRef};
+
+/// Default realm of the basic authentication
+const DEFAULT_REALM: &str = "Restricted";
+
+/// Default methods allowed by a cors policy
+const DEFAULT_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, OPTIONS";
+
+/// Quote a value for the nginx configuration, variables are still expanded
+fn quote(value: &str) -> String {
+  format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn validate_header(name: &str) -> IoResult<()> {
+  if name.is_empty()
+    || !name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+  {
+    return Err(IoError::invalid_input(
+      "ProxyHttpLocation",
+      &format!("Invalid header name {name}"),
+    ));
+  }
+  Ok(())
+}
+
+fn validate_max_body_size(size: &str) -> IoResult<()> {
+  let digits = size.trim_end_matches(['k', 'K', 'm', 'M', 'g', 'G']);
+  if digits.is_empty()
+    || size.len() - digits.len() > 1
+    || !digits.chars().all(|c| c.is_ascii_digit())
+  {
+    return Err(IoError::invalid_input(
+      "MaxBodySize",
+      &format!("Invalid size {size}, expected a number with an optional k, m or g unit"),
+    ));
+  }
+  Ok(())
+}
+
+/// Headers of the responses and of the preflight requests of a cors policy
+/// with the configuration matching the allowed origins
+fn gen_cors(
+  key: &str,
+  cors: &ProxyCors,
+) -> IoResult<(Vec<String>, Vec<String>, String)> {
+  if cors.allow_origins.is_empty() {
+    return Err(IoError::invalid_input(
+      "Cors",
+      "At least one origin must be allowed",
+    ));
+  }
+  let credentials = cors.allow_credentials.unwrap_or_default();
+  let any_origin = cors.allow_origins.iter().any(|origin| origin == "*");
+  // Sending back any origin with credentials would let every site
+  // make authenticated requests on behalf of the users
+  if any_origin && credentials {
+    return Err(IoError::invalid_input(
+      "Cors",
+      "Credentials can't be allowed for any origin `*`, list the origins instead",
+    ));
+  }
+  let mut conf = String::new();
+  let origin = match any_origin {
+    true => quote("*"),
+    false => {
+      let var = super::rule::gen_var_name("ncproxy_cors", key);
+      conf += &format!("map $http_origin ${var} {{\n  default \"\";\n");
+      for origin in &cors.allow_origins {
+        conf += &format!("  {} $http_origin;\n", quote(origin));
+      }
+      conf += "}\n";
+      format!("${var}")
+    }
+  };
+  let mut headers =
+    vec![format!("Access-Control-Allow-Origin {origin} always")];
+  if origin != quote("*") {
+    headers.push("Vary Origin always".to_owned());
+  }
+  if credentials {
+    headers.push("Access-Control-Allow-Credentials \"true\" always".to_owned());
+  }
+  if let Some(expose_headers) = &cors.expose_headers {
+    expose_headers.iter().try_for_each(|h| validate_header(h))?;
+    headers.push(format!(
+      "Access-Control-Expose-Headers {} always",
+      quote(&expose_headers.join(", "))
+    ));
+  }
+  let mut preflight = headers.clone();
+  let methods = match &cors.allow_methods {
+    Some(methods) => {
+      methods.iter().try_for_each(|m| validate_header(m))?;
+      methods.join(", ")
+    }
+    None => DEFAULT_METHODS.to_owned(),
+  };
+  preflight.push(format!("Access-Control-Allow-Methods {}", quote(&methods)));
+  let allow_headers = match &cors.allow_headers {
+    Some(allow_headers) => {
+      allow_headers.iter().try_for_each(|h| validate_header(h))?;
+      quote(&allow_headers.join(", "))
+    }
+    None => "$http_access_control_request_headers".to_owned(),
+  };
+  preflight.push(format!("Access-Control-Allow-Headers {allow_headers}"));
+  if let Some(max_age) = cors.max_age {
+    preflight.push(format!("Access-Control-Max-Age {max_age}"));
+  }
+  preflight.push("Content-Type \"text/plain; charset=utf-8\"".to_owned());
+  preflight.push("Content-Length 0".to_owned());
+  Ok((headers, preflight, conf))
+}
+
+/// Escape a path to match it literally in a regular expression
+fn escape_regex(path: &str) -> String {
+  path.chars().fold(String::new(), |mut acc, c| {
+    if "\\.^$*+?()[]{}|".contains(c) {
+      acc.push('\\');
+    }
+    acc.push(c);
+    acc
+  })
+}
+
+/// Arguments of the rewrite directives of a location
+fn gen_rewrites(rewrite: &ProxyRewrite) -> IoResult<Vec<String>> {
+  let mut rewrites = vec![];
+  if let Some(prefix) = &rewrite.strip_prefix {
+    let prefix = prefix.trim_end_matches('/');
+    if !prefix.starts_with('/') {
+      return Err(IoError::invalid_input(
+        "Rewrite",
+        &format!("Prefix {prefix} must start with /"),
+      ));
+    }
+    let prefix = escape_regex(prefix);
+    rewrites.push(format!("{} /", quote(&format!("^{prefix}$"))));
+    rewrites.push(format!("{} /$1", quote(&format!("^{prefix}/(.*)$"))));
+  }
+  for rule in rewrite.rules.iter().flatten() {
+    if rule.pattern.is_empty() || rule.replacement.is_empty() {
+      return Err(IoError::invalid_input(
+        "Rewrite",
+        "Pattern and Replacement can't be empty",
+      ));
+    }
+    rewrites.push(format!(
+      "{} {}",
+      quote(&rule.pattern),
+      quote(&rule.replacement)
+    ));
+  }
+  Ok(rewrites)
+}
+
+/// Headers to add and to hide of the responses
+fn gen_response_headers(
+  response_headers: &ProxyResponseHeaders,
+) -> IoResult<(Vec<String>, Vec<String>)> {
+  let mut add = vec![];
+  for (name, value) in response_headers.add.iter().flatten() {
+    validate_header(name)?;
+    add.push(format!("{name} {} always", quote(value)));
+  }
+  let mut hide = vec![];
+  for name in response_headers.remove.iter().flatten() {
+    validate_header(name)?;
+    hide.push(name.clone());
+  }
+  Ok((add, hide))
+}
+
+/// Alphabet of the crypt base64 encoding
+const CRYPT_ALPHABET: &[u8] =
+  b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Encode the `n` low groups of 6 bits of a value with the crypt alphabet
+fn crypt_base64(mut value: u32, n: usize, out: &mut String) {
+  for _ in 0..n {
+    out.push(CRYPT_ALPHABET[(value & 0x3f) as usize] as char);
+    value >>= 6;
+  }
+}
+
+fn md5(parts: &[&[u8]]) -> IoResult<[u8; 16]> {
+  let mut hasher =
+    openssl::hash::Hasher::new(openssl::hash::MessageDigest::md5())
+      .map_err(|err| IoError::invalid_data("BasicAuth", &err.to_string()))?;
+  for part in parts {
+    hasher
+      .update(part)
+      .map_err(|err| IoError::invalid_data("BasicAuth", &err.to_string()))?;
+  }
+  let digest = hasher
+    .finish()
+    .map_err(|err| IoError::invalid_data("BasicAuth", &err.to_string()))?;
+  let mut hash = [0; 16];
+  hash.copy_from_slice(&digest);
+  Ok(hash)
+}
+
+/// Hash a password with the apache md5 crypt `$apr1$` supported by nginx
+fn apr1(password: &str, salt: &str) -> IoResult<String> {
+  const MAGIC: &[u8] = b"$apr1$";
+  let password = password.as_bytes();
+  let salt = salt.as_bytes();
+  let alternate = md5(&[password, salt, password])?;
+  let mut data = [password, MAGIC, salt].concat();
+  for chunk in password.chunks(16) {
+    data.extend_from_slice(&alternate[..chunk.len()]);
+  }
+  let mut len = password.len();
+  while len > 0 {
+    if len & 1 == 1 {
+      data.push(0);
+    } else {
+      data.push(password[0]);
+    }
+    len >>= 1;
+  }
+  let mut hash = md5(&[&data])?;
+  for i in 0..1000 {
+    let mut parts: Vec<&[u8]> = vec![];
+    parts.push(if i & 1 == 1 { password } else { &hash });
+    if i % 3 != 0 {
+      parts.push(salt);
+    }
+    if i % 7 != 0 {
+      parts.push(password);
+    }
+    parts.push(if i & 1 == 1 { &hash } else { password });
+    hash = md5(&parts)?;
+  }
+  let mut out = format!("$apr1${}$", String::from_utf8_lossy(salt));
+  for (a, b, c) in [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)]
+  {
+    let value = (hash[a] as u32) << 16 | (hash[b] as u32) << 8 | hash[c] as u32;
+    crypt_base64(value, 4, &mut out);
+  }
+  crypt_base64(hash[11] as u32, 2, &mut out);
+  Ok(out)
+}
+
+/// Content of an htpasswd file, passwords already hashed
+/// with crypt or a `{SCHEME}` prefix are kept as is
+fn gen_htpasswd(users: &HashMap<String, String>) -> IoResult<String> {
+  let mut users = users.iter().collect::<Vec<_>>();
+  users.sort();
+  let mut htpasswd = String::new();
+  for (user, password) in users {
+    if user.is_empty()
+      || user.contains(':')
+      || user.chars().any(char::is_whitespace)
+      || password.contains('\n')
+    {
+      return Err(IoError::invalid_input(
+        "BasicAuth",
+        &format!("Invalid user {user}"),
+      ));
+    }
+    let hash = if password.starts_with('$') || password.starts_with('{') {
+      password.clone()
+    } else {
+      let mut salt = [0; 8];
+      openssl::rand::rand_bytes(&mut salt)
+        .map_err(|err| IoError::invalid_data("BasicAuth", &err.to_string()))?;
+      let salt = salt
+        .iter()
+        .map(|byte| CRYPT_ALPHABET[(byte & 0x3f) as usize] as char)
+        .collect::<String>();
+      apr1(password, &salt)?
+    };
+    htpasswd += &format!("{user}:{hash}\n");
+  }
+  Ok(htpasswd)
+}
+
+/// Stage the users of the secret of a basic authentication
+/// and return the quoted realm with the path of the file
+async fn gen_basic_auth(
+  basic_auth: &ProxyBasicAuth,
+  staged: &mut StagedConf,
+  state: &SystemStateRef,
+) -> IoResult<(String, String)> {
+  let secret = state.client.inspect_secret(&basic_auth.secret).await?;
+  let users = serde_json::from_value::<HashMap<String, String>>(secret.data)
+    .map_err(|err| {
+      err.map_err_context(|| "Unable to deserialize the basic auth users")
+    })?;
+  let file = format!("{}.htpasswd", secret.name);
+  let path = format!("{}/secrets/{file}", state.store.dir);
+  staged.write_secret(&file, &gen_htpasswd(&users)?);
+  let realm = quote(basic_auth.realm.as_deref().unwrap_or(DEFAULT_REALM));
+  Ok((realm, path))
+}
+
+/// Directives of a location from its options and the configuration
+/// to add to the http block, `key` is unique to the location
+pub async fn gen_options(
+  key: &str,
+  location: &ProxyHttpLocation,
+  staged: &mut StagedConf,
+  state: &SystemStateRef,
+) -> IoResult<(LocationOptions, String)> {
+  let mut options = LocationOptions {
+    timeouts: location.timeouts.clone(),
+    gzip: location.gzip,
+    websocket: location.websocket.unwrap_or_default(),
+    ..Default::default()
+  };
+  let mut conf = String::new();
+  if options.websocket && location.version.is_some_and(|v| v != 1.1) {
+    return Err(IoError::invalid_input(
+      "Websocket",
+      "Websocket requires the http version 1.1",
+    ));
+  }
+  if let Some(size) = &location.max_body_size {
+    validate_max_body_size(size)?;
+    options.max_body_size = Some(size.clone());
+  }
+  if let Some(cors) = &location.cors {
+    let (headers, preflight, cors_conf) = gen_cors(key, cors)?;
+    options.add_headers.extend(headers);
+    options.preflight = Some(preflight);
+    conf += &cors_conf;
+  }
+  if let Some(response_headers) = &location.response_headers {
+    let (add, hide) = gen_response_headers(response_headers)?;
+    options.add_headers.extend(add);
+    options.hide_headers = hide;
+  }
+  if let Some(rewrite) = &location.rewrite {
+    options.rewrites = gen_rewrites(rewrite)?;
+  }
+  if let Some(basic_auth) = &location.basic_auth {
+    let (realm, path) = gen_basic_auth(basic_auth, staged, state).await?;
+    options.auth_basic = Some(realm);
+    options.auth_basic_user_file = Some(path);
+  }
+  Ok((options, conf))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use nanocld_client::stubs::proxy::ProxyRewriteRule;
+
+  use crate::models::{LocationTemplate, HTTP_TEMPLATE};
+
+  #[test]
+  fn cors() {
+    let mut cors = ProxyCors {
+      allow_origins: vec!["*".to_owned()],
+      ..Default::default()
+    };
+    let (headers, preflight, conf) = gen_cors("app-0", &cors).unwrap();
+    assert_eq!(headers, ["Access-Control-Allow-Origin \"*\" always"]);
+    assert!(preflight.contains(&format!(
+      "Access-Control-Allow-Methods \"{DEFAULT_METHODS}\""
+    )));
+    assert!(conf.is_empty());
+    cors.allow_credentials = Some(true);
+    assert!(gen_cors("app-0", &cors).is_err());
+    cors.allow_origins = vec!["http://localhost:3000".to_owned()];
+    cors.allow_credentials = Some(true);
+    let (headers, _, conf) = gen_cors("app-0", &cors).unwrap();
+    assert_eq!(
+      headers[0],
+      "Access-Control-Allow-Origin $ncproxy_cors_app_0 always"
+    );
+    assert!(conf.contains("\"http://localhost:3000\" $http_origin;"));
+    assert!(headers.contains(
+      &"Access-Control-Allow-Credentials \"true\" always".to_owned()
+    ));
+    cors.allow_headers = Some(vec!["bad header".to_owned()]);
+    assert!(gen_cors("app-0", &cors).is_err());
+  }
+
+  #[test]
+  fn rewrites() {
+    let rewrite = ProxyRewrite {
+      strip_prefix: Some("/api.v1/".to_owned()),
+      rules: Some(vec![ProxyRewriteRule {
+        pattern: "^/old/(.*)$".to_owned(),
+        replacement: "/new/$1".to_owned(),
+      }]),
+    };
+    let rewrites = gen_rewrites(&rewrite).unwrap();
+    assert_eq!(
+      rewrites,
+      [
+        "\"^/api\\\\.v1$\" /",
+        "\"^/api\\\\.v1/(.*)$\" /$1",
+        "\"^/old/(.*)$\" \"/new/$1\"",
+      ]
+    );
+  }
+
+  #[test]
+  fn htpasswd_and_sizes() {
+    let users = HashMap::from([
+      ("admin".to_owned(), "secret".to_owned()),
+      ("bob".to_owned(), "$apr1$hash".to_owned()),
+    ]);
+    let htpasswd = gen_htpasswd(&users).unwrap();
+    let lines = htpasswd.lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("admin:$apr1$"));
+    assert_eq!(
+      apr1("secret", "r31..G9.").unwrap(),
+      "$apr1$r31..G9.$yDoBAwuyvXaUsJFJjQFvn."
+    );
+    assert_eq!(
+      apr1("a longer password that exceeds sixteen bytes", "abcdefgh").unwrap(),
+      "$apr1$abcdefgh$pfpSQCu/Zbt1KSujBWjsm."
+    );
+    assert_eq!(lines[1], "bob:$apr1$hash");
+    let invalid = HashMap::from([("a:b".to_owned(), "secret".to_owned())]);
+    assert!(gen_htpasswd(&invalid).is_err());
+    assert!(validate_max_body_size("10m").is_ok());
+    assert!(validate_max_body_size("512").is_ok());
+    assert!(validate_max_body_size("10mb").is_err());
+    assert!(validate_max_body_size("m").is_err());
+  }
+
+  #[test]
+  fn template() {
+    let options = LocationOptions {
+      add_headers: vec!["X-Frame-Options \"DENY\" always".to_owned()],
+      preflight: Some(vec!["Content-Length 0".to_owned()]),
+      rewrites: vec!["\"^/api$\" /".to_owned()],
+      max_body_size: Some("10m".to_owned()),
+      gzip: Some(false),
+      websocket: true,
+      ..Default::default()
+    };
+    let location = LocationTemplate {
+      path: "/".to_owned(),
+      upstream_key: "http://app".to_owned(),
+      upstream_path: "/".to_owned(),
+      redirect: None,
+      limit_req: None,
+      allowed_ips: None,
+      version: None,
+      headers: None,
+      ssl: None,
+      options,
+    };
+    let conf = HTTP_TEMPLATE
+      .compile(&liquid::object!({
+        "key": "app",
+        "listen": "0.0.0.0:80",
+        "locations": [location],
+      }))
+      .unwrap();
+    assert!(conf.contains("if ($request_method = OPTIONS)"));
+    assert!(conf.contains("rewrite \"^/api$\" / break;"));
+    assert!(conf.contains("client_max_body_size 10m;"));
+    assert!(conf.contains("gzip off;"));
+    assert!(conf.contains("add_header X-Frame-Options \"DENY\" always;"));
+    assert!(!conf.contains("Strict-Transport-Security"));
+    assert!(
+      conf.contains("proxy_set_header Connection         $connection_upgrade;")
+    );
+  }
+}
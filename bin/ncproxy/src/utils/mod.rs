@@ -1,3 +1,5 @@
+pub mod acme;
+pub mod location;
 pub mod nginx;
 pub mod resource;
 pub mod rule;
@@ -7,22 +7,38 @@ use nanocl_error::io::{IoError, IoResult};
 
 use nanocld_client::{
   bollard_next::exec::{CreateExecOptions, StartExecOptions},
-  stubs::proxy::{LocationTarget, ProxyRule, ResourceProxyRule},
+  stubs::proxy::{LocationTarget, ProxyRule, ProxySsl, ResourceProxyRule},
   NanocldClient,
 };
 
 use crate::models::{
-  LocationTemplate, NginxRuleKind, SystemStateRef, CONF_TEMPLATE,
-  HTTP_TEMPLATE, STREAM_TEMPLATE,
+  LocationTemplate, NginxRuleKind, StagedConf, SystemStateRef, UpstreamOptions,
+  CONF_TEMPLATE, HTTP_TEMPLATE, STREAM_TEMPLATE,
 };
 
+/// Directory where a candidate configuration is validated
+fn staging_dir(state: &SystemStateRef) -> String {
+  format!("{}/staging", state.store.dir)
+}
+
+/// Directory of the last configuration validated by nginx
+fn last_good_dir(state: &SystemStateRef) -> String {
+  format!("{}/last-good", state.store.dir)
+}
+
+/// Main configuration including the rules enabled in `conf_dir`
+fn gen_main_conf(state: &SystemStateRef, conf_dir: &str) -> IoResult<String> {
+  CONF_TEMPLATE.compile(&liquid::object!({
+    "nginx_dir": state.nginx_dir,
+    "state_dir": state.store.dir,
+    "conf_dir": conf_dir,
+  }))
+}
+
 pub async fn ensure_conf(state: &SystemStateRef) -> IoResult<()> {
   let state_ref = Arc::clone(state);
   let conf_path = format!("{}/nginx.conf", state_ref.store.dir);
-  let default_conf = CONF_TEMPLATE.compile(&liquid::object!({
-    "nginx_dir": state_ref.nginx_dir,
-    "state_dir": state_ref.store.dir,
-  }))?;
+  let default_conf = gen_main_conf(state, &state.store.dir)?;
   web::block(move || {
     [
       "sites-available",
@@ -31,6 +47,7 @@ pub async fn ensure_conf(state: &SystemStateRef) -> IoResult<()> {
       "streams-enabled",
       "log",
       "secrets",
+      "acme/challenges",
     ]
     .iter()
     .map(|name| {
@@ -45,18 +62,58 @@ pub async fn ensure_conf(state: &SystemStateRef) -> IoResult<()> {
     "NginxManager: writing default conf to {conf_path}:\n{default_conf}"
   );
   std::fs::write(conf_path, default_conf)?;
-  self::test(&state.client).await?;
+  if let Err(err) = self::test(&state.client).await {
+    log::warn!("nginx::ensure_conf: {err}");
+    let _lock = state.conf_lock.lock().await;
+    restore_last_good(state).await?;
+    self::test(&state.client).await?;
+  }
+  Ok(())
+}
+
+/// Lines of the output of `nginx -t` explaining why a configuration is invalid
+fn parse_test_error(output: &str) -> String {
+  let lines = output
+    .lines()
+    .filter(|line| {
+      ["[emerg]", "[alert]", "[crit]"]
+        .iter()
+        .any(|level| line.contains(level))
+    })
+    .map(|line| line.trim_start_matches("nginx: ").trim())
+    .collect::<Vec<_>>();
+  if lines.is_empty() {
+    return output.trim().to_owned();
+  }
+  lines.join("\n")
+}
+
+/// Validate a configuration with nginx, the default one when `path` is None
+async fn test_conf(path: Option<&str>, client: &NanocldClient) -> IoResult<()> {
+  let cmd = match path {
+    Some(path) => format!("nginx -t -c {path}"),
+    None => "nginx -t".to_owned(),
+  };
+  let (success, output) = exec_nginx_output(&cmd, client).await?;
+  if !success {
+    return Err(IoError::invalid_input("nginx", &parse_test_error(&output)));
+  }
   Ok(())
 }
 
 pub async fn test(client: &NanocldClient) -> IoResult<()> {
   log::info!("nginx::test: starting");
-  exec_nginx_cmd("nginx -t", client).await?;
+  test_conf(None, client).await?;
   log::info!("nginx::test: done");
   Ok(())
 }
 
-async fn exec_nginx_cmd(cmd: &str, client: &NanocldClient) -> IoResult<()> {
+/// Execute a command in the nginx container and return whether it succeeded
+/// with its output
+async fn exec_nginx_output(
+  cmd: &str,
+  client: &NanocldClient,
+) -> IoResult<(bool, String)> {
   let exec_options = CreateExecOptions {
     attach_stderr: Some(true),
     attach_stdout: Some(true),
@@ -77,15 +134,112 @@ async fn exec_nginx_cmd(cmd: &str, client: &NanocldClient) -> IoResult<()> {
     output += &output_log.data;
   }
   let inspect_result = client.inspect_exec(&start_res.id).await?;
-  match inspect_result.exit_code {
-    Some(code) => {
-      if code == 0 {
-        return Ok(());
+  let success = inspect_result.exit_code.map_or(true, |code| code == 0);
+  Ok((success, output))
+}
+
+async fn exec_nginx_cmd(cmd: &str, client: &NanocldClient) -> IoResult<()> {
+  let (success, output) = exec_nginx_output(cmd, client).await?;
+  if !success {
+    return Err(IoError::other("exec", &output));
+  }
+  Ok(())
+}
+
+/// Copy the enabled configurations of a directory to another,
+/// without the files named `exclude`
+async fn copy_enabled(
+  from: &str,
+  to: &str,
+  exclude: Option<&str>,
+) -> IoResult<()> {
+  for kind in [NginxRuleKind::Site, NginxRuleKind::Stream] {
+    let src = format!("{from}/{}", kind.enabled_dir());
+    let dst = format!("{to}/{}", kind.enabled_dir());
+    tokio::fs::create_dir_all(&dst).await?;
+    let Ok(mut entries) = tokio::fs::read_dir(&src).await else {
+      continue;
+    };
+    while let Some(entry) = entries.next_entry().await? {
+      let file_name = entry.file_name().to_string_lossy().to_string();
+      if Some(file_name.as_str()) == exclude {
+        continue;
+      }
+      // Enabled files are symlinks, their content is copied
+      if let Err(err) =
+        tokio::fs::copy(entry.path(), format!("{dst}/{file_name}")).await
+      {
+        log::warn!("nginx::copy_enabled: {file_name} {err}");
       }
-      Err(IoError::other("exec", &output))
     }
-    None => Ok(()),
   }
+  Ok(())
+}
+
+/// Validate the staged files of a rule with the rest of the configuration
+/// then swap them in. The live configuration is untouched when nginx rejects them
+/// and the validated one is kept as the last known good configuration.
+async fn apply_staged(
+  name: &str,
+  staged: &StagedConf,
+  state: &SystemStateRef,
+) -> IoResult<()> {
+  let _lock = state.conf_lock.lock().await;
+  let staging = staging_dir(state);
+  let _ = tokio::fs::remove_dir_all(&staging).await;
+  let rule_file = format!("{name}.conf");
+  copy_enabled(&state.store.dir, &staging, Some(&rule_file)).await?;
+  for (file, kind, data) in &staged.files {
+    let path = format!("{staging}/{}/{file}.conf", kind.enabled_dir());
+    tokio::fs::write(&path, data).await?;
+  }
+  let conf_path = format!("{staging}/nginx.conf");
+  tokio::fs::write(&conf_path, gen_main_conf(state, &staging)?).await?;
+  test_conf(Some(&conf_path), &state.client).await?;
+  for kind in [NginxRuleKind::Site, NginxRuleKind::Stream] {
+    if !staged.files.iter().any(|(f, k, _)| f == name && *k == kind) {
+      state.store.delete_conf_file(name, &kind).await;
+    }
+  }
+  for (file, data) in &staged.secrets {
+    state.store.write_secret_file(file, data).await?;
+  }
+  for (file, kind, data) in &staged.files {
+    state.store.write_conf_file(file, data, kind).await?;
+  }
+  let last_good = last_good_dir(state);
+  let _ = tokio::fs::remove_dir_all(&last_good).await;
+  tokio::fs::rename(&staging, &last_good).await?;
+  Ok(())
+}
+
+/// Replace the enabled configurations with the last ones validated by nginx
+async fn restore_last_good(state: &SystemStateRef) -> IoResult<()> {
+  let last_good = last_good_dir(state);
+  if tokio::fs::metadata(&last_good).await.is_err() {
+    return Err(IoError::not_found(
+      "nginx",
+      "No valid configuration to restore",
+    ));
+  }
+  log::info!("nginx::restore_last_good: restoring {last_good}");
+  for kind in [NginxRuleKind::Site, NginxRuleKind::Stream] {
+    let enabled = format!("{}/{}", state.store.dir, kind.enabled_dir());
+    let mut entries = tokio::fs::read_dir(&enabled).await?;
+    while let Some(entry) = entries.next_entry().await? {
+      tokio::fs::remove_file(entry.path()).await?;
+    }
+    let mut entries =
+      tokio::fs::read_dir(format!("{last_good}/{}", kind.enabled_dir()))
+        .await?;
+    while let Some(entry) = entries.next_entry().await? {
+      let file_name = entry.file_name().to_string_lossy().to_string();
+      let data = tokio::fs::read_to_string(entry.path()).await?;
+      let name = file_name.trim_end_matches(".conf");
+      state.store.write_conf_file(name, &data, &kind).await?;
+    }
+  }
+  Ok(())
 }
 
 pub async fn reload(client: &NanocldClient) -> IoResult<()> {
@@ -102,6 +256,9 @@ pub async fn add_rule(
 ) -> IoResult<()> {
   let mut stream_conf = String::new();
   let mut http_conf = String::new();
+  let mut acme_orders = vec![];
+  let mut location_index = 0;
+  let mut staged = StagedConf::default();
   for rule in &rule.rules {
     match rule {
       ProxyRule::Stream(stream_rule) => {
@@ -113,6 +270,7 @@ pub async fn add_rule(
         .await?;
         let upstream_key = match super::rule::gen_stream_upstream_key(
           &stream_rule.target,
+          &mut staged,
           state,
         )
         .await
@@ -159,22 +317,48 @@ pub async fn add_rule(
           &state.client,
         )
         .await?;
-        let ssl = match &http_rule.ssl {
+        let (ssl, listen_acme) = match &http_rule.ssl {
+          Some(ProxySsl::Acme { acme }) => {
+            let domain =
+              super::acme::validate_domain(http_rule.domain.as_ref())?;
+            let listen_acme = super::rule::get_network_addr(
+              &http_rule.network,
+              80,
+              &state.client,
+            )
+            .await?;
+            let secret = ProxySsl::Secret(super::acme::secret_name(domain));
+            let ssl = super::rule::gen_ssl_config(&secret, state).await.ok();
+            if ssl.is_none()
+              || super::acme::needs_renewal(domain, acme, state).await
+            {
+              acme_orders.push((domain, acme));
+            }
+            (ssl, Some(listen_acme))
+          }
           Some(ssl) => match super::rule::gen_ssl_config(ssl, state).await {
             Err(err) => {
               log::warn!("Not ssl found for {name} {ssl:#?} {err}");
-              None
+              (None, None)
             }
-            Ok(ssl) => Some(ssl),
+            Ok(ssl) => (Some(ssl), None),
           },
-          None => None,
+          None => (None, None),
         };
         for location in &http_rule.locations {
+          let key = format!("{name}-{location_index}");
+          location_index += 1;
+          let (mut options, conf) =
+            super::location::gen_options(&key, location, &mut staged, state)
+              .await?;
+          http_conf += &conf;
           match &location.target {
             LocationTarget::Upstream(upstream) => {
               let upstream_key = match super::rule::gen_upstream(
                 upstream,
                 &NginxRuleKind::Site,
+                &UpstreamOptions::from(location),
+                &mut staged,
                 state,
               )
               .await
@@ -197,6 +381,11 @@ pub async fn add_rule(
                 }
                 None => None,
               };
+              if let Some(sticky) = &location.sticky_session {
+                let cookie =
+                  super::rule::gen_sticky_cookie(&upstream_key, sticky)?;
+                options.add_headers.push(format!("Set-Cookie \"{cookie}\""));
+              }
               let location = LocationTemplate {
                 path: location.path.clone(),
                 limit_req: location.limit_req.clone(),
@@ -211,6 +400,7 @@ pub async fn add_rule(
                 allowed_ips: location.allowed_ips.clone(),
                 headers: location.headers.clone(),
                 ssl,
+                options,
               };
               locations.push(location);
             }
@@ -218,9 +408,8 @@ pub async fn add_rule(
               let upstream_key = super::rule::gen_unix_target_key(
                 unix,
                 &NginxRuleKind::Site,
-                state,
-              )
-              .await?;
+                &mut staged,
+              )?;
               let location = LocationTemplate {
                 path: location.path.clone(),
                 upstream_key: format!("http://{upstream_key}"),
@@ -231,6 +420,38 @@ pub async fn add_rule(
                 allowed_ips: location.allowed_ips.clone(),
                 headers: location.headers.clone(),
                 ssl: None,
+                options,
+              };
+              locations.push(location);
+            }
+            LocationTarget::Split(split) => {
+              let (var, conf) = match super::rule::gen_split(
+                &key,
+                split,
+                &UpstreamOptions::from(location),
+                &mut staged,
+                state,
+              )
+              .await
+              {
+                Err(err) => {
+                  log::warn!("{err} {:#?}", split);
+                  continue;
+                }
+                Ok(split) => split,
+              };
+              http_conf += &conf;
+              let location = LocationTemplate {
+                path: location.path.clone(),
+                upstream_key: format!("http://${var}"),
+                redirect: None,
+                limit_req: location.limit_req.clone(),
+                upstream_path: String::new(),
+                version: location.version,
+                allowed_ips: location.allowed_ips.clone(),
+                headers: location.headers.clone(),
+                ssl: None,
+                options,
               };
               locations.push(location);
             }
@@ -245,6 +466,7 @@ pub async fn add_rule(
                 headers: location.headers.clone(),
                 redirect: http.redirect.clone().map(|r| format!("{r}")),
                 ssl: None,
+                options,
               };
               locations.push(location);
             }
@@ -259,37 +481,54 @@ pub async fn add_rule(
           "locations": locations,
           "ssl": ssl,
           "hide_upstream": http_rule.ssl.is_some() && ssl.is_none(),
+          "acme_challenge": listen_acme
+            .as_ref()
+            .map(|_| super::acme::challenge_dir(state)),
+          "listen_acme": listen_acme,
         }))?;
         http_conf += &data;
       }
     }
   }
   if !stream_conf.is_empty() {
-    state
-      .store
-      .write_conf_file(name, &stream_conf, &NginxRuleKind::Stream)
-      .await?;
+    staged.write(name, &stream_conf, &NginxRuleKind::Stream);
   }
   if !http_conf.is_empty() {
-    state
-      .store
-      .write_conf_file(name, &http_conf, &NginxRuleKind::Site)
-      .await?;
+    staged.write(name, &http_conf, &NginxRuleKind::Site);
   }
-  if let Err(err) = self::test(&state.client).await {
-    let _ = del_rule(name, state).await;
-    return Err(err);
+  apply_staged(name, &staged, state).await?;
+  for (domain, acme) in acme_orders {
+    super::acme::spawn(name, rule, domain, acme, state);
   }
   Ok(())
 }
 
-pub async fn del_rule(name: &str, state: &SystemStateRef) {
-  let _ = state
-    .store
-    .delete_conf_file(name, &NginxRuleKind::Site)
-    .await;
-  let _ = state
-    .store
-    .delete_conf_file(name, &NginxRuleKind::Stream)
-    .await;
+/// Delete the configuration of a rule, returns whether it existed
+pub async fn del_rule(name: &str, state: &SystemStateRef) -> bool {
+  let _lock = state.conf_lock.lock().await;
+  let last_good = last_good_dir(state);
+  let mut existed = false;
+  for kind in [NginxRuleKind::Site, NginxRuleKind::Stream] {
+    existed |= state.store.delete_conf_file(name, &kind).await;
+    let path = format!("{last_good}/{}/{name}.conf", kind.enabled_dir());
+    let _ = tokio::fs::remove_file(path).await;
+  }
+  existed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_error() {
+    let output = "nginx: [warn] the \"ssl\" directive is deprecated\n\
+      nginx: [emerg] unknown directive \"proxy_pas\" in /opt/proxy/staging/sites-enabled/app.conf:12\n\
+      nginx: configuration file /opt/proxy/staging/nginx.conf test failed\n";
+    assert_eq!(
+      parse_test_error(output),
+      "[emerg] unknown directive \"proxy_pas\" in /opt/proxy/staging/sites-enabled/app.conf:12"
+    );
+    assert_eq!(parse_test_error(" exec failed \n"), "exec failed");
+  }
 }
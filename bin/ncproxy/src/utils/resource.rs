@@ -3,30 +3,56 @@ use nanocl_error::io::{FromIo, IoError, IoResult};
 
 use nanocld_client::{
   stubs::{
-    generic::{GenericClause, GenericFilter},
+    generic::{GenericClause, GenericFilter, GenericFilterNsp},
     proxy::ResourceProxyRule,
-    resource::{Resource, ResourcePartial},
+    resource::Resource,
   },
   NanocldClient,
 };
 
 use crate::{models::SystemStateRef, vars};
 
+/// List the rules whose data contains one of the patterns
+async fn list_by_patterns(
+  patterns: &[serde_json::Value],
+  client: &NanocldClient,
+) -> IoResult<Vec<Resource>> {
+  let mut resources = Vec::<Resource>::new();
+  for pattern in patterns {
+    let filter = GenericFilter::new()
+      .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()))
+      .r#where("data", GenericClause::Contains(pattern.clone()));
+    let query = GenericFilterNsp {
+      filter: Some(filter),
+      namespace: None,
+    };
+    let matches = client.list_resource(Some(&query)).await.map_err(|err| {
+      err.map_err_context(|| "Unable to list resources from nanocl daemon")
+    })?;
+    for resource in matches {
+      // A resource can match several patterns
+      if !resources
+        .iter()
+        .any(|r| r.spec.resource_key == resource.spec.resource_key)
+      {
+        resources.push(resource);
+      }
+    }
+  }
+  Ok(resources)
+}
+
 pub async fn list_by_secret(
   name: &str,
   client: &NanocldClient,
 ) -> IoResult<Vec<Resource>> {
-  let filter = GenericFilter::new()
-    .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()))
-    .r#where(
-      "data",
-      GenericClause::Contains(
-        serde_json::json!({ "Rules": [ { "Ssl": name }  ] }),
-      ),
-    );
-  let resources = client.list_resource(Some(&filter)).await.map_err(|err| {
-    err.map_err_context(|| "Unable to list resources from nanocl daemon")
-  })?;
+  let patterns = [
+    serde_json::json!({ "Rules": [ { "Ssl": name } ] }),
+    serde_json::json!({
+      "Rules": [ { "Locations": [ { "BasicAuth": { "Secret": name } } ] } ]
+    }),
+  ];
+  let resources = list_by_patterns(&patterns, client).await?;
   if resources.is_empty() {
     return Err(IoError::not_found(
       "Resource",
@@ -43,34 +69,18 @@ pub(crate) async fn list_by_cargo(
 ) -> IoResult<Vec<Resource>> {
   let namespace = namespace.unwrap_or("global".into());
   let target_key = format!("{name}.{namespace}.c");
-  let filter = GenericFilter::new()
-  .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()))
-  .r#where(
-    "data",
-    GenericClause::Contains(
-      serde_json::json!({ "Rules": [ { "Locations": [ { "Target": { "Key": target_key } } ] }  ] }),
-    ),
-  );
-  let http_resources =
-    client.list_resource(Some(&filter)).await.map_err(|err| {
-      err.map_err_context(|| "Unable to list resources from nanocl daemon")
-    })?;
-  let filter = GenericFilter::new()
-  .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()))
-  .r#where(
-    "data",
-    GenericClause::Contains(
-      serde_json::json!({ "Rules": [ {  "Target": { "Key": target_key } } ] }),
-    ),
-  );
-  let stream_resources =
-    client.list_resource(Some(&filter)).await.map_err(|err| {
-      err.map_err_context(|| "Unable to list resources from nanocl daemon")
-    })?;
-  let resources = http_resources
-    .into_iter()
-    .chain(stream_resources.into_iter())
-    .collect::<Vec<nanocld_client::stubs::resource::Resource>>();
+  let target = serde_json::json!({ "Key": target_key });
+  let patterns = [
+    serde_json::json!({ "Rules": [ { "Locations": [ { "Target": target } ] } ] }),
+    serde_json::json!({
+      "Rules": [ { "Locations": [ { "Target": { "Split": [ { "Target": target } ] } } ] } ]
+    }),
+    serde_json::json!({
+      "Rules": [ { "Locations": [ { "Target": { "Canary": [ { "Target": target } ] } } ] } ]
+    }),
+    serde_json::json!({ "Rules": [ { "Target": target } ] }),
+  ];
+  let resources = list_by_patterns(&patterns, client).await?;
   if resources.is_empty() {
     return Err(IoError::not_found(
       "Resource",
@@ -90,6 +100,41 @@ pub(crate) fn serialize(
   Ok(resource)
 }
 
+/// Rules of the global namespace used to be keyed by their name only,
+/// remove their previous configuration so it doesn't collide with the current one.
+/// A name is kept when it's the key of a current rule like `<name>.<namespace>`.
+pub(crate) async fn del_legacy_rules(state: &SystemStateRef) -> IoResult<()> {
+  let filter = GenericFilter::new()
+    .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()));
+  let query = GenericFilterNsp {
+    filter: Some(filter),
+    namespace: None,
+  };
+  let resources =
+    state
+      .client
+      .list_resource(Some(&query))
+      .await
+      .map_err(|err| {
+        err.map_err_context(|| "Unable to list resources from nanocl daemon")
+      })?;
+  let mut deleted = false;
+  for resource in &resources {
+    if resource.namespace_name != "global"
+      || resources
+        .iter()
+        .any(|r| r.spec.resource_key == resource.name)
+    {
+      continue;
+    }
+    deleted |= super::nginx::del_rule(&resource.name, state).await;
+  }
+  if deleted {
+    state.event_emitter.emit_reload().await;
+  }
+  Ok(())
+}
+
 pub(crate) async fn update_rules(
   resources: &[Resource],
   state: &SystemStateRef,
@@ -97,9 +142,8 @@ pub(crate) async fn update_rules(
   resources
     .iter()
     .map(|resource| async move {
-      let resource: ResourcePartial = resource.clone().into();
-      let rule = serialize(&resource.data)?;
-      super::nginx::add_rule(&resource.name, &rule, state).await?;
+      let rule = serialize(&resource.spec.data)?;
+      super::nginx::add_rule(&resource.spec.resource_key, &rule, state).await?;
       Ok::<_, IoError>(())
     })
     .collect::<FuturesUnordered<_>>()
@@ -3,18 +3,25 @@ use nanocl_error::io::{FromIo, IoError, IoResult};
 use nanocld_client::{
   stubs::{
     generic::NetworkKind,
+    namespace,
     process::Process,
     proxy::{
-      ProxySsl, ProxySslConfig, StreamTarget, UnixTarget, UpstreamTarget,
+      CanaryRoute, ProxyLoadBalancing, ProxySsl, ProxySslConfig,
+      ProxyStickySession, SplitTarget, StreamTarget, UnixTarget,
+      UpstreamTarget,
     },
   },
   NanocldClient,
 };
 
 use crate::models::{
-  NginxRuleKind, SystemStateRef, UNIX_UPSTREAM_TEMPLATE, UPSTREAM_TEMPLATE,
+  NginxRuleKind, StagedConf, SystemStateRef, UpstreamOptions, SPLIT_TEMPLATE,
+  UNIX_UPSTREAM_TEMPLATE, UPSTREAM_TEMPLATE,
 };
 
+/// Default name of the cookie storing a sticky session
+const STICKY_COOKIE: &str = "ncproxy_route";
+
 /// Get public address of host
 async fn get_host_addr(client: &NanocldClient) -> IoResult<String> {
   let info = client
@@ -55,10 +62,28 @@ fn parse_upstream_target(key: &str) -> IoResult<(String, String, String)> {
   Ok((name, namespace, kind))
 }
 
+/// Get the address and the node of the instances on a network
+/// Index of the instance of a cargo, vms have a single instance
+fn instance_index(process: &Process) -> usize {
+  process
+    .data
+    .config
+    .as_ref()
+    .and_then(|config| config.env.as_ref())
+    .and_then(|env| {
+      env
+        .iter()
+        .find_map(|var| var.strip_prefix("NANOCL_CARGO_INSTANCE="))
+    })
+    .and_then(|index| index.parse().ok())
+    .unwrap_or_default()
+}
+
+/// Addresses of the processes on a network with the index of their instance
 pub async fn get_addresses(
   processes: &[Process],
   network: &str,
-) -> IoResult<Vec<String>> {
+) -> IoResult<Vec<(String, usize)>> {
   let mut addresses = vec![];
   for process in processes {
     log::debug!("get_addresses from: {}", process.name);
@@ -72,7 +97,11 @@ pub async fn get_addresses(
       .unwrap_or_default()
       .networks
       .unwrap_or_default();
-    let network = networks.get(network);
+    // Instances created before their namespace had its own network
+    // are still attached to the default bridge until they are recreated
+    let network = networks
+      .get(network)
+      .or_else(|| networks.get(namespace::DEFAULT_NETWORK));
     let Some(network) = network else {
       continue;
     };
@@ -82,7 +111,7 @@ pub async fn get_addresses(
     if ip_address.is_empty() {
       continue;
     }
-    addresses.push(ip_address);
+    addresses.push((ip_address, instance_index(process)));
   }
   if addresses.is_empty() {
     return Err(IoError::invalid_data(
@@ -144,18 +173,161 @@ pub async fn gen_ssl_config(
       ssl_config.certificate_key = key_path;
       Ok(ssl_config)
     }
+    ProxySsl::Acme { .. } => Err(IoError::invalid_input(
+      "ProxySsl",
+      "Acme is only supported by http rules",
+    )),
+  }
+}
+
+/// Name of an nginx variable derived from a key
+pub fn gen_var_name(prefix: &str, key: &str) -> String {
+  let key = key
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect::<String>();
+  format!("{prefix}_{key}")
+}
+
+/// Name of the nginx variable holding the sticky session of an upstream
+fn sticky_var(upstream_key: &str) -> String {
+  gen_var_name("ncproxy_sticky", upstream_key)
+}
+
+/// Name of the cookie of a sticky session
+fn sticky_cookie_name(sticky: &ProxyStickySession) -> IoResult<&str> {
+  let cookie = sticky.cookie.as_deref().unwrap_or(STICKY_COOKIE);
+  if cookie.is_empty()
+    || !cookie
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_')
+  {
+    return Err(IoError::invalid_input(
+      "StickySession",
+      &format!("Cookie {cookie} must only contain letters, digits and _"),
+    ));
+  }
+  Ok(cookie)
+}
+
+/// Set-Cookie header keeping the session of a client on an upstream
+pub fn gen_sticky_cookie(
+  upstream_key: &str,
+  sticky: &ProxyStickySession,
+) -> IoResult<String> {
+  let cookie = sticky_cookie_name(sticky)?;
+  let max_age = sticky
+    .max_age
+    .map(|max_age| format!("; Max-Age={max_age}"))
+    .unwrap_or_default();
+  Ok(format!(
+    "{cookie}=${}; Path=/; HttpOnly{max_age}",
+    sticky_var(upstream_key)
+  ))
+}
+
+/// Key of an upstream, targets with options get their own upstream
+fn gen_upstream_key(
+  key: &str,
+  port: u16,
+  kind: &str,
+  options: &UpstreamOptions,
+) -> String {
+  if options.is_default() {
+    return format!("{key}-{port}-{kind}");
+  }
+  // The options serialize with their fields and the weights in a fixed order,
+  // so the same options get the same key on every node and release
+  let options = serde_json::to_vec(options).unwrap_or_default();
+  let digest = openssl::sha::sha256(&options);
+  let mut hash = [0; 8];
+  hash.copy_from_slice(&digest[..8]);
+  format!("{key}-{port}-{kind}-{:016x}", u64::from_be_bytes(hash))
+}
+
+/// Render an upstream balancing the requests between the given addresses
+fn gen_upstream_conf(
+  key: &str,
+  port: u16,
+  addresses: &[(String, usize)],
+  kind: &NginxRuleKind,
+  options: &UpstreamOptions,
+) -> IoResult<String> {
+  if matches!(kind, NginxRuleKind::Stream) && !options.is_default() {
+    return Err(IoError::invalid_input(
+      "UpstreamTarget",
+      "Upstream options are only supported by http locations",
+    ));
+  }
+  let mut sticky = None;
+  let mut directive = match &options.load_balancing {
+    Some(ProxyLoadBalancing::LeastConn) => Some("least_conn".to_owned()),
+    Some(ProxyLoadBalancing::IpHash) => Some("ip_hash".to_owned()),
+    _ => None,
+  };
+  if let Some(sticky_session) = &options.sticky_session {
+    if directive.is_some() {
+      return Err(IoError::invalid_input(
+        "StickySession",
+        "Only RoundRobin and Weighted load balancing support sticky sessions",
+      ));
+    }
+    let var = sticky_var(key);
+    directive = Some(format!("hash ${var} consistent"));
+    sticky = Some(liquid::object!({
+      "var": var,
+      "cookie": sticky_cookie_name(sticky_session)?,
+    }));
   }
+  let mut health = String::new();
+  if let Some(health_check) = &options.health_check {
+    if let Some(max_fails) = health_check.max_fails {
+      health += &format!(" max_fails={max_fails}");
+    }
+    if let Some(fail_timeout) = health_check.fail_timeout {
+      health += &format!(" fail_timeout={fail_timeout}s");
+    }
+  }
+  let servers = addresses
+    .iter()
+    .map(|(address, index)| {
+      let weight = match &options.load_balancing {
+        Some(ProxyLoadBalancing::Weighted(weights)) => {
+          match weights.get(index).copied().unwrap_or(1) {
+            0 => {
+              return Err(IoError::invalid_input(
+                "LoadBalancing",
+                &format!("Weight of instance {index} must be at least 1"),
+              ))
+            }
+            weight => format!(" weight={weight}"),
+          }
+        }
+        _ => String::new(),
+      };
+      Ok(format!("{address}:{port}{weight}{health}"))
+    })
+    .collect::<IoResult<Vec<_>>>()?;
+  UPSTREAM_TEMPLATE.compile(&liquid::object!({
+    "key": key,
+    "servers": servers,
+    "directive": directive,
+    "sticky": sticky,
+  }))
 }
 
 pub async fn gen_upstream(
   target: &UpstreamTarget,
   kind: &NginxRuleKind,
+  options: &UpstreamOptions,
+  staged: &mut StagedConf,
   state: &SystemStateRef,
 ) -> IoResult<String> {
   let (target_name, target_namespace, target_kind) =
     parse_upstream_target(&target.key)?;
   let port = target.port;
-  let (key, content) = match target_kind.as_str() {
+  let network = namespace::network_name(&target_namespace);
+  let (key, addresses) = match target_kind.as_str() {
     "c" => {
       let cargo = state
         .client
@@ -166,14 +338,9 @@ pub async fn gen_upstream(
             format!("Unable to inspect cargo {target_name}")
           })
         })?;
-      let addresses = get_addresses(&cargo.instances, "nanoclbr0").await?;
-      let key = format!("{}-{}-cargo", cargo.spec.cargo_key, port);
-      let data = UPSTREAM_TEMPLATE.compile(&liquid::object!({
-        "key": key,
-        "port": port,
-        "addresses": addresses,
-      }))?;
-      (key, data)
+      let addresses = get_addresses(&cargo.instances, &network).await?;
+      let key = gen_upstream_key(&cargo.spec.cargo_key, port, "cargo", options);
+      (key, addresses)
     }
     "v" => {
       let vm = state
@@ -183,14 +350,9 @@ pub async fn gen_upstream(
         .map_err(|err| {
           err.map_err_context(|| format!("Unable to inspect vm {target_name}"))
         })?;
-      let addresses = get_addresses(&vm.instances, "nanoclbr0").await?;
-      let key = format!("{}-{}-vm", vm.spec.vm_key, port);
-      let data = UPSTREAM_TEMPLATE.compile(&liquid::object!({
-        "key": key,
-        "port": port,
-        "addresses": addresses,
-      }))?;
-      (key, data)
+      let addresses = get_addresses(&vm.instances, &network).await?;
+      let key = gen_upstream_key(&vm.spec.vm_key, port, "vm", options);
+      (key, addresses)
     }
     _ => {
       return Err(IoError::invalid_data(
@@ -199,40 +361,378 @@ pub async fn gen_upstream(
       ))
     }
   };
-  state.store.write_conf_file(&key, &content, kind).await?;
+  let content = gen_upstream_conf(&key, port, &addresses, kind, options)?;
+  staged.write(&key, &content, kind);
   Ok(key)
 }
 
-pub async fn gen_unix_target_key(
+/// Nginx variable of a request header or cookie matched by a canary route
+fn canary_source(route: &CanaryRoute) -> IoResult<String> {
+  let (prefix, name) = match (&route.header, &route.cookie) {
+    (Some(header), None) => ("http_", header),
+    (None, Some(cookie)) => ("cookie_", cookie),
+    _ => {
+      return Err(IoError::invalid_input(
+        "CanaryRoute",
+        "Exactly one of Header or Cookie must be set",
+      ))
+    }
+  };
+  if name.is_empty()
+    || !name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    || (prefix == "cookie_" && name.contains('-'))
+  {
+    return Err(IoError::invalid_input(
+      "CanaryRoute",
+      &format!("Invalid header or cookie name {name}"),
+    ));
+  }
+  Ok(format!("{prefix}{}", name.to_lowercase().replace('-', "_")))
+}
+
+/// Escape a value to be quoted in a nginx map
+fn escape_map_value(value: &str) -> String {
+  value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render the variables selecting the upstream of a split target,
+/// `upstreams` are the weights and the upstreams of the split
+/// and `canaries` the routes with their upstream
+fn gen_split_conf(
+  key: &str,
+  upstreams: &[(u32, String)],
+  canaries: &[(&CanaryRoute, String)],
+) -> IoResult<(String, String)> {
+  let upstreams = upstreams
+    .iter()
+    .filter(|(weight, _)| *weight > 0)
+    .collect::<Vec<_>>();
+  let total = upstreams
+    .iter()
+    .map(|(weight, _)| *weight as u64)
+    .sum::<u64>();
+  if total == 0 {
+    return Err(IoError::invalid_input(
+      "SplitTarget",
+      "At least one target must have a weight",
+    ));
+  }
+  let splits = upstreams
+    .iter()
+    .enumerate()
+    .map(|(i, (weight, upstream))| {
+      if i + 1 == upstreams.len() {
+        return format!("* {upstream}");
+      }
+      let percent = *weight as u64 * 10000 / total;
+      format!("{}.{:02}% {upstream}", percent / 100, percent % 100)
+    })
+    .collect::<Vec<_>>();
+  let split_var = gen_var_name("ncproxy_split", key);
+  let mut var = split_var.clone();
+  let mut routes = vec![];
+  // Built from the last route so the first matching one takes precedence
+  for (i, (route, upstream)) in canaries.iter().enumerate().rev() {
+    let source = canary_source(route)?;
+    let entries = match &route.value {
+      Some(value) => vec![
+        format!("\"{}\" {upstream}", escape_map_value(value)),
+        format!("default ${var}"),
+      ],
+      None => vec![format!("\"\" ${var}"), format!("default {upstream}")],
+    };
+    var = gen_var_name("ncproxy_canary", &format!("{key}-{i}"));
+    routes.push(liquid::object!({
+      "source": source,
+      "var": var,
+      "entries": entries,
+    }));
+  }
+  let conf = SPLIT_TEMPLATE.compile(&liquid::object!({
+    "var": split_var,
+    "splits": splits,
+    "routes": routes,
+  }))?;
+  Ok((var, conf))
+}
+
+/// Generate the upstreams of a split target and the variables selecting them.
+/// Returns the variable to proxy the requests to and its configuration.
+pub async fn gen_split(
+  key: &str,
+  split: &SplitTarget,
+  options: &UpstreamOptions,
+  staged: &mut StagedConf,
+  state: &SystemStateRef,
+) -> IoResult<(String, String)> {
+  if options.sticky_session.is_some() {
+    return Err(IoError::invalid_input(
+      "SplitTarget",
+      "Sticky sessions aren't supported when splitting the traffic",
+    ));
+  }
+  // A target that can't be resolved fails the split instead of silently
+  // sending its share of the traffic to the others
+  let mut upstreams = vec![];
+  for weighted in &split.split {
+    let upstream = gen_upstream(
+      &weighted.target,
+      &NginxRuleKind::Site,
+      options,
+      staged,
+      state,
+    )
+    .await
+    .map_err(|err| {
+      err.map_err_context(|| {
+        format!("Unable to split traffic to {}", weighted.target.key)
+      })
+    })?;
+    upstreams.push((weighted.weight, upstream));
+  }
+  let mut canaries = vec![];
+  for route in split.canary.iter().flatten() {
+    let upstream =
+      gen_upstream(&route.target, &NginxRuleKind::Site, options, staged, state)
+        .await
+        .map_err(|err| {
+          err.map_err_context(|| {
+            format!("Unable to route canary traffic to {}", route.target.key)
+          })
+        })?;
+    canaries.push((route, upstream));
+  }
+  gen_split_conf(key, &upstreams, &canaries)
+}
+
+pub fn gen_unix_target_key(
   unix: &UnixTarget,
   kind: &NginxRuleKind,
-  state: &SystemStateRef,
+  staged: &mut StagedConf,
 ) -> IoResult<String> {
   let upstream_key = format!("unix-{}", unix.unix_path.replace('/', "-"));
   let data = UNIX_UPSTREAM_TEMPLATE.compile(&liquid::object!({
     "upstream_key": upstream_key,
     "path": unix.unix_path,
   }))?;
-  state
-    .store
-    .write_conf_file(&upstream_key, &data, kind)
-    .await?;
+  staged.write(&upstream_key, &data, kind);
   Ok(upstream_key)
 }
 
 pub async fn gen_stream_upstream_key(
   target: &StreamTarget,
+  staged: &mut StagedConf,
   state: &SystemStateRef,
 ) -> IoResult<String> {
   match target {
     StreamTarget::Upstream(upstream) => {
-      gen_upstream(upstream, &NginxRuleKind::Stream, state).await
+      gen_upstream(
+        upstream,
+        &NginxRuleKind::Stream,
+        &UpstreamOptions::default(),
+        staged,
+        state,
+      )
+      .await
     }
     StreamTarget::Unix(unix) => {
-      gen_unix_target_key(unix, &NginxRuleKind::Stream, state).await
+      gen_unix_target_key(unix, &NginxRuleKind::Stream, staged)
     }
     StreamTarget::Uri(_) => {
       Err(IoError::invalid_input("StreamTarget", "uri not supported"))
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use nanocld_client::stubs::proxy::ProxyHealthCheck;
+
+  fn addresses() -> Vec<(String, usize)> {
+    vec![("10.0.0.2".to_owned(), 0), ("10.0.0.3".to_owned(), 1)]
+  }
+
+  fn process(name: &str, network: &str, ip_address: &str) -> Process {
+    use nanocld_client::bollard_next::service::{
+      ContainerInspectResponse, EndpointSettings, NetworkSettings,
+    };
+    Process {
+      key: name.to_owned(),
+      created_at: Default::default(),
+      updated_at: Default::default(),
+      name: name.to_owned(),
+      kind: nanocld_client::stubs::process::ProcessKind::Cargo,
+      node_name: "node".to_owned(),
+      kind_key: "app.ns".to_owned(),
+      data: ContainerInspectResponse {
+        network_settings: Some(NetworkSettings {
+          networks: Some(
+            [(
+              network.to_owned(),
+              EndpointSettings {
+                ip_address: Some(ip_address.to_owned()),
+                ..Default::default()
+              },
+            )]
+            .into(),
+          ),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+    }
+  }
+
+  #[ntex::test]
+  async fn addresses_fallback_to_default_network() {
+    let network = namespace::network_name("ns");
+    let processes = vec![
+      process("app-0", &network, "10.1.0.2"),
+      process("app-1", namespace::DEFAULT_NETWORK, "10.0.0.3"),
+    ];
+    let addresses = get_addresses(&processes, &network).await.unwrap();
+    assert_eq!(
+      addresses,
+      vec![("10.1.0.2".to_owned(), 0), ("10.0.0.3".to_owned(), 0)]
+    );
+    let processes = vec![process("app-0", "other", "10.2.0.2")];
+    assert!(get_addresses(&processes, &network).await.is_err());
+  }
+
+  #[test]
+  fn upstream_options() {
+    let options = UpstreamOptions {
+      load_balancing: Some(ProxyLoadBalancing::Weighted([(0, 3)].into())),
+      sticky_session: Some(ProxyStickySession::default()),
+      health_check: Some(ProxyHealthCheck {
+        max_fails: Some(2),
+        fail_timeout: Some(5),
+      }),
+    };
+    let key = gen_upstream_key("app.global", 80, "cargo", &options);
+    assert_eq!(key, gen_upstream_key("app.global", 80, "cargo", &options));
+    assert_ne!(
+      key,
+      gen_upstream_key("app.global", 80, "cargo", &Default::default())
+    );
+    let conf =
+      gen_upstream_conf(&key, 80, &addresses(), &NginxRuleKind::Site, &options)
+        .unwrap();
+    let var = sticky_var(&key);
+    assert!(conf.contains(&format!("map $cookie_ncproxy_route ${var}")));
+    assert!(conf.contains(&format!("hash ${var} consistent;")));
+    assert!(
+      conf.contains("server 10.0.0.2:80 weight=3 max_fails=2 fail_timeout=5s;")
+    );
+    assert!(
+      conf.contains("server 10.0.0.3:80 weight=1 max_fails=2 fail_timeout=5s;")
+    );
+    let cookie =
+      gen_sticky_cookie(&key, &ProxyStickySession::default()).unwrap();
+    assert_eq!(cookie, format!("ncproxy_route=${var}; Path=/; HttpOnly"));
+  }
+
+  #[test]
+  fn upstream_invalid_options() {
+    let options = UpstreamOptions {
+      load_balancing: Some(ProxyLoadBalancing::LeastConn),
+      sticky_session: Some(ProxyStickySession::default()),
+      ..Default::default()
+    };
+    assert!(gen_upstream_conf(
+      "app",
+      80,
+      &addresses(),
+      &NginxRuleKind::Site,
+      &options
+    )
+    .is_err());
+    let options = UpstreamOptions {
+      load_balancing: Some(ProxyLoadBalancing::IpHash),
+      ..Default::default()
+    };
+    let conf = gen_upstream_conf(
+      "app",
+      80,
+      &addresses(),
+      &NginxRuleKind::Site,
+      &options,
+    )
+    .unwrap();
+    assert!(conf.contains("ip_hash;"));
+    // The key is the same on every node and release
+    assert_eq!(
+      gen_upstream_key("app.global", 80, "cargo", &options),
+      "app.global-80-cargo-5c95b5aa6aaf4ad1"
+    );
+    assert!(gen_upstream_conf(
+      "app",
+      80,
+      &addresses(),
+      &NginxRuleKind::Stream,
+      &options
+    )
+    .is_err());
+    let sticky = ProxyStickySession {
+      cookie: Some("bad-cookie".to_owned()),
+      max_age: None,
+    };
+    assert!(gen_sticky_cookie("app", &sticky).is_err());
+  }
+
+  #[test]
+  fn split_and_canary() {
+    let target = UpstreamTarget {
+      key: "app-canary.global.c".to_owned(),
+      port: 9000,
+      path: None,
+      disable_logging: None,
+      ssl: None,
+    };
+    let header = CanaryRoute {
+      header: Some("X-Canary".to_owned()),
+      cookie: None,
+      value: Some("always".to_owned()),
+      target: target.clone(),
+    };
+    let cookie = CanaryRoute {
+      header: None,
+      cookie: Some("canary".to_owned()),
+      value: None,
+      target,
+    };
+    let upstreams = [
+      (90, "v1".to_owned()),
+      (0, "v0".to_owned()),
+      (10, "v2".to_owned()),
+    ];
+    let canaries = [(&header, "c1".to_owned()), (&cookie, "c2".to_owned())];
+    let (var, conf) =
+      gen_split_conf("app-split-0", &upstreams, &canaries).unwrap();
+    assert_eq!(var, "ncproxy_canary_app_split_0_0");
+    assert!(conf.contains("90.00% v1;"));
+    assert!(conf.contains("* v2;"));
+    assert!(!conf.contains("v0"));
+    assert!(conf.contains("map $http_x_canary $ncproxy_canary_app_split_0_0"));
+    assert!(conf.contains("\"always\" c1;"));
+    assert!(conf.contains("default $ncproxy_canary_app_split_0_1;"));
+    assert!(conf.contains("map $cookie_canary $ncproxy_canary_app_split_0_1"));
+    assert!(conf.contains("default c2;"));
+    assert!(gen_split_conf("app", &[(0, "v1".to_owned())], &[]).is_err());
+    let escaped = CanaryRoute {
+      value: Some("a\\\"b".to_owned()),
+      ..header.clone()
+    };
+    let (_, conf) = gen_split_conf(
+      "app",
+      &[(1, "v1".to_owned())],
+      &[(&escaped, "c1".to_owned())],
+    )
+    .unwrap();
+    assert!(conf.contains("\"a\\\\\\\"b\" c1;"));
+  }
+}
@@ -116,8 +116,22 @@ async fn on_event(
   }
   match action {
     NativeEventAction::Create | NativeEventAction::Update => {
-      let key = actor.key.unwrap_or_default();
-      let resource = nanocl_client.inspect_resource(&key).await?;
+      let attributes = actor.attributes.unwrap_or_default();
+      let name = attributes
+        .get("Name")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| {
+          IoError::invalid_data("Attribute Name", "Missing value")
+        })?;
+      let namespace = attributes
+        .get("Namespace")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| {
+          IoError::invalid_data("Attribute Namespace", "Missing value")
+        })?;
+      let resource = nanocl_client
+        .inspect_resource(name, Some(namespace))
+        .await?;
       let r_proxy_rule = resource_to_proxy_rule(&resource)?;
       for rule in r_proxy_rule.rules.into_iter() {
         if let ProxyRule::Stream(stream) = rule {
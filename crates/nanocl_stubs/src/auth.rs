@@ -0,0 +1,262 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::system::{EventActor, EventActorKind};
+
+/// Action performed on an object of the api
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RoleVerb {
+  /// List, inspect, count, logs and events (GET and HEAD requests)
+  Read,
+  /// Create, update, start, stop and delete
+  Write,
+  /// Execute commands or attach into containers and virtual machines
+  Exec,
+  /// Every verb
+  All,
+}
+
+/// A rule granting verbs on kinds of objects in namespaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct RoleRule {
+  /// Kinds of objects as named in the api path (eg: cargoes, secrets) or `*` for every kind
+  pub kinds: Vec<String>,
+  /// Namespaces where the rule apply or `*` for every namespace
+  #[cfg_attr(feature = "serde", serde(default = "default_namespaces"))]
+  pub namespaces: Vec<String>,
+  /// Verbs allowed by the rule
+  pub verbs: Vec<RoleVerb>,
+}
+
+#[cfg(feature = "serde")]
+fn default_namespaces() -> Vec<String> {
+  vec!["*".to_owned()]
+}
+
+impl RoleRule {
+  /// Check if the rule grant the verb on the kind in the namespace
+  pub fn allows(&self, kind: &str, namespace: &str, verb: RoleVerb) -> bool {
+    let matches = |values: &[String], value: &str| {
+      values.iter().any(|v| v == "*" || v == value)
+    };
+    matches(&self.kinds, kind)
+      && matches(&self.namespaces, namespace)
+      && self.verbs.iter().any(|v| *v == RoleVerb::All || *v == verb)
+  }
+}
+
+/// Payload used to create or replace a role
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct RolePartial {
+  /// Name of the role
+  pub name: String,
+  /// Rules granted by the role
+  pub rules: Vec<RoleRule>,
+}
+
+/// Payload used to replace the rules of a role
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct RoleUpdate {
+  /// Rules granted by the role
+  pub rules: Vec<RoleRule>,
+}
+
+/// A role is a named set of rules that can be bound to identities
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct Role {
+  /// Name of the role
+  pub name: String,
+  /// When the role was created
+  pub created_at: chrono::NaiveDateTime,
+  /// When the role was last updated
+  pub updated_at: chrono::NaiveDateTime,
+  /// Rules granted by the role
+  pub rules: Vec<RoleRule>,
+}
+
+impl Role {
+  /// Check if one of the rules grant the verb on the kind in the namespace
+  pub fn allows(&self, kind: &str, namespace: &str, verb: RoleVerb) -> bool {
+    self
+      .rules
+      .iter()
+      .any(|rule| rule.allows(kind, namespace, verb))
+  }
+}
+
+impl From<Role> for RolePartial {
+  fn from(role: Role) -> Self {
+    Self {
+      name: role.name,
+      rules: role.rules,
+    }
+  }
+}
+
+/// Convert a Role into an EventActor
+impl From<Role> for EventActor {
+  fn from(role: Role) -> Self {
+    Self {
+      key: Some(role.name),
+      kind: EventActorKind::Role,
+      attributes: None,
+    }
+  }
+}
+
+/// Payload used to create an identity
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct IdentityPartial {
+  /// Name of the identity
+  pub name: String,
+  /// Names of the roles bound to the identity
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub roles: Vec<String>,
+  /// Common name of the client certificate authenticating as this identity
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub subject: Option<String>,
+}
+
+/// Payload used to update an identity
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct IdentityUpdate {
+  /// Names of the roles bound to the identity
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub roles: Option<Vec<String>>,
+  /// Common name of the client certificate authenticating as this identity
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub subject: Option<String>,
+}
+
+/// An identity is a user or a service that can authenticate to the api
+/// with an api token or a client certificate
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct Identity {
+  /// Name of the identity
+  pub name: String,
+  /// When the identity was created
+  pub created_at: chrono::NaiveDateTime,
+  /// When the identity was last updated
+  pub updated_at: chrono::NaiveDateTime,
+  /// Names of the roles bound to the identity
+  pub roles: Vec<String>,
+  /// Common name of the client certificate authenticating as this identity
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub subject: Option<String>,
+}
+
+/// Convert an Identity into an EventActor
+impl From<Identity> for EventActor {
+  fn from(identity: Identity) -> Self {
+    Self {
+      key: Some(identity.name),
+      kind: EventActorKind::Identity,
+      attributes: Some(serde_json::json!({
+        "Roles": identity.roles,
+      })),
+    }
+  }
+}
+
+/// Payload used to create an api token
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ApiTokenPartial {
+  /// Number of seconds before the token expire, never expire if not set
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub expires_in: Option<u64>,
+}
+
+/// An api token used to authenticate as an identity
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ApiToken {
+  /// Unique key of the token
+  pub key: uuid::Uuid,
+  /// Name of the identity authenticated by the token
+  pub identity: String,
+  /// When the token was created
+  pub created_at: chrono::NaiveDateTime,
+  /// When the token expire
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub expires_at: Option<chrono::NaiveDateTime>,
+  /// The token, only returned when it's created
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub token: Option<String>,
+}
@@ -35,7 +35,7 @@ pub enum ReplicationMode {
   UniqueByNodeGroups { groups: Vec<String> },
   /// UniqueByNodeNames is used to ensure one replica is running on each node name
   UniqueByNodeNames { names: Vec<String> },
-  /// Number is used to manually set the number of replicas in one node
+  /// Number is used to manually set the number of replicas in the cluster spread across the nodes
   Static(ReplicationStatic),
   /// NumberByNodes is used to manually set the number of replicas in each node
   StaticByNodes(ReplicationStatic),
@@ -57,6 +57,91 @@ pub struct ReplicationStatic {
   pub number: usize,
 }
 
+/// Rolling update strategy used to replace the instances of a cargo
+/// when its specification is updated
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct CargoUpdateStrategy {
+  /// Maximum number of instances created above the wanted number (default 1)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_surge: Option<usize>,
+  /// Maximum number of instances that can be unavailable (default 0)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_unavailable: Option<usize>,
+  /// Number of instances replaced at the same time
+  /// (default and maximum to max surge + max unavailable)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub batch_size: Option<usize>,
+  /// Seconds to wait for a new instance to be healthy (default 60)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub health_timeout: Option<u64>,
+  /// Rollback to the previous spec if new instances fail their checks (default true)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub rollback: Option<bool>,
+}
+
+/// Autoscaling policy adjusting the static number of replicas of a cargo
+/// from the load of its instances
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct CargoAutoscale {
+  /// Minimum number of replicas
+  pub min_replicas: usize,
+  /// Maximum number of replicas
+  pub max_replicas: usize,
+  /// Average CPU usage of an instance to maintain in percent of one CPU
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub target_cpu: Option<f64>,
+  /// Requests per second per instance to maintain from the proxy metrics
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub target_rps: Option<f64>,
+  /// Seconds to wait after a change of the cargo before scaling up (default 60)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub scale_up_cooldown: Option<u64>,
+  /// Seconds to wait after a change of the cargo before scaling down (default 300)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub scale_down_cooldown: Option<u64>,
+}
+
 /// A cargo spec partial is used to create a Cargo
 #[derive(Debug, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
@@ -108,6 +193,18 @@ pub struct CargoSpecPartial {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub replication: Option<ReplicationMode>,
+  /// Strategy used to replace instances when the cargo is updated
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub update_strategy: Option<CargoUpdateStrategy>,
+  /// Autoscaling policy of the cargo
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub autoscale: Option<CargoAutoscale>,
 }
 
 /// Payload used to patch a cargo
@@ -171,6 +268,18 @@ pub struct CargoSpecUpdate {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub replication: Option<ReplicationMode>,
+  /// Strategy used to replace instances when the cargo is updated
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub update_strategy: Option<CargoUpdateStrategy>,
+  /// Autoscaling policy of the cargo
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub autoscale: Option<CargoAutoscale>,
 }
 
 impl From<CargoSpecPartial> for CargoSpecUpdate {
@@ -180,6 +289,8 @@ impl From<CargoSpecPartial> for CargoSpecUpdate {
       init_container: spec.init_container,
       container: Some(spec.container),
       replication: spec.replication,
+      update_strategy: spec.update_strategy,
+      autoscale: spec.autoscale,
       metadata: spec.metadata,
       secrets: spec.secrets,
       image_pull_secret: spec.image_pull_secret,
@@ -246,6 +357,18 @@ pub struct CargoSpec {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub replication: Option<ReplicationMode>,
+  /// Strategy used to replace instances when the cargo is updated
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub update_strategy: Option<CargoUpdateStrategy>,
+  /// Autoscaling policy of the cargo
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub autoscale: Option<CargoAutoscale>,
 }
 
 impl From<CargoSpec> for CargoSpecPartial {
@@ -254,6 +377,8 @@ impl From<CargoSpec> for CargoSpecPartial {
       init_container: spec.init_container,
       name: spec.name,
       replication: spec.replication,
+      update_strategy: spec.update_strategy,
+      autoscale: spec.autoscale,
       container: spec.container,
       metadata: spec.metadata,
       secrets: spec.secrets,
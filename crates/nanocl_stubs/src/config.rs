@@ -33,6 +33,19 @@ pub struct DaemonConfig {
   pub gid: u32,
   /// Optional ssl configuration
   pub ssl: Option<SslConfig>,
+  /// Provider of the key used to encrypt secrets at rest
+  /// `file:///path/to/keyring` or `env://VARIABLE`
+  /// default to a keyring file in the state directory,
+  /// which is refused once more than one node is registered
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub secret_key: Option<String>,
+  /// Require tcp clients to authenticate with an api token
+  /// or a client certificate mapped to an identity
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub enable_auth: bool,
+  /// Retention and rollups of the metrics by kind
+  #[cfg_attr(feature = "serde", serde(default = "MetricRetention::defaults"))]
+  pub metric_retention: Vec<MetricRetention>,
 }
 
 /// Configuration File of the daemon
@@ -52,6 +65,85 @@ pub struct DaemonConfigFile {
   pub gateway: Option<String>,
   /// Hostname to use for the node automatically detected if not set
   pub hostname: Option<String>,
+  /// Provider of the key used to encrypt secrets at rest
+  pub secret_key: Option<String>,
+  /// Require tcp clients to authenticate
+  pub enable_auth: Option<bool>,
+  /// Retention and rollups of the metrics by kind, replace the defaults
+  pub metric_retention: Option<Vec<MetricRetention>>,
+}
+
+/// Downsampled copy of the metrics of a kind kept longer than the raw metrics
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MetricRollup {
+  /// Width of the time buckets in seconds
+  pub interval: u64,
+  /// How long the buckets are kept in seconds
+  pub retention: u64,
+}
+
+/// Retention policy of the metrics of a kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MetricRetention {
+  /// Kind of the metrics
+  pub kind: String,
+  /// How long the raw metrics are kept in seconds
+  pub retention: u64,
+  /// Fields of the data the rollups are grouped by,
+  /// the rollups are always grouped by node
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub group_by: Vec<String>,
+  /// Numeric fields of the data aggregated by the rollups
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub fields: Vec<String>,
+  /// Rollups of the metrics
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub rollups: Vec<MetricRollup>,
+}
+
+impl MetricRetention {
+  /// Policies of the metrics saved by nanocl and ncproxy
+  pub fn defaults() -> Vec<Self> {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    vec![
+      Self {
+        kind: "ncproxy.io/http".to_owned(),
+        retention: 7 * DAY,
+        group_by: vec!["host".to_owned(), "status".to_owned()],
+        fields: vec!["request_time".to_owned(), "bytes_sent".to_owned()],
+        rollups: vec![
+          MetricRollup {
+            interval: MINUTE,
+            retention: 30 * DAY,
+          },
+          MetricRollup {
+            interval: HOUR,
+            retention: 365 * DAY,
+          },
+        ],
+      },
+      Self {
+        kind: "nanocl.io/metrs".to_owned(),
+        retention: DAY,
+        group_by: Vec::new(),
+        fields: vec!["Memory.Used".to_owned()],
+        rollups: vec![MetricRollup {
+          interval: HOUR,
+          retention: 30 * DAY,
+        }],
+      },
+    ]
+  }
 }
 
 impl Default for DaemonConfig {
@@ -68,6 +160,9 @@ impl Default for DaemonConfig {
       nodes: Vec::default(),
       advertise_addr: String::default(),
       ssl: None,
+      secret_key: None,
+      enable_auth: false,
+      metric_retention: MetricRetention::defaults(),
     }
   }
 }
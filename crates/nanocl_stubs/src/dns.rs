@@ -1,9 +1,75 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 use crate::generic::NetworkKind;
 
-#[derive(Clone, Debug)]
+/// Service record, the ports of a cargo can be used instead of a fixed port
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct DnsSrv {
+  /// Host providing the service, the name of the instances of the cargo by default
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub target: Option<String>,
+  /// Port of the service, required without a cargo
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub port: Option<u16>,
+  /// Key of a cargo `<name>.<namespace>`, a record is created for each of its port mappings
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cargo: Option<String>,
+  /// Priority of the target, the lowest is tried first (default: 0)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub priority: Option<u16>,
+  /// Relative weight of the targets with the same priority (default: 0)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub weight: Option<u16>,
+}
+
+/// Mail exchange record
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct DnsMx {
+  /// Host accepting the mails of the domain
+  pub exchange: String,
+  /// Preference of the host, the lowest is tried first (default: 10)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub preference: Option<u16>,
+}
+
+/// A record of a dns rule, exactly one kind of record must be set.
+/// The name can start with a wildcard label `*.` to match any subdomain.
+#[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -13,7 +79,56 @@ use crate::generic::NetworkKind;
 )]
 pub struct DnsEntry {
   pub name: String,
-  pub ip_address: NetworkKind,
+  /// Address record of a network, A or AAAA depending on its address
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub ip_address: Option<NetworkKind>,
+  /// A record
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = Option<String>))]
+  pub ipv4: Option<Ipv4Addr>,
+  /// AAAA record
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = Option<String>))]
+  pub ipv6: Option<Ipv6Addr>,
+  /// CNAME record, the name is an alias of this one
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cname: Option<String>,
+  /// TXT record, each string is at most 255 bytes long
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub txt: Option<Vec<String>>,
+  /// SRV record
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub srv: Option<DnsSrv>,
+  /// MX record
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub mx: Option<DnsMx>,
+  /// Time to live of the record in seconds (default: 30)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub ttl: Option<u32>,
 }
 
 #[derive(Clone, Debug)]
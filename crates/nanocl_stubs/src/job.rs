@@ -11,6 +11,44 @@ use crate::{
 #[cfg(feature = "utoipa")]
 use super::generic::Any;
 
+/// Policy applied when a scheduled run of a job is due while it's still running
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum JobConcurrencyPolicy {
+  /// Start the job anyway, instances already running are left as is
+  #[default]
+  Allow,
+  /// Skip the run
+  Forbid,
+  /// Stop the running instances and start the job again
+  Replace,
+}
+
+/// Step of a job, it starts once the steps it depends on succeeded.
+/// Steps of a run share an artifacts directory mounted in `/opt/nanocl.io/artifacts`.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct JobStep {
+  /// Name of the step, unique in the job
+  pub name: String,
+  /// Steps that must succeed before this one starts
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub depends_on: Option<Vec<String>>,
+  /// Container to run
+  pub container: Config,
+}
+
 /// Job partial is used to create a new job
 #[derive(Debug, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
@@ -23,6 +61,13 @@ use super::generic::Any;
 pub struct JobPartial {
   /// Name of the job
   pub name: String,
+  /// Namespace of the job, its containers join the network of the namespace
+  /// and it counts against its quota (default global)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub namespace: Option<String>,
   #[cfg_attr(
     feature = "serde",
     serde(skip_serializing_if = "Option::is_none")
@@ -42,6 +87,58 @@ pub struct JobPartial {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub schedule: Option<String>,
+  /// Time zone used to evaluate the schedule (eg: Europe/Paris), default to UTC
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub time_zone: Option<String>,
+  /// What to do when a scheduled run is due while the job is still running
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub concurrency_policy: Option<JobConcurrencyPolicy>,
+  /// Run once the schedule missed while the daemon was down
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub catch_up: Option<bool>,
+  /// Number of times a failed container is run again (default 0)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub retries: Option<usize>,
+  /// Delay in seconds before the first retry, doubled after each retry
+  /// up to one hour (default 10)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub backoff: Option<u64>,
+  /// Maximum duration in seconds of a run, when exceeded the running
+  /// container is stopped and the job fails without retrying
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub active_deadline: Option<u64>,
+  /// Maximum duration in seconds of a container attempt,
+  /// when exceeded the container is stopped and the attempt fails
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub container_timeout: Option<u64>,
+  /// Run the next containers when one failed all its attempts,
+  /// the job still fails at the end
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub continue_on_failure: Option<bool>,
   /// Remove the job after (x) seconds after execution
   #[cfg_attr(
     feature = "serde",
@@ -59,8 +156,15 @@ pub struct JobPartial {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub image_pull_policy: Option<ImagePullPolicy>,
-  /// List of container to run
+  /// List of container to run in sequence
+  #[cfg_attr(feature = "serde", serde(default))]
   pub containers: Vec<Config>,
+  /// Named steps to run as a graph instead of containers in sequence
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub steps: Option<Vec<JobStep>>,
 }
 
 /// Convert a job into a job partial
@@ -68,11 +172,21 @@ impl From<Job> for JobPartial {
   fn from(job: Job) -> Self {
     JobPartial {
       name: job.name,
+      namespace: job.namespace,
       secrets: job.secrets,
       metadata: job.metadata,
       schedule: job.schedule,
+      time_zone: job.time_zone,
+      concurrency_policy: job.concurrency_policy,
+      catch_up: job.catch_up,
+      retries: job.retries,
+      backoff: job.backoff,
+      active_deadline: job.active_deadline,
+      container_timeout: job.container_timeout,
+      continue_on_failure: job.continue_on_failure,
       ttl: job.ttl,
       containers: job.containers,
+      steps: job.steps,
       image_pull_secret: job.image_pull_secret,
       image_pull_policy: job.image_pull_policy,
     }
@@ -88,6 +202,13 @@ impl From<Job> for JobPartial {
 pub struct Job {
   /// Name of the job
   pub name: String,
+  /// Namespace of the job, its containers join the network of the namespace
+  /// and it counts against its quota (default global)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub namespace: Option<String>,
   /// When the job have been created
   pub created_at: chrono::NaiveDateTime,
   /// When the job have been updated
@@ -113,6 +234,58 @@ pub struct Job {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub schedule: Option<String>,
+  /// Time zone used to evaluate the schedule (eg: Europe/Paris), default to UTC
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub time_zone: Option<String>,
+  /// What to do when a scheduled run is due while the job is still running
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub concurrency_policy: Option<JobConcurrencyPolicy>,
+  /// Run once the schedule missed while the daemon was down
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub catch_up: Option<bool>,
+  /// Number of times a failed container is run again (default 0)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub retries: Option<usize>,
+  /// Delay in seconds before the first retry, doubled after each retry
+  /// up to one hour (default 10)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub backoff: Option<u64>,
+  /// Maximum duration in seconds of a run, when exceeded the running
+  /// container is stopped and the job fails without retrying
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub active_deadline: Option<u64>,
+  /// Maximum duration in seconds of a container attempt,
+  /// when exceeded the container is stopped and the attempt fails
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub container_timeout: Option<u64>,
+  /// Run the next containers when one failed all its attempts,
+  /// the job still fails at the end
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub continue_on_failure: Option<bool>,
   /// Remove the job after (x) seconds after execution
   #[cfg_attr(
     feature = "serde",
@@ -131,8 +304,29 @@ pub struct Job {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub image_pull_policy: Option<ImagePullPolicy>,
-  /// Containers to run
+  /// Containers to run in sequence
+  #[cfg_attr(feature = "serde", serde(default))]
   pub containers: Vec<Config>,
+  /// Named steps to run as a graph instead of containers in sequence
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub steps: Option<Vec<JobStep>>,
+}
+
+impl JobPartial {
+  /// Namespace of the job
+  pub fn namespace_name(&self) -> &str {
+    self.namespace.as_deref().unwrap_or("global")
+  }
+}
+
+impl Job {
+  /// Namespace of the job
+  pub fn namespace_name(&self) -> &str {
+    self.namespace.as_deref().unwrap_or("global")
+  }
 }
 
 /// Convert a Job into an EventActor
@@ -143,6 +337,7 @@ impl From<Job> for EventActor {
       kind: EventActorKind::Job,
       attributes: Some(serde_json::json!({
         "Name": job.name,
+        "Namespace": job.namespace_name(),
         "Metadata": job.metadata,
       })),
     }
@@ -188,6 +383,94 @@ pub struct JobInspect {
   pub spec: Job,
   /// List of instances
   pub instances: Vec<Process>,
+  /// When the job will run next if it's scheduled
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub next_run_at: Option<chrono::NaiveDateTime>,
+  /// When the job was last started by its schedule
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub last_run_at: Option<chrono::NaiveDateTime>,
+  /// Attempts of the containers during the last run
+  pub attempts: Vec<JobAttempt>,
+  /// State of the steps during the last run
+  pub steps: Vec<JobStepState>,
+}
+
+/// Status of a step of a job
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum JobStepStatus {
+  /// Waiting for the steps it depends on
+  #[default]
+  Pending,
+  Running,
+  Succeeded,
+  Failed,
+  /// Not run because a step it depends on failed or the job stopped
+  Skipped,
+}
+
+/// State of a step of a job during a run
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct JobStepState {
+  /// Name of the step
+  pub name: String,
+  /// Steps it depends on
+  pub depends_on: Vec<String>,
+  /// Current status
+  pub status: JobStepStatus,
+}
+
+/// Attempt to run a container of a job
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct JobAttempt {
+  /// Index of the container in the job
+  pub container: usize,
+  /// Name of the step
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub step: Option<String>,
+  /// Number of the attempt starting at 1
+  pub attempt: usize,
+  /// Name of the process that ran the container
+  pub process: String,
+  /// When the attempt started
+  pub started_at: chrono::NaiveDateTime,
+  /// When the attempt ended
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub ended_at: Option<chrono::NaiveDateTime>,
+  /// Exit code of the container
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub exit_code: Option<i64>,
+  /// Why the attempt failed
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub error: Option<String>,
 }
 
 /// Convert a job inspect into a job partial
@@ -1,6 +1,7 @@
 pub mod generic;
 pub mod system;
 
+pub mod auth;
 pub mod cargo;
 pub mod cargo_spec;
 pub mod config;
@@ -14,6 +15,7 @@ pub mod proxy;
 pub mod resource;
 pub mod resource_kind;
 pub mod secret;
+pub mod service;
 pub mod statefile;
 pub mod vm;
 pub mod vm_image;
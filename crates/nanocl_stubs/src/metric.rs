@@ -40,6 +40,98 @@ pub struct MetricPartial {
   pub note: Option<String>,
 }
 
+/// Query to aggregate the metrics of a kind by time bucket
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MetricAggregateQuery {
+  /// Kind of the metrics to aggregate
+  pub kind: String,
+  /// Width of the time buckets in seconds (default: 60)
+  pub interval: Option<u64>,
+  /// Start of the time range in UTC (default: one hour before `until`)
+  pub since: Option<chrono::NaiveDateTime>,
+  /// End of the time range in UTC (default: now)
+  pub until: Option<chrono::NaiveDateTime>,
+  /// Comma separated fields to group by.
+  /// Nested fields of the data are separated by dots, `node_name` is the node of the metric.
+  pub group_by: Option<String>,
+  /// Comma separated numeric fields of the data to aggregate
+  pub fields: Option<String>,
+}
+
+/// Aggregated values of a numeric field in a time bucket,
+/// values that aren't numbers are ignored
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct MetricFieldAggregate {
+  /// Name of the field
+  pub field: String,
+  /// Number of numeric values
+  pub count: i64,
+  /// Sum of the values
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub sum: Option<f64>,
+  /// Average of the values
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub avg: Option<f64>,
+  /// Smallest value
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub min: Option<f64>,
+  /// Largest value
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max: Option<f64>,
+  /// Median of the values
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub p50: Option<f64>,
+  /// 95th percentile of the values
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub p95: Option<f64>,
+  /// 99th percentile of the values
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub p99: Option<f64>,
+}
+
+/// Metrics of a time bucket sharing the same values for the grouped fields
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct MetricAggregate {
+  /// Start of the time bucket
+  pub bucket: chrono::NaiveDateTime,
+  /// Values of the grouped fields, null when a metric doesn't have the field
+  pub group: std::collections::BTreeMap<String, Option<String>>,
+  /// Number of metrics
+  pub count: i64,
+  /// Aggregated values of the numeric fields
+  pub fields: Vec<MetricFieldAggregate>,
+}
+
 /// ## deserialize empty string
 ///
 /// Serde helper to deserialize string that can be empty to `Option<String>`.
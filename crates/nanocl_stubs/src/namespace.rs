@@ -6,6 +6,141 @@ use crate::{
   system::{EventActor, EventActorKind},
 };
 
+/// Bridge network used by the `global` and `system` namespaces
+pub const DEFAULT_NETWORK: &str = "nanoclbr0";
+
+/// Name of the bridge network the workloads of a namespace are attached to
+pub fn network_name(namespace: &str) -> String {
+  match namespace {
+    "global" | "system" => DEFAULT_NETWORK.to_owned(),
+    _ => format!("nanocl.{namespace}"),
+  }
+}
+
+/// Network configuration of a namespace
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct NamespaceNetwork {
+  /// Namespaces reachable from the workloads of the namespace.
+  /// The workloads are also attached to the network of these namespaces.
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub connect: Vec<String>,
+}
+
+/// Maximum number of objects and resources reserved in a namespace
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct NamespaceQuota {
+  /// Maximum number of cargoes
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_cargoes: Option<usize>,
+  /// Maximum number of cargo instances in the cluster
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_instances: Option<usize>,
+  /// Maximum cpu reserved by the cargo instances and vms in billionths of a cpu
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_nano_cpus: Option<i64>,
+  /// Maximum memory reserved by the cargo instances and vms in bytes
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_memory: Option<i64>,
+  /// Maximum number of virtual machines
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_vms: Option<usize>,
+  /// Maximum number of jobs.
+  /// Jobs aren't namespaced and count against the `global` namespace.
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_jobs: Option<usize>,
+}
+
+/// Default and maximum resources of the workloads of a namespace.
+/// Defaults are applied to the containers without resources,
+/// maximums are checked against every container and vm.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct NamespaceLimitRange {
+  /// Cpu of a container without `NanoCpus` in billionths of a cpu
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub default_nano_cpus: Option<i64>,
+  /// Memory of a container without `Memory` in bytes
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub default_memory: Option<i64>,
+  /// Maximum cpu of a container or vm in billionths of a cpu
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_nano_cpus: Option<i64>,
+  /// Maximum memory of a container or vm in bytes
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_memory: Option<i64>,
+}
+
+/// Objects and resources currently reserved in a namespace
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct NamespaceUsage {
+  /// Number of cargoes
+  pub cargoes: usize,
+  /// Number of cargo instances in the cluster
+  pub instances: usize,
+  /// Cpu reserved in billionths of a cpu
+  pub nano_cpus: i64,
+  /// Memory reserved in bytes
+  pub memory: i64,
+  /// Number of virtual machines
+  pub vms: usize,
+  /// Number of jobs
+  pub jobs: usize,
+}
+
 /// Namespace is a identifier for a set of cargoes
 /// It is used to group cargoes together
 #[derive(Clone, Debug)]
@@ -24,6 +159,24 @@ pub struct Namespace {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub metadata: Option<serde_json::Value>,
+  /// Network configuration
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub network: Option<NamespaceNetwork>,
+  /// Maximum objects and resources of the namespace
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub quota: Option<NamespaceQuota>,
+  /// Default and maximum resources of the workloads
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub limit_range: Option<NamespaceLimitRange>,
 }
 
 /// A Namespace partial is a payload used to create a new namespace
@@ -44,6 +197,24 @@ pub struct NamespacePartial {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub metadata: Option<serde_json::Value>,
+  /// Network configuration
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub network: Option<NamespaceNetwork>,
+  /// Maximum objects and resources of the namespace
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub quota: Option<NamespaceQuota>,
+  /// Default and maximum resources of the workloads
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub limit_range: Option<NamespaceLimitRange>,
 }
 
 /// A Namespace Summary is a summary of a namespace
@@ -79,6 +250,20 @@ pub struct NamespaceInspect {
   pub name: String,
   /// Number of cargoes
   pub cargoes: Vec<CargoInspect>,
+  /// Maximum objects and resources of the namespace
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub quota: Option<NamespaceQuota>,
+  /// Default and maximum resources of the workloads
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub limit_range: Option<NamespaceLimitRange>,
+  /// Objects and resources currently reserved
+  pub usage: NamespaceUsage,
 }
 
 /// Convert a Namespace into an EventActor
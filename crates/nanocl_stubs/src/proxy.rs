@@ -49,6 +49,44 @@ pub struct ProxySslConfig {
   pub dhparam: Option<String>,
 }
 
+/// Certificate obtained and renewed by ncproxy from an ACME server
+/// using the HTTP-01 challenge on the domain of the rule
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxySslAcme {
+  /// Contact email of the ACME account
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub email: Option<String>,
+  /// Url of the ACME directory (default: Let's Encrypt)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub directory: Option<String>,
+  /// Skip the tls verification of the ACME directory,
+  /// for test servers such as Pebble
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub insecure: Option<bool>,
+  /// Number of days before the expiration to renew the certificate (default: 30)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub renew_before: Option<u32>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
@@ -57,6 +95,11 @@ pub struct ProxySslConfig {
 pub enum ProxySsl {
   Config(ProxySslConfig),
   Secret(String),
+  /// Certificate managed by ncproxy, stored in the `acme.{domain}` secret
+  Acme {
+    #[cfg_attr(feature = "serde", serde(rename = "Acme"))]
+    acme: ProxySslAcme,
+  },
 }
 
 /// Config for targeting a cargo or a vm
@@ -139,6 +182,76 @@ pub struct HttpTarget {
   pub redirect: Option<UrlRedirect>,
 }
 
+/// Share of the traffic sent to a cargo or a vm
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct WeightedTarget {
+  /// Weight of the target relative to the others
+  pub weight: u32,
+  /// The cargo or the vm to target
+  pub target: UpstreamTarget,
+}
+
+/// Send the requests carrying a header or a cookie to a canary target
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct CanaryRoute {
+  /// Name of the header to match
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub header: Option<String>,
+  /// Name of the cookie to match
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cookie: Option<String>,
+  /// Value to match, any non empty value matches by default
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub value: Option<String>,
+  /// The cargo or the vm to target
+  pub target: UpstreamTarget,
+}
+
+/// Split the traffic between several cargoes or vms.
+/// The request uri is forwarded unchanged, the `Path` of the targets is ignored.
+/// The upstream of each target is reported as the `ProxyHost` of the http metrics.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct SplitTarget {
+  /// Targets sharing the traffic according to their weight
+  pub split: Vec<WeightedTarget>,
+  /// Routes taking precedence over the split, the first matching route wins
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub canary: Option<Vec<CanaryRoute>>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
@@ -151,6 +264,8 @@ pub enum LocationTarget {
   Http(HttpTarget),
   /// Target a specific unix socket
   Unix(UnixTarget),
+  /// Split the traffic between several cargoes
+  Split(SplitTarget),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -275,6 +390,236 @@ pub struct LimitReq {
   pub delay: Option<usize>,
 }
 
+/// Algorithm used to balance the requests between the instances of a target
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub enum ProxyLoadBalancing {
+  /// Send the requests to the instances in turn (default)
+  RoundRobin,
+  /// Send the requests to the instance with the least active connections
+  LeastConn,
+  /// Send the requests of a client ip to the same instance
+  IpHash,
+  /// Round robin weighted by instance, the keys are the indexes of the
+  /// instances of the cargo, the instances not listed have a weight of 1
+  Weighted(std::collections::BTreeMap<usize, u32>),
+}
+
+/// Cookie based session affinity
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyStickySession {
+  /// Name of the cookie storing the session (default: ncproxy_route)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cookie: Option<String>,
+  /// Lifetime of the cookie in seconds, it lasts the browser session by default
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_age: Option<u64>,
+}
+
+/// Passive health check of the instances of a target
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyHealthCheck {
+  /// Number of failed requests before an instance is marked unavailable (default: 1)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_fails: Option<u32>,
+  /// Period in seconds to count the failures
+  /// and to keep an instance unavailable (default: 10)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub fail_timeout: Option<u32>,
+}
+
+/// Timeouts in seconds of the requests to a target
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyTimeouts {
+  /// Timeout to establish a connection with an instance (default: 60)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub connect: Option<u32>,
+  /// Timeout between two reads of the response (default: 60)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub read: Option<u32>,
+  /// Timeout between two writes of the request (default: 60)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub send: Option<u32>,
+}
+
+/// Cross-origin resource sharing policy answered by the proxy
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyCors {
+  /// Origins allowed to send requests, `*` allows any origin
+  pub allow_origins: Vec<String>,
+  /// Methods allowed in the requests (default: GET, POST, PUT, PATCH, DELETE, OPTIONS)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub allow_methods: Option<Vec<String>>,
+  /// Headers allowed in the requests, any requested header by default
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub allow_headers: Option<Vec<String>>,
+  /// Response headers readable by the browser
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub expose_headers: Option<Vec<String>>,
+  /// Allow the requests to include cookies and credentials,
+  /// the origins must be listed explicitly
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub allow_credentials: Option<bool>,
+  /// Number of seconds a preflight response can be cached
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_age: Option<u32>,
+}
+
+/// Http basic authentication with the users of a secret
+/// of kind `nanocl.io/basic-auth` mapping the users to their password
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyBasicAuth {
+  /// Name of the secret with the users
+  pub secret: String,
+  /// Realm displayed by the browser (default: Restricted)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub realm: Option<String>,
+}
+
+/// Rewrite of the request path with a regular expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyRewriteRule {
+  /// Regular expression matching the path
+  pub pattern: String,
+  /// Replacement of the path, can reference the captures as `$1`
+  pub replacement: String,
+}
+
+/// Rewrite of the request path before it's sent to the target
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyRewrite {
+  /// Prefix removed from the path
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub strip_prefix: Option<String>,
+  /// Rules applied in order, the first matching rule wins
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub rules: Option<Vec<ProxyRewriteRule>>,
+}
+
+/// Headers added to or removed from the responses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyResponseHeaders {
+  /// Headers to add with their value
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub add: Option<std::collections::BTreeMap<String, String>>,
+  /// Headers of the target to remove
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub remove: Option<Vec<String>>,
+}
+
 /// Defines a proxy rule location
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
@@ -313,6 +658,72 @@ pub struct ProxyHttpLocation {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub version: Option<f64>,
+  /// Algorithm to balance the requests between the instances of the target
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub load_balancing: Option<ProxyLoadBalancing>,
+  /// Send the requests of a session to the same instance
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub sticky_session: Option<ProxyStickySession>,
+  /// Thresholds to stop sending requests to a failing instance
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub health_check: Option<ProxyHealthCheck>,
+  /// Timeouts of the requests to the target
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub timeouts: Option<ProxyTimeouts>,
+  /// Cross-origin resource sharing policy
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cors: Option<ProxyCors>,
+  /// Require an http basic authentication
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub basic_auth: Option<ProxyBasicAuth>,
+  /// Rewrite the path of the requests
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub rewrite: Option<ProxyRewrite>,
+  /// Add or remove headers of the responses
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub response_headers: Option<ProxyResponseHeaders>,
+  /// Maximum size of the request body, e.g. `10m` (default: 2000m)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub max_body_size: Option<String>,
+  /// Compress the responses with gzip (default: true)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub gzip: Option<bool>,
+  /// Forward the websocket upgrades
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub websocket: Option<bool>,
 }
 
 /// Defines a proxy rule http config
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -54,6 +56,24 @@ pub struct ResourceUpdate {
   pub metadata: Option<serde_json::Value>,
 }
 
+/// Payload used to migrate a resource to another version of its kind
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceMigrate {
+  /// Version to migrate to, the current version of the kind by default
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub version: Option<String>,
+}
+
 /// Convert a ResourcePartial into a Resource
 impl From<ResourcePartial> for ResourceUpdate {
   fn from(resource: ResourcePartial) -> Self {
@@ -81,7 +101,7 @@ pub struct ResourceSpec {
   pub version: String,
   /// The creation date of the resource
   pub created_at: chrono::NaiveDateTime,
-  /// Resource key associated with the data
+  /// Key of the resource associated with the data `<name>.<namespace>`
   pub resource_key: String,
   /// The data of the resource as a json object
   #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
@@ -95,6 +115,84 @@ pub struct ResourceSpec {
   pub metadata: Option<serde_json::Value>,
 }
 
+/// Phase of the reconciliation of a resource with the controller of its kind
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ResourceStatusKind {
+  /// Waiting for the controller, retried with a backoff
+  #[default]
+  Pending,
+  /// Applied by the controller
+  Applied,
+  /// Rejected by the controller, retried when its spec changes
+  /// or when its controller comes back
+  Failed,
+}
+
+impl FromStr for ResourceStatusKind {
+  type Err = std::io::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "pending" => Ok(Self::Pending),
+      "applied" => Ok(Self::Applied),
+      "failed" => Ok(Self::Failed),
+      _ => Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("Invalid resource status {s}"),
+      )),
+    }
+  }
+}
+
+impl std::fmt::Display for ResourceStatusKind {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let data = match self {
+      Self::Pending => "pending",
+      Self::Applied => "applied",
+      Self::Failed => "failed",
+    };
+    write!(f, "{data}")
+  }
+}
+
+/// Status of the reconciliation of a resource with the controller of its kind
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "test", derive(Default))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceStatus {
+  /// Phase of the reconciliation
+  pub phase: ResourceStatusKind,
+  /// Error of the last attempt
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub message: Option<String>,
+  /// Key of the spec last applied by the controller
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub observed_spec_key: Option<uuid::Uuid>,
+  /// Number of failed attempts since the spec was last applied
+  pub attempts: u32,
+  /// When the next attempt is made for a pending resource
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub next_attempt_at: Option<chrono::NaiveDateTime>,
+  /// When the status changed
+  pub updated_at: chrono::NaiveDateTime,
+}
+
 /// Resource is a specification with a name and a kind
 /// It is used to define [proxy rules](ProxyRule) and other kind of spec
 #[derive(Clone, Debug)]
@@ -104,12 +202,18 @@ pub struct ResourceSpec {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
 pub struct Resource {
+  /// The name of the resource
+  pub name: String,
+  /// The namespace of the resource
+  pub namespace_name: String,
   /// The kind of the resource
   pub kind: String,
   /// The creation date of the resource
   pub created_at: chrono::NaiveDateTime,
   /// Specification of the ressource
   pub spec: ResourceSpec,
+  /// Status of the reconciliation with the controller of its kind
+  pub status: ResourceStatus,
 }
 
 /// Convert a Resource into an EventActor
@@ -119,6 +223,8 @@ impl From<Resource> for EventActor {
       key: Some(resource.spec.resource_key),
       kind: EventActorKind::Resource,
       attributes: Some(serde_json::json!({
+        "Name": resource.name,
+        "Namespace": resource.namespace_name,
         "Kind": resource.kind,
         "Version": resource.spec.version,
         "Metadata": resource.spec.metadata,
@@ -132,7 +238,7 @@ impl From<Resource> for EventActor {
 impl From<Resource> for ResourcePartial {
   fn from(resource: Resource) -> Self {
     Self {
-      name: resource.spec.resource_key,
+      name: resource.name,
       kind: resource.kind,
       data: resource.spec.data,
       metadata: resource.spec.metadata,
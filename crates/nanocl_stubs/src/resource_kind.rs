@@ -28,6 +28,77 @@ pub struct ResourceKindSpec {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub url: Option<String>,
+  /// How the resources of the previous versions are converted to this version
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub conversion: Option<ResourceKindConversion>,
+}
+
+/// Conversion of the data of a resource from the previous version of its kind.
+/// Migrating over several versions chains their conversions.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub enum ResourceKindConversion {
+  /// Controller converting the data on `POST /{version}/rules/{name}/convert`
+  /// with the previous version as `from` in the query
+  Url(String),
+  /// Operations applied in order to the data
+  Transform(Vec<ResourceKindTransform>),
+}
+
+/// Operation of a declarative transform
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub enum ResourceKindTransformOp {
+  /// Set `Value` at `Path`
+  Set,
+  /// Move the value at `From` to `Path`
+  Move,
+  /// Copy the value at `From` to `Path`
+  Copy,
+  /// Remove the value at `Path`
+  Remove,
+}
+
+/// Step of a declarative transform, paths are JSON pointers eg: `/Target/Port`.
+/// Missing objects of `Path` are created and a missing `From` is ignored.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceKindTransform {
+  /// Operation to apply
+  pub op: ResourceKindTransformOp,
+  /// Path of the value to set, move or copy to or remove
+  pub path: String,
+  /// Path of the value to move or copy
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub from: Option<String>,
+  /// Value to set
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = Option<Any>))]
+  pub value: Option<serde_json::Value>,
 }
 
 /// This structure is a partial representation of a resource kind.
@@ -125,3 +125,16 @@ impl From<SecretPartial> for SecretUpdate {
     }
   }
 }
+
+/// Result of the rotation of the key used to encrypt the secrets at rest
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct SecretKeyRotation {
+  /// Id of the new key used to encrypt the secrets
+  pub key_id: String,
+  /// Number of secrets encrypted with the new key
+  pub count: usize,
+}
@@ -0,0 +1,121 @@
+use std::collections::BTreeSet;
+
+use bollard_next::container::Config;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Port a service listen on inside its instances
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ServicePort {
+  /// Port of the container
+  pub port: u16,
+  /// Protocol of the port `tcp`, `udp` or `sctp`
+  pub protocol: String,
+}
+
+impl ServicePort {
+  /// Ports mapped or exposed by a container,
+  /// tcp when a port doesn't specify its protocol
+  pub fn of_container(container: &Config) -> Vec<Self> {
+    let bindings = container
+      .host_config
+      .as_ref()
+      .and_then(|host_config| host_config.port_bindings.as_ref())
+      .map(|bindings| bindings.keys().cloned().collect::<Vec<_>>())
+      .unwrap_or_default();
+    let exposed = container
+      .exposed_ports
+      .as_ref()
+      .map(|ports| ports.keys().cloned().collect::<Vec<_>>())
+      .unwrap_or_default();
+    bindings
+      .iter()
+      .chain(exposed.iter())
+      .filter_map(|port| {
+        let (port, protocol) = port.split_once('/').unwrap_or((port, "tcp"));
+        Some(Self {
+          port: port.parse().ok()?,
+          protocol: protocol.to_owned(),
+        })
+      })
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect()
+  }
+}
+
+/// Running instance of a service reachable on its namespace network
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ServiceEndpoint {
+  /// Name of the process of the instance
+  pub name: String,
+  /// Node where the instance is running
+  pub node_name: String,
+  /// Address of the instance on the namespace network
+  pub ip_address: String,
+  /// Gateway of the namespace network on the node of the instance
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub gateway: Option<String>,
+  /// Whether the instance can receive traffic.
+  /// Instances without health check are healthy once running.
+  pub healthy: bool,
+}
+
+/// Discovery view of a cargo, its ports and its instances across the nodes
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct Service {
+  /// Key of the cargo `<name>.<namespace>`
+  pub key: String,
+  /// Name of the cargo
+  pub name: String,
+  /// Namespace of the cargo
+  pub namespace_name: String,
+  /// Ports exposed or mapped by the cargo
+  pub ports: Vec<ServicePort>,
+  /// Instances of the cargo, only the healthy ones by default
+  pub endpoints: Vec<ServiceEndpoint>,
+}
+
+/// Query to list or inspect services
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ServiceQuery {
+  /// Name of the namespace (default: global)
+  pub namespace: Option<String>,
+  /// Include the running instances that aren't healthy (default: false)
+  pub all: Option<bool>,
+}
+
+impl ServiceQuery {
+  /// Create a new query with an optional namespace
+  pub fn new(namespace: Option<&str>) -> Self {
+    Self {
+      namespace: namespace.map(|s| s.to_owned()),
+      ..Default::default()
+    }
+  }
+}
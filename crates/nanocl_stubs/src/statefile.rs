@@ -156,7 +156,7 @@ pub struct Statefile {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub group: Option<String>,
-  /// Namespace where the cargoes and virtual machines are deployed
+  /// Namespace where the cargoes, virtual machines and resources are deployed
   #[cfg_attr(
     feature = "serde",
     serde(skip_serializing_if = "Option::is_none")
@@ -186,6 +186,8 @@ pub enum EventActorKind {
   Secret,
   Process,
   ContainerImage,
+  Role,
+  Identity,
 }
 
 impl std::fmt::Display for EventActorKind {
@@ -199,6 +201,8 @@ impl std::fmt::Display for EventActorKind {
       EventActorKind::Secret => write!(f, "Secret"),
       EventActorKind::Process => write!(f, "Process"),
       EventActorKind::ContainerImage => write!(f, "ContainerImage"),
+      EventActorKind::Role => write!(f, "Role"),
+      EventActorKind::Identity => write!(f, "Identity"),
     }
   }
 }
@@ -224,6 +228,9 @@ pub enum NativeEventAction {
   Die,
   Downloading,
   Download,
+  Rollback,
+  Attempt,
+  Scale,
   Other(String),
 }
 
@@ -247,6 +254,9 @@ impl FromStr for NativeEventAction {
       "die" => Ok(NativeEventAction::Die),
       "downloading" => Ok(NativeEventAction::Downloading),
       "download" => Ok(NativeEventAction::Download),
+      "rollback" => Ok(NativeEventAction::Rollback),
+      "attempt" => Ok(NativeEventAction::Attempt),
+      "scale" => Ok(NativeEventAction::Scale),
       _ => Ok(NativeEventAction::Other(s.to_owned())),
     }
   }
@@ -270,6 +280,9 @@ impl std::fmt::Display for NativeEventAction {
       NativeEventAction::Die => write!(f, "die"),
       NativeEventAction::Downloading => write!(f, "downloading"),
       NativeEventAction::Download => write!(f, "download"),
+      NativeEventAction::Rollback => write!(f, "rollback"),
+      NativeEventAction::Attempt => write!(f, "attempt"),
+      NativeEventAction::Scale => write!(f, "scale"),
       NativeEventAction::Other(s) => write!(f, "{}", s),
     }
   }
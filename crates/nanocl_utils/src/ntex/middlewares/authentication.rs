@@ -0,0 +1,145 @@
+/// Authentication middleware
+use std::{future::Future, rc::Rc};
+
+use ntex::http::{header, Method};
+use ntex::web::{
+  Error, ErrorRenderer, HttpRequest, HttpResponse, WebRequest, WebResponse,
+  WebResponseError,
+};
+use ntex::{Middleware, Service, ServiceCtx};
+
+/// Decide if a request is allowed to reach the api
+pub trait Authorizer: 'static {
+  type Error;
+
+  /// Return an error to reject the request
+  fn authorize(
+    &self,
+    req: &HttpRequest,
+  ) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+/// Authentication middleware creator
+///
+/// ```no_run,ignore
+/// use ntex::web;
+/// use nanocl_utils::ntex::middlewares::Authentication;
+///
+/// let authentication = Authentication::new(MyAuthorizer).finish();
+///
+/// web::scope("/{version}")
+///  .wrap(authentication)
+///  .route("/test", web::get().to(|| async { "test" }));
+/// ```
+pub struct Authentication<A> {
+  authorizer: A,
+}
+
+impl<A> Authentication<A>
+where
+  A: Authorizer,
+{
+  pub fn new(authorizer: A) -> Self {
+    Self { authorizer }
+  }
+
+  pub fn finish(self) -> AuthenticationFactory<A> {
+    AuthenticationFactory {
+      inner: Rc::new(self.authorizer),
+    }
+  }
+}
+
+pub struct AuthenticationFactory<A> {
+  inner: Rc<A>,
+}
+
+impl<S, A> Middleware<S> for AuthenticationFactory<A> {
+  type Service = AuthenticationMiddleware<S, A>;
+
+  fn create(&self, service: S) -> Self::Service {
+    AuthenticationMiddleware {
+      service,
+      inner: self.inner.clone(),
+    }
+  }
+}
+
+pub struct AuthenticationMiddleware<S, A> {
+  service: S,
+  inner: Rc<A>,
+}
+
+impl<S, A, Err> Service<WebRequest<Err>> for AuthenticationMiddleware<S, A>
+where
+  S: Service<WebRequest<Err>, Response = WebResponse, Error = Error>,
+  A: Authorizer,
+  A::Error: WebResponseError<Err>,
+  Err: ErrorRenderer,
+{
+  type Response = WebResponse;
+  type Error = Error;
+
+  ntex::forward_ready!(service);
+  ntex::forward_shutdown!(service);
+
+  async fn call(
+    &self,
+    req: WebRequest<Err>,
+    ctx: ServiceCtx<'_, Self>,
+  ) -> Result<Self::Response, Self::Error> {
+    let (http_req, payload) = req.into_parts();
+    let authorized = self.inner.authorize(&http_req).await;
+    let req = match WebRequest::from_parts(http_req, payload) {
+      Ok(req) => req,
+      Err((http_req, _)) => {
+        return Ok(WebResponse::new(
+          HttpResponse::InternalServerError().finish(),
+          http_req,
+        ))
+      }
+    };
+    if let Err(err) = authorized {
+      return Ok(req.render_error(err));
+    }
+    ctx.call(&self.service, req).await
+  }
+}
+
+/// Get the token of the `Authorization: Bearer <token>` header
+pub fn bearer_token(req: &HttpRequest) -> Option<String> {
+  let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+  let (scheme, token) = value.split_once(' ')?;
+  if !scheme.eq_ignore_ascii_case("bearer") || token.trim().is_empty() {
+    return None;
+  }
+  Some(token.trim().to_owned())
+}
+
+/// Path of the request without its `/{version}` prefix
+pub fn unversioned_path(req: &HttpRequest) -> &str {
+  let path = req.path().trim_start_matches('/');
+  match path.split_once('/') {
+    Some((_, rest)) => rest,
+    None => "",
+  }
+}
+
+/// Decoded values of a query string parameter of the request
+/// in the order they appear
+pub fn query_params(req: &HttpRequest, name: &str) -> Vec<String> {
+  form_urlencoded::parse(req.query_string().as_bytes())
+    .filter(|(key, _)| key == name)
+    .map(|(_, value)| value.into_owned())
+    .collect()
+}
+
+/// Decoded value of a query string parameter of the request
+pub fn query_param(req: &HttpRequest, name: &str) -> Option<String> {
+  query_params(req, name).into_iter().next()
+}
+
+/// Check if the request only read data
+pub fn is_read_method(method: &Method) -> bool {
+  matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
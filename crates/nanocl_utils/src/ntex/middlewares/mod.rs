@@ -4,3 +4,9 @@ pub use serialize_error::SerializeError;
 
 mod versioning;
 pub use versioning::Versioning;
+
+mod authentication;
+pub use authentication::{
+  bearer_token, is_read_method, query_param, query_params, unversioned_path,
+  Authentication, Authorizer,
+};
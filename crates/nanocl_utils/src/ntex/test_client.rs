@@ -38,6 +38,10 @@ impl TestClient {
     self.srv.get(self.gen_url(url))
   }
 
+  pub fn get_unversioned(&self, url: &str) -> ClientRequest {
+    self.srv.get(url)
+  }
+
   pub fn delete(&self, url: &str) -> ClientRequest {
     self
       .srv
@@ -0,0 +1,204 @@
+use nanocl_error::http_client::HttpClientResult;
+
+use nanocl_stubs::auth::{
+  ApiToken, ApiTokenPartial, Identity, IdentityPartial, IdentityUpdate, Role,
+  RolePartial, RoleUpdate,
+};
+use nanocl_stubs::generic::GenericFilter;
+
+use super::http_client::NanocldClient;
+
+impl NanocldClient {
+  /// ## Default path for roles
+  const ROLE_PATH: &'static str = "/roles";
+  /// ## Default path for identities
+  const IDENTITY_PATH: &'static str = "/identities";
+
+  /// List existing roles in the system.
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.list_role(None).await;
+  /// ```
+  pub async fn list_role(
+    &self,
+    query: Option<&GenericFilter>,
+  ) -> HttpClientResult<Vec<Role>> {
+    let query = Self::convert_query(query)?;
+    let res = self.send_get(Self::ROLE_PATH, Some(&query)).await?;
+    Self::res_json(res).await
+  }
+
+  /// Create a new role
+  pub async fn create_role(
+    &self,
+    item: &RolePartial,
+  ) -> HttpClientResult<Role> {
+    let res = self
+      .send_post(Self::ROLE_PATH, Some(item), None::<String>)
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Inspect a role by it's name
+  pub async fn inspect_role(&self, name: &str) -> HttpClientResult<Role> {
+    let res = self
+      .send_get(
+        &format!("{}/{name}/inspect", Self::ROLE_PATH),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Replace the rules of a role by it's name
+  pub async fn put_role(
+    &self,
+    name: &str,
+    item: &RoleUpdate,
+  ) -> HttpClientResult<Role> {
+    let res = self
+      .send_put(
+        &format!("{}/{name}", Self::ROLE_PATH),
+        Some(item),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Delete a role by it's name
+  pub async fn delete_role(&self, name: &str) -> HttpClientResult<()> {
+    self
+      .send_delete(&format!("{}/{name}", Self::ROLE_PATH), None::<String>)
+      .await?;
+    Ok(())
+  }
+
+  /// List existing identities in the system.
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.list_identity(None).await;
+  /// ```
+  pub async fn list_identity(
+    &self,
+    query: Option<&GenericFilter>,
+  ) -> HttpClientResult<Vec<Identity>> {
+    let query = Self::convert_query(query)?;
+    let res = self.send_get(Self::IDENTITY_PATH, Some(&query)).await?;
+    Self::res_json(res).await
+  }
+
+  /// Create a new identity
+  pub async fn create_identity(
+    &self,
+    item: &IdentityPartial,
+  ) -> HttpClientResult<Identity> {
+    let res = self
+      .send_post(Self::IDENTITY_PATH, Some(item), None::<String>)
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Inspect an identity by it's name
+  pub async fn inspect_identity(
+    &self,
+    name: &str,
+  ) -> HttpClientResult<Identity> {
+    let res = self
+      .send_get(
+        &format!("{}/{name}/inspect", Self::IDENTITY_PATH),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Patch an identity by it's name to update it's roles or subject
+  pub async fn patch_identity(
+    &self,
+    name: &str,
+    item: &IdentityUpdate,
+  ) -> HttpClientResult<Identity> {
+    let res = self
+      .send_patch(
+        &format!("{}/{name}", Self::IDENTITY_PATH),
+        Some(item),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Delete an identity by it's name, it's api tokens are revoked
+  pub async fn delete_identity(&self, name: &str) -> HttpClientResult<()> {
+    self
+      .send_delete(&format!("{}/{name}", Self::IDENTITY_PATH), None::<String>)
+      .await?;
+    Ok(())
+  }
+
+  /// Create a new api token for an identity.
+  /// The token is only returned by this call.
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let token = client.create_identity_token("ci", &Default::default()).await?;
+  /// ```
+  pub async fn create_identity_token(
+    &self,
+    name: &str,
+    item: &ApiTokenPartial,
+  ) -> HttpClientResult<ApiToken> {
+    let res = self
+      .send_post(
+        &format!("{}/{name}/tokens", Self::IDENTITY_PATH),
+        Some(item),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// List the api tokens of an identity
+  pub async fn list_identity_token(
+    &self,
+    name: &str,
+  ) -> HttpClientResult<Vec<ApiToken>> {
+    let res = self
+      .send_get(
+        &format!("{}/{name}/tokens", Self::IDENTITY_PATH),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Revoke an api token of an identity by it's key
+  pub async fn delete_identity_token(
+    &self,
+    name: &str,
+    key: &str,
+  ) -> HttpClientResult<()> {
+    self
+      .send_delete(
+        &format!("{}/{name}/tokens/{key}", Self::IDENTITY_PATH),
+        None::<String>,
+      )
+      .await?;
+    Ok(())
+  }
+}
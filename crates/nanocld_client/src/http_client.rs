@@ -26,6 +26,8 @@ pub struct ConnectOpts {
   pub version: Option<String>,
   /// Optional certificate path
   pub ssl: Option<SslConfig>,
+  /// Optional api token to authenticate with
+  pub token: Option<String>,
 }
 
 #[derive(Clone)]
@@ -34,6 +36,7 @@ pub struct NanocldClient {
   pub version: String,
   pub unix_socket: Option<String>,
   pub ssl: Option<SslConfig>,
+  pub token: Option<String>,
 }
 
 impl Default for ConnectOpts {
@@ -42,6 +45,7 @@ impl Default for ConnectOpts {
       url: String::from("unix:///run/nanocl/nanocl.sock"),
       version: None,
       ssl: None,
+      token: None,
     }
   }
 }
@@ -59,6 +63,7 @@ impl NanocldClient {
       version: format!("v{NANOCLD_DEFAULT_VERSION}"),
       url: "http://localhost".to_owned(),
       ssl: None,
+      token: None,
     }
   }
 
@@ -70,6 +75,7 @@ impl NanocldClient {
         Ok(NanocldClient {
           url: url.to_owned(),
           ssl: opts.ssl.clone(),
+          token: opts.token.clone(),
           unix_socket: None,
           version: version.unwrap_or(format!("v{NANOCLD_DEFAULT_VERSION}")),
         })
@@ -78,6 +84,7 @@ impl NanocldClient {
         let path = url.trim_start_matches("unix://");
         Ok(NanocldClient {
           ssl: None,
+          token: opts.token.clone(),
           url: "http://localhost".to_owned(),
           unix_socket: Some(path.to_owned()),
           version: version.unwrap_or(format!("v{NANOCLD_DEFAULT_VERSION}")),
@@ -97,6 +104,7 @@ impl NanocldClient {
       version: version.to_owned(),
       url: String::from("http://localhost"),
       ssl: None,
+      token: None,
     }
   }
 
@@ -196,58 +204,46 @@ impl NanocldClient {
     format!("{}/{}{}", self.url, self.version, url)
   }
 
+  /// Set the headers sent with every request
+  fn gen_headers(
+    &self,
+    req: http::client::ClientRequest,
+  ) -> http::client::ClientRequest {
+    let req = req.header("User-Agent", "nanocld_client");
+    match &self.token {
+      Some(token) => req.header("Authorization", format!("Bearer {token}")),
+      None => req,
+    }
+  }
+
   fn get(&self, url: &str) -> IoResult<http::client::ClientRequest> {
-    Ok(
-      self
-        .gen_client()?
-        .get(self.gen_url(url))
-        .header("User-Agent", "nanocld_client"),
-    )
+    let req = self.gen_client()?.get(self.gen_url(url));
+    Ok(self.gen_headers(req))
   }
 
   fn delete(&self, url: &str) -> IoResult<http::client::ClientRequest> {
-    Ok(
-      self
-        .gen_client()?
-        .delete(self.gen_url(url))
-        .header("User-Agent", "nanocld_client"),
-    )
+    let req = self.gen_client()?.delete(self.gen_url(url));
+    Ok(self.gen_headers(req))
   }
 
   fn post(&self, url: &str) -> IoResult<http::client::ClientRequest> {
-    Ok(
-      self
-        .gen_client()?
-        .post(self.gen_url(url))
-        .header("User-Agent", "nanocld_client"),
-    )
+    let req = self.gen_client()?.post(self.gen_url(url));
+    Ok(self.gen_headers(req))
   }
 
   fn patch(&self, url: &str) -> IoResult<http::client::ClientRequest> {
-    Ok(
-      self
-        .gen_client()?
-        .patch(self.gen_url(url))
-        .header("User-Agent", "nanocld_client"),
-    )
+    let req = self.gen_client()?.patch(self.gen_url(url));
+    Ok(self.gen_headers(req))
   }
 
   fn put(&self, url: &str) -> IoResult<http::client::ClientRequest> {
-    Ok(
-      self
-        .gen_client()?
-        .put(self.gen_url(url))
-        .header("User-Agent", "nanocld_client"),
-    )
+    let req = self.gen_client()?.put(self.gen_url(url));
+    Ok(self.gen_headers(req))
   }
 
   fn head(&self, url: &str) -> IoResult<http::client::ClientRequest> {
-    Ok(
-      self
-        .gen_client()?
-        .head(self.gen_url(url))
-        .header("User-Agent", "nanocld_client"),
-    )
+    let req = self.gen_client()?.head(self.gen_url(url));
+    Ok(self.gen_headers(req))
   }
 
   pub async fn send_get<Q>(
@@ -134,6 +134,7 @@ mod tests {
         ttl: None,
         image_pull_secret: None,
         image_pull_policy: None,
+        ..Default::default()
       })
       .await
       .unwrap();
@@ -1,5 +1,6 @@
 mod http_client;
 
+pub(crate) mod auth;
 pub(crate) mod cargo;
 pub(crate) mod exec;
 pub(crate) mod job;
@@ -10,6 +11,7 @@ pub(crate) mod process;
 pub(crate) mod resource;
 pub(crate) mod resource_kind;
 pub(crate) mod secret;
+pub(crate) mod service;
 pub(crate) mod system;
 pub(crate) mod vm;
 pub(crate) mod vm_image;
@@ -2,7 +2,7 @@ use nanocl_error::http_client::HttpClientResult;
 
 use nanocl_stubs::{
   generic::GenericFilter,
-  metric::{Metric, MetricPartial},
+  metric::{Metric, MetricAggregate, MetricAggregateQuery, MetricPartial},
 };
 
 use super::http_client::NanocldClient;
@@ -76,6 +76,32 @@ impl NanocldClient {
       .await?;
     Self::res_json(res).await
   }
+
+  /// Aggregate the metrics of a kind by time bucket and grouped fields
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  /// use nanocld_client::stubs::metric::MetricAggregateQuery;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.aggregate_metric(&MetricAggregateQuery {
+  ///   kind: "ncproxy.io/http".to_owned(),
+  ///   group_by: Some("host,status".to_owned()),
+  ///   fields: Some("request_time".to_owned()),
+  ///   ..Default::default()
+  /// }).await;
+  /// ```
+  pub async fn aggregate_metric(
+    &self,
+    query: &MetricAggregateQuery,
+  ) -> HttpClientResult<Vec<MetricAggregate>> {
+    let res = self
+      .send_get(&format!("{}/aggregate", Self::METRIC_PATH), Some(query))
+      .await?;
+    Self::res_json(res).await
+  }
 }
 
 #[cfg(test)]
@@ -109,5 +135,14 @@ mod tests {
       .inspect_metric(metrics[0].key.to_string().as_str())
       .await
       .unwrap();
+    let aggregates = client
+      .aggregate_metric(&MetricAggregateQuery {
+        kind: "my-source.io/type".to_owned(),
+        group_by: Some("name".to_owned()),
+        ..Default::default()
+      })
+      .await
+      .unwrap();
+    assert!(!aggregates.is_empty());
   }
 }
@@ -40,6 +40,9 @@ impl NanocldClient {
     let new_item = NamespacePartial {
       name: name.to_owned(),
       metadata: None,
+      network: None,
+      quota: None,
+      limit_range: None,
     };
     let res = self
       .send_post(Self::NAMESPACE_PATH, Some(new_item), None::<String>)
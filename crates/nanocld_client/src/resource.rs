@@ -1,8 +1,8 @@
 use nanocl_error::http_client::HttpClientResult;
 
-use nanocl_stubs::generic::GenericFilter;
+use nanocl_stubs::generic::{GenericFilterNsp, GenericNspQuery};
 use nanocl_stubs::resource::{
-  Resource, ResourcePartial, ResourceSpec, ResourceUpdate,
+  Resource, ResourceMigrate, ResourcePartial, ResourceSpec, ResourceUpdate,
 };
 
 use super::http_client::NanocldClient;
@@ -12,6 +12,7 @@ impl NanocldClient {
   const RESOURCE_PATH: &'static str = "/resources";
 
   /// List existing resources in the system.
+  /// Every namespace is listed unless the query has a namespace.
   ///
   /// ## Example
   ///
@@ -19,19 +20,19 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.list_resource().await;
+  /// let res = client.list_resource(None).await;
   /// ```
   ///
   pub async fn list_resource(
     &self,
-    query: Option<&GenericFilter>,
+    query: Option<&GenericFilterNsp>,
   ) -> HttpClientResult<Vec<Resource>> {
     let query = Self::convert_query(query)?;
     let res = self.send_get(Self::RESOURCE_PATH, Some(query)).await?;
     Self::res_json(res).await
   }
 
-  /// Create a new resource from a partial resource in the system.
+  /// Create a new resource from a partial resource in a namespace.
   ///
   /// ## Example
   ///
@@ -45,14 +46,19 @@ impl NanocldClient {
   ///   kind: String::from("Custom")s,
   ///   // Your data
   ///   data: serde_json::json!({}),
-  /// }).await;
+  /// }, None).await;
   /// ```
   pub async fn create_resource(
     &self,
     data: &ResourcePartial,
+    namespace: Option<&str>,
   ) -> HttpClientResult<Resource> {
     let res = self
-      .send_post(Self::RESOURCE_PATH, Some(data), None::<String>)
+      .send_post(
+        Self::RESOURCE_PATH,
+        Some(data),
+        Some(&GenericNspQuery::new(namespace)),
+      )
       .await?;
     Self::res_json(res).await
   }
@@ -65,16 +71,17 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.inspect_resource("my-resource").await;
+  /// let res = client.inspect_resource("my-resource", None).await;
   /// ```
   pub async fn inspect_resource(
     &self,
-    key: &str,
+    name: &str,
+    namespace: Option<&str>,
   ) -> HttpClientResult<Resource> {
     let res = self
       .send_get(
-        &format!("{}/{key}/inspect", Self::RESOURCE_PATH),
-        None::<String>,
+        &format!("{}/{name}/inspect", Self::RESOURCE_PATH),
+        Some(&GenericNspQuery::new(namespace)),
       )
       .await?;
     Self::res_json(res).await
@@ -88,18 +95,19 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.patch_resource("my-resource", serde_json::json!({})).await;
+  /// let res = client.put_resource("my-resource", &update, None).await;
   /// ```
   pub async fn put_resource(
     &self,
-    key: &str,
+    name: &str,
     config: &ResourceUpdate,
+    namespace: Option<&str>,
   ) -> HttpClientResult<Resource> {
     let res = self
       .send_put(
-        &format!("{}/{key}", Self::RESOURCE_PATH),
+        &format!("{}/{name}", Self::RESOURCE_PATH),
         Some(config),
-        None::<String>,
+        Some(&GenericNspQuery::new(namespace)),
       )
       .await?;
     Self::res_json(res).await
@@ -113,11 +121,18 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.delete_resource("my-resource").await;
+  /// let res = client.delete_resource("my-resource", None).await;
   /// ```
-  pub async fn delete_resource(&self, key: &str) -> HttpClientResult<()> {
+  pub async fn delete_resource(
+    &self,
+    name: &str,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<()> {
     self
-      .send_delete(&format!("{}/{key}", Self::RESOURCE_PATH), None::<String>)
+      .send_delete(
+        &format!("{}/{name}", Self::RESOURCE_PATH),
+        Some(&GenericNspQuery::new(namespace)),
+      )
       .await?;
     Ok(())
   }
@@ -130,16 +145,17 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.list_history_resource("my-resource").await;
+  /// let res = client.list_history_resource("my-resource", None).await;
   /// ```
   pub async fn list_history_resource(
     &self,
-    key: &str,
+    name: &str,
+    namespace: Option<&str>,
   ) -> HttpClientResult<Vec<ResourceSpec>> {
     let res = self
       .send_get(
-        &format!("{}/{key}/histories", Self::RESOURCE_PATH),
-        None::<String>,
+        &format!("{}/{name}/histories", Self::RESOURCE_PATH),
+        Some(&GenericNspQuery::new(namespace)),
       )
       .await?;
     Self::res_json(res).await
@@ -153,19 +169,47 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let history = client.list_history_resource("my-resource").await.unwrap().first().unwrap();
-  /// let res = client.revert_resource("my-resource", history.key).await;
+  /// let history = client.list_history_resource("my-resource", None).await.unwrap().first().unwrap();
+  /// let res = client.revert_resource("my-resource", history.key, None).await;
   /// ```
   pub async fn revert_resource(
     &self,
     name: &str,
     key: &str,
+    namespace: Option<&str>,
   ) -> HttpClientResult<Resource> {
     let res = self
       .send_patch(
         &format!("{}/{name}/histories/{key}/revert", Self::RESOURCE_PATH),
         None::<String>,
-        None::<String>,
+        Some(&GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Migrate a resource to another version of its kind
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  /// use nanocld_client::stubs::resource::ResourceMigrate;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.migrate_resource("my-resource", &ResourceMigrate::default(), None).await;
+  /// ```
+  pub async fn migrate_resource(
+    &self,
+    name: &str,
+    payload: &ResourceMigrate,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<Resource> {
+    let res = self
+      .send_post(
+        &format!("{}/{name}/migrate", Self::RESOURCE_PATH),
+        Some(payload),
+        Some(&GenericNspQuery::new(namespace)),
       )
       .await?;
     Self::res_json(res).await
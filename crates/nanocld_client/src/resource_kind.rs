@@ -114,6 +114,30 @@ impl NanocldClient {
       .await?;
     Ok(())
   }
+
+  /// Delete a version of a resource kind not used by any resource
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// client.delete_resource_kind_version("ncproxy.io/rule", "v0.1").await?;
+  /// ```
+  pub async fn delete_resource_kind_version(
+    &self,
+    key: &str,
+    version: &str,
+  ) -> HttpClientResult<()> {
+    self
+      .send_delete(
+        &format!("{}/{key}/version/{version}", Self::RESOURCE_KIND_PATH),
+        None::<String>,
+      )
+      .await?;
+    Ok(())
+  }
 }
 
 #[cfg(test)]
@@ -140,6 +164,7 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+        conversion: None,
       },
     };
     let resource_kind =
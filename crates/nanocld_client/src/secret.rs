@@ -1,7 +1,9 @@
 use nanocl_error::http_client::HttpClientResult;
 
 use nanocl_stubs::generic::GenericFilter;
-use nanocl_stubs::secret::{Secret, SecretPartial, SecretUpdate};
+use nanocl_stubs::secret::{
+  Secret, SecretKeyRotation, SecretPartial, SecretUpdate,
+};
 
 use super::http_client::NanocldClient;
 
@@ -91,6 +93,27 @@ impl NanocldClient {
       .await?;
     Ok(())
   }
+
+  /// Generate a new key to encrypt secrets at rest and encrypt every secret with it
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let rotation = client.rotate_secret_key().await?;
+  /// ```
+  pub async fn rotate_secret_key(&self) -> HttpClientResult<SecretKeyRotation> {
+    let res = self
+      .send_post(
+        &format!("{}/rotate-key", Self::SECRET_PATH),
+        None::<String>,
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
 }
 
 #[cfg(test)]
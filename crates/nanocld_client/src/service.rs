@@ -0,0 +1,74 @@
+use nanocl_error::http_client::HttpClientResult;
+
+use nanocl_stubs::service::{Service, ServiceQuery};
+
+use super::http_client::NanocldClient;
+
+impl NanocldClient {
+  /// ## Default path for services
+  const SERVICE_PATH: &'static str = "/services";
+
+  /// List the services of the cargoes of a namespace
+  /// with their healthy instances across the nodes
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.list_service(None).await;
+  /// ```
+  pub async fn list_service(
+    &self,
+    query: Option<&ServiceQuery>,
+  ) -> HttpClientResult<Vec<Service>> {
+    let res = self.send_get(Self::SERVICE_PATH, query).await?;
+    Self::res_json(res).await
+  }
+
+  /// Inspect the service of a cargo by its name
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.inspect_service("my-cargo", None).await;
+  /// ```
+  pub async fn inspect_service(
+    &self,
+    name: &str,
+    query: Option<&ServiceQuery>,
+  ) -> HttpClientResult<Service> {
+    let res = self
+      .send_get(&format!("{}/{name}/inspect", Self::SERVICE_PATH), query)
+      .await?;
+    Self::res_json(res).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::ConnectOpts;
+
+  use super::*;
+
+  #[ntex::test]
+  async fn basic() {
+    let client = NanocldClient::connect_to(&ConnectOpts {
+      url: "http://nanocl.internal:8585".into(),
+      ..Default::default()
+    })
+    .expect("Failed to create a nanocl client");
+    let query = ServiceQuery::new(Some("system"));
+    let services = client.list_service(Some(&query)).await.unwrap();
+    assert!(services.iter().any(|service| service.name == "nstore"));
+    let service = client
+      .inspect_service("nstore", Some(&query))
+      .await
+      .unwrap();
+    assert_eq!(service.key, "nstore.system");
+  }
+}
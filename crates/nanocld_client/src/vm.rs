@@ -149,18 +149,28 @@ impl NanocldClient {
           .connect()
           .await
           .map_err(|err| err.map_err_context(|| path))?,
-        None => ws::WsClient::build(&url)
-          .finish()
-          .map_err(|err| err.map_err_context(|| &self.url))?
-          .connect()
-          .await
-          .map_err(|err| err.map_err_context(|| &self.url))?,
+        None => {
+          let mut builder = ws::WsClient::build(&url);
+          if let Some(token) = &self.token {
+            builder.bearer_auth(token);
+          }
+          builder
+            .finish()
+            .map_err(|err| err.map_err_context(|| &self.url))?
+            .connect()
+            .await
+            .map_err(|err| err.map_err_context(|| &self.url))?
+        }
       };
       Ok(con)
     }
     #[cfg(target_os = "windows")]
     {
-      let con = ws::WsClient::build(&url)
+      let mut builder = ws::WsClient::build(&url);
+      if let Some(token) = &self.token {
+        builder.bearer_auth(token);
+      }
+      let con = builder
         .finish()
         .map_err(|err| err.map_err_context(|| &self.url))?
         .connect()